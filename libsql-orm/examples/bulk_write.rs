@@ -0,0 +1,167 @@
+//! Compares the single-statement bulk writes with row-by-row writes.
+//!
+//! ```text
+//! cargo run --release --example bulk_write --features local
+//! LIBSQL_URL=http://127.0.0.1:8080 cargo run --release --example bulk_write --features local
+//! ```
+//!
+//! `LIBSQL_URL` defaults to a temporary SQLite file, `LIBSQL_TOKEN` is passed
+//! to remote databases. `ROWS` sets the batch size. Against a local server
+//! every statement is a round trip, which is where the bulk paths pay off.
+
+use std::time::{Duration, Instant};
+
+use libsql_orm::{Database, Error, Model};
+use serde::{Deserialize, Serialize};
+
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("bulk_write")]
+struct Product {
+    pub id: Option<i64>,
+    #[orm_column(not_null, unique)]
+    pub sku: String,
+    pub name: String,
+    pub stock: i64,
+}
+
+fn env_or(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn products(rows: usize, stock: i64) -> Vec<Product> {
+    (0..rows)
+        .map(|i| Product {
+            id: None,
+            sku: format!("sku-{i}"),
+            name: format!("Product {i}"),
+            stock,
+        })
+        .collect()
+}
+
+fn report(name: &str, rows: usize, elapsed: Duration) {
+    println!(
+        "{name:<28} {:>10.2?}   {:>9.0} rows/s",
+        elapsed,
+        rows as f64 / elapsed.as_secs_f64()
+    );
+}
+
+async fn reset(db: &Database) -> Result<(), Error> {
+    db.get_connection()
+        .execute_batch(&format!(
+            "DROP TABLE IF EXISTS bulk_write; {}",
+            Product::migration_sql()
+        ))
+        .await?;
+    Ok(())
+}
+
+/// The previous `bulk_create`: one INSERT per row in a transaction.
+async fn create_each(products: &[Product], db: &Database) -> Result<Vec<Product>, Error> {
+    db.transaction(|tx| async move {
+        let mut created = Vec::new();
+        for product in products {
+            created.push(product.create(&tx).await?);
+        }
+        Ok(created)
+    })
+    .await
+}
+
+/// The previous `upsert`: SELECT the id, then UPDATE or INSERT.
+async fn select_then_write(product: &Product, db: &Database) -> Result<Product, Error> {
+    let mut rows = db
+        .query(
+            "SELECT id FROM bulk_write WHERE sku = ?",
+            vec![libsql::Value::Text(product.sku.clone())],
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => {
+            let mut product = product.clone();
+            product.set_primary_key(row.get::<i64>(0)?);
+            product.update(db).await
+        }
+        None => product.create(db).await,
+    }
+}
+
+/// The previous `bulk_update`: one UPDATE per row in a transaction.
+async fn update_each(products: &[Product], db: &Database) -> Result<(), Error> {
+    db.transaction(|tx| async move {
+        for product in products {
+            product.update(&tx).await?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join("libsql-orm-bulk-write.db");
+    let url = std::env::var("LIBSQL_URL").unwrap_or_else(|_| format!("file:{}", path.display()));
+    let token = std::env::var("LIBSQL_TOKEN").unwrap_or_default();
+    let rows = env_or("ROWS", 1000);
+    let db = Database::open(&url, &token).await?;
+
+    println!("{rows} rows against {url}");
+
+    reset(&db).await?;
+    let start = Instant::now();
+    create_each(&products(rows, 1), &db).await?;
+    report("insert per row", rows, start.elapsed());
+
+    reset(&db).await?;
+    let start = Instant::now();
+    let created = Product::bulk_create(&products(rows, 1), &db).await?;
+    report("bulk_create", rows, start.elapsed());
+
+    let mut changed = created.clone();
+    for product in &mut changed {
+        product.stock += 1;
+    }
+    let start = Instant::now();
+    update_each(&changed, &db).await?;
+    report("update per row", rows, start.elapsed());
+
+    let start = Instant::now();
+    Product::bulk_update(&changed, &db).await?;
+    report("bulk_update", rows, start.elapsed());
+
+    // Half of the rows exist, half are new
+    let upserts = products(rows + rows / 2, 3).split_off(rows / 2);
+    reset(&db).await?;
+    Product::bulk_create(&products(rows, 1), &db).await?;
+    let start = Instant::now();
+    for product in &upserts {
+        select_then_write(product, &db).await?;
+    }
+    report("select, then write", upserts.len(), start.elapsed());
+
+    reset(&db).await?;
+    Product::bulk_create(&products(rows, 1), &db).await?;
+    let start = Instant::now();
+    for product in &upserts {
+        product.upsert(&["sku"], &db).await?;
+    }
+    report("upsert", upserts.len(), start.elapsed());
+
+    reset(&db).await?;
+    Product::bulk_create(&products(rows, 1), &db).await?;
+    let start = Instant::now();
+    Product::bulk_upsert(&upserts, &["sku"], &db).await?;
+    report("bulk_upsert", upserts.len(), start.elapsed());
+
+    db.get_connection()
+        .execute("DROP TABLE IF EXISTS bulk_write", ())
+        .await?;
+    if std::env::var("LIBSQL_URL").is_err() {
+        std::fs::remove_file(&path).ok();
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Compares query latency with a new connection per query against a pool.
+//!
+//! ```text
+//! cargo run --release --example pool_load --features pool,local
+//! LIBSQL_URL=http://127.0.0.1:8080 cargo run --release --example pool_load --features pool,local
+//! ```
+//!
+//! `LIBSQL_URL` defaults to a temporary SQLite file, `LIBSQL_TOKEN` is passed
+//! to remote databases. `REQUESTS` and `CONCURRENCY` shape the load.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use libsql_orm::{Database, Pool, PoolConfig};
+
+const QUERY: &str = "SELECT COUNT(*) FROM load_test";
+
+fn env_or(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn report(name: &str, mut latencies: Vec<Duration>, total: Duration) {
+    latencies.sort();
+    let percentile = |p: usize| latencies[(latencies.len() * p / 100).min(latencies.len() - 1)];
+    println!(
+        "{name:<22} {:>8.0} req/s   p50 {:>9.2?}   p95 {:>9.2?}   p99 {:>9.2?}",
+        latencies.len() as f64 / total.as_secs_f64(),
+        percentile(50),
+        percentile(95),
+        percentile(99),
+    );
+}
+
+/// Runs `requests` queries from `concurrency` tasks, timing each one.
+async fn run<F, Fut>(requests: usize, concurrency: usize, query: F) -> (Vec<Duration>, Duration)
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: std::future::Future<Output = ()> + Send,
+{
+    let query = Arc::new(query);
+    let started = Instant::now();
+    let tasks: Vec<_> = (0..concurrency)
+        .map(|worker| {
+            let query = query.clone();
+            tokio::spawn(async move {
+                let mut latencies = Vec::new();
+                for _ in (worker..requests).step_by(concurrency) {
+                    let start = Instant::now();
+                    query().await;
+                    latencies.push(start.elapsed());
+                }
+                latencies
+            })
+        })
+        .collect();
+
+    let mut latencies = Vec::with_capacity(requests);
+    for task in tasks {
+        latencies.extend(task.await.unwrap());
+    }
+    (latencies, started.elapsed())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join("libsql-orm-pool-load.db");
+    let url = std::env::var("LIBSQL_URL").unwrap_or_else(|_| format!("file:{}", path.display()));
+    let token = std::env::var("LIBSQL_TOKEN").unwrap_or_default();
+    let requests = env_or("REQUESTS", 2000);
+    let concurrency = env_or("CONCURRENCY", 16);
+
+    let setup = Database::open(&url, &token).await?;
+    setup
+        .get_connection()
+        .execute_batch(
+            "CREATE TABLE IF NOT EXISTS load_test (id INTEGER PRIMARY KEY, body TEXT);
+             INSERT INTO load_test (body) VALUES ('a'), ('b'), ('c');",
+        )
+        .await?;
+    drop(setup);
+
+    println!("{requests} queries from {concurrency} tasks against {url}");
+
+    let (connect_url, connect_token) = (url.clone(), token.clone());
+    let (latencies, total) = run(requests, concurrency, move || {
+        let (url, token) = (connect_url.clone(), connect_token.clone());
+        async move {
+            let db = Database::open(&url, &token).await.unwrap();
+            db.get_connection().query(QUERY, ()).await.unwrap();
+        }
+    })
+    .await;
+    report("connection per query", latencies, total);
+
+    let pool = Pool::new(
+        &url,
+        &token,
+        PoolConfig {
+            min_size: concurrency,
+            max_size: concurrency,
+            ..PoolConfig::default()
+        },
+    )
+    .await?;
+    let pooled = pool.clone();
+    let (latencies, total) = run(requests, concurrency, move || {
+        let pool = pooled.clone();
+        async move {
+            let db = pool.get().await.unwrap();
+            db.get_connection().query(QUERY, ()).await.unwrap();
+        }
+    })
+    .await;
+    report("pool", latencies, total);
+    println!("{:?}", pool.metrics());
+
+    if std::env::var("LIBSQL_URL").is_err() {
+        std::fs::remove_file(&path).ok();
+    }
+    Ok(())
+}
@@ -0,0 +1,280 @@
+//! Parsing of `#[orm_column(...)]` field attributes
+
+use syn::spanned::Spanned;
+use syn::{Expr, Field, GenericArgument, Ident, Lit, LitStr, PathArguments, Type, UnOp};
+
+/// Options accepted by `#[orm_column(...)]`, for error messages.
+const OPTIONS: &str = "`type`, `not_null`, `unique`, `primary_key`, `auto_increment`, `default`, `references(table.column)`, `index`, `skip`, `rename`";
+
+/// A struct field and the table column it maps to
+pub struct Column {
+    pub field: Ident,
+    /// Column name, the field name unless renamed.
+    pub name: String,
+    pub sql_type: String,
+    /// The field type without `Option`.
+    pub value_type: Type,
+    pub optional: bool,
+    pub is_bool: bool,
+    pub has_attribute: bool,
+    pub not_null: bool,
+    pub unique: bool,
+    pub primary_key: bool,
+    pub auto_increment: bool,
+    /// SQL literal of the default value.
+    pub default: Option<String>,
+    pub references: Option<(String, String)>,
+    pub index: bool,
+    pub skip: bool,
+}
+
+impl Column {
+    /// Reads the field's type and `orm_column` attributes, rejecting invalid
+    /// combinations.
+    pub fn parse(field: &Field) -> syn::Result<Self> {
+        let ident = field.ident.clone().expect("named field");
+        let (inner, optional) = match option_inner(&field.ty) {
+            Some(inner) => (inner, true),
+            None => (&field.ty, false),
+        };
+
+        let mut column = Column {
+            name: ident.to_string(),
+            field: ident,
+            sql_type: sql_type(inner).to_string(),
+            value_type: inner.clone(),
+            optional,
+            is_bool: is_bool(inner),
+            has_attribute: false,
+            not_null: false,
+            unique: false,
+            primary_key: false,
+            auto_increment: false,
+            default: None,
+            references: None,
+            index: false,
+            skip: false,
+        };
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("orm_column"))
+        {
+            column.has_attribute = true;
+            let mut seen: Vec<String> = Vec::new();
+            let mut explicit_type = false;
+
+            attr.parse_nested_meta(|meta| {
+                let option = meta
+                    .path
+                    .get_ident()
+                    .map(|ident| ident.to_string())
+                    .unwrap_or_default();
+                if seen.contains(&option) {
+                    return Err(meta.error(format!("duplicate `{option}`")));
+                }
+                seen.push(option.clone());
+
+                match option.as_str() {
+                    "type" => {
+                        let lit: LitStr = meta.value()?.parse()?;
+                        column.sql_type = lit.value();
+                        explicit_type = true;
+                    }
+                    "rename" => {
+                        let lit: LitStr = meta.value()?.parse()?;
+                        if lit.value().is_empty() {
+                            return Err(syn::Error::new(
+                                lit.span(),
+                                "`rename` needs a column name",
+                            ));
+                        }
+                        column.name = lit.value();
+                    }
+                    "default" => {
+                        let expr: Expr = meta.value()?.parse()?;
+                        column.default = Some(default_literal(&expr)?);
+                    }
+                    "references" => {
+                        let content;
+                        syn::parenthesized!(content in meta.input);
+                        let table: Ident = content.parse()?;
+                        content.parse::<syn::Token![.]>()?;
+                        let referenced: Ident = content.parse()?;
+                        if !content.is_empty() {
+                            return Err(content.error("expected `references(table.column)`"));
+                        }
+                        column.references = Some((table.to_string(), referenced.to_string()));
+                    }
+                    "not_null" => column.not_null = true,
+                    "unique" => column.unique = true,
+                    "primary_key" => column.primary_key = true,
+                    "auto_increment" => column.auto_increment = true,
+                    "index" => column.index = true,
+                    "skip" => column.skip = true,
+                    _ => {
+                        return Err(meta.error(format!(
+                            "unknown `orm_column` option, expected one of {OPTIONS}"
+                        )))
+                    }
+                }
+                Ok(())
+            })?;
+
+            column.validate(attr.span(), &seen, explicit_type)?;
+        }
+
+        Ok(column)
+    }
+
+    fn validate(
+        &self,
+        span: proc_macro2::Span,
+        seen: &[String],
+        explicit_type: bool,
+    ) -> syn::Result<()> {
+        let error = |message: &str| Err(syn::Error::new(span, message));
+
+        if self.skip && seen.len() > 1 {
+            return error("`skip` cannot be combined with other column options");
+        }
+        if self.auto_increment && !self.primary_key {
+            return error("`auto_increment` requires `primary_key`");
+        }
+        if self.auto_increment && !self.sql_type.trim().eq_ignore_ascii_case("INTEGER") {
+            return error(if explicit_type {
+                "`auto_increment` needs `type = \"INTEGER\"`"
+            } else {
+                "`auto_increment` needs an integer field"
+            });
+        }
+        if self.not_null && self.optional {
+            return error("`not_null` conflicts with an `Option` field, which can be NULL");
+        }
+        if self.primary_key && self.default.is_some() {
+            return error("a `primary_key` cannot have a `default`");
+        }
+        if self.index && (self.unique || self.primary_key) {
+            return error("`unique` and `primary_key` columns are indexed already, remove `index`");
+        }
+        Ok(())
+    }
+
+    /// The column's part of `CREATE TABLE`.
+    pub fn definition(&self, implicit_primary_key: bool) -> String {
+        if implicit_primary_key && !self.has_attribute {
+            return format!("{} INTEGER PRIMARY KEY AUTOINCREMENT", self.name);
+        }
+
+        let mut definition = format!("{} {}", self.name, self.sql_type);
+        if self.primary_key {
+            definition.push_str(" PRIMARY KEY");
+        }
+        if self.auto_increment {
+            definition.push_str(" AUTOINCREMENT");
+        }
+        if self.not_null {
+            definition.push_str(" NOT NULL");
+        }
+        if self.unique {
+            definition.push_str(" UNIQUE");
+        }
+        if let Some(default) = &self.default {
+            definition.push_str(&format!(" DEFAULT {default}"));
+        }
+        if let Some((table, column)) = &self.references {
+            definition.push_str(&format!(" REFERENCES {table}({column})"));
+        }
+        definition
+    }
+}
+
+/// `T` of an `Option<T>`.
+pub fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(GenericArgument::Type(inner)) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// SQLite type for a Rust type, TEXT for anything serialized as a string.
+fn sql_type(ty: &Type) -> &'static str {
+    match type_name(ty).as_str() {
+        "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "isize" | "usize" => {
+            "INTEGER"
+        }
+        "f32" | "f64" => "REAL",
+        "bool" => "BOOLEAN",
+        "Vec" if is_bytes(ty) => "BLOB",
+        _ => "TEXT",
+    }
+}
+
+fn is_bytes(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    let Some(segment) = type_path.path.segments.last() else {
+        return false;
+    };
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => matches!(
+            args.args.first(),
+            Some(GenericArgument::Type(inner)) if type_name(inner) == "u8"
+        ),
+        _ => false,
+    }
+}
+
+fn is_bool(ty: &Type) -> bool {
+    type_name(ty) == "bool"
+}
+
+/// Renders `default = ...` as an SQL literal.
+fn default_literal(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Str(s) => Ok(format!("'{}'", s.value().replace('\'', "''"))),
+            Lit::Int(i) => Ok(i.base10_digits().to_string()),
+            Lit::Float(f) => Ok(f.base10_digits().to_string()),
+            Lit::Bool(b) => Ok(if b.value { "1" } else { "0" }.to_string()),
+            other => Err(syn::Error::new(
+                other.span(),
+                "`default` must be a string, number or bool literal",
+            )),
+        },
+        Expr::Unary(unary)
+            if matches!(unary.op, UnOp::Neg(_))
+                && matches!(&*unary.expr, Expr::Lit(lit) if matches!(lit.lit, Lit::Int(_) | Lit::Float(_))) =>
+        {
+            Ok(format!("-{}", default_literal(&unary.expr)?))
+        }
+        other => Err(syn::Error::new(
+            other.span(),
+            "`default` must be a string, number or bool literal",
+        )),
+    }
+}
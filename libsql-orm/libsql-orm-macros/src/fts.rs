@@ -0,0 +1,81 @@
+//! Parsing of the `#[fts(...)]` struct attribute
+
+use syn::{Attribute, Ident, LitStr};
+
+use crate::column::Column;
+
+/// The FTS5 index of a model
+pub struct Fts {
+    /// Indexed column names, in declaration order.
+    pub columns: Vec<String>,
+    pub tokenize: Option<String>,
+}
+
+impl Fts {
+    /// Reads `#[fts(field, ..., tokenize = "...")]`, checking the fields
+    /// against the model's columns.
+    pub fn parse(
+        attrs: &[Attribute],
+        columns: &[Column],
+        key_field: &Ident,
+    ) -> syn::Result<Option<Self>> {
+        let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("fts")) else {
+            return Ok(None);
+        };
+
+        let mut fields: Vec<Ident> = Vec::new();
+        let mut tokenize = None;
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("tokenize") {
+                let lit: LitStr = meta.value()?.parse()?;
+                tokenize = Some(lit.value());
+                return Ok(());
+            }
+            let Some(ident) = meta.path.get_ident() else {
+                return Err(meta.error("expected a field name or `tokenize = \"...\"`"));
+            };
+            if fields.contains(ident) {
+                return Err(meta.error(format!("duplicate `{ident}`")));
+            }
+            fields.push(ident.clone());
+            Ok(())
+        })?;
+
+        if fields.is_empty() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "name the fields to index, e.g. `#[fts(title, body)]`",
+            ));
+        }
+
+        let mut names = Vec::new();
+        for field in &fields {
+            match columns.iter().find(|column| column.field == *field) {
+                Some(column) if column.skip => {
+                    return Err(syn::Error::new(
+                        field.span(),
+                        "skipped fields cannot be indexed",
+                    ))
+                }
+                Some(_) if field == key_field => {
+                    return Err(syn::Error::new(
+                        field.span(),
+                        "the primary key is the FTS rowid, index text fields",
+                    ))
+                }
+                Some(column) => names.push(column.name.clone()),
+                None => {
+                    return Err(syn::Error::new(
+                        field.span(),
+                        format!("no field named `{field}`"),
+                    ))
+                }
+            }
+        }
+
+        Ok(Some(Fts {
+            columns: names,
+            tokenize,
+        }))
+    }
+}
@@ -57,8 +57,14 @@
 //!     #[orm_column(not_null, unique)]
 //!     pub email: String,
 //!     
-//!     #[orm_column(type = "TEXT DEFAULT 'active'")]
+//!     #[orm_column(default = "active", index)]
 //!     pub status: String,
+//!
+//!     #[orm_column(references(teams.id))]
+//!     pub team_id: Option<i64>,
+//!
+//!     #[orm_column(rename = "display_name")]
+//!     pub name: String,
 //! }
 //! ```
 //!
@@ -71,14 +77,21 @@
 //! ```rust
 //! use libsql_orm::{generate_migration, MigrationManager};
 //!
-//! let migration = generate_migration!(User);
+//! let migration = generate_migration!(User, 1);
 //! let manager = MigrationManager::new(db);
 //! manager.execute_migration(&migration).await?;
 //! ```
 
+mod column;
+mod fts;
+mod relation;
+
+use column::Column;
+use fts::Fts;
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, Attribute, Data, DeriveInput, Field, Fields, Lit, Type};
+use quote::{format_ident, quote};
+use relation::{Kind, Relation};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, Lit};
 
 /// Column attribute macro for defining SQL column properties
 ///
@@ -90,6 +103,15 @@ use syn::{parse_macro_input, Attribute, Data, DeriveInput, Field, Fields, Lit, T
 /// - `unique` - Add UNIQUE constraint  
 /// - `primary_key` - Mark as PRIMARY KEY
 /// - `auto_increment` - Add AUTOINCREMENT (for INTEGER PRIMARY KEY)
+/// - `default = value` - DEFAULT from a string, number or bool literal
+/// - `references(table.column)` - Add a foreign key
+/// - `index` - Create an index on the column
+/// - `skip` - Leave the field out of the table (add `#[serde(default)]` to
+///   non-`Option` fields so rows still deserialize)
+/// - `rename = "name"` - Store the field under another column name
+///
+/// Invalid combinations, such as `auto_increment` without `primary_key`,
+/// fail to compile.
 ///
 /// # Examples:
 ///
@@ -108,8 +130,7 @@ use syn::{parse_macro_input, Attribute, Data, DeriveInput, Field, Fields, Lit, T
 /// ```
 #[proc_macro_attribute]
 pub fn orm_column(_args: TokenStream, input: TokenStream) -> TokenStream {
-    // For now, just return the input unchanged
-    // We'll parse the column attributes in the Model macro
+    // The attribute is read by the Model derive
     input
 }
 
@@ -122,6 +143,16 @@ pub fn orm_column(_args: TokenStream, input: TokenStream) -> TokenStream {
 /// # Attributes:
 /// - `#[table_name("custom_name")]` - Specify custom table name
 /// - `#[orm_column(...)]` - Configure column properties
+/// - `#[has_many(name = Model, foreign_key = "column")]` - Generate a
+///   `NAME` relation and a `name(&db)` method returning the children
+/// - `#[belongs_to(name = Model, foreign_key = "column")]` - Same for the
+///   parent, `foreign_key` defaults to `name_id`
+/// - `#[fts(field, ..., tokenize = "porter unicode61")]` - FTS5 index for
+///   `full_text_search`
+///
+/// Non-generic models also get a `<Model>Columns` struct and a `COLUMNS`
+/// constant with one typed `Column` per stored field, e.g.
+/// `User::COLUMNS.email.eq("a@b.c")`.
 ///
 /// # Examples:
 ///
@@ -137,9 +168,16 @@ pub fn orm_column(_args: TokenStream, input: TokenStream) -> TokenStream {
 ///     pub email: String,
 /// }
 /// ```
-#[proc_macro_derive(Model, attributes(table_name, orm_column))]
+#[proc_macro_derive(Model, attributes(table_name, orm_column, has_many, belongs_to, fts))]
 pub fn derive_model(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    match expand_model(input) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn expand_model(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = input.ident;
 
     // Extract table name from attributes or use default
@@ -148,38 +186,172 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
 
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    // Extract field names and column metadata for columns
-    let (field_names, column_definitions, boolean_field_names, boolean_flags) =
-        if let Data::Struct(data) = &input.data {
-            if let Fields::Named(fields) = &data.fields {
-                let mut field_names = Vec::new();
-                let mut column_defs = Vec::new();
-                let mut bool_field_names = Vec::new();
-                let mut bool_flags = Vec::new();
-
-                for field in &fields.named {
-                    let field_name = &field.ident;
-                    let field_name_str = quote! { stringify!(#field_name) };
-                    field_names.push(field_name_str);
-
-                    // Parse column attributes to get SQL definition
-                    let column_def = parse_column_definition(field);
-                    column_defs.push(column_def);
-
-                    // Extract field type information for conversion
-                    let field_type = &field.ty;
-                    let is_bool = is_boolean_type(field_type);
-                    bool_field_names.push(quote! { stringify!(#field_name) });
-                    bool_flags.push(is_bool);
-                }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
 
-                (field_names, column_defs, bool_field_names, bool_flags)
-            } else {
-                (vec![], vec![], vec![], vec![])
-            }
-        } else {
-            (vec![], vec![], vec![], vec![])
+    let mut columns: Vec<Column> = Vec::new();
+    let mut errors: Option<syn::Error> = None;
+    for field in fields {
+        match Column::parse(field) {
+            Ok(column) => columns.push(column),
+            Err(err) => match &mut errors {
+                Some(errors) => errors.combine(err),
+                None => errors = Some(err),
+            },
+        }
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    // The primary key is the field marked `primary_key`, or `id`
+    let mut primary_keys = columns.iter().filter(|column| column.primary_key);
+    let declared_key = primary_keys.next();
+    if let Some(second) = primary_keys.next() {
+        return Err(syn::Error::new(
+            second.field.span(),
+            "only one field can be the `primary_key`",
+        ));
+    }
+    let implicit_key = declared_key.is_none();
+    let key = declared_key.or_else(|| columns.iter().find(|column| column.field == "id"));
+    if let Some(key) = key {
+        if key.skip {
+            return Err(syn::Error::new(
+                key.field.span(),
+                "the primary key cannot be skipped",
+            ));
+        }
+        if !key.optional {
+            return Err(syn::Error::new(
+                key.field.span(),
+                "the primary key field must be an `Option<i64>`",
+            ));
+        }
+    }
+    let key_field = key
+        .map(|key| key.field.clone())
+        .unwrap_or_else(|| syn::Ident::new("id", proc_macro2::Span::call_site()));
+    let key_column = key
+        .map(|key| key.name.clone())
+        .unwrap_or_else(|| "id".to_string());
+
+    let stored: Vec<&Column> = columns.iter().filter(|column| !column.skip).collect();
+    let field_names: Vec<&String> = stored.iter().map(|column| &column.name).collect();
+    let column_definitions: Vec<String> = stored
+        .iter()
+        .map(|column| column.definition(implicit_key && column.field == key_field))
+        .collect();
+    let index_statements: Vec<String> = stored
+        .iter()
+        .filter(|column| column.index)
+        .map(|column| {
+            format!(
+                "CREATE INDEX IF NOT EXISTS idx_{table_name}_{name} ON {table_name}({name})",
+                name = column.name
+            )
+        })
+        .collect();
+
+    let boolean_field_names: Vec<String> = columns
+        .iter()
+        .map(|column| column.field.to_string())
+        .collect();
+    let boolean_flags: Vec<bool> = columns.iter().map(|column| column.is_bool).collect();
+
+    let renamed: Vec<&&Column> = stored
+        .iter()
+        .filter(|column| column.field != column.name.as_str())
+        .collect();
+    let renamed_fields: Vec<String> = renamed
+        .iter()
+        .map(|column| column.field.to_string())
+        .collect();
+    let renamed_columns: Vec<&String> = renamed.iter().map(|column| &column.name).collect();
+    let skipped_fields: Vec<String> = columns
+        .iter()
+        .filter(|column| column.skip)
+        .map(|column| column.field.to_string())
+        .collect();
+
+    let fts = Fts::parse(&input.attrs, &columns, &key_field)?;
+    let fts_methods = fts.map(|fts| {
+        let fts_columns = &fts.columns;
+        let tokenize = match &fts.tokenize {
+            Some(tokenize) => quote! { Some(#tokenize) },
+            None => quote! { None },
         };
+        quote! {
+            fn fts_columns() -> Vec<&'static str> {
+                vec![#(#fts_columns),*]
+            }
+
+            fn fts_migration_sql() -> Option<String> {
+                Some(libsql_orm::fts::migration_sql(
+                    Self::table_name(),
+                    Self::primary_key(),
+                    &[#(#fts_columns),*],
+                    #tokenize,
+                ))
+            }
+        }
+    });
+
+    let relations = Relation::parse_all(&input.attrs, &name)?;
+    for relation in &relations {
+        if relation.kind == Kind::BelongsTo
+            && !stored
+                .iter()
+                .any(|column| column.name == relation.foreign_key)
+        {
+            return Err(syn::Error::new(
+                relation.foreign_key_span.unwrap_or(relation.name.span()),
+                format!(
+                    "`{}` has no `{}` column, set it with `foreign_key = \"column\"`",
+                    name, relation.foreign_key
+                ),
+            ));
+        }
+    }
+    let relation_items = relations.iter().map(Relation::expand);
+    let relations_impl = (!relations.is_empty()).then(|| {
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                #(#relation_items)*
+            }
+        }
+    });
+
+    // Typed columns, left out for generic models
+    let columns_items = input.generics.params.is_empty().then(|| {
+        let vis = &input.vis;
+        let columns_struct = format_ident!("{}Columns", name);
+        let doc = format!("Typed columns of [`{name}`], see `{name}::COLUMNS`");
+        let fields: Vec<&syn::Ident> = stored.iter().map(|column| &column.field).collect();
+        let types: Vec<&syn::Type> = stored.iter().map(|column| &column.value_type).collect();
+        let names: Vec<&String> = stored.iter().map(|column| &column.name).collect();
+        quote! {
+            #[doc = #doc]
+            #[allow(dead_code)]
+            #[derive(Debug, Clone, Copy)]
+            #vis struct #columns_struct {
+                #( pub #fields: libsql_orm::Column<#name, #types>, )*
+            }
+
+            #[allow(dead_code)]
+            impl #name {
+                /// Columns for filters and sorts checked at compile time
+                pub const COLUMNS: #columns_struct = #columns_struct {
+                    #( #fields: libsql_orm::Column::new(#names), )*
+                };
+            }
+        }
+    });
 
     let expanded = quote! {
         impl #impl_generics libsql_orm::Model for #name #ty_generics #where_clause {
@@ -187,12 +359,16 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
                 #table_name
             }
 
+            fn primary_key() -> &'static str {
+                #key_column
+            }
+
             fn get_primary_key(&self) -> Option<i64> {
-                self.id
+                self.#key_field
             }
 
             fn set_primary_key(&mut self, id: i64) {
-                self.id = Some(id);
+                self.#key_field = Some(id);
             }
 
             fn columns() -> Vec<&'static str> {
@@ -201,14 +377,25 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
 
             /// Generate SQL for creating the table
             fn migration_sql() -> String {
-                let columns = vec![#(#column_definitions),*];
-                format!(
+                let columns: Vec<&str> = vec![#(#column_definitions),*];
+                let mut sql = format!(
                     "CREATE TABLE IF NOT EXISTS {} (\n    {}\n)",
                     Self::table_name(),
                     columns.join(",\n    ")
-                )
+                );
+                #(
+                    sql.push_str(";\n");
+                    sql.push_str(#index_statements);
+                )*
+                if let Some(fts) = Self::fts_migration_sql() {
+                    sql.push_str(";\n");
+                    sql.push_str(&fts);
+                }
+                sql
             }
 
+            #fts_methods
+
             fn to_map(&self) -> libsql_orm::Result<std::collections::HashMap<String, libsql_orm::Value>> {
                 use serde_json;
                 let json = serde_json::to_value(self)?;
@@ -234,11 +421,25 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
                     };
                     result.insert(k, value);
                 }
+                #(
+                    if let Some(value) = result.remove(#renamed_fields) {
+                        result.insert(#renamed_columns.to_string(), value);
+                    }
+                )*
+                #(
+                    result.remove(#skipped_fields);
+                )*
                 Ok(result)
             }
 
             fn from_map(map: std::collections::HashMap<String, libsql_orm::Value>) -> libsql_orm::Result<Self> {
                 use serde_json;
+                let mut map = map;
+                #(
+                    if let Some(value) = map.remove(#renamed_columns) {
+                        map.insert(#renamed_fields.to_string(), value);
+                    }
+                )*
                 let mut json_map = serde_json::Map::new();
 
                 for (k, v) in map {
@@ -282,82 +483,14 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
             }
         }
 
-        // Note: Clone is already derived in the struct definition
-    };
+        #relations_impl
 
-    TokenStream::from(expanded)
-}
+        #columns_items
 
-/// Parse column definition from field attributes
-fn parse_column_definition(field: &Field) -> proc_macro2::TokenStream {
-    let field_name = &field.ident;
-    let field_name_str = field_name.as_ref().unwrap().to_string();
-
-    // Default column definitions based on field type
-    let default_def = match &field.ty {
-        Type::Path(type_path) => {
-            let type_name = &type_path.path.segments.last().unwrap().ident;
-            match type_name.to_string().as_str() {
-                "i64" => format!("{field_name_str} INTEGER"),
-                "i32" => format!("{field_name_str} INTEGER"),
-                "f64" => format!("{field_name_str} REAL"),
-                "f32" => format!("{field_name_str} REAL"),
-                "bool" => format!("{field_name_str} BOOLEAN"),
-                "String" => format!("{field_name_str} TEXT"),
-                _ => format!("{field_name_str} TEXT"),
-            }
-        }
-        _ => format!("{field_name_str} TEXT"),
+        // Note: Clone is already derived in the struct definition
     };
 
-    // Check for orm_column attributes
-    for attr in &field.attrs {
-        if attr.path().is_ident("orm_column") {
-            let mut column_type = None;
-            let mut not_null = false;
-            let mut unique = false;
-            let mut primary_key = false;
-            let mut auto_increment = false;
-
-            // Parse the nested meta items
-            let _ = attr.parse_nested_meta(|meta| {
-                if meta.path.is_ident("type") {
-                    if let Ok(value) = meta.value() {
-                        let lit: Lit = value.parse()?;
-                        if let Lit::Str(lit_str) = lit {
-                            column_type = Some(lit_str.value());
-                        }
-                    }
-                } else if meta.path.is_ident("not_null") {
-                    not_null = true;
-                } else if meta.path.is_ident("unique") {
-                    unique = true;
-                } else if meta.path.is_ident("primary_key") {
-                    primary_key = true;
-                } else if meta.path.is_ident("auto_increment") {
-                    auto_increment = true;
-                }
-                Ok(())
-            });
-
-            let mut column_def = column_type.unwrap_or_else(|| default_def.clone());
-            if primary_key {
-                column_def = format!("{column_def} PRIMARY KEY");
-            }
-            if auto_increment {
-                column_def = format!("{column_def} AUTOINCREMENT");
-            }
-            if not_null {
-                column_def = format!("{column_def} NOT NULL");
-            }
-            if unique {
-                column_def = format!("{column_def} UNIQUE");
-            }
-            return quote! { #column_def };
-        }
-    }
-    // Return default definition
-    quote! { #default_def }
+    Ok(expanded)
 }
 
 /// Extract table name from struct attributes
@@ -372,21 +505,12 @@ fn extract_table_name(attrs: &[Attribute]) -> Option<String> {
     None
 }
 
-/// Check if a type is a boolean type
-fn is_boolean_type(ty: &Type) -> bool {
-    if let Type::Path(type_path) = ty {
-        if let Some(segment) = type_path.path.segments.last() {
-            let type_name = &segment.ident;
-            return type_name == "bool";
-        }
-    }
-    false
-}
-
 /// Macro to generate migration from a model
 ///
 /// Creates a migration instance from a model's schema definition. The migration
-/// will contain the SQL necessary to create the table for the model.
+/// will contain the SQL necessary to create the table for the model. Pass the
+/// version as second argument, migrations without one are refused by
+/// `MigrationManager`.
 ///
 /// # Examples:
 ///
@@ -394,7 +518,7 @@ fn is_boolean_type(ty: &Type) -> bool {
 /// use libsql_orm::{generate_migration, MigrationManager};
 ///
 /// // Generate migration for User model
-/// let user_migration = generate_migration!(User);
+/// let user_migration = generate_migration!(User, 1);
 ///
 /// // Execute the migration
 /// let manager = MigrationManager::new(db);
@@ -402,17 +526,41 @@ fn is_boolean_type(ty: &Type) -> bool {
 /// ```
 #[proc_macro]
 pub fn generate_migration(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as syn::Ident);
+    let args = parse_macro_input!(input as MigrationArgs);
+    let model = args.model;
+    let version = match args.version {
+        Some(version) => quote! { .with_version(#version) },
+        None => quote! {},
+    };
 
     let expanded = quote! {
         {
-            let sql = #input::migration_sql();
+            let sql = #model::migration_sql();
             libsql_orm::MigrationManager::create_migration(
-                &format!("create_table_{}", #input::table_name()),
+                &format!("create_table_{}", #model::table_name()),
                 &sql
             )
+            #version
         }
     };
 
     TokenStream::from(expanded)
 }
+
+/// `Model` or `Model, version`
+struct MigrationArgs {
+    model: syn::Ident,
+    version: Option<syn::LitInt>,
+}
+
+impl syn::parse::Parse for MigrationArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let model = input.parse()?;
+        let version = if input.parse::<Option<syn::Token![,]>>()?.is_some() && !input.is_empty() {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        Ok(MigrationArgs { model, version })
+    }
+}
@@ -0,0 +1,143 @@
+//! Parsing of `#[has_many(...)]` and `#[belongs_to(...)]` struct attributes
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{Attribute, Ident, LitStr, Type};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    HasMany,
+    BelongsTo,
+}
+
+/// A relation declared on a model
+pub struct Relation {
+    pub kind: Kind,
+    /// Name of the accessor method.
+    pub name: Ident,
+    /// The related model.
+    pub target: Type,
+    /// Column holding the key, on the child model.
+    pub foreign_key: String,
+    /// Where `foreign_key` was given, `None` for the default.
+    pub foreign_key_span: Option<Span>,
+}
+
+impl Relation {
+    /// Reads every relation attribute of a model named `model`.
+    pub fn parse_all(attrs: &[Attribute], model: &Ident) -> syn::Result<Vec<Self>> {
+        let mut relations = Vec::new();
+        for attr in attrs {
+            let kind = if attr.path().is_ident("has_many") {
+                Kind::HasMany
+            } else if attr.path().is_ident("belongs_to") {
+                Kind::BelongsTo
+            } else {
+                continue;
+            };
+
+            let mut relation: Option<Relation> = None;
+            attr.parse_nested_meta(|meta| {
+                let Some(ident) = meta.path.get_ident().cloned() else {
+                    return Err(meta.error("expected `name = Model`"));
+                };
+                if ident == "foreign_key" {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    let Some(relation) = relation.as_mut() else {
+                        return Err(meta.error("`foreign_key` goes after `name = Model`"));
+                    };
+                    if relation.foreign_key_span.is_some() {
+                        return Err(meta.error("duplicate `foreign_key`"));
+                    }
+                    relation.foreign_key = lit.value();
+                    relation.foreign_key_span = Some(lit.span());
+                    return Ok(());
+                }
+                if relation.is_some() {
+                    return Err(meta
+                        .error("one relation per attribute, expected `foreign_key = \"column\"`"));
+                }
+                let target: Type = meta.value()?.parse()?;
+                let foreign_key = match kind {
+                    Kind::HasMany => format!("{}_id", snake_case(&model.to_string())),
+                    Kind::BelongsTo => format!("{ident}_id"),
+                };
+                relation = Some(Relation {
+                    kind,
+                    name: ident,
+                    target,
+                    foreign_key,
+                    foreign_key_span: None,
+                });
+                Ok(())
+            })?;
+
+            match relation {
+                Some(relation) => relations.push(relation),
+                None => {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "expected `name = Model`, e.g. `#[has_many(books = Book)]`",
+                    ))
+                }
+            }
+        }
+        Ok(relations)
+    }
+
+    /// The relation constant and accessor method.
+    pub fn expand(&self) -> TokenStream2 {
+        let name = &self.name;
+        let target = &self.target;
+        let foreign_key = &self.foreign_key;
+        let constant = format_ident!("{}", name.to_string().to_uppercase());
+
+        match self.kind {
+            Kind::HasMany => {
+                let doc = format!("The `{name}` relation, for eager loading with `.with(...)`");
+                quote! {
+                    #[doc = #doc]
+                    pub const #constant: libsql_orm::HasMany<Self, #target> =
+                        libsql_orm::HasMany::new(#foreign_key);
+
+                    pub async fn #name(
+                        &self,
+                        db: &libsql_orm::Database,
+                    ) -> libsql_orm::Result<Vec<#target>> {
+                        Self::#constant.get(self, db).await
+                    }
+                }
+            }
+            Kind::BelongsTo => {
+                let doc = format!("The `{name}` relation, for eager loading with `.with(...)`");
+                quote! {
+                    #[doc = #doc]
+                    pub const #constant: libsql_orm::BelongsTo<Self, #target> =
+                        libsql_orm::BelongsTo::new(#foreign_key);
+
+                    pub async fn #name(
+                        &self,
+                        db: &libsql_orm::Database,
+                    ) -> libsql_orm::Result<Option<#target>> {
+                        Self::#constant.get(self, db).await
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
@@ -0,0 +1,171 @@
+//! Typed column identifiers
+//!
+//! `#[derive(Model)]` generates a `<Model>Columns` struct with one [`Column`]
+//! per stored field and a `COLUMNS` constant holding it. Filters built from
+//! them name columns that exist and take values of the field's type, so
+//! typos and mismatched values fail to compile:
+//!
+//! ```rust
+//! use libsql_orm::{Database, Model};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+//! #[table_name("tasks")]
+//! struct Task {
+//!     pub id: Option<i64>,
+//!     pub owner_id: String,
+//!     pub list: Option<String>,
+//!     pub done: bool,
+//! }
+//!
+//! async fn example(db: &Database) -> libsql_orm::Result<()> {
+//!     let columns = Task::COLUMNS;
+//!     let open = Task::find_where(
+//!         columns
+//!             .owner_id
+//!             .eq("alice")
+//!             .and_with(columns.done.eq(false))
+//!             .and_with(columns.list.is_null()),
+//!         db,
+//!     )
+//!     .await?;
+//!     // columns.done.eq("yes") and Task::COLUMNS.owner does not compile
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Optional fields take the inner type, `None` is matched with
+//! [`Column::is_null`]. Values are converted like [`Model::to_map`] converts
+//! fields, so dates and enums compare the way they are stored.
+
+use std::marker::PhantomData;
+
+use serde::Serialize;
+
+use crate::{Filter, FilterOperator, Model, Sort, Value};
+
+/// A column of `M` holding values of type `T`
+pub struct Column<M, T> {
+    name: &'static str,
+    _types: PhantomData<fn() -> (M, T)>,
+}
+
+impl<M, T> Column<M, T> {
+    pub const fn new(name: &'static str) -> Self {
+        Column {
+            name,
+            _types: PhantomData,
+        }
+    }
+
+    /// The column name in the table
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<M, T> Clone for Column<M, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M, T> Copy for Column<M, T> {}
+
+impl<M, T> std::fmt::Debug for Column<M, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Column").field(&self.name).finish()
+    }
+}
+
+impl<M: Model, T: Serialize> Column<M, T> {
+    /// `table.column`, for queries joining other tables
+    pub fn qualified(&self) -> String {
+        format!("{}.{}", M::table_name(), self.name)
+    }
+
+    pub fn eq(&self, value: impl Into<T>) -> FilterOperator {
+        self.filter(Filter::eq(self.name, value_of(value.into())))
+    }
+
+    pub fn ne(&self, value: impl Into<T>) -> FilterOperator {
+        self.filter(Filter::ne(self.name, value_of(value.into())))
+    }
+
+    pub fn lt(&self, value: impl Into<T>) -> FilterOperator {
+        self.filter(Filter::lt(self.name, value_of(value.into())))
+    }
+
+    pub fn le(&self, value: impl Into<T>) -> FilterOperator {
+        self.filter(Filter::le(self.name, value_of(value.into())))
+    }
+
+    pub fn gt(&self, value: impl Into<T>) -> FilterOperator {
+        self.filter(Filter::gt(self.name, value_of(value.into())))
+    }
+
+    pub fn ge(&self, value: impl Into<T>) -> FilterOperator {
+        self.filter(Filter::ge(self.name, value_of(value.into())))
+    }
+
+    pub fn between(&self, min: impl Into<T>, max: impl Into<T>) -> FilterOperator {
+        self.filter(Filter::between(
+            self.name,
+            value_of(min.into()),
+            value_of(max.into()),
+        ))
+    }
+
+    pub fn in_values<V: Into<T>>(&self, values: impl IntoIterator<Item = V>) -> FilterOperator {
+        self.filter(Filter::in_values(self.name, values_of(values)))
+    }
+
+    pub fn not_in_values<V: Into<T>>(&self, values: impl IntoIterator<Item = V>) -> FilterOperator {
+        self.filter(Filter::not_in_values(self.name, values_of(values)))
+    }
+
+    pub fn is_null(&self) -> FilterOperator {
+        self.filter(Filter::is_null(self.name))
+    }
+
+    pub fn is_not_null(&self) -> FilterOperator {
+        self.filter(Filter::is_not_null(self.name))
+    }
+
+    pub fn asc(&self) -> Sort {
+        Sort::asc(self.name)
+    }
+
+    pub fn desc(&self) -> Sort {
+        Sort::desc(self.name)
+    }
+
+    fn filter(&self, filter: Filter) -> FilterOperator {
+        FilterOperator::Single(filter)
+    }
+}
+
+impl<M: Model> Column<M, String> {
+    pub fn like(&self, pattern: impl Into<String>) -> FilterOperator {
+        FilterOperator::Single(Filter::like(self.name, pattern))
+    }
+
+    pub fn not_like(&self, pattern: impl Into<String>) -> FilterOperator {
+        FilterOperator::Single(Filter::not_like(self.name, pattern))
+    }
+}
+
+/// A field value as stored, the same conversion as the derived `to_map`.
+fn value_of<T: Serialize>(value: T) -> Value {
+    // Field types serialize to JSON, or the model could not be saved either
+    serde_json::to_value(value)
+        .map(Value::from)
+        .unwrap_or(Value::Null)
+}
+
+fn values_of<T: Serialize, V: Into<T>>(values: impl IntoIterator<Item = V>) -> Vec<Value> {
+    values
+        .into_iter()
+        .map(|value| value_of(value.into()))
+        .collect()
+}
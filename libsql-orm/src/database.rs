@@ -1,10 +1,33 @@
 //! Database connection and query execution
 //!
 //! This module handles the connection to libsql databases and provides
-//! query execution capabilities for Cloudflare Workers.
+//! query execution capabilities for Cloudflare Workers. With the `local`
+//! feature, which has to be enabled explicitly, it can also open SQLite files
+//! and in-memory databases, and with `replication` embedded replicas of a
+//! remote database.
+
+#[cfg(feature = "local")]
+use std::sync::Arc;
+#[cfg(feature = "local")]
+use std::time::Duration;
+
+use std::future::Future;
 
 use libsql::{Builder, Connection, Rows};
 
+/// How long a local connection waits for another one's write lock before
+/// failing with `SQLITE_BUSY`.
+#[cfg(feature = "local")]
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A connection to a local database that waits out other writers
+#[cfg(feature = "local")]
+fn connect_local(db: &libsql::Database) -> std::result::Result<Connection, libsql::Error> {
+    let conn = db.connect()?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
 /// Database connection wrapper for libsql in Cloudflare Workers
 ///
 /// Provides a high-level interface for connecting to and interacting with
@@ -24,8 +47,22 @@ use libsql::{Builder, Connection, Rows};
 ///     Ok(())
 /// }
 /// ```
+#[derive(Clone)]
 pub struct Database {
     pub inner: Connection,
+    source: Source,
+}
+
+/// Where a [`Database`] connection comes from
+#[derive(Clone)]
+enum Source {
+    Remote,
+    /// In-memory databases are shared through one connection.
+    #[cfg(feature = "local")]
+    Memory,
+    /// Local files and embedded replicas.
+    #[cfg(feature = "local")]
+    Local(Arc<libsql::Database>),
 }
 
 impl Database {
@@ -62,11 +99,230 @@ impl Database {
 
         // Test the connection
         match conn.execute("SELECT 1", ()).await {
-            Ok(_) => Ok(Database { inner: conn }),
+            Ok(_) => Ok(Database {
+                inner: conn,
+                source: Source::Remote,
+            }),
             Err(e) => Err(e),
         }
     }
 
+    /// Opens a database from a URL
+    ///
+    /// `:memory:` opens an in-memory database, `file:<path>` a local SQLite
+    /// file. Anything else is treated as a remote URL and opened with
+    /// [`new_connect`](Self::new_connect).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let db = Database::open("file:rezi.db", "").await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn open(url: &str, token: &str) -> std::result::Result<Self, libsql::Error> {
+        #[cfg(feature = "local")]
+        {
+            if url == ":memory:" {
+                return Self::open_memory().await;
+            }
+            if let Some(path) = url.strip_prefix("file:") {
+                return Self::open_local(path.trim_start_matches("//")).await;
+            }
+        }
+        Self::new_connect(url, token).await
+    }
+
+    /// Opens or creates a local SQLite database file
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let db = Database::open_local("data/app.db").await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "local")]
+    pub async fn open_local(
+        path: impl AsRef<std::path::Path>,
+    ) -> std::result::Result<Self, libsql::Error> {
+        let db = Builder::new_local(path.as_ref()).build().await?;
+        Ok(Database {
+            inner: connect_local(&db)?,
+            source: Source::Local(Arc::new(db)),
+        })
+    }
+
+    /// Opens a fresh in-memory database
+    ///
+    /// The data lives as long as this `Database` or one of its clones, and is
+    /// only visible through them. Useful for tests.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let db = Database::open_memory().await?;
+    ///     db.get_connection()
+    ///         .execute("CREATE TABLE notes (id INTEGER PRIMARY KEY)", ())
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "local")]
+    pub async fn open_memory() -> std::result::Result<Self, libsql::Error> {
+        let db = Builder::new_local(":memory:").build().await?;
+        Ok(Database {
+            inner: db.connect()?,
+            source: Source::Memory,
+        })
+    }
+
+    /// Opens an embedded replica of a remote database
+    ///
+    /// Reads are served from the local file at `path`, writes go to the
+    /// remote primary. The replica is synced once before it is returned, call
+    /// [`sync`](Self::sync) to pull later changes.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let db = Database::open_replica(
+    ///         "replica.db",
+    ///         "libsql://your-db.turso.io",
+    ///         "your-auth-token",
+    ///     ).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "replication")]
+    pub async fn open_replica(
+        path: impl AsRef<std::path::Path>,
+        url: &str,
+        token: &str,
+    ) -> std::result::Result<Self, libsql::Error> {
+        let db = Builder::new_remote_replica(path.as_ref(), url.to_string(), token.to_string())
+            .build()
+            .await?;
+        db.sync().await?;
+        Ok(Database {
+            inner: connect_local(&db)?,
+            source: Source::Local(Arc::new(db)),
+        })
+    }
+
+    /// Pulls changes from the primary into an embedded replica
+    ///
+    /// Does nothing for databases that are not replicas.
+    #[cfg(feature = "replication")]
+    pub async fn sync(&self) -> std::result::Result<(), libsql::Error> {
+        if let Source::Local(db) = &self.source {
+            if let Err(err) = db.sync().await {
+                // Plain local databases have nothing to sync from
+                if !matches!(err, libsql::Error::SyncNotSupported(_)) {
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A separate connection to the same database
+    ///
+    /// Local files and replicas get a new connection, so transactions on one
+    /// do not interfere with the other. A writer waits up to five seconds for
+    /// another connection's transaction to finish. In-memory databases share their
+    /// connection, since a new one would see an empty database. Remote
+    /// connections are cheap handles and are shared as well.
+    pub fn connect(&self) -> std::result::Result<Self, libsql::Error> {
+        match &self.source {
+            #[cfg(feature = "local")]
+            Source::Local(db) => Ok(Database {
+                inner: connect_local(db)?,
+                source: self.source.clone(),
+            }),
+            _ => Ok(self.clone()),
+        }
+    }
+
+    /// Runs `f` in a transaction
+    ///
+    /// The transaction commits when `f` returns `Ok` and rolls back when it
+    /// returns `Err`. `f` gets a [`Database`] on the same connection, so every
+    /// `Model` method called with it takes part in the transaction. Calling
+    /// `transaction` again inside `f` nests with a savepoint, which rolls back
+    /// on its own without ending the outer transaction.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, Error};
+    ///
+    /// async fn example(db: &Database) -> Result<(), Error> {
+    ///     db.transaction(|tx| async move {
+    ///         tx.get_connection()
+    ///             .execute("UPDATE accounts SET balance = balance - 10 WHERE id = 1", ())
+    ///             .await?;
+    ///         tx.get_connection()
+    ///             .execute("UPDATE accounts SET balance = balance + 10 WHERE id = 2", ())
+    ///             .await?;
+    ///         Ok(())
+    ///     })
+    ///     .await
+    /// }
+    /// ```
+    pub async fn transaction<F, Fut, T, E>(&self, f: F) -> std::result::Result<T, E>
+    where
+        F: FnOnce(Database) -> Fut,
+        Fut: Future<Output = std::result::Result<T, E>>,
+        E: From<crate::Error>,
+    {
+        let (begin, commit, rollback) = if self.inner.is_autocommit() {
+            ("BEGIN", "COMMIT", "ROLLBACK")
+        } else {
+            (
+                "SAVEPOINT libsql_orm",
+                "RELEASE libsql_orm",
+                "ROLLBACK TO libsql_orm; RELEASE libsql_orm",
+            )
+        };
+
+        self.inner
+            .execute_batch(begin)
+            .await
+            .map_err(|err| E::from(err.into()))?;
+
+        match f(self.clone()).await {
+            Ok(value) => {
+                if let Err(err) = self.inner.execute_batch(commit).await {
+                    // A failed COMMIT leaves the transaction open
+                    if let Err(rollback_err) = self.inner.execute_batch(rollback).await {
+                        log::error!("Could not roll back transaction: {rollback_err}");
+                    }
+                    return Err(E::from(err.into()));
+                }
+                Ok(value)
+            }
+            Err(err) => {
+                if let Err(rollback_err) = self.inner.execute_batch(rollback).await {
+                    log::error!("Could not roll back transaction: {rollback_err}");
+                }
+                Err(err)
+            }
+        }
+    }
+
     /// Gets a reference to the underlying libsql connection
     ///
     /// This method provides direct access to the libsql connection for advanced use cases
@@ -13,6 +13,7 @@
 //! - **Not Found Errors**: Resource not found
 //! - **Pagination Errors**: Pagination parameter issues
 //! - **Query Errors**: Query building problems
+//! - **Migration Errors**: Edited, failed or concurrently run migrations
 //!
 //! # Examples
 //!
@@ -51,6 +52,8 @@ pub enum Error {
     Pagination(String),
     /// Query building error
     Query(String),
+    /// Migration error, e.g. an edited migration or a held lock
+    Migration(String),
     /// Worker environment error
     AnyhowError(String),
     /// Database error
@@ -71,6 +74,7 @@ impl fmt::Display for Error {
             Error::NotFound(msg) => write!(f, "Not found: {msg}"),
             Error::Pagination(msg) => write!(f, "Pagination error: {msg}"),
             Error::Query(msg) => write!(f, "Query error: {msg}"),
+            Error::Migration(msg) => write!(f, "Migration error: {msg}"),
             Error::AnyhowError(msg) => write!(f, "Anyhow error: {msg}"),
             Error::DatabaseError(msg) => write!(f, "Database error: {msg}"),
             Error::Generic(msg) => write!(f, "Error: {msg}"),
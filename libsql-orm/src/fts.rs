@@ -0,0 +1,243 @@
+//! FTS5 full-text search
+//!
+//! `#[fts(column, ...)]` on a model adds an external-content FTS5 table named
+//! `<table>_fts` to [`Model::migration_sql`], with triggers keeping it in sync
+//! on insert, update and delete. [`Model::full_text_search`] then ranks
+//! matches with bm25 and returns a snippet and highlighted columns for each.
+//!
+//! ```rust
+//! use libsql_orm::{Database, Model, Ranking};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+//! #[table_name("notes")]
+//! #[fts(title, body, tokenize = "porter unicode61")]
+//! struct Note {
+//!     pub id: Option<i64>,
+//!     pub title: String,
+//!     pub body: String,
+//! }
+//!
+//! async fn example(db: &Database) -> libsql_orm::Result<()> {
+//!     // Titles count ten times as much as bodies
+//!     let ranking = Ranking::Weighted(vec![10.0, 1.0]);
+//!     for hit in Note::full_text_search("tomato soup", ranking, db).await? {
+//!         println!("{} ({:.2}): {}", hit.model.title, hit.score, hit.snippet);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Queries are plain words, not FTS5 syntax: every word has to match, as a
+//! prefix, in any indexed column. Matches in snippets and highlights are
+//! wrapped in [`MATCH_START`] and [`MATCH_END`], see [`highlight_parts`].
+
+use std::collections::HashMap;
+
+use crate::{Database, FilterOperator, JoinType, Model, QueryBuilder, Result, Value};
+
+/// Marks the start of a match in snippets and highlights.
+pub const MATCH_START: &str = "\u{2}";
+/// Marks the end of a match in snippets and highlights.
+pub const MATCH_END: &str = "\u{3}";
+/// Tokens around the match in a snippet.
+const SNIPPET_TOKENS: u32 = 16;
+
+/// Order of full-text search results
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Ranking {
+    /// bm25 with every column weighted the same.
+    #[default]
+    Relevance,
+    /// bm25 with one weight per FTS column, in declaration order.
+    Weighted(Vec<f64>),
+    /// Newest rows first by primary key, ignoring relevance.
+    Recent,
+}
+
+/// A row matching a full-text search
+#[derive(Debug, Clone)]
+pub struct FullTextMatch<M> {
+    pub model: M,
+    /// Relevance, higher is better. Negated bm25.
+    pub score: f64,
+    /// The best matching part of the best matching column.
+    pub snippet: String,
+    /// Every non-NULL FTS column with its matches marked.
+    pub highlights: HashMap<String, String>,
+}
+
+impl<M> FullTextMatch<M> {
+    /// A column with its matches marked
+    pub fn highlight(&self, column: &str) -> Option<&str> {
+        self.highlights.get(column).map(String::as_str)
+    }
+}
+
+/// The FTS5 table and its sync triggers for `table`, indexing `columns`.
+/// Existing rows are indexed too, so this can be added to a table with data.
+pub fn migration_sql(
+    table: &str,
+    primary_key: &str,
+    columns: &[&str],
+    tokenize: Option<&str>,
+) -> String {
+    let fts = format!("{table}_fts");
+    let column_list = columns.join(", ");
+    let new_values = values("new", primary_key, columns);
+    let old_values = values("old", primary_key, columns);
+    let tokenize = tokenize
+        .map(|tokenize| format!(", tokenize = '{}'", tokenize.replace('\'', "''")))
+        .unwrap_or_default();
+
+    format!(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS {fts} USING fts5({column_list}, content = '{table}', content_rowid = '{primary_key}'{tokenize});\n\
+         CREATE TRIGGER IF NOT EXISTS {fts}_insert AFTER INSERT ON {table} BEGIN\n    \
+         INSERT INTO {fts} (rowid, {column_list}) VALUES ({new_values});\n\
+         END;\n\
+         CREATE TRIGGER IF NOT EXISTS {fts}_delete AFTER DELETE ON {table} BEGIN\n    \
+         INSERT INTO {fts} ({fts}, rowid, {column_list}) VALUES ('delete', {old_values});\n\
+         END;\n\
+         CREATE TRIGGER IF NOT EXISTS {fts}_update AFTER UPDATE ON {table} BEGIN\n    \
+         INSERT INTO {fts} ({fts}, rowid, {column_list}) VALUES ('delete', {old_values});\n    \
+         INSERT INTO {fts} (rowid, {column_list}) VALUES ({new_values});\n\
+         END;\n\
+         INSERT INTO {fts} ({fts}) VALUES ('rebuild')"
+    )
+}
+
+fn values(row: &str, primary_key: &str, columns: &[&str]) -> String {
+    std::iter::once(primary_key)
+        .chain(columns.iter().copied())
+        .map(|column| format!("{row}.{column}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Turns user input into an FTS5 query: every word quoted, as a prefix, all
+/// of them required. `None` if there is nothing to search for.
+pub fn match_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|word| word.replace('"', ""))
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .map(|word| format!("\"{word}\"*"))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Splits marked text into `(text, is_match)` parts, for rendering matches
+/// with escaping in between.
+pub fn highlight_parts(marked: &str) -> Vec<(&str, bool)> {
+    let mut parts = Vec::new();
+    let mut rest = marked;
+    while let Some(start) = rest.find(MATCH_START) {
+        if start > 0 {
+            parts.push((&rest[..start], false));
+        }
+        rest = &rest[start + MATCH_START.len()..];
+        let end = rest.find(MATCH_END).unwrap_or(rest.len());
+        parts.push((&rest[..end], true));
+        rest = rest.get(end + MATCH_END.len()..).unwrap_or_default();
+    }
+    if !rest.is_empty() {
+        parts.push((rest, false));
+    }
+    parts
+}
+
+/// Runs a full-text search over `M`'s FTS table, see
+/// [`Model::full_text_search_where`].
+pub(crate) async fn search<M: Model>(
+    query: &str,
+    ranking: Ranking,
+    filter: Option<FilterOperator>,
+    db: &Database,
+) -> Result<Vec<FullTextMatch<M>>> {
+    let columns = M::fts_columns();
+    if columns.is_empty() {
+        return Err(crate::Error::Query(format!(
+            "{} has no full-text index, add #[fts(...)] to the model",
+            M::table_name()
+        )));
+    }
+    let Some(match_query) = match_query(query) else {
+        return Ok(Vec::new());
+    };
+
+    let table = M::table_name();
+    let fts = format!("{table}_fts");
+    let bm25 = match &ranking {
+        Ranking::Weighted(weights) => {
+            if weights.len() != columns.len() {
+                return Err(crate::Error::Query(format!(
+                    "{} weights for {} FTS columns",
+                    weights.len(),
+                    columns.len()
+                )));
+            }
+            let weights: Vec<String> = weights.iter().map(f64::to_string).collect();
+            format!("bm25({fts}, {})", weights.join(", "))
+        }
+        _ => format!("bm25({fts})"),
+    };
+    let highlights: Vec<String> = (0..columns.len())
+        .map(|i| {
+            format!("highlight({fts}, {i}, '{MATCH_START}', '{MATCH_END}') AS _fts_highlight_{i}")
+        })
+        .collect();
+    let matches = format!(
+        "(SELECT rowid AS _fts_rowid, -{bm25} AS _fts_score, \
+         snippet({fts}, -1, '{MATCH_START}', '{MATCH_END}', '…', {SNIPPET_TOKENS}) AS _fts_snippet, \
+         {} FROM {fts} WHERE {fts} MATCH ?)",
+        highlights.join(", ")
+    );
+
+    let mut builder = QueryBuilder::new(table)
+        .select(vec![format!("{table}.*"), "_fts.*".to_string()])
+        .join_as(
+            JoinType::Inner,
+            matches,
+            "_fts",
+            format!("_fts._fts_rowid = {table}.{}", M::primary_key()),
+        )
+        .order_by(match ranking {
+            Ranking::Recent => crate::Sort::desc(format!("{table}.{}", M::primary_key())),
+            _ => crate::Sort::desc("_fts_score"),
+        });
+    if let Some(filter) = filter {
+        builder = builder.r#where(filter);
+    }
+    let (sql, where_params) = builder.build()?;
+    let mut params = vec![libsql::Value::Text(match_query)];
+    params.extend(where_params);
+
+    let mut rows = db.query(&sql, params).await?;
+    let mut results = Vec::new();
+    while let Some(row) = rows.next().await? {
+        let mut map = M::row_to_map(&row)?;
+        map.remove("_fts_rowid");
+        let score = match map.remove("_fts_score") {
+            Some(Value::Real(score)) => score,
+            Some(Value::Integer(score)) => score as f64,
+            _ => 0.0,
+        };
+        let snippet = match map.remove("_fts_snippet") {
+            Some(Value::Text(snippet)) => snippet,
+            _ => String::new(),
+        };
+        let mut highlighted = HashMap::new();
+        for (i, column) in columns.iter().enumerate() {
+            if let Some(Value::Text(text)) = map.remove(&format!("_fts_highlight_{i}")) {
+                highlighted.insert(column.to_string(), text);
+            }
+        }
+        results.push(FullTextMatch {
+            model: M::from_map(map)?,
+            score,
+            snippet,
+            highlights: highlighted,
+        });
+    }
+    Ok(results)
+}
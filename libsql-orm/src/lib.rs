@@ -194,24 +194,46 @@
 //!     Response::from_json(&users)
 //! }
 //! ```
+pub mod columns;
 pub mod database;
 pub mod error;
 pub mod filters;
+pub mod fts;
 pub mod macros;
+pub mod migrations;
 pub mod model;
 pub mod pagination;
+#[cfg(feature = "pool")]
+pub mod pool;
 pub mod query;
+pub mod relations;
+#[cfg(feature = "local")]
+pub mod schema;
 pub mod types;
 
 #[cfg(test)]
 mod tests;
 
+// Lets the derive macros resolve `libsql_orm::` paths in unit tests
+#[cfg(test)]
+extern crate self as libsql_orm;
+
+pub use columns::Column;
 pub use database::Database;
 pub use error::{Error, Result};
 pub use filters::{Filter, FilterOperator, SearchFilter, Sort};
+pub use fts::{FullTextMatch, Ranking};
+pub use migrations::{AppliedMigration, Migration, MigrationManager, MigrationReport};
 pub use model::Model;
 pub use pagination::{CursorPaginatedResult, CursorPagination, PaginatedResult, Pagination};
+#[cfg(feature = "pool")]
+pub use pool::{Pool, PoolConfig, PoolMetrics, PooledDatabase};
 pub use query::{QueryBuilder, QueryResult};
+pub use relations::{BelongsTo, HasMany, Relation, WithRelation};
+#[cfg(feature = "local")]
+pub use schema::{
+    ColumnSchema, DependentSchema, ForeignKeySchema, IndexSchema, SchemaDiff, TableSchema,
+};
 pub use types::*;
 
 // Export the boolean deserializer
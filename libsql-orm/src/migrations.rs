@@ -0,0 +1,483 @@
+//! Versioned schema migrations
+//!
+//! Migrations are plain SQL with an increasing version number. Applied
+//! migrations are recorded in the `_migrations` table together with a
+//! checksum of their SQL, so a migration that was edited after it ran is
+//! reported instead of silently skipped.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use libsql_orm::{Database, Migration, MigrationManager};
+//!
+//! async fn migrate(db: Database) -> Result<(), Box<dyn std::error::Error>> {
+//!     let migrations = vec![
+//!         Migration::new(1, "create_users", "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL);")
+//!             .with_down("DROP TABLE users;"),
+//!         Migration::new(2, "users_name", "ALTER TABLE users ADD COLUMN name TEXT;")
+//!             .with_down("ALTER TABLE users DROP COLUMN name;"),
+//!     ];
+//!
+//!     let manager = MigrationManager::new(db);
+//!
+//!     // See what would run without changing anything
+//!     let plan = manager.plan(&migrations).await?;
+//!     println!("pending: {:?}", plan.pending());
+//!
+//!     let report = manager.migrate(&migrations).await?;
+//!     println!("applied: {:?}", report.applied());
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use crate::database::Database;
+use crate::error::{Error, Result};
+
+/// Table recording applied migrations.
+pub const MIGRATIONS_TABLE: &str = "_migrations";
+/// Table holding the lock against concurrent runs.
+pub const MIGRATIONS_LOCK_TABLE: &str = "_migrations_lock";
+/// Locks older than this are considered left behind by a crashed run.
+const DEFAULT_STALE_LOCK_SECONDS: i64 = 10 * 60;
+
+/// A versioned schema change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// Position of the migration, unique and greater than zero.
+    pub version: i64,
+    pub name: String,
+    /// SQL applying the change, may contain several statements.
+    pub up: String,
+    /// SQL reverting the change, needed to roll back.
+    pub down: Option<String>,
+}
+
+impl Migration {
+    pub fn new(version: i64, name: impl Into<String>, up: impl Into<String>) -> Self {
+        Migration {
+            version,
+            name: name.into(),
+            up: up.into(),
+            down: None,
+        }
+    }
+
+    pub fn with_version(mut self, version: i64) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn with_down(mut self, down: impl Into<String>) -> Self {
+        self.down = Some(down.into());
+        self
+    }
+
+    /// Checksum of the `up` SQL. Line endings and surrounding whitespace do
+    /// not count, anything else does.
+    pub fn checksum(&self) -> String {
+        checksum(&self.up)
+    }
+}
+
+/// 64-bit FNV-1a as hex, stable across platforms and releases.
+fn checksum(sql: &str) -> String {
+    let normalized = sql.replace("\r\n", "\n");
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in normalized.trim().bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
+/// A row of the `_migrations` table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: String,
+}
+
+/// What a run did, or would do in dry-run mode
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Whether nothing was changed.
+    pub dry_run: bool,
+    /// Migrations run, or due to run, in order.
+    pub migrations: Vec<(i64, String)>,
+}
+
+impl MigrationReport {
+    /// Versions that were applied or rolled back.
+    pub fn applied(&self) -> Vec<i64> {
+        if self.dry_run {
+            Vec::new()
+        } else {
+            self.migrations
+                .iter()
+                .map(|(version, _)| *version)
+                .collect()
+        }
+    }
+
+    /// Versions a dry run found outstanding.
+    pub fn pending(&self) -> Vec<i64> {
+        if self.dry_run {
+            self.migrations
+                .iter()
+                .map(|(version, _)| *version)
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Applies and rolls back [`Migration`]s
+///
+/// Every run takes a lock row in `_migrations_lock`, so instances starting
+/// at the same time do not apply a migration twice. A run that finds the
+/// lock taken fails with [`Error::Migration`] and can be retried. Each
+/// migration runs in its own transaction together with its bookkeeping.
+pub struct MigrationManager {
+    db: Database,
+    dry_run: bool,
+    stale_lock_seconds: i64,
+}
+
+impl MigrationManager {
+    pub fn new(db: Database) -> Self {
+        MigrationManager {
+            db,
+            dry_run: false,
+            stale_lock_seconds: DEFAULT_STALE_LOCK_SECONDS,
+        }
+    }
+
+    /// Only report what would run, without touching the database.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// How old a lock has to be before another run may take it over.
+    pub fn stale_lock_after(mut self, seconds: i64) -> Self {
+        self.stale_lock_seconds = seconds;
+        self
+    }
+
+    /// An unversioned migration, as created by `generate_migration!`. Give it
+    /// a version with [`Migration::with_version`] before running it.
+    pub fn create_migration(name: &str, sql: &str) -> Migration {
+        Migration::new(0, name, sql)
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+
+    /// Applies a single migration unless it already ran.
+    pub async fn execute_migration(&self, migration: &Migration) -> Result<MigrationReport> {
+        self.migrate(std::slice::from_ref(migration)).await
+    }
+
+    /// The migrations [`migrate`](Self::migrate) would apply, without
+    /// changing anything.
+    pub async fn plan(&self, migrations: &[Migration]) -> Result<MigrationReport> {
+        let migrations = validate(migrations)?;
+        let applied = self.applied().await?;
+        verify_checksums(&migrations, &applied)?;
+
+        Ok(MigrationReport {
+            dry_run: true,
+            migrations: pending(&migrations, &applied)
+                .into_iter()
+                .map(|migration| (migration.version, migration.name.clone()))
+                .collect(),
+        })
+    }
+
+    /// Applies every migration that has not run yet, in version order. In
+    /// dry-run mode this is [`plan`](Self::plan).
+    pub async fn migrate(&self, migrations: &[Migration]) -> Result<MigrationReport> {
+        if self.dry_run {
+            return self.plan(migrations).await;
+        }
+        let migrations = validate(migrations)?;
+
+        self.ensure_tables().await?;
+        let owner = self.lock().await?;
+        let result = self.apply_pending(&migrations).await;
+        self.unlock(&owner).await?;
+        result
+    }
+
+    /// Reverts applied migrations newer than `target`, newest first.
+    pub async fn rollback_to(
+        &self,
+        migrations: &[Migration],
+        target: i64,
+    ) -> Result<MigrationReport> {
+        let migrations = validate(migrations)?;
+        let applied = self.applied().await?;
+        verify_checksums(&migrations, &applied)?;
+
+        let mut to_revert = Vec::new();
+        for row in applied.iter().rev().filter(|row| row.version > target) {
+            let migration = migrations
+                .iter()
+                .find(|migration| migration.version == row.version)
+                .ok_or_else(|| {
+                    Error::Migration(format!(
+                        "migration {} ({}) is applied but unknown",
+                        row.version, row.name
+                    ))
+                })?;
+            if migration.down.is_none() {
+                return Err(Error::Migration(format!(
+                    "migration {} ({}) cannot be rolled back, it has no down SQL",
+                    migration.version, migration.name
+                )));
+            }
+            to_revert.push(*migration);
+        }
+
+        let report = MigrationReport {
+            dry_run: self.dry_run,
+            migrations: to_revert
+                .iter()
+                .map(|migration| (migration.version, migration.name.clone()))
+                .collect(),
+        };
+        if self.dry_run {
+            return Ok(report);
+        }
+
+        self.ensure_tables().await?;
+        let owner = self.lock().await?;
+        let mut result = Ok(());
+        for migration in to_revert {
+            result = self.revert(migration).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        self.unlock(&owner).await?;
+        result.map(|_| report)
+    }
+
+    /// Rows of `_migrations` in version order, empty before the first run.
+    pub async fn applied(&self) -> Result<Vec<AppliedMigration>> {
+        let conn = self.db.get_connection();
+        let mut rows = conn
+            .query(
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?",
+                [MIGRATIONS_TABLE],
+            )
+            .await?;
+        if rows.next().await?.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let mut rows = conn
+            .query(
+                &format!(
+                    "SELECT version, name, checksum, applied_at FROM {MIGRATIONS_TABLE} ORDER BY version"
+                ),
+                (),
+            )
+            .await?;
+        let mut applied = Vec::new();
+        while let Some(row) = rows.next().await? {
+            applied.push(AppliedMigration {
+                version: row.get(0)?,
+                name: row.get(1)?,
+                checksum: row.get(2)?,
+                applied_at: row.get(3)?,
+            });
+        }
+        Ok(applied)
+    }
+
+    async fn ensure_tables(&self) -> Result<()> {
+        self.db
+            .get_connection()
+            .execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {MIGRATIONS_TABLE} (
+                    version INTEGER PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    checksum TEXT NOT NULL,
+                    applied_at TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS {MIGRATIONS_LOCK_TABLE} (
+                    id INTEGER PRIMARY KEY CHECK (id = 1),
+                    owner TEXT NOT NULL,
+                    locked_at TEXT NOT NULL
+                );"
+            ))
+            .await?;
+        Ok(())
+    }
+
+    /// Takes the lock and returns the owner token needed to release it.
+    async fn lock(&self) -> Result<String> {
+        let conn = self.db.get_connection();
+        let now = chrono::Utc::now();
+        let stale = now - chrono::Duration::seconds(self.stale_lock_seconds);
+
+        let removed = conn
+            .execute(
+                &format!("DELETE FROM {MIGRATIONS_LOCK_TABLE} WHERE locked_at < ?"),
+                [stale.to_rfc3339()],
+            )
+            .await?;
+        if removed > 0 {
+            log::warn!("Took over a stale migration lock");
+        }
+
+        let owner = uuid::Uuid::new_v4().to_string();
+        let taken = conn
+            .execute(
+                &format!(
+                    "INSERT OR IGNORE INTO {MIGRATIONS_LOCK_TABLE} (id, owner, locked_at) VALUES (1, ?, ?)"
+                ),
+                (owner.as_str(), now.to_rfc3339()),
+            )
+            .await?;
+        if taken == 0 {
+            return Err(Error::Migration(
+                "another migration run holds the lock".to_string(),
+            ));
+        }
+        Ok(owner)
+    }
+
+    async fn unlock(&self, owner: &str) -> Result<()> {
+        self.db
+            .get_connection()
+            .execute(
+                &format!("DELETE FROM {MIGRATIONS_LOCK_TABLE} WHERE id = 1 AND owner = ?"),
+                [owner],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn apply_pending(&self, migrations: &[&Migration]) -> Result<MigrationReport> {
+        // Read again under the lock, another run may have finished meanwhile
+        let applied = self.applied().await?;
+        verify_checksums(migrations, &applied)?;
+
+        let mut report = MigrationReport::default();
+        for migration in pending(migrations, &applied) {
+            log::info!(
+                "Applying migration {} ({})",
+                migration.version,
+                migration.name
+            );
+            let tx = self.db.get_connection().transaction().await?;
+            if let Err(err) = tx.execute_batch(&migration.up).await {
+                tx.rollback().await?;
+                return Err(Error::Migration(format!(
+                    "migration {} ({}) failed: {err}",
+                    migration.version, migration.name
+                )));
+            }
+            tx.execute(
+                &format!(
+                    "INSERT INTO {MIGRATIONS_TABLE} (version, name, checksum, applied_at) VALUES (?, ?, ?, ?)"
+                ),
+                (
+                    migration.version,
+                    migration.name.as_str(),
+                    migration.checksum(),
+                    chrono::Utc::now().to_rfc3339(),
+                ),
+            )
+            .await?;
+            tx.commit().await?;
+            report
+                .migrations
+                .push((migration.version, migration.name.clone()));
+        }
+        Ok(report)
+    }
+
+    async fn revert(&self, migration: &Migration) -> Result<()> {
+        log::info!(
+            "Rolling back migration {} ({})",
+            migration.version,
+            migration.name
+        );
+        let down = migration.down.as_deref().unwrap_or_default();
+        let tx = self.db.get_connection().transaction().await?;
+        if let Err(err) = tx.execute_batch(down).await {
+            tx.rollback().await?;
+            return Err(Error::Migration(format!(
+                "rolling back migration {} ({}) failed: {err}",
+                migration.version, migration.name
+            )));
+        }
+        tx.execute(
+            &format!("DELETE FROM {MIGRATIONS_TABLE} WHERE version = ?"),
+            [migration.version],
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Sorts the migrations by version and rejects missing or duplicate ones.
+fn validate(migrations: &[Migration]) -> Result<Vec<&Migration>> {
+    let mut sorted: Vec<&Migration> = migrations.iter().collect();
+    sorted.sort_by_key(|migration| migration.version);
+
+    if let Some(migration) = sorted.iter().find(|migration| migration.version <= 0) {
+        return Err(Error::Validation(format!(
+            "migration {} needs a version greater than zero",
+            migration.name
+        )));
+    }
+    if let Some(pair) = sorted
+        .windows(2)
+        .find(|pair| pair[0].version == pair[1].version)
+    {
+        return Err(Error::Validation(format!(
+            "migrations {} and {} share version {}",
+            pair[0].name, pair[1].name, pair[0].version
+        )));
+    }
+    Ok(sorted)
+}
+
+/// Fails when an applied migration no longer matches its recorded SQL.
+fn verify_checksums(migrations: &[&Migration], applied: &[AppliedMigration]) -> Result<()> {
+    let known: HashMap<i64, &Migration> = migrations
+        .iter()
+        .map(|migration| (migration.version, *migration))
+        .collect();
+
+    for row in applied {
+        if let Some(migration) = known.get(&row.version) {
+            if migration.checksum() != row.checksum {
+                return Err(Error::Migration(format!(
+                    "migration {} ({}) was changed after it was applied",
+                    row.version, row.name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn pending<'a>(migrations: &[&'a Migration], applied: &[AppliedMigration]) -> Vec<&'a Migration> {
+    migrations
+        .iter()
+        .filter(|migration| !applied.iter().any(|row| row.version == migration.version))
+        .copied()
+        .collect()
+}
@@ -25,8 +25,8 @@
 //! ```
 
 use crate::{
-    Aggregate, Database, Error, FilterOperator, PaginatedResult, Pagination, QueryBuilder, Result,
-    SearchFilter, Sort,
+    Aggregate, Database, Error, FilterOperator, FullTextMatch, PaginatedResult, Pagination,
+    QueryBuilder, Ranking, Result, SearchFilter, Sort,
 };
 use std::collections::HashMap;
 
@@ -43,6 +43,82 @@ fn mask_id(id: i64) -> String {
     format!("{}{}", &id_str[..visible_digits], "*".repeat(masked_digits))
 }
 
+/// Bound parameters per statement, SQLite's default limit since 3.32 which
+/// libsql keeps.
+const MAX_PARAMS: usize = 32_766;
+
+/// Rows per multi-row statement when each row binds `per_row` parameters.
+fn rows_per_statement(per_row: usize) -> usize {
+    (MAX_PARAMS / per_row.max(1)).max(1)
+}
+
+/// `(?, ?), (?, ?)` for `rows` rows of `columns` values.
+fn placeholders(rows: usize, columns: usize) -> String {
+    let row = format!("({})", vec!["?"; columns].join(", "));
+    vec![row; rows].join(", ")
+}
+
+/// Whether `ON CONFLICT (columns)` has a constraint to match: the primary key
+/// or a full UNIQUE index on exactly these columns.
+async fn has_unique_index(
+    db: &Database,
+    table: &str,
+    primary_key: &str,
+    columns: &[&str],
+) -> Result<bool> {
+    if columns == [primary_key] {
+        return Ok(true);
+    }
+    let mut wanted: Vec<&str> = columns.to_vec();
+    wanted.sort_unstable();
+
+    let mut indexes = Vec::new();
+    let mut rows = db
+        .inner
+        .query(&format!("PRAGMA index_list({table})"), ())
+        .await?;
+    while let Some(row) = rows.next().await? {
+        // seq, name, unique, origin, partial
+        if row.get::<i64>(2)? == 1 && row.get::<i64>(4)? == 0 {
+            indexes.push(row.get::<String>(1)?);
+        }
+    }
+    for index in indexes {
+        let mut indexed = Vec::new();
+        let mut rows = db
+            .inner
+            .query(&format!("PRAGMA index_info({index})"), ())
+            .await?;
+        while let Some(row) = rows.next().await? {
+            // seqno, cid, name; expressions have no name
+            indexed.push(row.get::<Option<String>>(2)?.unwrap_or_default());
+        }
+        indexed.sort_unstable();
+        if indexed == wanted {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Rejects records whose unique values contain NULL, which never conflict
+/// with an existing row.
+fn check_upsert_key(
+    table: &str,
+    map: &HashMap<String, crate::Value>,
+    unique_columns: &[&str],
+) -> Result<()> {
+    match unique_columns
+        .iter()
+        .find(|column| matches!(map.get(**column), None | Some(crate::Value::Null)))
+    {
+        Some(column) => Err(Error::Validation(format!(
+            "Cannot upsert into {table} with a NULL {column}"
+        ))),
+        None => Ok(()),
+    }
+}
+
 /// Core trait for all database models
 #[allow(async_fn_in_trait)]
 pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
@@ -66,6 +142,18 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
     /// Generate SQL for creating the table
     fn migration_sql() -> String;
 
+    /// Columns in the FTS5 index declared with `#[fts(...)]`, empty without
+    /// one
+    fn fts_columns() -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// The FTS5 table and its triggers, also part of `migration_sql()`.
+    /// Adds full-text search to an existing table.
+    fn fts_migration_sql() -> Option<String> {
+        None
+    }
+
     /// Convert the model to a HashMap for database operations
     fn to_map(&self) -> Result<HashMap<String, crate::Value>>;
 
@@ -135,109 +223,227 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         }
     }
 
-    /// Create or update a record based on unique constraints
+    /// Insert a record, or update the row it conflicts with on
+    /// `unique_columns`, in one `INSERT ... ON CONFLICT DO UPDATE`
+    ///
+    /// Without a UNIQUE index on exactly these columns the row is looked up
+    /// first, then updated or inserted. The unique values must not be NULL.
     async fn upsert(&self, unique_columns: &[&str], db: &Database) -> Result<Self> {
+        let columns = Self::columns();
+        let sql = format!(
+            "{} RETURNING {}",
+            Self::upsert_sql(&columns, unique_columns, 1)?,
+            Self::primary_key()
+        );
         let map = self.to_map()?;
+        check_upsert_key(Self::table_name(), &map, unique_columns)?;
 
-        // Build WHERE clause for unique columns
-        let mut where_conditions = Vec::new();
-        let mut where_params = Vec::new();
-
-        for &column in unique_columns {
-            if let Some(value) = map.get(column) {
-                where_conditions.push(format!("{column} = ?"));
-                where_params.push(Self::value_to_libsql_value(value));
-            }
-        }
+        Self::log_info(&format!(
+            "Upserting record in table: {}",
+            Self::table_name()
+        ));
 
-        if where_conditions.is_empty() {
-            return Err(Error::Validation(
-                "No unique columns provided for upsert".to_string(),
+        if !has_unique_index(db, Self::table_name(), Self::primary_key(), unique_columns).await? {
+            Self::log_debug(&format!(
+                "No unique index on {}, looking up the existing record",
+                unique_columns.join(", ")
             ));
+            let where_clause: Vec<String> = unique_columns
+                .iter()
+                .map(|column| format!("{column} = ?"))
+                .collect();
+            let sql = format!(
+                "SELECT {} FROM {} WHERE {}",
+                Self::primary_key(),
+                Self::table_name(),
+                where_clause.join(" AND ")
+            );
+            let mut rows = db
+                .inner
+                .query(&sql, Self::row_params(unique_columns, &[map]))
+                .await?;
+            return match rows.next().await? {
+                Some(row) => {
+                    let mut existing = self.clone();
+                    existing.set_primary_key(row.get::<i64>(0)?);
+                    existing.update(db).await
+                }
+                None => self.create(db).await,
+            };
         }
 
-        let where_clause = where_conditions.join(" AND ");
-        let sql = format!(
-            "SELECT {} FROM {} WHERE {}",
-            Self::primary_key(),
-            Self::table_name(),
-            where_clause
-        );
+        Self::log_debug(&format!("SQL: {sql}"));
+        let params = Self::row_params(&columns, &[map]);
+        let mut rows = db.inner.query(&sql, params).await?;
+        let id = match rows.next().await? {
+            Some(row) => row.get::<i64>(0)?,
+            None => return Err(Error::Query("Upsert returned no primary key".to_string())),
+        };
+
+        let mut result = self.clone();
+        result.set_primary_key(id);
+        Self::log_info(&format!("Upserted record with ID: {}", mask_id(id)));
+        Ok(result)
+    }
+
+    /// [`Model::upsert`] for many records in a transaction, one statement per
+    /// 32766 bound parameters. Records with the same unique values end up as
+    /// one row, the last one wins.
+    async fn bulk_upsert(
+        models: &[Self],
+        unique_columns: &[&str],
+        db: &Database,
+    ) -> Result<Vec<Self>> {
+        if models.is_empty() {
+            return Ok(Vec::new());
+        }
+        let columns = Self::columns();
+        // Validates the columns before anything runs
+        Self::upsert_sql(&columns, unique_columns, 1)?;
+        let rows = models
+            .iter()
+            .map(Self::to_map)
+            .collect::<Result<Vec<_>>>()?;
+        for row in &rows {
+            check_upsert_key(Self::table_name(), row, unique_columns)?;
+        }
 
         Self::log_info(&format!(
-            "Checking for existing record in table: {}",
+            "Upserting {} records in table: {}",
+            models.len(),
             Self::table_name()
         ));
-        Self::log_debug(&format!("SQL: {sql}"));
-
-        let mut rows = db.inner.query(&sql, where_params).await?;
 
-        if let Some(row) = rows.next().await? {
-            // Record exists, update it
-            if let Some(existing_id) = row.get_value(0).ok().and_then(|v| match v {
-                libsql::Value::Integer(i) => Some(i),
-                _ => None,
-            }) {
-                Self::log_info(&format!(
-                    "Found existing record with ID: {}, updating",
-                    mask_id(existing_id)
-                ));
-                let mut updated_self = self.clone();
-                updated_self.set_primary_key(existing_id);
-                updated_self.update(db).await
-            } else {
-                Err(Error::Query(
-                    "Failed to get primary key from existing record".to_string(),
-                ))
-            }
-        } else {
-            // Record doesn't exist, create it
-            Self::log_info("No existing record found, creating new one");
-            self.create(db).await
+        if !has_unique_index(db, Self::table_name(), Self::primary_key(), unique_columns).await? {
+            return db
+                .transaction(|tx| async move {
+                    let mut results = Vec::with_capacity(models.len());
+                    for model in models {
+                        results.push(model.upsert(unique_columns, &tx).await?);
+                    }
+                    Ok(results)
+                })
+                .await;
         }
+
+        // RETURNING gives rows in no particular order, so each chunk's ids
+        // are looked up again by position. Comparing in SQL applies the
+        // columns' type affinity like the upsert did, and a record without
+        // an id rolls everything back.
+        let join: Vec<String> = unique_columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| format!("{}.{column} = _keys.column{}", Self::table_name(), i + 2))
+            .collect();
+        let ids = db
+            .transaction(|tx| async move {
+                let mut ids = Vec::with_capacity(rows.len());
+                for chunk in rows.chunks(rows_per_statement(columns.len())) {
+                    let sql = Self::upsert_sql(&columns, unique_columns, chunk.len())?;
+                    tx.inner
+                        .execute(&sql, Self::row_params(&columns, chunk))
+                        .await?;
+
+                    let keys: Vec<String> = (0..chunk.len())
+                        .map(|i| format!("({i}, {})", vec!["?"; unique_columns.len()].join(", ")))
+                        .collect();
+                    let sql = format!(
+                        "SELECT _keys.column1, {table}.{} FROM (VALUES {}) AS _keys JOIN {table} ON {}",
+                        Self::primary_key(),
+                        keys.join(", "),
+                        join.join(" AND "),
+                        table = Self::table_name()
+                    );
+                    let mut found = vec![None; chunk.len()];
+                    let mut returned = tx
+                        .inner
+                        .query(&sql, Self::row_params(unique_columns, chunk))
+                        .await?;
+                    while let Some(row) = returned.next().await? {
+                        found[row.get::<i64>(0)? as usize] = Some(row.get::<i64>(1)?);
+                    }
+                    for id in found {
+                        ids.push(id.ok_or_else(|| {
+                            Error::Query("Upsert returned no primary key".to_string())
+                        })?);
+                    }
+                }
+                Ok::<_, Error>(ids)
+            })
+            .await?;
+
+        Ok(models
+            .iter()
+            .zip(ids)
+            .map(|(model, id)| {
+                let mut result = model.clone();
+                result.set_primary_key(id);
+                result
+            })
+            .collect())
     }
 
-    /// Create multiple records in the database
+    /// Create multiple records with multi-row `INSERT`s, one per 32766 bound
+    /// parameters, in a transaction. The ids come back through `RETURNING`.
     async fn bulk_create(models: &[Self], db: &Database) -> Result<Vec<Self>> {
         if models.is_empty() {
             return Ok(Vec::new());
         }
+        let columns = Self::columns();
+        let rows = models
+            .iter()
+            .map(Self::to_map)
+            .collect::<Result<Vec<_>>>()?;
 
-        let mut results = Vec::new();
-        // Note: Manual transaction handling for WASM
-        db.inner
-            .execute("BEGIN", vec![libsql::Value::Null; 0])
-            .await?;
-
-        for model in models {
-            let map = model.to_map()?;
-            let columns: Vec<String> = map.keys().cloned().collect();
-            let values: Vec<String> = map.keys().map(|_| "?".to_string()).collect();
-
-            let sql = format!(
-                "INSERT INTO {} ({}) VALUES ({})",
-                Self::table_name(),
-                columns.join(", "),
-                values.join(", ")
-            );
-
-            let params: Vec<libsql::Value> = map
-                .values()
-                .map(|v| Self::value_to_libsql_value(v))
-                .collect();
+        Self::log_info(&format!(
+            "Creating {} records in table: {}",
+            models.len(),
+            Self::table_name()
+        ));
 
-            db.inner.execute(&sql, params).await?;
-            let id = db.inner.last_insert_rowid();
+        // All rows or none
+        let mut ids = db
+            .transaction(|tx| async move {
+                let mut ids = Vec::with_capacity(rows.len());
+                for chunk in rows.chunks(rows_per_statement(columns.len())) {
+                    let sql = format!(
+                        "INSERT INTO {} ({}) VALUES {} RETURNING {}",
+                        Self::table_name(),
+                        columns.join(", "),
+                        placeholders(chunk.len(), columns.len()),
+                        Self::primary_key()
+                    );
+                    let mut returned = tx
+                        .inner
+                        .query(&sql, Self::row_params(&columns, chunk))
+                        .await?;
+                    while let Some(row) = returned.next().await? {
+                        ids.push(row.get::<i64>(0)?);
+                    }
+                }
+                Ok::<_, Error>(ids)
+            })
+            .await?;
 
-            let mut result = model.clone();
-            result.set_primary_key(id);
-            results.push(result);
-        }
+        // Rows come back in no particular order. Generated ids increase in
+        // insert order, so after the given ids they match the new records.
+        let given: Vec<i64> = models.iter().filter_map(Self::get_primary_key).collect();
+        ids.retain(|id| !given.contains(id));
+        ids.sort_unstable();
+        let mut generated = ids.into_iter();
 
-        db.inner
-            .execute("COMMIT", vec![libsql::Value::Null; 0])
-            .await?;
-        Ok(results)
+        Ok(models
+            .iter()
+            .map(|model| {
+                let mut result = model.clone();
+                if result.get_primary_key().is_none() {
+                    if let Some(id) = generated.next() {
+                        result.set_primary_key(id);
+                    }
+                }
+                result
+            })
+            .collect())
     }
 
     /// Find a record by its primary key
@@ -319,6 +525,25 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         Self::find_where_paginated(filter, &pagination, db).await
     }
 
+    /// Full-text search through the `#[fts(...)]` index, best matches first
+    async fn full_text_search(
+        query: &str,
+        ranking: Ranking,
+        db: &Database,
+    ) -> Result<Vec<FullTextMatch<Self>>> {
+        crate::fts::search(query, ranking, None, db).await
+    }
+
+    /// Full-text search limited to rows matching `filter`
+    async fn full_text_search_where(
+        query: &str,
+        ranking: Ranking,
+        filter: FilterOperator,
+        db: &Database,
+    ) -> Result<Vec<FullTextMatch<Self>>> {
+        crate::fts::search(query, ranking, Some(filter), db).await
+    }
+
     /// Count all records
     async fn count(db: &Database) -> Result<u64> {
         let sql = format!("SELECT COUNT(*) FROM {}", Self::table_name());
@@ -395,27 +620,84 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         Ok(self.clone())
     }
 
-    /// Update multiple records
+    /// Update multiple records by primary key with
+    /// `UPDATE ... FROM (VALUES ...)`, one statement per 32766 bound
+    /// parameters, in a transaction. Fails without changes when a record
+    /// does not exist.
     async fn bulk_update(models: &[Self], db: &Database) -> Result<Vec<Self>> {
         if models.is_empty() {
             return Ok(Vec::new());
         }
+        let ids = models
+            .iter()
+            .map(|model| {
+                model.get_primary_key().ok_or_else(|| {
+                    Error::Validation("Cannot update record without primary key".to_string())
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut distinct = ids.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        if distinct.len() != ids.len() {
+            return Err(Error::Validation(
+                "Cannot update the same record twice in one bulk update".to_string(),
+            ));
+        }
+        let table = Self::table_name();
+        let primary_key = Self::primary_key();
+        let columns: Vec<&str> = Self::columns()
+            .into_iter()
+            .filter(|column| *column != primary_key)
+            .collect();
+        if columns.is_empty() {
+            return Ok(models.to_vec());
+        }
+        let rows = models
+            .iter()
+            .map(Self::to_map)
+            .collect::<Result<Vec<_>>>()?;
 
-        let mut results = Vec::new();
-        // Note: Manual transaction handling for WASM
-        db.inner
-            .execute("BEGIN", vec![libsql::Value::Null; 0])
-            .await?;
+        Self::log_info(&format!(
+            "Updating {} records in table: {table}",
+            models.len()
+        ));
 
-        for model in models {
-            let result = model.update(db).await?;
-            results.push(result);
-        }
+        // VALUES names its columns column1, column2, ..., the id comes first
+        let set_clauses: Vec<String> = columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| format!("{column} = _rows.column{}", i + 2))
+            .collect();
+        let per_statement = rows_per_statement(columns.len() + 1);
+
+        db.transaction(|tx| async move {
+            let mut updated = 0;
+            for (rows, ids) in rows.chunks(per_statement).zip(ids.chunks(per_statement)) {
+                let sql = format!(
+                    "UPDATE {table} SET {} FROM (VALUES {}) AS _rows WHERE {table}.{primary_key} = _rows.column1",
+                    set_clauses.join(", "),
+                    placeholders(rows.len(), columns.len() + 1)
+                );
+                let mut params = Vec::with_capacity(rows.len() * (columns.len() + 1));
+                for (row, id) in rows.iter().zip(ids) {
+                    params.push(libsql::Value::Integer(*id));
+                    params.extend(Self::row_params(&columns, std::slice::from_ref(row)));
+                }
+                updated += tx.inner.execute(&sql, params).await?;
+            }
+            // Rolls back when a record does not exist
+            if updated != models.len() as u64 {
+                return Err(Error::NotFound(format!(
+                    "Updated {updated} of {} records in {table}",
+                    models.len()
+                )));
+            }
+            Ok::<_, Error>(())
+        })
+        .await?;
 
-        db.inner
-            .execute("COMMIT", vec![libsql::Value::Null; 0])
-            .await?;
-        Ok(results)
+        Ok(models.to_vec())
     }
 
     /// Delete a record
@@ -567,6 +849,54 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         Ok(map)
     }
 
+    /// `INSERT ... ON CONFLICT (unique_columns) DO UPDATE` for `rows` rows,
+    /// updating every other column but the primary key
+    fn upsert_sql(columns: &[&str], unique_columns: &[&str], rows: usize) -> Result<String> {
+        if unique_columns.is_empty() {
+            return Err(Error::Validation(
+                "No unique columns provided for upsert".to_string(),
+            ));
+        }
+        if let Some(unknown) = unique_columns.iter().find(|c| !columns.contains(c)) {
+            return Err(Error::Validation(format!(
+                "{} has no column {unknown}",
+                Self::table_name()
+            )));
+        }
+
+        let mut updates: Vec<String> = columns
+            .iter()
+            .filter(|c| **c != Self::primary_key() && !unique_columns.contains(c))
+            .map(|c| format!("{c} = excluded.{c}"))
+            .collect();
+        if updates.is_empty() {
+            // DO NOTHING would not return the existing row
+            updates.push(format!("{0} = excluded.{0}", unique_columns[0]));
+        }
+
+        Ok(format!(
+            "INSERT INTO {} ({}) VALUES {} ON CONFLICT ({}) DO UPDATE SET {}",
+            Self::table_name(),
+            columns.join(", "),
+            placeholders(rows, columns.len()),
+            unique_columns.join(", "),
+            updates.join(", ")
+        ))
+    }
+
+    /// The values of `columns` in each row, NULL where a row has none
+    fn row_params(columns: &[&str], rows: &[HashMap<String, crate::Value>]) -> Vec<libsql::Value> {
+        rows.iter()
+            .flat_map(|row| {
+                columns.iter().map(|column| {
+                    row.get(*column)
+                        .map(Self::value_to_libsql_value)
+                        .unwrap_or(libsql::Value::Null)
+                })
+            })
+            .collect()
+    }
+
     /// Convert our Value type to libsql::Value
     fn value_to_libsql_value(value: &crate::Value) -> libsql::Value {
         match value {
@@ -0,0 +1,338 @@
+//! Async connection pool
+//!
+//! Opening a remote libsql connection costs a round trip, so servers should
+//! reuse connections instead of opening one per query. [`Pool`] keeps between
+//! `min_size` and `max_size` connections, checks idle ones before handing
+//! them out, closes connections idle for too long and bounds how long a
+//! caller waits for a free one. Requires the `pool` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsql_orm::{Pool, PoolConfig};
+//!
+//! async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//!     let pool = Pool::new("libsql://your-db.turso.io", "your-auth-token", PoolConfig::default()).await?;
+//!
+//!     let db = pool.get().await?;
+//!     db.get_connection().query("SELECT 1", ()).await?;
+//!     // Dropping `db` returns the connection to the pool
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{Database, Error, Result};
+
+/// Sizes and timeouts of a [`Pool`]
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    /// Connections opened up front and kept through idle timeouts.
+    pub min_size: usize,
+    /// Upper bound of open connections.
+    pub max_size: usize,
+    /// How long an unused connection above `min_size` stays open.
+    pub idle_timeout: Duration,
+    /// How long [`Pool::get`] waits for a free connection.
+    pub acquire_timeout: Duration,
+    /// Connections idle for longer run `SELECT 1` before being handed out.
+    pub health_check_after: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            min_size: 1,
+            max_size: 10,
+            idle_timeout: Duration::from_secs(300),
+            acquire_timeout: Duration::from_secs(5),
+            health_check_after: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Counters describing a [`Pool`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Open connections, idle or in use.
+    pub size: usize,
+    pub idle: usize,
+    pub in_use: usize,
+    pub max_size: usize,
+    /// Successful [`Pool::get`] calls.
+    pub acquired: u64,
+    /// Connections opened over the pool's lifetime.
+    pub created: u64,
+    /// Connections closed for being idle, unhealthy or left in a transaction.
+    pub closed: u64,
+    /// [`Pool::get`] calls that gave up waiting.
+    pub timeouts: u64,
+    /// Idle connections that failed their health check.
+    pub failed_health_checks: u64,
+    /// Total time callers spent waiting in [`Pool::get`].
+    pub wait_time: Duration,
+}
+
+struct Idle {
+    db: Database,
+    since: Instant,
+}
+
+#[derive(Default)]
+struct Counters {
+    acquired: AtomicU64,
+    created: AtomicU64,
+    closed: AtomicU64,
+    timeouts: AtomicU64,
+    failed_health_checks: AtomicU64,
+    wait_micros: AtomicU64,
+}
+
+struct Shared {
+    url: String,
+    token: String,
+    config: PoolConfig,
+    /// Local databases are opened once and handed out as new connections.
+    local: Option<Database>,
+    idle: Mutex<VecDeque<Idle>>,
+    /// One permit per connection that may be in use.
+    permits: Arc<Semaphore>,
+    counters: Counters,
+}
+
+/// A pool of [`Database`] connections
+///
+/// Cloning is cheap and shares the pool. URLs are understood like
+/// [`Database::open`]. An in-memory database has a single connection, so a
+/// `:memory:` pool holds at most one connection whatever `max_size` says.
+#[derive(Clone)]
+pub struct Pool {
+    shared: Arc<Shared>,
+}
+
+impl Pool {
+    /// Creates a pool and opens `min_size` connections
+    pub async fn new(url: &str, token: &str, mut config: PoolConfig) -> Result<Self> {
+        if config.max_size == 0 || config.min_size > config.max_size {
+            return Err(Error::Validation(format!(
+                "invalid pool size {}..{}",
+                config.min_size, config.max_size
+            )));
+        }
+        // Every handle to an in-memory database is the same connection, two
+        // holders would share its transactions
+        if url == ":memory:" {
+            config.max_size = 1;
+            config.min_size = config.min_size.min(1);
+        }
+
+        let local = if url == ":memory:" || url.starts_with("file:") {
+            Some(Database::open(url, token).await?)
+        } else {
+            None
+        };
+        let pool = Pool {
+            shared: Arc::new(Shared {
+                url: url.to_string(),
+                token: token.to_string(),
+                permits: Arc::new(Semaphore::new(config.max_size)),
+                config,
+                local,
+                idle: Mutex::new(VecDeque::new()),
+                counters: Counters::default(),
+            }),
+        };
+
+        for _ in 0..pool.shared.config.min_size {
+            let db = pool.open().await?;
+            pool.shared.idle.lock().unwrap().push_back(Idle {
+                db,
+                since: Instant::now(),
+            });
+        }
+        Ok(pool)
+    }
+
+    /// Borrows a connection, waiting up to `acquire_timeout` for one
+    ///
+    /// The connection goes back to the pool when the returned guard is
+    /// dropped.
+    pub async fn get(&self) -> Result<PooledDatabase> {
+        let started = Instant::now();
+        let permit = tokio::time::timeout(
+            self.shared.config.acquire_timeout,
+            self.shared.permits.clone().acquire_owned(),
+        )
+        .await;
+        let counters = &self.shared.counters;
+        counters
+            .wait_micros
+            .fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+
+        let permit = match permit {
+            Ok(Ok(permit)) => permit,
+            Ok(Err(_)) => return Err(Error::Connection("connection pool is closed".to_string())),
+            Err(_) => {
+                counters.timeouts.fetch_add(1, Ordering::Relaxed);
+                return Err(Error::Connection(format!(
+                    "no database connection free within {:?}",
+                    self.shared.config.acquire_timeout
+                )));
+            }
+        };
+
+        let db = match self.take_idle().await {
+            Some(db) => db,
+            None => self.open().await?,
+        };
+        counters.acquired.fetch_add(1, Ordering::Relaxed);
+
+        Ok(PooledDatabase {
+            db: Some(db),
+            pool: self.clone(),
+            _permit: permit,
+        })
+    }
+
+    /// Current counters of the pool
+    pub fn metrics(&self) -> PoolMetrics {
+        let shared = &self.shared;
+        let idle = shared.idle.lock().unwrap().len();
+        let in_use = shared.config.max_size - shared.permits.available_permits();
+        PoolMetrics {
+            size: idle + in_use,
+            idle,
+            in_use,
+            max_size: shared.config.max_size,
+            acquired: shared.counters.acquired.load(Ordering::Relaxed),
+            created: shared.counters.created.load(Ordering::Relaxed),
+            closed: shared.counters.closed.load(Ordering::Relaxed),
+            timeouts: shared.counters.timeouts.load(Ordering::Relaxed),
+            failed_health_checks: shared.counters.failed_health_checks.load(Ordering::Relaxed),
+            wait_time: Duration::from_micros(shared.counters.wait_micros.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub fn config(&self) -> &PoolConfig {
+        &self.shared.config
+    }
+
+    async fn open(&self) -> Result<Database> {
+        let db = match &self.shared.local {
+            Some(local) => local.connect()?,
+            None => Database::new_connect(&self.shared.url, &self.shared.token).await?,
+        };
+        // A closed in-memory handle leaves its transaction on the shared
+        // connection
+        if !db.get_connection().is_autocommit() {
+            log::warn!("Rolling back a transaction left open on a pooled connection");
+            db.get_connection().execute("ROLLBACK", ()).await?;
+        }
+        self.shared.counters.created.fetch_add(1, Ordering::Relaxed);
+        Ok(db)
+    }
+
+    /// The most recently used healthy idle connection, closing expired ones.
+    async fn take_idle(&self) -> Option<Database> {
+        loop {
+            let idle = {
+                let mut queue = self.shared.idle.lock().unwrap();
+                self.close_expired(&mut queue);
+                queue.pop_back()?
+            };
+
+            if idle.since.elapsed() < self.shared.config.health_check_after {
+                return Some(idle.db);
+            }
+            match idle.db.get_connection().query("SELECT 1", ()).await {
+                Ok(_) => return Some(idle.db),
+                Err(err) => {
+                    log::warn!("Dropping unhealthy pooled connection: {err}");
+                    let counters = &self.shared.counters;
+                    counters
+                        .failed_health_checks
+                        .fetch_add(1, Ordering::Relaxed);
+                    counters.closed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Closes the oldest idle connections past the idle timeout, keeping at
+    /// least `min_size` connections open.
+    fn close_expired(&self, queue: &mut VecDeque<Idle>) {
+        let config = &self.shared.config;
+        // The caller holds a permit but no connection outside the queue
+        let in_use = (config.max_size - self.shared.permits.available_permits()).saturating_sub(1);
+        while queue.len() + in_use > config.min_size
+            && queue
+                .front()
+                .is_some_and(|idle| idle.since.elapsed() >= config.idle_timeout)
+        {
+            queue.pop_front();
+            self.shared.counters.closed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn release(&self, db: Database) {
+        // A connection left inside a transaction would leak it to the next user
+        if !db.get_connection().is_autocommit() {
+            log::warn!("Closing pooled connection returned inside a transaction");
+            self.shared.counters.closed.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let mut queue = self.shared.idle.lock().unwrap();
+        queue.push_back(Idle {
+            db,
+            since: Instant::now(),
+        });
+        self.close_expired(&mut queue);
+    }
+}
+
+/// A connection borrowed from a [`Pool`]
+///
+/// Derefs to [`Database`], so it can be passed wherever `&Database` is
+/// expected. Dropping it returns the connection.
+pub struct PooledDatabase {
+    db: Option<Database>,
+    pool: Pool,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledDatabase {
+    /// Takes the connection out of the pool for good, e.g. for a long-lived
+    /// [`MigrationManager`](crate::MigrationManager). The pool may open a
+    /// replacement.
+    pub fn detach(mut self) -> Database {
+        self.pool
+            .shared
+            .counters
+            .closed
+            .fetch_add(1, Ordering::Relaxed);
+        self.db.take().expect("pooled connection already taken")
+    }
+}
+
+impl Deref for PooledDatabase {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.db.as_ref().expect("pooled connection already taken")
+    }
+}
+
+impl Drop for PooledDatabase {
+    fn drop(&mut self) {
+        if let Some(db) = self.db.take() {
+            self.pool.release(db);
+        }
+    }
+}
@@ -657,10 +657,8 @@ impl QueryBuilder {
     where
         T: serde::de::DeserializeOwned,
     {
-        // Get total count
-        let count_builder = QueryBuilder::new(&self.table).select(vec!["COUNT(*) as count"]);
-
-        let (count_sql, count_params) = count_builder.build_count()?;
+        // Get total count, with the same filters as the data
+        let (count_sql, count_params) = self.build_count()?;
         let mut count_rows = db.query(&count_sql, count_params).await?;
         let total: u64 = if let Some(row) = count_rows.next().await? {
             row.get_value(0)
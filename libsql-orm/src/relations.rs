@@ -0,0 +1,245 @@
+//! Relations between models and eager loading
+//!
+//! `#[has_many(name = Child)]` and `#[belongs_to(name = Parent)]` on a model
+//! generate a relation constant and an accessor method:
+//!
+//! ```rust
+//! use libsql_orm::{Database, Model, WithRelation};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+//! #[table_name("authors")]
+//! #[has_many(books = Book)] // Book::author_id points here
+//! struct Author {
+//!     pub id: Option<i64>,
+//!     pub name: String,
+//! }
+//!
+//! #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+//! #[table_name("books")]
+//! #[belongs_to(author = Author)] // through the author_id column
+//! struct Book {
+//!     pub id: Option<i64>,
+//!     pub author_id: i64,
+//!     pub title: String,
+//! }
+//!
+//! async fn example(author: Author, db: &Database) -> libsql_orm::Result<()> {
+//!     // One query for this author's books
+//!     let books = author.books(db).await?;
+//!
+//!     // Two queries in total: the books, then their authors with `IN (...)`
+//!     let books = Book::find_all(db).with(Book::AUTHOR, db).await?;
+//!     for (book, author) in books {
+//!         println!("{} by {:?}", book.title, author.map(|a| a.name));
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The foreign key defaults to `<parent>_id`, the snake case parent struct
+//! name for `has_many` and the relation name for `belongs_to`. Set it with
+//! `foreign_key = "column"`.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::marker::PhantomData;
+
+use crate::{Database, Filter, FilterOperator, JoinType, Model, QueryBuilder, Result, Value};
+
+/// Keys per `IN (...)` query, well below SQLite's parameter limit.
+const IN_CHUNK: usize = 500;
+
+/// A relation that can be loaded for many models at once
+#[allow(async_fn_in_trait)]
+pub trait Relation<M: Model> {
+    /// What each model gets, e.g. `Vec<Child>` or `Option<Parent>`.
+    type Loaded;
+
+    /// Loads the related models of all `models` with one query per 500
+    /// keys, keeping the order of `models`
+    async fn load(&self, models: Vec<M>, db: &Database) -> Result<Vec<(M, Self::Loaded)>>;
+}
+
+/// `P` has many `C`, through `C`'s `foreign_key` column
+pub struct HasMany<P, C> {
+    pub foreign_key: &'static str,
+    _models: PhantomData<fn() -> (P, C)>,
+}
+
+/// `C` belongs to `P`, through `C`'s `foreign_key` column
+pub struct BelongsTo<C, P> {
+    pub foreign_key: &'static str,
+    _models: PhantomData<fn() -> (C, P)>,
+}
+
+impl<P: Model, C: Model> HasMany<P, C> {
+    pub const fn new(foreign_key: &'static str) -> Self {
+        HasMany {
+            foreign_key,
+            _models: PhantomData,
+        }
+    }
+
+    /// The children of one parent, empty if the parent is not saved yet
+    pub async fn get(&self, parent: &P, db: &Database) -> Result<Vec<C>> {
+        let Some(id) = parent.get_primary_key() else {
+            return Ok(Vec::new());
+        };
+        find_in::<C>(self.foreign_key, vec![id], db).await
+    }
+
+    /// Joins the children's table onto a query of the parents
+    pub fn join(&self, builder: QueryBuilder, join_type: JoinType) -> QueryBuilder {
+        builder.join(
+            join_type,
+            C::table_name(),
+            format!(
+                "{}.{} = {}.{}",
+                C::table_name(),
+                self.foreign_key,
+                P::table_name(),
+                P::primary_key()
+            ),
+        )
+    }
+}
+
+impl<C: Model, P: Model> BelongsTo<C, P> {
+    pub const fn new(foreign_key: &'static str) -> Self {
+        BelongsTo {
+            foreign_key,
+            _models: PhantomData,
+        }
+    }
+
+    /// The parent of one child, `None` if the foreign key is NULL or dangling
+    pub async fn get(&self, child: &C, db: &Database) -> Result<Option<P>> {
+        match foreign_key_of(child, self.foreign_key)? {
+            Some(id) => P::find_by_id(id, db).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Joins the parents' table onto a query of the children
+    pub fn join(&self, builder: QueryBuilder, join_type: JoinType) -> QueryBuilder {
+        builder.join(
+            join_type,
+            P::table_name(),
+            format!(
+                "{}.{} = {}.{}",
+                P::table_name(),
+                P::primary_key(),
+                C::table_name(),
+                self.foreign_key
+            ),
+        )
+    }
+}
+
+impl<P: Model, C: Model> Relation<P> for HasMany<P, C> {
+    type Loaded = Vec<C>;
+
+    async fn load(&self, parents: Vec<P>, db: &Database) -> Result<Vec<(P, Vec<C>)>> {
+        let ids = unique(parents.iter().filter_map(|parent| parent.get_primary_key()));
+        let mut by_parent: HashMap<i64, Vec<C>> = HashMap::new();
+        for child in find_in::<C>(self.foreign_key, ids, db).await? {
+            if let Some(id) = foreign_key_of(&child, self.foreign_key)? {
+                by_parent.entry(id).or_default().push(child);
+            }
+        }
+
+        Ok(parents
+            .into_iter()
+            .map(|parent| {
+                let children = parent
+                    .get_primary_key()
+                    .and_then(|id| by_parent.get(&id).cloned())
+                    .unwrap_or_default();
+                (parent, children)
+            })
+            .collect())
+    }
+}
+
+impl<C: Model, P: Model> Relation<C> for BelongsTo<C, P> {
+    type Loaded = Option<P>;
+
+    async fn load(&self, children: Vec<C>, db: &Database) -> Result<Vec<(C, Option<P>)>> {
+        let keys = children
+            .iter()
+            .map(|child| foreign_key_of(child, self.foreign_key))
+            .collect::<Result<Vec<_>>>()?;
+        let parents: HashMap<i64, P> =
+            find_in::<P>(P::primary_key(), unique(keys.iter().flatten().copied()), db)
+                .await?
+                .into_iter()
+                .filter_map(|parent| parent.get_primary_key().map(|id| (id, parent)))
+                .collect();
+
+        Ok(children
+            .into_iter()
+            .zip(keys)
+            .map(|(child, key)| {
+                let parent = key.and_then(|id| parents.get(&id).cloned());
+                (child, parent)
+            })
+            .collect())
+    }
+}
+
+/// Eager loading on the result of a query, e.g.
+/// `Book::find_all(db).with(Book::AUTHOR, db)`
+pub trait WithRelation<M: Model>: Future<Output = Result<Vec<M>>> + Sized {
+    /// Runs the query, then loads `relation` for every model it returned
+    fn with<R: Relation<M>>(
+        self,
+        relation: R,
+        db: &Database,
+    ) -> impl Future<Output = Result<Vec<(M, R::Loaded)>>> {
+        async move { relation.load(self.await?, db).await }
+    }
+}
+
+impl<M: Model, F: Future<Output = Result<Vec<M>>>> WithRelation<M> for F {}
+
+/// Models whose `column` is one of `keys`, in chunks of [`IN_CHUNK`].
+async fn find_in<M: Model>(column: &str, keys: Vec<i64>, db: &Database) -> Result<Vec<M>> {
+    let mut models = Vec::new();
+    for chunk in keys.chunks(IN_CHUNK) {
+        let (sql, params) = QueryBuilder::new(M::table_name())
+            .r#where(FilterOperator::Single(Filter::in_values(
+                column,
+                chunk.to_vec(),
+            )))
+            .build()?;
+        let mut rows = db.query(&sql, params).await?;
+        while let Some(row) = rows.next().await? {
+            models.push(M::from_map(M::row_to_map(&row)?)?);
+        }
+    }
+    Ok(models)
+}
+
+/// The integer in a model's `column`, `None` for NULL.
+fn foreign_key_of<M: Model>(model: &M, column: &str) -> Result<Option<i64>> {
+    match model.to_map()?.get(column) {
+        Some(Value::Integer(id)) => Ok(Some(*id)),
+        Some(Value::Null) => Ok(None),
+        Some(other) => Err(crate::Error::Validation(format!(
+            "{}.{column} is not an integer key: {other:?}",
+            M::table_name()
+        ))),
+        None => Err(crate::Error::Validation(format!(
+            "{} has no column {column}",
+            M::table_name()
+        ))),
+    }
+}
+
+fn unique(keys: impl Iterator<Item = i64>) -> Vec<i64> {
+    let mut keys: Vec<i64> = keys.collect();
+    keys.sort_unstable();
+    keys.dedup();
+    keys
+}
@@ -0,0 +1,591 @@
+//! Schema introspection and diffing
+//!
+//! [`SchemaDiff::for_model`] compares a table in a live database with what
+//! [`Model::migration_sql`] would create and produces the statements that
+//! bring the table in line: `CREATE TABLE` for a missing table, `ALTER TABLE
+//! ... ADD COLUMN` where SQLite allows it, and a table rebuild (create a new
+//! table, copy the rows, drop the old one, rename) for everything else, such
+//! as dropped columns or changed types and constraints.
+//!
+//! The result is meant to be reviewed and saved as a migration, not run
+//! blindly: [`SchemaDiff::to_sql`] renders it with the reasons and warnings
+//! as comments. Requires the `local` feature, the desired schema is read
+//! from a scratch in-memory database.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use libsql_orm::{Database, Model, SchemaDiff};
+//!
+//! async fn check<M: Model>(db: &Database) -> Result<(), Box<dyn std::error::Error>> {
+//!     let diff = SchemaDiff::for_model::<M>(db).await?;
+//!     if !diff.is_empty() {
+//!         std::fs::write(format!("migrations/{}_schema.sql", diff.table), diff.to_sql())?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::migrations::Migration;
+use crate::model::Model;
+
+/// A column as reported by `PRAGMA table_info`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSchema {
+    pub name: String,
+    /// Declared type, empty when the column has none.
+    pub sql_type: String,
+    pub not_null: bool,
+    /// Default as written in the `CREATE TABLE`, e.g. `'active'`.
+    pub default: Option<String>,
+    pub primary_key: bool,
+}
+
+/// An index as reported by `PRAGMA index_list` and `index_info`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexSchema {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+    /// `c` for `CREATE INDEX`, `u` for a UNIQUE and `pk` for a PRIMARY KEY
+    /// constraint.
+    pub origin: String,
+    /// The `CREATE INDEX` statement, `None` for constraint indexes.
+    pub sql: Option<String>,
+}
+
+/// A foreign key as reported by `PRAGMA foreign_key_list`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKeySchema {
+    pub column: String,
+    pub table: String,
+    pub to: String,
+}
+
+/// A trigger or view using a table, from `sqlite_master`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependentSchema {
+    /// `trigger` or `view`
+    pub kind: String,
+    pub name: String,
+    /// The `CREATE TRIGGER` or `CREATE VIEW` statement.
+    pub sql: String,
+}
+
+/// The structure of a table in a database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableSchema {
+    pub name: String,
+    /// The `CREATE TABLE` statement as stored in `sqlite_master`.
+    pub sql: String,
+    pub columns: Vec<ColumnSchema>,
+    pub indexes: Vec<IndexSchema>,
+    pub foreign_keys: Vec<ForeignKeySchema>,
+    /// Triggers on the table and triggers or views naming it, such as the
+    /// ones keeping an FTS index in sync.
+    pub dependents: Vec<DependentSchema>,
+}
+
+impl TableSchema {
+    /// Reads a table's structure, `None` if it does not exist
+    pub async fn load(db: &Database, table: &str) -> Result<Option<Self>> {
+        let conn = db.get_connection();
+        let mut rows = conn
+            .query(
+                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?",
+                [table],
+            )
+            .await?;
+        let Some(row) = rows.next().await? else {
+            return Ok(None);
+        };
+        let sql: String = row.get(0)?;
+
+        let mut columns = Vec::new();
+        let mut rows = conn
+            .query(&format!("PRAGMA table_info({})", quote(table)), ())
+            .await?;
+        while let Some(row) = rows.next().await? {
+            columns.push(ColumnSchema {
+                name: row.get(1)?,
+                sql_type: row.get(2)?,
+                not_null: row.get::<i64>(3)? != 0,
+                default: row.get(4)?,
+                primary_key: row.get::<i64>(5)? != 0,
+            });
+        }
+
+        let mut indexes = Vec::new();
+        let mut rows = conn
+            .query(&format!("PRAGMA index_list({})", quote(table)), ())
+            .await?;
+        while let Some(row) = rows.next().await? {
+            indexes.push(IndexSchema {
+                name: row.get(1)?,
+                columns: Vec::new(),
+                unique: row.get::<i64>(2)? != 0,
+                origin: row.get(3)?,
+                sql: None,
+            });
+        }
+        for index in &mut indexes {
+            let mut rows = conn
+                .query(&format!("PRAGMA index_info({})", quote(&index.name)), ())
+                .await?;
+            while let Some(row) = rows.next().await? {
+                // Expression indexes have no column name
+                index
+                    .columns
+                    .push(row.get::<Option<String>>(2)?.unwrap_or_default());
+            }
+            let mut rows = conn
+                .query(
+                    "SELECT sql FROM sqlite_master WHERE type = 'index' AND name = ?",
+                    [index.name.as_str()],
+                )
+                .await?;
+            if let Some(row) = rows.next().await? {
+                index.sql = row.get(0)?;
+            }
+        }
+        indexes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut foreign_keys = Vec::new();
+        let mut rows = conn
+            .query(&format!("PRAGMA foreign_key_list({})", quote(table)), ())
+            .await?;
+        while let Some(row) = rows.next().await? {
+            foreign_keys.push(ForeignKeySchema {
+                table: row.get(2)?,
+                column: row.get(3)?,
+                to: row.get::<Option<String>>(4)?.unwrap_or_default(),
+            });
+        }
+
+        let mut dependents = Vec::new();
+        let mut rows = conn
+            .query(
+                "SELECT type, name, tbl_name, sql FROM sqlite_master WHERE type IN ('trigger', 'view') AND sql IS NOT NULL ORDER BY name",
+                (),
+            )
+            .await?;
+        while let Some(row) = rows.next().await? {
+            let owner: String = row.get(2)?;
+            let sql: String = row.get(3)?;
+            if owner == table || names(&sql, table) {
+                dependents.push(DependentSchema {
+                    kind: row.get(0)?,
+                    name: row.get(1)?,
+                    sql,
+                });
+            }
+        }
+
+        Ok(Some(TableSchema {
+            name: table.to_string(),
+            sql,
+            columns,
+            indexes,
+            foreign_keys,
+            dependents,
+        }))
+    }
+
+    /// The structure `M::migration_sql()` creates, read from a scratch
+    /// in-memory database
+    pub async fn of_model<M: Model>() -> Result<Self> {
+        let scratch = Database::open_memory().await?;
+        scratch
+            .get_connection()
+            .execute_batch(&M::migration_sql())
+            .await?;
+        TableSchema::load(&scratch, M::table_name())
+            .await?
+            .ok_or_else(|| {
+                crate::Error::Validation(format!(
+                    "migration_sql() of {} does not create the table",
+                    M::table_name()
+                ))
+            })
+    }
+
+    pub fn column(&self, name: &str) -> Option<&ColumnSchema> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Whether a UNIQUE or PRIMARY KEY constraint covers exactly `column`.
+    fn is_unique(&self, column: &str) -> bool {
+        self.indexes
+            .iter()
+            .any(|index| index.origin != "c" && index.columns == [column])
+    }
+
+    fn foreign_key(&self, column: &str) -> Option<&ForeignKeySchema> {
+        self.foreign_keys.iter().find(|fk| fk.column == column)
+    }
+
+    /// Indexes created with `CREATE INDEX`, as opposed to constraints.
+    fn created_indexes(&self) -> impl Iterator<Item = &IndexSchema> {
+        self.indexes.iter().filter(|index| index.origin == "c")
+    }
+}
+
+/// Statements turning a live table into the one a model describes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    pub table: String,
+    /// Statements to run, in order.
+    pub statements: Vec<String>,
+    /// Why the table has to be rebuilt, empty if it does not.
+    pub rebuild_reasons: Vec<String>,
+    /// Things to check before running the statements, such as data loss.
+    pub warnings: Vec<String>,
+}
+
+impl SchemaDiff {
+    /// Compares the model's table in `db` with its `migration_sql()`
+    pub async fn for_model<M: Model>(db: &Database) -> Result<Self> {
+        let desired = TableSchema::of_model::<M>().await?;
+        let current = TableSchema::load(db, M::table_name()).await?;
+        Ok(SchemaDiff::between(current.as_ref(), &desired))
+    }
+
+    /// The statements turning `current`, `None` for a missing table, into
+    /// `desired`
+    pub fn between(current: Option<&TableSchema>, desired: &TableSchema) -> Self {
+        let mut diff = SchemaDiff {
+            table: desired.name.clone(),
+            ..SchemaDiff::default()
+        };
+
+        let Some(current) = current else {
+            diff.statements.push(desired.sql.clone());
+            diff.statements.extend(
+                desired
+                    .created_indexes()
+                    .filter_map(|index| index.sql.clone()),
+            );
+            return diff;
+        };
+
+        let mut added = Vec::new();
+        for column in &desired.columns {
+            match current.column(&column.name) {
+                Some(existing) => diff.compare_column(current, existing, desired, column),
+                None => added.push(column),
+            }
+        }
+        for column in &current.columns {
+            if desired.column(&column.name).is_none() {
+                diff.rebuild_reasons
+                    .push(format!("column `{}` is removed", column.name));
+                diff.warnings.push(format!(
+                    "the data in `{}` is lost, a renamed column shows up as removed and added",
+                    column.name
+                ));
+            }
+        }
+
+        let mut additions = Vec::new();
+        for column in &added {
+            match add_column_blocker(desired, column) {
+                Some(reason) => diff.rebuild_reasons.push(format!(
+                    "column `{}` cannot be added with ALTER TABLE: {reason}",
+                    column.name
+                )),
+                None => additions.push(format!(
+                    "ALTER TABLE {} ADD COLUMN {}",
+                    quote(&desired.name),
+                    column_definition(desired, column)
+                )),
+            }
+            if column.not_null && column.default.is_none() {
+                diff.warnings.push(format!(
+                    "`{}` is NOT NULL without a default, existing rows need a value",
+                    column.name
+                ));
+            }
+        }
+
+        if diff.rebuild_reasons.is_empty() {
+            diff.statements.extend(additions);
+            diff.sync_indexes(current, desired);
+        } else {
+            diff.rebuild(current, desired);
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    /// Whether the table has to be recreated.
+    pub fn needs_rebuild(&self) -> bool {
+        !self.rebuild_reasons.is_empty()
+    }
+
+    /// A migration file to review, with the reasons for a rebuild and any
+    /// warnings as comments
+    pub fn to_sql(&self) -> String {
+        let mut sql = format!("-- Schema changes for {}\n", self.table);
+        if self.needs_rebuild() {
+            sql.push_str("-- SQLite cannot make these changes in place, the table is rebuilt:\n");
+            for reason in &self.rebuild_reasons {
+                sql.push_str(&format!("--   {reason}\n"));
+            }
+        }
+        for warning in &self.warnings {
+            sql.push_str(&format!("-- WARNING: {warning}\n"));
+        }
+        for statement in &self.statements {
+            sql.push('\n');
+            sql.push_str(statement.trim_end_matches(';'));
+            sql.push_str(";\n");
+        }
+        sql
+    }
+
+    /// The changes as a migration, to be added to the app's list once reviewed
+    pub fn to_migration(&self, version: i64) -> Migration {
+        Migration::new(version, format!("{}_schema", self.table), self.to_sql())
+    }
+
+    fn compare_column(
+        &mut self,
+        current_table: &TableSchema,
+        current: &ColumnSchema,
+        desired_table: &TableSchema,
+        desired: &ColumnSchema,
+    ) {
+        let name = &desired.name;
+        if !current
+            .sql_type
+            .trim()
+            .eq_ignore_ascii_case(desired.sql_type.trim())
+        {
+            self.rebuild_reasons.push(format!(
+                "column `{name}` changes type from {} to {}",
+                current.sql_type, desired.sql_type
+            ));
+        }
+        if current.not_null != desired.not_null {
+            self.rebuild_reasons.push(format!(
+                "column `{name}` {} NOT NULL",
+                if desired.not_null { "becomes" } else { "drops" }
+            ));
+            if desired.not_null && desired.default.is_none() {
+                self.warnings.push(format!(
+                    "rows with a NULL `{name}` fail to copy into the rebuilt table"
+                ));
+            }
+        }
+        if normalize_default(&current.default) != normalize_default(&desired.default) {
+            self.rebuild_reasons.push(format!(
+                "column `{name}` changes default from {} to {}",
+                current.default.as_deref().unwrap_or("none"),
+                desired.default.as_deref().unwrap_or("none")
+            ));
+        }
+        if current.primary_key != desired.primary_key {
+            self.rebuild_reasons
+                .push(format!("column `{name}` changes its primary key"));
+        }
+        if current_table.is_unique(name) != desired_table.is_unique(name) {
+            self.rebuild_reasons
+                .push(format!("column `{name}` changes its UNIQUE constraint"));
+        }
+        if current_table.foreign_key(name) != desired_table.foreign_key(name) {
+            self.rebuild_reasons
+                .push(format!("column `{name}` changes its foreign key"));
+        }
+    }
+
+    /// Creates missing or changed indexes. Indexes the model does not declare
+    /// are kept, e.g. composite ones from hand-written migrations.
+    fn sync_indexes(&mut self, current: &TableSchema, desired: &TableSchema) {
+        for index in desired.created_indexes() {
+            let existing = current.created_indexes().find(|i| i.name == index.name);
+            if existing.is_some_and(|i| i.columns == index.columns && i.unique == index.unique) {
+                continue;
+            }
+            if existing.is_some() {
+                self.statements
+                    .push(format!("DROP INDEX IF EXISTS {}", quote(&index.name)));
+            }
+            self.statements.extend(index.sql.clone());
+        }
+    }
+
+    /// The table rebuild from https://www.sqlite.org/lang_altertable.html:
+    /// create the new table, copy the rows over, drop the old table and
+    /// rename the new one, then recreate the indexes, triggers and views.
+    ///
+    /// Foreign keys are switched off so dropping the old table does not
+    /// cascade into child tables. SQLite ignores that inside a transaction,
+    /// where only the deferred checks apply.
+    fn rebuild(&mut self, current: &TableSchema, desired: &TableSchema) {
+        let table = quote(&desired.name);
+        let new_table = quote(&format!("_new_{}", desired.name));
+        let body = desired
+            .sql
+            .find('(')
+            .map(|start| &desired.sql[start..])
+            .unwrap_or_default();
+        let copied: Vec<String> = desired
+            .columns
+            .iter()
+            .filter(|column| current.column(&column.name).is_some())
+            .map(|column| quote(&column.name))
+            .collect();
+        let copied_count = copied.len();
+        let copied = copied.join(", ");
+
+        self.statements.extend([
+            "PRAGMA foreign_keys = OFF".to_string(),
+            "PRAGMA defer_foreign_keys = ON".to_string(),
+        ]);
+        // Views naming the table would stop the rename, triggers on it go
+        // with the table
+        for dependent in &current.dependents {
+            self.statements.push(format!(
+                "DROP {} IF EXISTS {}",
+                dependent.kind.to_uppercase(),
+                quote(&dependent.name)
+            ));
+        }
+        self.statements.extend([
+            format!("CREATE TABLE {new_table} {body}"),
+            format!("INSERT INTO {new_table} ({copied}) SELECT {copied} FROM {table}"),
+            format!("DROP TABLE {table}"),
+            format!("ALTER TABLE {new_table} RENAME TO {table}"),
+        ]);
+        self.statements.extend(
+            desired
+                .created_indexes()
+                .filter_map(|index| index.sql.clone()),
+        );
+
+        // Dropping the table dropped its other indexes too
+        for index in current.created_indexes() {
+            let declared = desired.created_indexes().any(|i| i.name == index.name);
+            let columns_kept = index
+                .columns
+                .iter()
+                .all(|column| desired.column(column).is_some());
+            match (&index.sql, declared) {
+                (_, true) => {}
+                (Some(sql), false) if columns_kept => self.statements.push(sql.clone()),
+                _ => self.warnings.push(format!(
+                    "index `{}` is dropped with the columns it covers",
+                    index.name
+                )),
+            }
+        }
+
+        self.statements.extend(
+            current
+                .dependents
+                .iter()
+                .map(|dependent| dependent.sql.clone()),
+        );
+        if current.columns.len() != copied_count {
+            for dependent in &current.dependents {
+                self.warnings.push(format!(
+                    "{} `{}` is recreated as it was, check it does not use a removed column",
+                    dependent.kind, dependent.name
+                ));
+            }
+        }
+        self.statements.extend([
+            "PRAGMA foreign_key_check".to_string(),
+            "PRAGMA foreign_keys = ON".to_string(),
+        ]);
+    }
+}
+
+/// Why `ALTER TABLE ADD COLUMN` cannot add the column, see
+/// https://www.sqlite.org/lang_altertable.html#altertabaddcol
+fn add_column_blocker(table: &TableSchema, column: &ColumnSchema) -> Option<&'static str> {
+    let default = normalize_default(&column.default);
+    if column.primary_key {
+        Some("it is the primary key")
+    } else if table.is_unique(&column.name) {
+        Some("it is UNIQUE")
+    } else if column.not_null && default.is_none() {
+        Some("it is NOT NULL without a default")
+    } else if default
+        .as_deref()
+        .is_some_and(|default| default.starts_with('(') || default.starts_with("CURRENT_"))
+    {
+        Some("its default is not a constant")
+    } else if table.foreign_key(&column.name).is_some() && default.is_some_and(|d| d != "NULL") {
+        Some("it has a foreign key and a default")
+    } else {
+        None
+    }
+}
+
+/// The column as written in `ALTER TABLE ADD COLUMN`.
+fn column_definition(table: &TableSchema, column: &ColumnSchema) -> String {
+    let mut definition = quote(&column.name);
+    if !column.sql_type.is_empty() {
+        definition.push_str(&format!(" {}", column.sql_type));
+    }
+    if column.not_null {
+        definition.push_str(" NOT NULL");
+    }
+    if let Some(default) = &column.default {
+        definition.push_str(&format!(" DEFAULT {default}"));
+    }
+    if let Some(fk) = table.foreign_key(&column.name) {
+        definition.push_str(&format!(
+            " REFERENCES {}({})",
+            quote(&fk.table),
+            quote(&fk.to)
+        ));
+    }
+    definition
+}
+
+/// Defaults compared by meaning: keywords are case-insensitive and
+/// `TRUE`/`FALSE` are `1`/`0`.
+fn normalize_default(default: &Option<String>) -> Option<String> {
+    let default = default.as_deref()?.trim();
+    if default.starts_with('\'') || default.starts_with('"') {
+        return Some(default.to_string());
+    }
+    Some(match default.to_ascii_uppercase().as_str() {
+        "TRUE" => "1".to_string(),
+        "FALSE" => "0".to_string(),
+        other => other.to_string(),
+    })
+}
+
+/// Quotes an identifier only where needed, keeping generated SQL readable.
+/// Whether `sql` mentions `name` as a whole identifier, ignoring case.
+fn names(sql: &str, name: &str) -> bool {
+    let sql = sql.to_lowercase();
+    let name = name.to_lowercase();
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    sql.match_indices(&name).any(|(start, _)| {
+        let before = sql[..start].chars().next_back();
+        let after = sql[start + name.len()..].chars().next();
+        !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+    })
+}
+
+fn quote(name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
@@ -102,3 +102,1531 @@ mod value_tests {
         assert_eq!(value, Value::Null);
     }
 }
+
+#[cfg(test)]
+mod query_tests {
+    use crate::{Filter, FilterOperator, QueryBuilder};
+
+    #[test]
+    fn test_count_keeps_filters() {
+        let builder = QueryBuilder::new("items")
+            .r#where(FilterOperator::Single(Filter::eq("owner_id", "42")))
+            .limit(10)
+            .offset(20);
+
+        let (sql, params) = builder.build_count().unwrap();
+        assert_eq!(sql, "SELECT COUNT(*) FROM items WHERE owner_id = ?");
+        assert_eq!(params.len(), 1);
+    }
+}
+
+#[cfg(all(test, feature = "local"))]
+mod database_tests {
+    use crate::Database;
+
+    async fn count(db: &Database) -> i64 {
+        let mut rows = db
+            .get_connection()
+            .query("SELECT COUNT(*) FROM notes", ())
+            .await
+            .unwrap();
+        rows.next().await.unwrap().unwrap().get(0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_open_memory() {
+        let db = Database::open_memory().await.unwrap();
+        db.get_connection()
+            .execute_batch("CREATE TABLE notes (body TEXT); INSERT INTO notes VALUES ('a');")
+            .await
+            .unwrap();
+
+        // Connections to an in-memory database see the same data
+        assert_eq!(count(&db.connect().unwrap()).await, 1);
+
+        // Every in-memory database starts empty
+        let other = Database::open(":memory:", "").await.unwrap();
+        assert!(other
+            .get_connection()
+            .query("SELECT COUNT(*) FROM notes", ())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_open_local() {
+        let path = std::env::temp_dir().join(format!("libsql-orm-{}.db", uuid::Uuid::new_v4()));
+        let url = format!("file:{}", path.display());
+
+        {
+            let db = Database::open(&url, "").await.unwrap();
+            db.get_connection()
+                .execute_batch("CREATE TABLE notes (body TEXT); INSERT INTO notes VALUES ('a');")
+                .await
+                .unwrap();
+            assert_eq!(count(&db.connect().unwrap()).await, 1);
+        }
+
+        let reopened = Database::open_local(&path).await.unwrap();
+        assert_eq!(count(&reopened).await, 1);
+        drop(reopened);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[cfg(all(test, feature = "local"))]
+mod migration_tests {
+    use crate::{Database, Error, Migration, MigrationManager};
+
+    async fn memory_db() -> Database {
+        Database::open_memory().await.unwrap()
+    }
+
+    fn migrations() -> Vec<Migration> {
+        vec![
+            Migration::new(2, "users_name", "ALTER TABLE users ADD COLUMN name TEXT;")
+                .with_down("ALTER TABLE users DROP COLUMN name;"),
+            Migration::new(
+                1,
+                "create_users",
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL);",
+            )
+            .with_down("DROP TABLE users;"),
+        ]
+    }
+
+    async fn columns(manager: &MigrationManager) -> Vec<String> {
+        let mut rows = manager
+            .database()
+            .get_connection()
+            .query("SELECT name FROM pragma_table_info('users')", ())
+            .await
+            .unwrap();
+        let mut columns = Vec::new();
+        while let Some(row) = rows.next().await.unwrap() {
+            columns.push(row.get::<String>(0).unwrap());
+        }
+        columns
+    }
+
+    #[test]
+    fn test_checksum() {
+        let migration = Migration::new(1, "a", "CREATE TABLE a (id INTEGER);\n");
+        let crlf = Migration::new(1, "a", "  CREATE TABLE a (id INTEGER);\r\n");
+        let edited = Migration::new(1, "a", "CREATE TABLE a (id INTEGER, b TEXT);");
+
+        assert_eq!(migration.checksum(), crlf.checksum());
+        assert_ne!(migration.checksum(), edited.checksum());
+        assert_eq!(migration.checksum().len(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_versions() {
+        let manager = MigrationManager::new(memory_db().await);
+
+        let unversioned = MigrationManager::create_migration("a", "SELECT 1;");
+        assert!(matches!(
+            manager.execute_migration(&unversioned).await,
+            Err(Error::Validation(_))
+        ));
+
+        let duplicate = vec![
+            Migration::new(1, "a", "SELECT 1;"),
+            Migration::new(1, "b", "SELECT 2;"),
+        ];
+        assert!(matches!(
+            manager.migrate(&duplicate).await,
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_migrate_in_order_once() {
+        let manager = MigrationManager::new(memory_db().await);
+
+        let plan = manager.plan(&migrations()).await.unwrap();
+        assert_eq!(plan.pending(), vec![1, 2]);
+        assert!(columns(&manager).await.is_empty());
+
+        let report = manager.migrate(&migrations()).await.unwrap();
+        assert_eq!(report.applied(), vec![1, 2]);
+        assert_eq!(columns(&manager).await, vec!["id", "email", "name"]);
+
+        let report = manager.migrate(&migrations()).await.unwrap();
+        assert!(report.applied().is_empty());
+
+        let applied = manager.applied().await.unwrap();
+        assert_eq!(applied.len(), 2);
+        assert_eq!(applied[0].name, "create_users");
+        assert_eq!(applied[1].checksum, migrations()[0].checksum());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_changes_nothing() {
+        let manager = MigrationManager::new(memory_db().await).dry_run(true);
+
+        let report = manager.migrate(&migrations()).await.unwrap();
+        assert_eq!(report.pending(), vec![1, 2]);
+        assert!(report.applied().is_empty());
+        assert!(columns(&manager).await.is_empty());
+        assert!(manager.applied().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_detects_edited_migrations() {
+        let manager = MigrationManager::new(memory_db().await);
+        manager.migrate(&migrations()).await.unwrap();
+
+        let mut edited = migrations();
+        edited[1].up = "CREATE TABLE users (id INTEGER PRIMARY KEY);".to_string();
+        let err = manager.migrate(&edited).await.unwrap_err();
+        assert!(matches!(err, Error::Migration(_)));
+        assert!(err.to_string().contains("create_users"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_is_not_recorded() {
+        let manager = MigrationManager::new(memory_db().await);
+        let mut broken = migrations();
+        broken.push(Migration::new(
+            3,
+            "broken",
+            "ALTER TABLE users ADD COLUMN age INTEGER; ALTER TABLE missing ADD COLUMN x TEXT;",
+        ));
+
+        assert!(manager.migrate(&broken).await.is_err());
+        let applied: Vec<i64> = manager
+            .applied()
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.version)
+            .collect();
+        assert_eq!(applied, vec![1, 2]);
+        assert_eq!(columns(&manager).await, vec!["id", "email", "name"]);
+
+        // The lock was released despite the failure
+        assert!(manager
+            .migrate(&migrations())
+            .await
+            .unwrap()
+            .applied()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rollback() {
+        let manager = MigrationManager::new(memory_db().await);
+        manager.migrate(&migrations()).await.unwrap();
+
+        let report = manager.rollback_to(&migrations(), 1).await.unwrap();
+        assert_eq!(report.applied(), vec![2]);
+        assert_eq!(columns(&manager).await, vec!["id", "email"]);
+
+        let without_down = vec![Migration::new(
+            1,
+            "create_users",
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL);",
+        )];
+        assert!(matches!(
+            manager.rollback_to(&without_down, 0).await,
+            Err(Error::Migration(_))
+        ));
+
+        manager.rollback_to(&migrations(), 0).await.unwrap();
+        assert!(columns(&manager).await.is_empty());
+        assert!(manager.applied().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rollback_before_first_run() {
+        let manager = MigrationManager::new(memory_db().await);
+        let report = manager.rollback_to(&migrations(), 0).await.unwrap();
+        assert!(report.applied().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_lock() {
+        let manager = MigrationManager::new(memory_db().await);
+        manager.migrate(&[]).await.unwrap();
+
+        // Another instance is running
+        let now = chrono::Utc::now().to_rfc3339();
+        manager
+            .database()
+            .get_connection()
+            .execute(
+                "INSERT INTO _migrations_lock (id, owner, locked_at) VALUES (1, 'other', ?)",
+                [now],
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            manager.migrate(&migrations()).await,
+            Err(Error::Migration(_))
+        ));
+        assert!(manager.applied().await.unwrap().is_empty());
+
+        // Until its lock goes stale
+        let manager = manager.stale_lock_after(-1);
+        assert_eq!(
+            manager.migrate(&migrations()).await.unwrap().applied(),
+            vec![1, 2]
+        );
+    }
+}
+
+#[cfg(all(test, feature = "local"))]
+mod transaction_tests {
+    use crate::{Database, Error, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("notes")]
+    struct Note {
+        pub id: Option<i64>,
+        pub body: String,
+    }
+
+    fn note(body: &str) -> Note {
+        Note {
+            id: None,
+            body: body.to_string(),
+        }
+    }
+
+    async fn notes_db() -> Database {
+        let db = Database::open_memory().await.unwrap();
+        db.get_connection()
+            .execute(
+                "CREATE TABLE notes (id INTEGER PRIMARY KEY AUTOINCREMENT, body TEXT NOT NULL UNIQUE)",
+                (),
+            )
+            .await
+            .unwrap();
+        db
+    }
+
+    async fn bodies(db: &Database) -> Vec<String> {
+        let mut bodies: Vec<String> = Note::find_all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|note| note.body)
+            .collect();
+        bodies.sort();
+        bodies
+    }
+
+    #[tokio::test]
+    async fn test_commit_and_rollback() {
+        let db = notes_db().await;
+
+        db.transaction(|tx| async move {
+            note("a").create(&tx).await?;
+            note("b").create(&tx).await?;
+            Ok::<_, Error>(())
+        })
+        .await
+        .unwrap();
+
+        let result = db
+            .transaction(|tx| async move {
+                note("c").create(&tx).await?;
+                Err::<(), _>(Error::Validation("changed my mind".to_string()))
+            })
+            .await;
+        assert!(result.is_err());
+
+        assert_eq!(bodies(&db).await, vec!["a", "b"]);
+        assert!(db.get_connection().is_autocommit());
+    }
+
+    #[tokio::test]
+    async fn test_nested_savepoint() {
+        let db = notes_db().await;
+
+        db.transaction(|tx| async move {
+            note("outer").create(&tx).await?;
+            let inner = tx
+                .transaction(|tx| async move {
+                    note("inner").create(&tx).await?;
+                    // Duplicate body, fails the savepoint only
+                    note("outer").create(&tx).await
+                })
+                .await;
+            assert!(inner.is_err());
+            Ok::<_, Error>(())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(bodies(&db).await, vec!["outer"]);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_create_is_atomic() {
+        let db = notes_db().await;
+
+        let created = Note::bulk_create(&[note("a"), note("b")], &db)
+            .await
+            .unwrap();
+        assert_eq!(created.len(), 2);
+        assert!(created.iter().all(|note| note.id.is_some()));
+
+        // The duplicate fails the batch, "c" is not kept
+        assert!(Note::bulk_create(&[note("c"), note("a")], &db)
+            .await
+            .is_err());
+        assert_eq!(bodies(&db).await, vec!["a", "b"]);
+        assert!(db.get_connection().is_autocommit());
+    }
+}
+
+#[cfg(all(test, feature = "pool", feature = "local"))]
+mod pool_tests {
+    use std::time::Duration;
+
+    use crate::{Pool, PoolConfig};
+
+    fn config(min_size: usize, max_size: usize) -> PoolConfig {
+        PoolConfig {
+            min_size,
+            max_size,
+            acquire_timeout: Duration::from_millis(50),
+            ..PoolConfig::default()
+        }
+    }
+
+    /// A pool over a fresh SQLite file, removed when the guard drops
+    struct TempFile(std::path::PathBuf);
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            for suffix in ["", "-journal", "-wal", "-shm"] {
+                let mut path = self.0.clone().into_os_string();
+                path.push(suffix);
+                std::fs::remove_file(path).ok();
+            }
+        }
+    }
+
+    async fn file_pool(name: &str, config: PoolConfig) -> (Pool, TempFile) {
+        let path = std::env::temp_dir().join(format!(
+            "libsql-orm-{name}-{}-{}.db",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let pool = Pool::new(&format!("file:{}", path.display()), "", config)
+            .await
+            .unwrap();
+        (pool, TempFile(path))
+    }
+
+    #[tokio::test]
+    async fn test_reuses_connections() {
+        let (pool, _file) = file_pool("reuse", config(1, 4)).await;
+        assert_eq!(pool.metrics().created, 1);
+
+        for _ in 0..3 {
+            let db = pool.get().await.unwrap();
+            db.get_connection().query("SELECT 1", ()).await.unwrap();
+        }
+        let metrics = pool.metrics();
+        assert_eq!(metrics.created, 1);
+        assert_eq!(metrics.acquired, 3);
+        assert_eq!(metrics.idle, 1);
+        assert_eq!(metrics.in_use, 0);
+
+        let first = pool.get().await.unwrap();
+        let _second = pool.get().await.unwrap();
+        assert_eq!(pool.metrics().created, 2);
+        assert_eq!(pool.metrics().in_use, 2);
+        drop(first);
+        assert_eq!(pool.metrics().idle, 1);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_timeout() {
+        let pool = Pool::new(":memory:", "", config(0, 1)).await.unwrap();
+        let held = pool.get().await.unwrap();
+
+        assert!(pool.get().await.is_err());
+        assert_eq!(pool.metrics().timeouts, 1);
+
+        drop(held);
+        assert!(pool.get().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_keeps_min_size() {
+        let (pool, _file) = file_pool(
+            "idle",
+            PoolConfig {
+                idle_timeout: Duration::ZERO,
+                ..config(1, 4)
+            },
+        )
+        .await;
+
+        let first = pool.get().await.unwrap();
+        let second = pool.get().await.unwrap();
+        drop(first);
+        drop(second);
+
+        let metrics = pool.metrics();
+        assert_eq!(metrics.size, 1);
+        assert_eq!(metrics.closed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_open_transaction_is_not_reused() {
+        let (pool, _file) = file_pool("transaction", config(0, 2)).await;
+        let db = pool.get().await.unwrap();
+        db.get_connection().execute("BEGIN", ()).await.unwrap();
+        let detached = (*db).clone();
+        drop(db);
+
+        assert_eq!(pool.metrics().idle, 0);
+        assert_eq!(pool.metrics().closed, 1);
+        detached
+            .get_connection()
+            .execute("ROLLBACK", ())
+            .await
+            .unwrap();
+    }
+
+    // Blocked writers sleep in SQLite's busy handler, which needs a second
+    // thread for the other transaction to finish
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_concurrent_writers_wait_for_each_other() {
+        let (pool, _file) = file_pool("writers", config(2, 2)).await;
+        pool.get()
+            .await
+            .unwrap()
+            .get_connection()
+            .execute("CREATE TABLE writes (writer INTEGER, step INTEGER)", ())
+            .await
+            .unwrap();
+
+        let writers: Vec<_> = (0..2)
+            .map(|writer| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let db = pool.get().await?;
+                    db.transaction(|tx| async move {
+                        for step in 0..2 {
+                            tx.get_connection()
+                                .execute(
+                                    "INSERT INTO writes (writer, step) VALUES (?, ?)",
+                                    (writer, step),
+                                )
+                                .await?;
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                        }
+                        Ok::<_, crate::Error>(())
+                    })
+                    .await
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap().unwrap();
+        }
+
+        let db = pool.get().await.unwrap();
+        let mut rows = db
+            .get_connection()
+            .query("SELECT COUNT(*) FROM writes", ())
+            .await
+            .unwrap();
+        let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+        assert_eq!(count, 4);
+    }
+
+    #[tokio::test]
+    async fn test_memory_pool_has_one_connection() {
+        let pool = Pool::new(":memory:", "", config(1, 4)).await.unwrap();
+        assert_eq!(pool.config().max_size, 1);
+        assert_eq!(pool.metrics().max_size, 1);
+
+        let held = pool.get().await.unwrap();
+        assert!(pool.get().await.is_err());
+        drop(held);
+
+        let db = pool.get().await.unwrap();
+        db.get_connection()
+            .execute_batch("CREATE TABLE notes (id INTEGER); BEGIN; INSERT INTO notes VALUES (1)")
+            .await
+            .unwrap();
+        drop(db);
+
+        // The next holder does not inherit the abandoned transaction
+        let db = pool.get().await.unwrap();
+        assert!(db.get_connection().is_autocommit());
+        let mut rows = db
+            .get_connection()
+            .query("SELECT COUNT(*) FROM notes", ())
+            .await
+            .unwrap();
+        let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_sizes() {
+        assert!(Pool::new(":memory:", "", config(2, 1)).await.is_err());
+        assert!(Pool::new(":memory:", "", config(0, 0)).await.is_err());
+    }
+}
+
+#[cfg(all(test, feature = "local"))]
+mod column_tests {
+    use crate::{Database, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[table_name("posts")]
+    struct Post {
+        pub id: Option<i64>,
+        #[orm_column(not_null, unique)]
+        pub slug: String,
+        #[orm_column(rename = "body_text", default = "it's empty")]
+        pub body: String,
+        #[orm_column(references(users.id), index)]
+        pub author_id: Option<i64>,
+        #[orm_column(default = -1)]
+        pub views: i64,
+        #[orm_column(default = true)]
+        pub published: bool,
+        #[orm_column(type = "REAL CHECK(score >= 0)")]
+        pub score: f64,
+        #[orm_column(skip)]
+        #[serde(default)]
+        pub cached: Option<String>,
+    }
+
+    #[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[table_name("tags")]
+    struct Tag {
+        #[orm_column(primary_key, auto_increment)]
+        pub tag_id: Option<i64>,
+        pub name: String,
+    }
+
+    fn post() -> Post {
+        Post {
+            id: None,
+            slug: "hello".to_string(),
+            body: "Hello world".to_string(),
+            author_id: Some(7),
+            views: 3,
+            published: true,
+            score: 1.5,
+            cached: Some("not stored".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_migration_sql() {
+        assert_eq!(
+            Post::migration_sql(),
+            "CREATE TABLE IF NOT EXISTS posts (\n    \
+             id INTEGER PRIMARY KEY AUTOINCREMENT,\n    \
+             slug TEXT NOT NULL UNIQUE,\n    \
+             body_text TEXT DEFAULT 'it''s empty',\n    \
+             author_id INTEGER REFERENCES users(id),\n    \
+             views INTEGER DEFAULT -1,\n    \
+             published BOOLEAN DEFAULT 1,\n    \
+             score REAL CHECK(score >= 0)\n\
+             );\n\
+             CREATE INDEX IF NOT EXISTS idx_posts_author_id ON posts(author_id)"
+        );
+        assert_eq!(
+            Post::columns(),
+            vec![
+                "id",
+                "slug",
+                "body_text",
+                "author_id",
+                "views",
+                "published",
+                "score"
+            ]
+        );
+
+        assert_eq!(Tag::primary_key(), "tag_id");
+        assert_eq!(
+            Tag::migration_sql(),
+            "CREATE TABLE IF NOT EXISTS tags (\n    tag_id INTEGER PRIMARY KEY AUTOINCREMENT,\n    name TEXT\n)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_renamed_and_skipped_columns_round_trip() {
+        let db = Database::open_memory().await.unwrap();
+        db.get_connection()
+            .execute_batch(&format!(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY);
+                 INSERT INTO users (id) VALUES (7);
+                 {};
+                 {};",
+                Post::migration_sql(),
+                Tag::migration_sql()
+            ))
+            .await
+            .unwrap();
+
+        let created = post().create(&db).await.unwrap();
+        let found = Post::find_by_id(created.id.unwrap(), &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            found,
+            Post {
+                id: created.id,
+                cached: None,
+                ..post()
+            }
+        );
+
+        let tag = Tag {
+            tag_id: None,
+            name: "breakfast".to_string(),
+        }
+        .create(&db)
+        .await
+        .unwrap();
+        assert!(tag.tag_id.is_some());
+        assert_eq!(
+            Tag::find_by_id(tag.tag_id.unwrap(), &db).await.unwrap(),
+            Some(tag)
+        );
+    }
+}
+
+#[cfg(all(test, feature = "local"))]
+mod schema_tests {
+    use crate::{Database, MigrationManager, Model, SchemaDiff};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[table_name("books")]
+    struct Book {
+        pub id: Option<i64>,
+        #[orm_column(not_null)]
+        pub title: String,
+        #[orm_column(index)]
+        pub author: Option<String>,
+        #[orm_column(default = 0)]
+        pub pages: i64,
+    }
+
+    async fn database(sql: &str) -> Database {
+        let db = Database::open_memory().await.unwrap();
+        db.get_connection().execute_batch(sql).await.unwrap();
+        db
+    }
+
+    async fn apply(db: &Database, diff: &SchemaDiff) {
+        MigrationManager::new(db.clone())
+            .migrate(&[diff.to_migration(1)])
+            .await
+            .unwrap();
+        assert!(SchemaDiff::for_model::<Book>(db).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_creates_missing_table() {
+        let db = database("").await;
+        let diff = SchemaDiff::for_model::<Book>(&db).await.unwrap();
+        assert!(!diff.needs_rebuild());
+        assert_eq!(diff.statements.len(), 2);
+        assert!(diff.statements[0].starts_with("CREATE TABLE"));
+        assert!(diff.statements[1].contains("idx_books_author"));
+
+        apply(&db, &diff).await;
+    }
+
+    #[tokio::test]
+    async fn test_adds_columns_in_place() {
+        let db = database(
+            "CREATE TABLE books (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL);
+             INSERT INTO books (title) VALUES ('Dune');",
+        )
+        .await;
+        let diff = SchemaDiff::for_model::<Book>(&db).await.unwrap();
+        assert!(!diff.needs_rebuild(), "{:?}", diff.rebuild_reasons);
+        assert_eq!(
+            diff.statements,
+            vec![
+                "ALTER TABLE books ADD COLUMN author TEXT",
+                "ALTER TABLE books ADD COLUMN pages INTEGER DEFAULT 0",
+                "CREATE INDEX idx_books_author ON books(author)",
+            ]
+        );
+
+        apply(&db, &diff).await;
+        let books = Book::find_all(&db).await.unwrap();
+        assert_eq!(books[0].pages, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rebuilds_table_for_unsupported_changes() {
+        let db = database(
+            "CREATE TABLE books (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT, author TEXT, pages TEXT, isbn TEXT UNIQUE);
+             CREATE INDEX idx_books_title_author ON books(title, author);
+             CREATE INDEX idx_books_isbn_title ON books(isbn, title);
+             INSERT INTO books (title, author, pages, isbn) VALUES ('Dune', 'Herbert', 412, '978');",
+        )
+        .await;
+        let diff = SchemaDiff::for_model::<Book>(&db).await.unwrap();
+        assert!(diff.needs_rebuild());
+        let reasons = diff.rebuild_reasons.join("\n");
+        assert!(reasons.contains("`title` becomes NOT NULL"), "{reasons}");
+        assert!(reasons.contains("`pages` changes type from TEXT to INTEGER"));
+        assert!(reasons.contains("`isbn` is removed"));
+        assert!(diff
+            .warnings
+            .iter()
+            .any(|w| w.contains("idx_books_isbn_title")));
+
+        let sql = diff.to_sql();
+        assert!(sql.contains("CREATE TABLE _new_books"), "{sql}");
+        assert!(sql.contains(
+            "INSERT INTO _new_books (id, title, author, pages) SELECT id, title, author, pages FROM books"
+        ));
+
+        apply(&db, &diff).await;
+        let books = Book::find_all(&db).await.unwrap();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].title, "Dune");
+        assert_eq!(books[0].pages, 412);
+
+        // Indexes the model does not declare survive the rebuild
+        let schema = crate::TableSchema::load(&db, "books")
+            .await
+            .unwrap()
+            .unwrap();
+        let names: Vec<&str> = schema.indexes.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["idx_books_author", "idx_books_title_author"]);
+    }
+
+    #[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[table_name("notes")]
+    #[fts(body)]
+    struct Note {
+        pub id: Option<i64>,
+        pub body: String,
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_keeps_triggers_and_views() {
+        let db = database(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY AUTOINCREMENT, body TEXT, archived INTEGER);",
+        )
+        .await;
+        db.get_connection()
+            .execute_batch(&Note::fts_migration_sql().unwrap())
+            .await
+            .unwrap();
+        db.get_connection()
+            .execute_batch(
+                "CREATE VIEW recent_notes AS SELECT id, body FROM notes ORDER BY id DESC;
+                 INSERT INTO notes (body) VALUES ('tomato soup');",
+            )
+            .await
+            .unwrap();
+
+        let diff = SchemaDiff::for_model::<Note>(&db).await.unwrap();
+        assert!(diff.needs_rebuild());
+        assert!(diff
+            .statements
+            .contains(&"PRAGMA foreign_keys = OFF".to_string()));
+        assert!(diff
+            .statements
+            .contains(&"PRAGMA foreign_key_check".to_string()));
+        MigrationManager::new(db.clone())
+            .migrate(&[diff.to_migration(1)])
+            .await
+            .unwrap();
+        assert!(SchemaDiff::for_model::<Note>(&db).await.unwrap().is_empty());
+
+        // The FTS triggers index rows written after the rebuild
+        Note {
+            id: None,
+            body: "basil pesto".to_string(),
+        }
+        .create(&db)
+        .await
+        .unwrap();
+        let hits = Note::full_text_search("pesto", crate::Ranking::Relevance, &db)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].model.body, "basil pesto");
+        let hits = Note::full_text_search("tomato", crate::Ranking::Relevance, &db)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+
+        let mut rows = db
+            .get_connection()
+            .query("SELECT COUNT(*) FROM recent_notes", ())
+            .await
+            .unwrap();
+        let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+        assert_eq!(count, 2);
+    }
+}
+
+#[cfg(all(test, feature = "local"))]
+mod relation_tests {
+    use crate::{Database, Model, QueryBuilder, WithRelation};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[table_name("authors")]
+    #[has_many(books = Book)]
+    struct Author {
+        pub id: Option<i64>,
+        pub name: String,
+    }
+
+    #[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[table_name("books")]
+    #[belongs_to(author = Author)]
+    #[belongs_to(editor = Author, foreign_key = "edited_by")]
+    struct Book {
+        pub id: Option<i64>,
+        pub author_id: i64,
+        pub edited_by: Option<i64>,
+        pub title: String,
+    }
+
+    async fn library() -> (Database, Vec<Author>) {
+        let db = Database::open_memory().await.unwrap();
+        db.get_connection()
+            .execute_batch(&format!(
+                "{};{};",
+                Author::migration_sql(),
+                Book::migration_sql()
+            ))
+            .await
+            .unwrap();
+
+        let mut authors = Vec::new();
+        for name in ["Le Guin", "Herbert", "Banks"] {
+            let author = Author {
+                id: None,
+                name: name.to_string(),
+            };
+            authors.push(author.create(&db).await.unwrap());
+        }
+        for (author, title) in [(0, "Earthsea"), (0, "The Dispossessed"), (1, "Dune")] {
+            Book {
+                id: None,
+                author_id: authors[author].id.unwrap(),
+                edited_by: (author == 1).then(|| authors[0].id.unwrap()),
+                title: title.to_string(),
+            }
+            .create(&db)
+            .await
+            .unwrap();
+        }
+        (db, authors)
+    }
+
+    #[tokio::test]
+    async fn test_accessors() {
+        let (db, authors) = library().await;
+
+        let books = authors[0].books(&db).await.unwrap();
+        let titles: Vec<&str> = books.iter().map(|book| book.title.as_str()).collect();
+        assert_eq!(titles, vec!["Earthsea", "The Dispossessed"]);
+        assert!(authors[2].books(&db).await.unwrap().is_empty());
+
+        assert_eq!(
+            books[0].author(&db).await.unwrap(),
+            Some(authors[0].clone())
+        );
+        assert_eq!(books[0].editor(&db).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_eager_loading() {
+        let (db, _) = library().await;
+
+        let loaded = Author::find_all(&db)
+            .with(Author::BOOKS, &db)
+            .await
+            .unwrap();
+        let counts: Vec<(&str, usize)> = loaded
+            .iter()
+            .map(|(author, books)| (author.name.as_str(), books.len()))
+            .collect();
+        assert_eq!(counts, vec![("Le Guin", 2), ("Herbert", 1), ("Banks", 0)]);
+
+        let loaded = Book::find_all(&db).with(Book::EDITOR, &db).await.unwrap();
+        let editors: Vec<Option<&str>> = loaded
+            .iter()
+            .map(|(_, editor)| editor.as_ref().map(|editor| editor.name.as_str()))
+            .collect();
+        assert_eq!(editors, vec![None, None, Some("Le Guin")]);
+    }
+
+    #[tokio::test]
+    async fn test_eager_loading_many_keys() {
+        let (db, _) = library().await;
+        let books: Vec<Book> = (0..1200)
+            .map(|i| Book {
+                id: Some(i),
+                author_id: i % 3 + 1,
+                edited_by: None,
+                title: format!("Book {i}"),
+            })
+            .collect();
+
+        // More keys than fit in one IN (...) query
+        let loaded = crate::Relation::load(&Book::AUTHOR, books, &db)
+            .await
+            .unwrap();
+        assert_eq!(loaded.len(), 1200);
+        assert!(loaded.iter().all(|(book, author)| {
+            author.as_ref().and_then(|author| author.id) == Some(book.author_id)
+        }));
+
+        let authors: Vec<Author> = (1..=1200)
+            .map(|i| Author {
+                id: Some(i),
+                name: String::new(),
+            })
+            .collect();
+        let loaded = crate::Relation::load(&Author::BOOKS, authors, &db)
+            .await
+            .unwrap();
+        let total: usize = loaded.iter().map(|(_, books)| books.len()).sum();
+        assert_eq!(total, 3);
+    }
+
+    #[test]
+    fn test_join() {
+        let (sql, _) = Author::BOOKS
+            .join(QueryBuilder::new("authors"), crate::JoinType::Left)
+            .build()
+            .unwrap();
+        assert!(
+            sql.contains("LEFT JOIN books ON books.author_id = authors.id"),
+            "{sql}"
+        );
+    }
+}
+
+#[cfg(all(test, feature = "local"))]
+mod fts_tests {
+    use crate::fts::{highlight_parts, match_query, MATCH_END, MATCH_START};
+    use crate::{Database, Filter, FilterOperator, Model, Ranking};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[table_name("dishes")]
+    #[fts(name, recipe, tokenize = "porter unicode61")]
+    struct Dish {
+        pub id: Option<i64>,
+        pub owner: String,
+        pub name: Option<String>,
+        pub recipe: String,
+    }
+
+    #[derive(Model, Debug, Clone, Serialize, Deserialize)]
+    #[table_name("plain")]
+    struct Plain {
+        pub id: Option<i64>,
+        pub text: String,
+    }
+
+    fn dish(owner: &str, name: &str, recipe: &str) -> Dish {
+        Dish {
+            id: None,
+            owner: owner.to_string(),
+            name: Some(name.to_string()),
+            recipe: recipe.to_string(),
+        }
+    }
+
+    async fn kitchen() -> Database {
+        let db = Database::open_memory().await.unwrap();
+        db.get_connection()
+            .execute_batch(&Dish::migration_sql())
+            .await
+            .unwrap();
+        for dish in [
+            dish("ann", "Tomato soup", "Simmer tomatoes with onion and basil"),
+            dish(
+                "ann",
+                "Pancakes",
+                "Whisk flour, milk and eggs. Serve with tomato jam",
+            ),
+            dish("bob", "Tomato salad", "Slice tomatoes"),
+        ] {
+            dish.create(&db).await.unwrap();
+        }
+        db
+    }
+
+    fn names(hits: &[crate::FullTextMatch<Dish>]) -> Vec<&str> {
+        hits.iter()
+            .map(|hit| hit.model.name.as_deref().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_and_marks_matches() {
+        let db = kitchen().await;
+
+        // Stemming finds "tomatoes", titles weigh more than recipes
+        let hits = Dish::full_text_search("tomato", Ranking::Weighted(vec![10.0, 1.0]), &db)
+            .await
+            .unwrap();
+        assert_eq!(names(&hits)[2], "Pancakes");
+        assert!(hits[0].score >= hits[1].score && hits[1].score >= hits[2].score);
+        assert_eq!(
+            hits[2].highlight("recipe").unwrap(),
+            format!("Whisk flour, milk and eggs. Serve with {MATCH_START}tomato{MATCH_END} jam")
+        );
+        assert!(hits[2]
+            .snippet
+            .contains(&format!("{MATCH_START}tomato{MATCH_END}")));
+
+        // Every word has to match, the last one as a prefix
+        let hits = Dish::full_text_search("soup basi", Ranking::Relevance, &db)
+            .await
+            .unwrap();
+        assert_eq!(names(&hits), vec!["Tomato soup"]);
+
+        let owner = FilterOperator::Single(Filter::eq("owner", "ann"));
+        let hits = Dish::full_text_search_where("tomato", Ranking::Recent, owner, &db)
+            .await
+            .unwrap();
+        assert_eq!(names(&hits), vec!["Pancakes", "Tomato soup"]);
+
+        assert!(Dish::full_text_search("  \"* ", Ranking::Relevance, &db)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(
+            Dish::full_text_search("tomato", Ranking::Weighted(vec![1.0]), &db)
+                .await
+                .is_err()
+        );
+        assert!(Plain::full_text_search("tomato", Ranking::Relevance, &db)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_index_follows_changes() {
+        let db = kitchen().await;
+        let search = |query: &'static str| {
+            let db = db.clone();
+            async move {
+                Dish::full_text_search(query, Ranking::Relevance, &db)
+                    .await
+                    .unwrap()
+                    .len()
+            }
+        };
+
+        let mut pancakes = Dish::find_by_id(2, &db).await.unwrap().unwrap();
+        pancakes.recipe = "Whisk flour, milk and eggs. Serve with maple syrup".to_string();
+        pancakes.update(&db).await.unwrap();
+        assert_eq!(search("tomato").await, 2);
+        assert_eq!(search("syrup").await, 1);
+
+        pancakes.delete(&db).await.unwrap();
+        assert_eq!(search("syrup").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_adds_index_to_existing_rows() {
+        let db = Database::open_memory().await.unwrap();
+        db.get_connection()
+            .execute_batch(
+                "CREATE TABLE dishes (id INTEGER PRIMARY KEY, owner TEXT, name TEXT, recipe TEXT);
+                 INSERT INTO dishes (owner, name, recipe) VALUES ('ann', NULL, 'Boil pasta');",
+            )
+            .await
+            .unwrap();
+        db.get_connection()
+            .execute_batch(&Dish::fts_migration_sql().unwrap())
+            .await
+            .unwrap();
+
+        let hits = Dish::full_text_search("pasta", Ranking::Relevance, &db)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].highlight("name"), None);
+        assert_eq!(Plain::fts_migration_sql(), None);
+    }
+
+    #[test]
+    fn test_match_query() {
+        assert_eq!(
+            match_query(r#"tomato "soup" -x"#).as_deref(),
+            Some(r#""tomato"* "soup"* "-x"*"#)
+        );
+        assert_eq!(match_query(" - * "), None);
+    }
+
+    #[test]
+    fn test_highlight_parts() {
+        let marked = format!("a {MATCH_START}b{MATCH_END} c {MATCH_START}d{MATCH_END}");
+        assert_eq!(
+            highlight_parts(&marked),
+            vec![("a ", false), ("b", true), (" c ", false), ("d", true)]
+        );
+        assert_eq!(highlight_parts("plain"), vec![("plain", false)]);
+    }
+}
+
+#[cfg(all(test, feature = "local"))]
+mod typed_column_tests {
+    use crate::{Database, FilterOperator, Model, QueryBuilder};
+    use chrono::NaiveDate;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[table_name("chores")]
+    struct Chore {
+        pub id: Option<i64>,
+        pub owner_id: String,
+        #[orm_column(rename = "room_name")]
+        pub room: Option<String>,
+        #[serde(deserialize_with = "crate::deserialize_bool")]
+        pub done: bool,
+        pub due_on: NaiveDate,
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    async fn chores() -> Database {
+        let db = Database::open_memory().await.unwrap();
+        db.get_connection()
+            .execute_batch(&Chore::migration_sql())
+            .await
+            .unwrap();
+        for (owner_id, room, done, day) in [
+            ("alice", Some("kitchen"), false, 3),
+            ("alice", None, true, 1),
+            ("alice", Some("bath"), false, 9),
+            ("bob", Some("kitchen"), false, 2),
+        ] {
+            Chore {
+                id: None,
+                owner_id: owner_id.to_string(),
+                room: room.map(str::to_string),
+                done,
+                due_on: date(day),
+            }
+            .create(&db)
+            .await
+            .unwrap();
+        }
+        db
+    }
+
+    fn days(chores: &[Chore]) -> Vec<u32> {
+        use chrono::Datelike;
+        chores.iter().map(|chore| chore.due_on.day()).collect()
+    }
+
+    #[test]
+    fn test_column_names() {
+        assert_eq!(Chore::COLUMNS.owner_id.name(), "owner_id");
+        assert_eq!(Chore::COLUMNS.room.name(), "room_name");
+        assert_eq!(Chore::COLUMNS.due_on.qualified(), "chores.due_on");
+    }
+
+    #[tokio::test]
+    async fn test_typed_filters() {
+        let db = chores().await;
+        let columns = Chore::COLUMNS;
+
+        let open = Chore::find_where(
+            columns
+                .owner_id
+                .eq("alice")
+                .and_with(columns.done.eq(false)),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(days(&open), [3, 9]);
+
+        let no_room = Chore::find_where(columns.room.is_null(), &db)
+            .await
+            .unwrap();
+        assert_eq!(days(&no_room), [1]);
+
+        let kitchen = Chore::find_where(columns.room.in_values(["kitchen"]), &db)
+            .await
+            .unwrap();
+        assert_eq!(days(&kitchen), [3, 2]);
+
+        // Dates compare the way they are stored
+        let (sql, params) = QueryBuilder::new(Chore::table_name())
+            .r#where(FilterOperator::And(vec![
+                columns.due_on.between(date(2), date(5)),
+                columns.room.like("k%"),
+            ]))
+            .order_by(columns.due_on.desc())
+            .build()
+            .unwrap();
+        let mut rows = db.query(&sql, params).await.unwrap();
+        let mut found = Vec::new();
+        while let Some(row) = rows.next().await.unwrap() {
+            found.push(Chore::from_map(Chore::row_to_map(&row).unwrap()).unwrap());
+        }
+        assert_eq!(days(&found), [3, 2]);
+    }
+}
+
+#[cfg(all(test, feature = "local"))]
+mod bulk_tests {
+    use crate::{Database, Error, Model};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[table_name("products")]
+    struct Product {
+        pub id: Option<i64>,
+        #[orm_column(not_null, unique)]
+        pub sku: String,
+        pub name: String,
+        pub stock: i64,
+        #[serde(deserialize_with = "crate::deserialize_bool")]
+        pub active: bool,
+    }
+
+    fn product(sku: &str, stock: i64) -> Product {
+        Product {
+            id: None,
+            sku: sku.to_string(),
+            name: format!("Product {sku}"),
+            stock,
+            active: true,
+        }
+    }
+
+    async fn shop() -> Database {
+        let db = Database::open_memory().await.unwrap();
+        db.get_connection()
+            .execute_batch(&Product::migration_sql())
+            .await
+            .unwrap();
+        db
+    }
+
+    async fn stored(id: i64, db: &Database) -> Product {
+        Product::find_by_id(id, db).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bulk_create_returns_ids_across_statements() {
+        let db = shop().await;
+        // 5 columns, more rows than one statement can bind
+        let mut products: Vec<Product> =
+            (0..7000).map(|i| product(&format!("sku-{i}"), i)).collect();
+        products[3].id = Some(100_000);
+
+        let created = Product::bulk_create(&products, &db).await.unwrap();
+        assert_eq!(Product::count(&db).await.unwrap(), 7000);
+        assert_eq!(created[3].id, Some(100_000));
+        for i in [0, 3, 4, 6553, 6554, 6999] {
+            let id = created[i].id.unwrap();
+            assert_eq!(stored(id, &db).await.sku, format!("sku-{i}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upsert_inserts_then_updates() {
+        let db = shop().await;
+
+        let first = product("a", 1).upsert(&["sku"], &db).await.unwrap();
+        let second = Product {
+            name: "Renamed".to_string(),
+            ..product("a", 5)
+        }
+        .upsert(&["sku"], &db)
+        .await
+        .unwrap();
+
+        assert_eq!(first.id, second.id);
+        assert_eq!(Product::count(&db).await.unwrap(), 1);
+        let row = stored(second.id.unwrap(), &db).await;
+        assert_eq!((row.name.as_str(), row.stock), ("Renamed", 5));
+
+        assert!(matches!(
+            product("b", 1).upsert(&["code"], &db).await,
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_without_unique_index() {
+        let db = shop().await;
+        let first = product("a", 1).upsert(&["name"], &db).await.unwrap();
+        let second = product("a", 2).upsert(&["name"], &db).await.unwrap();
+        assert_eq!(first.id, second.id);
+        assert_eq!(stored(first.id.unwrap(), &db).await.stock, 2);
+
+        let upserted = Product::bulk_upsert(&[product("b", 3), product("a", 4)], &["name"], &db)
+            .await
+            .unwrap();
+        assert_eq!(upserted[1].id, first.id);
+        assert_eq!(stored(upserted[0].id.unwrap(), &db).await.sku, "b");
+        assert_eq!(Product::count(&db).await.unwrap(), 2);
+    }
+
+    #[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[table_name("codes")]
+    struct Code {
+        pub id: Option<i64>,
+        #[orm_column(type = "INTEGER UNIQUE")]
+        pub code: Option<String>,
+        pub label: String,
+    }
+
+    fn code(code: Option<&str>, label: &str) -> Code {
+        Code {
+            id: None,
+            code: code.map(str::to_string),
+            label: label.to_string(),
+        }
+    }
+
+    async fn codes() -> Database {
+        let db = Database::open_memory().await.unwrap();
+        db.get_connection()
+            .execute_batch(&Code::migration_sql())
+            .await
+            .unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_upsert_rejects_null_keys() {
+        let db = codes().await;
+        assert!(matches!(
+            code(None, "a").upsert(&["code"], &db).await,
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            Code::bulk_upsert(&[code(Some("1"), "a"), code(None, "b")], &["code"], &db).await,
+            Err(Error::Validation(_))
+        ));
+        assert_eq!(Code::count(&db).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_upsert_ids_follow_input_order() {
+        let db = codes().await;
+        // The INTEGER column stores "5" as 5
+        let existing = code(Some("5"), "five")
+            .upsert(&["code"], &db)
+            .await
+            .unwrap();
+
+        let upserted = Code::bulk_upsert(
+            &[
+                code(Some("7"), "seven"),
+                code(Some("5"), "five again"),
+                code(Some("6"), "six"),
+            ],
+            &["code"],
+            &db,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(upserted[1].id, existing.id);
+        for upserted in &upserted {
+            let mut rows = db
+                .get_connection()
+                .query(
+                    "SELECT label FROM codes WHERE id = ?",
+                    [upserted.id.unwrap()],
+                )
+                .await
+                .unwrap();
+            let label: String = rows.next().await.unwrap().unwrap().get(0).unwrap();
+            assert_eq!(label, upserted.label);
+        }
+        assert_eq!(Code::count(&db).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_upsert_matches_ids() {
+        let db = shop().await;
+        let existing = Product::bulk_create(&[product("a", 1), product("b", 2)], &db)
+            .await
+            .unwrap();
+
+        let upserted = Product::bulk_upsert(&[product("c", 3), product("a", 10)], &["sku"], &db)
+            .await
+            .unwrap();
+
+        assert_eq!(upserted[1].id, existing[0].id);
+        assert_ne!(upserted[0].id, existing[1].id);
+        assert_eq!(stored(upserted[0].id.unwrap(), &db).await.sku, "c");
+        assert_eq!(stored(existing[0].id.unwrap(), &db).await.stock, 10);
+        assert_eq!(Product::count(&db).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_update() {
+        let db = shop().await;
+        let mut products = Product::bulk_create(&[product("a", 1), product("b", 2)], &db)
+            .await
+            .unwrap();
+        products[0].stock = 7;
+        products[1].active = false;
+
+        Product::bulk_update(&products, &db).await.unwrap();
+        assert_eq!(stored(products[0].id.unwrap(), &db).await.stock, 7);
+        assert!(!stored(products[1].id.unwrap(), &db).await.active);
+
+        assert!(matches!(
+            Product::bulk_update(&[product("c", 3)], &db).await,
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            Product::bulk_update(&[products[0].clone(), products[0].clone()], &db).await,
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_update_fails_for_missing_records() {
+        let db = shop().await;
+        let mut existing = product("a", 1).create(&db).await.unwrap();
+        existing.stock = 9;
+        let missing = Product {
+            id: Some(existing.id.unwrap() + 1),
+            ..product("b", 2)
+        };
+
+        assert!(matches!(
+            Product::bulk_update(&[existing.clone(), missing], &db).await,
+            Err(Error::NotFound(_))
+        ));
+        assert_eq!(stored(existing.id.unwrap(), &db).await.stock, 1);
+    }
+}
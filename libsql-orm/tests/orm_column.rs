@@ -0,0 +1,7 @@
+//! Invalid model attributes and misused columns must not compile.
+
+#[test]
+fn orm_column_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,11 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+#[derive(Model, Clone, Serialize, Deserialize)]
+struct User {
+    #[orm_column(primary_key, auto_increment, type = "TEXT")]
+    pub id: Option<i64>,
+    pub name: String,
+}
+
+fn main() {}
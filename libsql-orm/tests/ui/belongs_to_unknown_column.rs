@@ -0,0 +1,16 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+#[derive(Model, Clone, Serialize, Deserialize)]
+struct Author {
+    pub id: Option<i64>,
+}
+
+#[derive(Model, Clone, Serialize, Deserialize)]
+#[belongs_to(author = Author)]
+struct Book {
+    pub id: Option<i64>,
+    pub writer_id: i64,
+}
+
+fn main() {}
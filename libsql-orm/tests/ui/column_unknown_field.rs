@@ -0,0 +1,12 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+#[derive(Model, Clone, Serialize, Deserialize)]
+struct Note {
+    pub id: Option<i64>,
+    pub title: String,
+}
+
+fn main() {
+    let _ = Note::COLUMNS.titel.eq("draft");
+}
@@ -0,0 +1,19 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+enum Priority {
+    Low,
+    High,
+}
+
+#[derive(Model, Clone, Serialize, Deserialize)]
+struct Note {
+    pub id: Option<i64>,
+    pub title: String,
+    pub priority: Priority,
+}
+
+fn main() {
+    let _ = Note::COLUMNS.priority.eq("high");
+}
@@ -0,0 +1,11 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+#[derive(Model, Clone, Serialize, Deserialize)]
+#[fts(title, body)]
+struct Note {
+    pub id: Option<i64>,
+    pub title: String,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+#[derive(Model, Clone, Serialize, Deserialize)]
+struct User {
+    pub id: Option<i64>,
+    #[orm_column(references(users))]
+    pub owner_id: Option<i64>,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+#[derive(Model, Clone, Serialize, Deserialize)]
+struct User {
+    pub id: Option<i64>,
+    #[orm_column(not_null)]
+    pub nickname: Option<String>,
+}
+
+fn main() {}
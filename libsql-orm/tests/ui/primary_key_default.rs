@@ -0,0 +1,10 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+#[derive(Model, Clone, Serialize, Deserialize)]
+struct User {
+    #[orm_column(primary_key, default = 1)]
+    pub id: Option<i64>,
+}
+
+fn main() {}
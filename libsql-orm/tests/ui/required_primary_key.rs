@@ -0,0 +1,10 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+#[derive(Model, Clone, Serialize, Deserialize)]
+struct User {
+    pub id: i64,
+    pub name: String,
+}
+
+fn main() {}
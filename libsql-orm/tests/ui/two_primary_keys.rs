@@ -0,0 +1,12 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+#[derive(Model, Clone, Serialize, Deserialize)]
+struct User {
+    #[orm_column(primary_key)]
+    pub id: Option<i64>,
+    #[orm_column(primary_key)]
+    pub other_id: Option<i64>,
+}
+
+fn main() {}
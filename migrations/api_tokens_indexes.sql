@@ -0,0 +1,3 @@
+-- Create indexes for api_tokens table
+CREATE UNIQUE INDEX IF NOT EXISTS idx_api_tokens_token_hash ON api_tokens(token_hash);
+CREATE INDEX IF NOT EXISTS idx_api_tokens_owner_id ON api_tokens(owner_id);
@@ -0,0 +1,17 @@
+-- Create auth_sessions table for AuthSession struct
+CREATE TABLE IF NOT EXISTS auth_sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    owner_id TEXT NOT NULL,
+    provider TEXT,
+    access_token TEXT NOT NULL,
+    refresh_token TEXT,
+    id_token TEXT,
+    sid TEXT,
+    user_agent TEXT,
+    ip TEXT,
+    last_seen_at DATETIME,
+    expires_at DATETIME NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL
+);
@@ -0,0 +1,10 @@
+-- Create indexes for auth_sessions table
+
+-- Lookup of the session referenced by the cookie on every request
+CREATE UNIQUE INDEX IF NOT EXISTS idx_auth_sessions_session_id ON auth_sessions(session_id);
+
+-- Index on owner_id for listing and revoking a user's sessions
+CREATE INDEX IF NOT EXISTS idx_auth_sessions_owner_id ON auth_sessions(owner_id);
+
+-- Index on the identity provider session id for back-channel logout
+CREATE INDEX IF NOT EXISTS idx_auth_sessions_sid ON auth_sessions(sid);
@@ -0,0 +1,7 @@
+-- Create indexes for calendar_feeds table
+
+-- One feed per owner
+CREATE UNIQUE INDEX IF NOT EXISTS idx_calendar_feeds_owner_id ON calendar_feeds(owner_id);
+
+-- Feed lookups by token from calendar apps
+CREATE UNIQUE INDEX IF NOT EXISTS idx_calendar_feeds_token ON calendar_feeds(token);
@@ -0,0 +1,4 @@
+-- Create indexes for identities table
+CREATE UNIQUE INDEX IF NOT EXISTS idx_identities_provider_subject ON identities(provider, subject);
+CREATE INDEX IF NOT EXISTS idx_identities_user_id ON identities(user_id);
+CREATE INDEX IF NOT EXISTS idx_identities_email ON identities(email);
@@ -4,6 +4,7 @@ CREATE TABLE IF NOT EXISTS items (
     owner_id TEXT NOT NULL,
     task TEXT NOT NULL,
     completed INTEGER NOT NULL DEFAULT FALSE,
+    list TEXT,
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
     updated_at DATETIME NOT NULL
 );
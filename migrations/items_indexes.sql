@@ -20,3 +20,6 @@ CREATE INDEX IF NOT EXISTS idx_items_owner_created ON items(owner_id, created_at
 
 -- Composite index for owner + updated time (for user's recently updated items)
 CREATE INDEX IF NOT EXISTS idx_items_owner_updated ON items(owner_id, updated_at);
+
+-- Composite index for owner + list (items page filtered by list)
+CREATE INDEX IF NOT EXISTS idx_items_owner_list ON items(owner_id, list);
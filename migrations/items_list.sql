@@ -0,0 +1,2 @@
+-- Named lists, items without a list belong to the main shopping list
+ALTER TABLE items ADD COLUMN list TEXT;
@@ -0,0 +1,7 @@
+-- Create indexes for jobs table
+
+-- Composite index for status + run_after (workers picking the next due job)
+CREATE INDEX IF NOT EXISTS idx_jobs_status_run_after ON jobs(status, run_after);
+
+-- Composite index for owner + creation time (for user's jobs ordered by date)
+CREATE INDEX IF NOT EXISTS idx_jobs_owner_created ON jobs(owner_id, created_at);
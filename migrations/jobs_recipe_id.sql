@@ -0,0 +1,2 @@
+-- Recipe a job saved, so a retried job does not save it again
+ALTER TABLE jobs ADD COLUMN recipe_id INTEGER;
@@ -0,0 +1,3 @@
+-- Create indexes for llm_calls table
+CREATE INDEX IF NOT EXISTS idx_llm_calls_owner_id ON llm_calls(owner_id);
+CREATE INDEX IF NOT EXISTS idx_llm_calls_created_at ON llm_calls(created_at);
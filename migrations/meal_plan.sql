@@ -0,0 +1,11 @@
+-- Create meal_plan table for MealPlanEntry struct
+CREATE TABLE IF NOT EXISTS meal_plan (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    owner_id TEXT NOT NULL,
+    recipe_id INTEGER NOT NULL,
+    planned_on DATE NOT NULL,
+    slot TEXT NOT NULL,
+    servings INTEGER NOT NULL DEFAULT 2,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL
+);
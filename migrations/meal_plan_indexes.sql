@@ -0,0 +1,7 @@
+-- Create indexes for meal_plan table
+
+-- Composite index for owner + date (loading a week of the plan)
+CREATE INDEX IF NOT EXISTS idx_meal_plan_owner_planned_on ON meal_plan(owner_id, planned_on);
+
+-- Index on recipe_id for finding where a recipe is planned
+CREATE INDEX IF NOT EXISTS idx_meal_plan_recipe_id ON meal_plan(recipe_id);
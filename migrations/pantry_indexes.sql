@@ -0,0 +1,7 @@
+-- Create indexes for pantry table
+
+-- Composite index for owner + name (matching grocery items against stock)
+CREATE INDEX IF NOT EXISTS idx_pantry_owner_name ON pantry(owner_id, name);
+
+-- Composite index for owner + expiry date (items running out of date)
+CREATE INDEX IF NOT EXISTS idx_pantry_owner_expires ON pantry(owner_id, expires_on);
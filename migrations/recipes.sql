@@ -5,6 +5,7 @@ CREATE TABLE IF NOT EXISTS recipes (
     title TEXT,
     url TEXT,
     content TEXT NOT NULL,
+    prompt_version TEXT,
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
     updated_at DATETIME NOT NULL
 );
@@ -0,0 +1,20 @@
+-- Full-text search over recipe titles and content (ingredients and
+-- instructions), kept in sync by triggers. Generated by
+-- Recipe::fts_migration_sql().
+
+-- B-tree indexes cannot serve text search, recipes_fts replaces them
+DROP INDEX IF EXISTS idx_recipes_content_fts;
+DROP INDEX IF EXISTS idx_recipes_title_fts;
+
+CREATE VIRTUAL TABLE IF NOT EXISTS recipes_fts USING fts5(title, content, content = 'recipes', content_rowid = 'id', tokenize = 'porter unicode61');
+CREATE TRIGGER IF NOT EXISTS recipes_fts_insert AFTER INSERT ON recipes BEGIN
+    INSERT INTO recipes_fts (rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
+CREATE TRIGGER IF NOT EXISTS recipes_fts_delete AFTER DELETE ON recipes BEGIN
+    INSERT INTO recipes_fts (recipes_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+END;
+CREATE TRIGGER IF NOT EXISTS recipes_fts_update AFTER UPDATE ON recipes BEGIN
+    INSERT INTO recipes_fts (recipes_fts, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+    INSERT INTO recipes_fts (rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
+INSERT INTO recipes_fts (recipes_fts) VALUES ('rebuild');
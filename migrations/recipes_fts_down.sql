@@ -0,0 +1,8 @@
+-- Remove recipe full-text search and restore the indexes it replaced
+DROP TRIGGER IF EXISTS recipes_fts_insert;
+DROP TRIGGER IF EXISTS recipes_fts_delete;
+DROP TRIGGER IF EXISTS recipes_fts_update;
+DROP TABLE IF EXISTS recipes_fts;
+
+CREATE INDEX IF NOT EXISTS idx_recipes_content_fts ON recipes(content);
+CREATE INDEX IF NOT EXISTS idx_recipes_title_fts ON recipes(title);
@@ -0,0 +1,2 @@
+-- Tag recipes with the prompt template version that produced them
+ALTER TABLE recipes ADD COLUMN prompt_version TEXT;
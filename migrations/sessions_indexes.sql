@@ -0,0 +1,3 @@
+-- Create indexes for sessions table
+CREATE UNIQUE INDEX IF NOT EXISTS idx_sessions_session_key ON sessions(session_key);
+CREATE INDEX IF NOT EXISTS idx_sessions_expires_at ON sessions(expires_at);
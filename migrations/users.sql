@@ -0,0 +1,17 @@
+-- Create users table, one row per user id used as owner_id elsewhere
+CREATE TABLE IF NOT EXISTS users (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    email TEXT NOT NULL,
+    name TEXT,
+    given_name TEXT,
+    family_name TEXT,
+    avatar_url TEXT,
+    locale TEXT,
+    role TEXT NOT NULL DEFAULT 'member',
+    disabled_at DATETIME,
+    first_seen_at DATETIME NOT NULL,
+    last_login_at DATETIME NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL
+);
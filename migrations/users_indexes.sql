@@ -0,0 +1,2 @@
+-- Create indexes for users table
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_user_id ON users(user_id);
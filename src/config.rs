@@ -1,4 +1,5 @@
 use std::env;
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct Server {
@@ -6,13 +7,26 @@ pub struct Server {
     host: String,
     db_url: String,
     token: Option<String>,
+    db_pool: libsql_orm::PoolConfig,
 
     llm_provider: String,
     llm_api_key: String,
     fake_user: bool,
     local: bool,
+
+    job_workers: usize,
+    job_max_attempts: i64,
+
+    prompts_dir: Option<String>,
+
+    session_secret: Option<String>,
+
+    admin_emails: Vec<String>,
 }
 
+/// The cookie signing key needs at least 64 bytes.
+pub const MIN_SESSION_SECRET_LEN: usize = 64;
+
 impl Server {
     pub fn port(&self) -> u16 {
         self.port
@@ -29,6 +43,10 @@ impl Server {
         self.token.clone()
     }
 
+    pub fn db_pool(&self) -> libsql_orm::PoolConfig {
+        self.db_pool.clone()
+    }
+
     #[allow(dead_code)]
     pub fn delay(&self) -> bool {
         self.db_token().is_none()
@@ -49,9 +67,51 @@ impl Server {
     pub fn llm_api_key(&self) -> String {
         self.llm_api_key.clone()
     }
+
+    pub fn job_workers(&self) -> usize {
+        self.job_workers
+    }
+
+    pub fn job_max_attempts(&self) -> i64 {
+        self.job_max_attempts
+    }
+
+    pub fn prompts_dir(&self) -> Option<String> {
+        self.prompts_dir.clone()
+    }
+
+    /// Addresses that are made admins when they sign in verified.
+    pub fn admin_emails(&self) -> &[String] {
+        &self.admin_emails
+    }
+
+    /// Key for signing and encrypting the session cookie. Without a secret,
+    /// which is only allowed in local mode, a random key is used and
+    /// sessions do not survive a restart.
+    pub fn session_key(&self) -> actix_web::cookie::Key {
+        match &self.session_secret {
+            Some(secret) => actix_web::cookie::Key::from(secret.as_bytes()),
+            None => actix_web::cookie::Key::generate(),
+        }
+    }
 }
 
-pub fn from_env() -> Server {
+/// The cookie signing secret. Outside local mode it is required, in local
+/// mode a missing or short one falls back to a random key.
+fn session_secret(secret: Option<String>, local: bool) -> Result<Option<String>, String> {
+    match secret.filter(|secret| secret.len() >= MIN_SESSION_SECRET_LEN) {
+        Some(secret) => Ok(Some(secret)),
+        None if local => {
+            log::warn!("SESSION_SECRET missing or too short, using a random key");
+            Ok(None)
+        }
+        None => Err(format!(
+            "Need SESSION_SECRET with at least {MIN_SESSION_SECRET_LEN} bytes"
+        )),
+    }
+}
+
+pub fn from_env() -> Result<Server, String> {
     let fake_user = env::var("FAKE_USER").unwrap_or("false".to_string());
     let fake_user = fake_user == "true";
 
@@ -76,15 +136,88 @@ pub fn from_env() -> Server {
         .map(|e| e.parse().expect("could not parse db url"))
         .unwrap_or("http://127.0.0.1:8080".to_string());
     let db_token: Option<String> = env::var("g_db_token").ok();
-    Server {
+
+    let pool_defaults = libsql_orm::PoolConfig::default();
+    let db_pool = libsql_orm::PoolConfig {
+        min_size: env::var("DB_POOL_MIN")
+            .map(|e| e.parse().expect("could not parse DB_POOL_MIN"))
+            .unwrap_or(pool_defaults.min_size),
+        max_size: env::var("DB_POOL_MAX")
+            .map(|e| e.parse().expect("could not parse DB_POOL_MAX"))
+            .unwrap_or(pool_defaults.max_size),
+        acquire_timeout: env::var("DB_POOL_ACQUIRE_TIMEOUT_SECS")
+            .map(|e| {
+                Duration::from_secs(
+                    e.parse()
+                        .expect("could not parse DB_POOL_ACQUIRE_TIMEOUT_SECS"),
+                )
+            })
+            .unwrap_or(pool_defaults.acquire_timeout),
+        idle_timeout: env::var("DB_POOL_IDLE_TIMEOUT_SECS")
+            .map(|e| {
+                Duration::from_secs(
+                    e.parse()
+                        .expect("could not parse DB_POOL_IDLE_TIMEOUT_SECS"),
+                )
+            })
+            .unwrap_or(pool_defaults.idle_timeout),
+        ..pool_defaults
+    };
+
+    let job_workers: usize = env::var("JOB_WORKERS")
+        .map(|e| e.parse().expect("could not parse JOB_WORKERS"))
+        .unwrap_or(2);
+    let job_max_attempts: i64 = env::var("JOB_MAX_ATTEMPTS")
+        .map(|e| e.parse().expect("could not parse JOB_MAX_ATTEMPTS"))
+        .unwrap_or(3);
+
+    let prompts_dir: Option<String> = env::var("PROMPTS_DIR").ok();
+
+    let session_secret = session_secret(env::var("SESSION_SECRET").ok(), local)?;
+
+    let admin_emails: Vec<String> = env::var("ADMIN_EMAILS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| !email.is_empty())
+        .collect();
+
+    Ok(Server {
         port,
         host,
         db_url,
         token: db_token,
+        db_pool,
 
         llm_provider,
         llm_api_key,
         fake_user,
         local,
+
+        job_workers,
+        job_max_attempts,
+
+        prompts_dir,
+
+        session_secret,
+
+        admin_emails,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_secret() {
+        let long = "x".repeat(MIN_SESSION_SECRET_LEN);
+        assert_eq!(
+            session_secret(Some(long.clone()), false),
+            Ok(Some(long.clone()))
+        );
+        assert!(session_secret(Some("short".to_string()), false).is_err());
+        assert!(session_secret(None, false).is_err());
+        assert_eq!(session_secret(Some("short".to_string()), true), Ok(None));
     }
 }
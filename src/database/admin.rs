@@ -0,0 +1,56 @@
+//! Instance wide numbers for the admin area.
+
+use crate::database::DBClient;
+
+/// Bytes of text a user keeps, counted over the tables that grow with use.
+#[derive(Debug, Clone, Default)]
+pub struct Storage {
+    pub owner_id: String,
+    pub items: i64,
+    pub recipes: i64,
+    pub pantry: i64,
+    pub bytes: i64,
+}
+
+const STORAGE_SQL: &str = "\
+SELECT owner_id, SUM(items), SUM(recipes), SUM(pantry), SUM(bytes) FROM (
+    SELECT owner_id, 1 AS items, 0 AS recipes, 0 AS pantry,
+        LENGTH(CAST(task AS BLOB)) + COALESCE(LENGTH(CAST(list AS BLOB)), 0) AS bytes
+    FROM items
+    UNION ALL
+    SELECT owner_id, 0, 1, 0,
+        LENGTH(CAST(content AS BLOB)) + COALESCE(LENGTH(CAST(title AS BLOB)), 0)
+            + COALESCE(LENGTH(CAST(url AS BLOB)), 0)
+    FROM recipes
+    UNION ALL
+    SELECT owner_id, 0, 0, 1, LENGTH(CAST(name AS BLOB)) + COALESCE(LENGTH(CAST(unit AS BLOB)), 0)
+    FROM pantry
+    UNION ALL
+    SELECT owner_id, 0, 0, 0, LENGTH(CAST(payload AS BLOB)) + COALESCE(LENGTH(CAST(result AS BLOB)), 0)
+    FROM jobs
+) GROUP BY owner_id ORDER BY SUM(bytes) DESC";
+
+pub async fn get_storage(client: &DBClient) -> Result<Vec<Storage>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let rows = db.get_connection().query(STORAGE_SQL, ()).await;
+
+    let mut rows = match rows {
+        Ok(rows) => rows,
+        Err(err) => {
+            log::error!("Error getting storage: {err}");
+            return Err("Could not get storage".to_string());
+        }
+    };
+
+    let mut storage = Vec::new();
+    while let Ok(Some(row)) = rows.next().await {
+        storage.push(Storage {
+            owner_id: row.get::<String>(0).unwrap_or_default(),
+            items: row.get::<i64>(1).unwrap_or_default(),
+            recipes: row.get::<i64>(2).unwrap_or_default(),
+            pantry: row.get::<i64>(3).unwrap_or_default(),
+            bytes: row.get::<i64>(4).unwrap_or_default(),
+        });
+    }
+    Ok(storage)
+}
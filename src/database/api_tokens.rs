@@ -0,0 +1,218 @@
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::database::DBClient;
+
+/// Every token starts with this, so leaked tokens are easy to spot.
+const TOKEN_PREFIX: &str = "rezi_";
+/// Characters of the token kept in clear text to tell tokens apart.
+const VISIBLE_CHARS: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiScope {
+    ItemsRead,
+    ItemsWrite,
+    RecipesRead,
+    RecipesWrite,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 4] = [
+        ApiScope::ItemsRead,
+        ApiScope::ItemsWrite,
+        ApiScope::RecipesRead,
+        ApiScope::RecipesWrite,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::ItemsRead => "items:read",
+            ApiScope::ItemsWrite => "items:write",
+            ApiScope::RecipesRead => "recipes:read",
+            ApiScope::RecipesWrite => "recipes:write",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ApiScope::ItemsRead => "Read grocery items",
+            ApiScope::ItemsWrite => "Add and change grocery items",
+            ApiScope::RecipesRead => "Read recipes",
+            ApiScope::RecipesWrite => "Add and change recipes",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ApiScope> {
+        ApiScope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == value.trim())
+    }
+}
+
+/// Token for scripts and integrations, sent as `Authorization: Bearer`.
+/// Only the SHA-256 hash of the token is stored.
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("api_tokens")]
+pub struct ApiToken {
+    pub id: std::option::Option<i64>,
+    pub owner_id: String,
+    pub name: String,
+    pub token_hash: String,
+    /// Start of the token, shown to recognise it.
+    pub prefix: String,
+    /// Comma separated scopes, e.g. `items:read,items:write`.
+    pub scopes: String,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ApiToken {
+    /// Creates a token and returns it together with the secret, which is
+    /// shown once and never stored.
+    pub fn new(owner_id: String, name: String, scopes: &[ApiScope]) -> (Self, String) {
+        let mut random_bytes = [0u8; 32];
+        use rand::RngCore;
+        rand::rng().fill_bytes(&mut random_bytes);
+        let secret = format!("{TOKEN_PREFIX}{}", URL_SAFE_NO_PAD.encode(random_bytes));
+
+        let token = ApiToken {
+            id: None,
+            owner_id,
+            name,
+            token_hash: hash_token(&secret),
+            prefix: secret[..VISIBLE_CHARS].to_string(),
+            scopes: scopes
+                .iter()
+                .map(ApiScope::as_str)
+                .collect::<Vec<_>>()
+                .join(","),
+            last_used_at: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        (token, secret)
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id.unwrap_or_default()
+    }
+
+    pub fn scopes(&self) -> Vec<ApiScope> {
+        self.scopes.split(',').filter_map(ApiScope::parse).collect()
+    }
+
+    pub fn has_scope(&self, scope: ApiScope) -> bool {
+        self.scopes().contains(&scope)
+    }
+}
+
+/// Tokens carry 256 random bits, so a plain hash is enough to make a leaked
+/// database useless.
+pub fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.trim().as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+pub async fn create_api_token(client: &DBClient, token: ApiToken) -> Result<ApiToken, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let res = ApiToken::create(&token, &db).await;
+    drop(db);
+
+    match res {
+        Ok(created) => {
+            log::info!("created API token for owner {}", created.owner_id);
+            Ok(created)
+        }
+        Err(err) => {
+            log::error!("could not create API token: {err}");
+            Err("Could not create token".to_string())
+        }
+    }
+}
+
+/// Looks up the token a request was sent with.
+pub async fn find_api_token(client: &DBClient, secret: &str) -> Result<Option<ApiToken>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let tokens =
+        ApiToken::find_where(ApiToken::COLUMNS.token_hash.eq(hash_token(secret)), &db).await;
+    drop(db);
+
+    match tokens {
+        Ok(tokens) => Ok(tokens.into_iter().next()),
+        Err(err) => {
+            log::error!("Error getting API token: {err}");
+            Err("Could not get token".to_string())
+        }
+    }
+}
+
+/// Tokens of the owner, newest first.
+pub async fn get_api_tokens(client: &DBClient, owner_id: String) -> Result<Vec<ApiToken>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let tokens = ApiToken::find_where(ApiToken::COLUMNS.owner_id.eq(owner_id), &db).await;
+    drop(db);
+
+    match tokens {
+        Ok(mut tokens) => {
+            tokens.sort_by_key(|token| std::cmp::Reverse(token.created_at));
+            Ok(tokens)
+        }
+        Err(err) => {
+            log::error!("Error getting API tokens: {err}");
+            Err("Could not get tokens".to_string())
+        }
+    }
+}
+
+/// Bumps `last_used_at` only, so concurrent changes to the token are kept.
+pub async fn record_use(client: &DBClient, token: ApiToken) -> Result<(), String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let result = db
+        .get_connection()
+        .execute(
+            "UPDATE api_tokens SET last_used_at = ? WHERE id = ?",
+            (
+                chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+                token.id.unwrap_or_default(),
+            ),
+        )
+        .await;
+    drop(db);
+
+    result.map(|_| ()).map_err(|err| {
+        log::error!("could not record API token use: {err}");
+        "Failed to update token".to_string()
+    })
+}
+
+pub async fn revoke_api_token(client: &DBClient, id: i64, owner_id: String) -> Result<(), String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let result = db
+        .get_connection()
+        .execute(
+            "DELETE FROM api_tokens WHERE id = ? AND owner_id = ?",
+            (id, owner_id.clone()),
+        )
+        .await;
+    drop(db);
+
+    match result {
+        Ok(0) => Err("Token not found".to_string()),
+        Ok(_) => {
+            log::info!("revoked API token {id} for owner {owner_id}");
+            Ok(())
+        }
+        Err(err) => {
+            log::error!("could not revoke API token: {err}");
+            Err("Failed to revoke token".to_string())
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+
+/// Tokens of a login, kept on the server so the cookie only carries the
+/// random `session_id`.
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("auth_sessions")]
+pub struct AuthSession {
+    pub id: std::option::Option<i64>,
+    pub session_id: String,
+    pub owner_id: String,
+    /// Id of the identity provider, `None` for the primary provider.
+    pub provider: Option<String>,
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub id_token: Option<String>,
+    /// Session id at the identity provider, from the `sid` claim.
+    pub sid: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub last_seen_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// When the access token has to be refreshed.
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl AuthSession {
+    pub fn new(
+        owner_id: String,
+        provider: String,
+        access_token: String,
+        refresh_token: Option<String>,
+        id_token: Option<String>,
+        sid: Option<String>,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        AuthSession {
+            id: None,
+            session_id: uuid::Uuid::new_v4().to_string(),
+            owner_id,
+            provider: Some(provider),
+            access_token,
+            refresh_token,
+            id_token,
+            sid,
+            user_agent: None,
+            ip: None,
+            last_seen_at: Some(chrono::Utc::now()),
+            expires_at,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub fn expires_within(&self, margin: chrono::Duration) -> bool {
+        self.expires_at - margin <= chrono::Utc::now()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_within(chrono::Duration::zero())
+    }
+
+    pub fn device(&self) -> String {
+        crate::user_agent::describe(self.user_agent.as_deref().unwrap_or_default())
+    }
+
+    pub fn last_seen(&self) -> chrono::DateTime<chrono::Utc> {
+        self.last_seen_at.unwrap_or(self.created_at)
+    }
+}
+
+pub async fn create_auth_session(
+    client: &DBClient,
+    session: AuthSession,
+) -> Result<AuthSession, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let res = AuthSession::create(&session, &db).await;
+    drop(db);
+
+    match res {
+        Ok(created) => {
+            log::info!("created auth session for owner {}", created.owner_id);
+            Ok(created)
+        }
+        Err(err) => {
+            log::error!("{err:?}");
+            Err("Could not create session".to_string())
+        }
+    }
+}
+
+pub async fn get_auth_session(
+    client: &DBClient,
+    session_id: &str,
+) -> Result<Option<AuthSession>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let sessions =
+        AuthSession::find_where(AuthSession::COLUMNS.session_id.eq(session_id), &db).await;
+    drop(db);
+
+    match sessions {
+        Ok(sessions) => Ok(sessions.into_iter().next()),
+        Err(err) => {
+            log::error!("Error getting auth session: {err}");
+            Err("Could not get session".to_string())
+        }
+    }
+}
+
+/// Stores the tokens of a successful refresh. Providers that do not rotate
+/// refresh tokens or omit the ID token keep the previous ones.
+pub async fn update_tokens(
+    client: &DBClient,
+    mut session: AuthSession,
+    access_token: String,
+    refresh_token: Option<String>,
+    id_token: Option<String>,
+    expires_at: chrono::DateTime<chrono::Utc>,
+) -> Result<AuthSession, String> {
+    session.access_token = access_token;
+    if refresh_token.is_some() {
+        session.refresh_token = refresh_token;
+    }
+    if id_token.is_some() {
+        session.id_token = id_token;
+    }
+    session.expires_at = expires_at;
+    session.updated_at = chrono::Utc::now();
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let update_result = session.update(&db).await;
+    drop(db);
+
+    update_result.map_err(|err| {
+        log::error!("could not update auth session: {err}");
+        "Failed to update session".to_string()
+    })
+}
+
+/// Sessions of the owner, most recently used first.
+pub async fn get_owner_auth_sessions(
+    client: &DBClient,
+    owner_id: String,
+) -> Result<Vec<AuthSession>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let sessions = AuthSession::find_where(AuthSession::COLUMNS.owner_id.eq(owner_id), &db).await;
+    drop(db);
+
+    match sessions {
+        Ok(mut sessions) => {
+            sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen()));
+            Ok(sessions)
+        }
+        Err(err) => {
+            log::error!("Error getting auth sessions: {err}");
+            Err("Could not get sessions".to_string())
+        }
+    }
+}
+
+/// Records where and when a session was last used.
+///
+/// Only the activity columns are written: a refresh in the same request may
+/// already have stored newer tokens than the ones in `session`.
+pub async fn record_activity(
+    client: &DBClient,
+    session: AuthSession,
+    ip: Option<String>,
+    user_agent: Option<String>,
+) -> Result<(), String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let result = db
+        .get_connection()
+        .execute(
+            "UPDATE auth_sessions SET last_seen_at = ?, ip = COALESCE(?, ip), user_agent = COALESCE(?, user_agent) WHERE id = ?",
+            (
+                chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+                ip,
+                user_agent,
+                session.id.unwrap_or_default(),
+            ),
+        )
+        .await;
+    drop(db);
+
+    result.map(|_| ()).map_err(|err| {
+        log::error!("could not record session activity: {err}");
+        "Failed to update session".to_string()
+    })
+}
+
+/// Revokes one of the owner's sessions by its row id.
+pub async fn revoke_owner_auth_session(
+    client: &DBClient,
+    id: i64,
+    owner_id: String,
+) -> Result<(), String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let result = db
+        .get_connection()
+        .execute(
+            "DELETE FROM auth_sessions WHERE id = ? AND owner_id = ?",
+            (id, owner_id.clone()),
+        )
+        .await;
+    drop(db);
+
+    match result {
+        Ok(0) => Err("Session not found".to_string()),
+        Ok(_) => {
+            log::info!("revoked auth session {id} for owner {owner_id}");
+            Ok(())
+        }
+        Err(err) => {
+            log::error!("could not revoke auth session: {err}");
+            Err("Failed to revoke session".to_string())
+        }
+    }
+}
+
+pub async fn revoke_auth_session(client: &DBClient, session_id: &str) -> Result<(), String> {
+    let Some(session) = get_auth_session(client, session_id).await? else {
+        return Ok(());
+    };
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let delete_result = session.delete(&db).await;
+    drop(db);
+
+    match delete_result {
+        Ok(_) => {
+            log::info!("revoked auth session for owner {}", session.owner_id);
+            Ok(())
+        }
+        Err(err) => {
+            log::error!("could not revoke auth session: {err}");
+            Err("Failed to revoke session".to_string())
+        }
+    }
+}
+
+/// Sessions from before multiple providers have no provider and belong to
+/// the primary one.
+fn provider_condition(primary: bool) -> &'static str {
+    if primary {
+        "(provider = ? OR provider IS NULL)"
+    } else {
+        "provider = ?"
+    }
+}
+
+/// Ends the sessions of one login at an identity provider. The owner is
+/// checked as well when the provider names one.
+pub async fn revoke_sessions_by_sid(
+    client: &DBClient,
+    provider: &str,
+    primary: bool,
+    sid: &str,
+    owner_id: Option<&str>,
+) -> Result<u64, String> {
+    let condition = provider_condition(primary);
+    let db = client.get().await.map_err(super::pool_error)?;
+    let result = match owner_id {
+        Some(owner_id) => {
+            db.get_connection()
+                .execute(
+                    &format!(
+                        "DELETE FROM auth_sessions WHERE {condition} AND sid = ? AND owner_id = ?"
+                    ),
+                    (provider.to_string(), sid.to_string(), owner_id.to_string()),
+                )
+                .await
+        }
+        None => {
+            db.get_connection()
+                .execute(
+                    &format!("DELETE FROM auth_sessions WHERE {condition} AND sid = ?"),
+                    (provider.to_string(), sid.to_string()),
+                )
+                .await
+        }
+    };
+    drop(db);
+
+    match result {
+        Ok(count) => {
+            log::info!("revoked {count} auth sessions for provider session {sid}");
+            Ok(count)
+        }
+        Err(err) => {
+            log::error!("could not revoke auth sessions: {err}");
+            Err("Failed to revoke sessions".to_string())
+        }
+    }
+}
+
+/// Ends every session the owner started with the provider.
+pub async fn revoke_owner_sessions(
+    client: &DBClient,
+    provider: &str,
+    primary: bool,
+    owner_id: &str,
+) -> Result<u64, String> {
+    let condition = provider_condition(primary);
+    let db = client.get().await.map_err(super::pool_error)?;
+    let result = db
+        .get_connection()
+        .execute(
+            &format!("DELETE FROM auth_sessions WHERE {condition} AND owner_id = ?"),
+            (provider.to_string(), owner_id.to_string()),
+        )
+        .await;
+    drop(db);
+
+    match result {
+        Ok(count) => {
+            log::info!("revoked {count} auth sessions for owner {owner_id}");
+            Ok(count)
+        }
+        Err(err) => {
+            log::error!("could not revoke auth sessions: {err}");
+            Err("Failed to revoke sessions".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_activity_keeps_refreshed_tokens() {
+        let client = crate::database::test_client().await;
+        let session = AuthSession::new(
+            "alice".to_string(),
+            "primary".to_string(),
+            "old-access".to_string(),
+            Some("old-refresh".to_string()),
+            None,
+            None,
+            chrono::Utc::now(),
+        );
+        let session = create_auth_session(&client, session).await.unwrap();
+        let session_id = session.session_id().to_string();
+        let stale = session.clone();
+
+        let expires_at = chrono::Utc::now() + chrono::Duration::hours(1);
+        update_tokens(
+            &client,
+            session,
+            "new-access".to_string(),
+            Some("new-refresh".to_string()),
+            None,
+            expires_at,
+        )
+        .await
+        .unwrap();
+        record_activity(
+            &client,
+            stale.clone(),
+            Some("10.0.0.1".to_string()),
+            Some("curl/8.0".to_string()),
+        )
+        .await
+        .unwrap();
+        // Unknown client details keep the recorded ones
+        record_activity(&client, stale, None, None).await.unwrap();
+
+        let stored = get_auth_session(&client, &session_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.access_token, "new-access");
+        assert_eq!(stored.refresh_token.as_deref(), Some("new-refresh"));
+        assert_eq!(stored.expires_at, expires_at);
+        assert_eq!(stored.ip.as_deref(), Some("10.0.0.1"));
+        assert_eq!(stored.user_agent.as_deref(), Some("curl/8.0"));
+    }
+}
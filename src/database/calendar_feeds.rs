@@ -0,0 +1,106 @@
+use libsql_orm::{FilterOperator, Model};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+
+/// Secret feed URL for calendar apps, which cannot send the session cookie.
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("calendar_feeds")]
+pub struct CalendarFeed {
+    pub id: std::option::Option<i64>,
+    pub owner_id: String,
+    pub token: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl CalendarFeed {
+    pub fn new(owner_id: String) -> Self {
+        CalendarFeed {
+            id: None,
+            owner_id,
+            token: new_token(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+fn new_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+async fn find_feed(
+    client: &DBClient,
+    filter: FilterOperator,
+) -> Result<Option<CalendarFeed>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let feeds = CalendarFeed::find_where(filter, &db).await;
+    drop(db);
+
+    match feeds {
+        Ok(feeds) => Ok(feeds.into_iter().next()),
+        Err(err) => {
+            log::error!("Error getting calendar feed: {err}");
+            Err("Could not get calendar feed".to_string())
+        }
+    }
+}
+
+/// Returns the owner's feed, creating it on first use.
+pub async fn get_or_create_feed(
+    client: &DBClient,
+    owner_id: String,
+) -> Result<CalendarFeed, String> {
+    if let Some(feed) =
+        find_feed(client, CalendarFeed::COLUMNS.owner_id.eq(owner_id.clone())).await?
+    {
+        return Ok(feed);
+    }
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let res = CalendarFeed::create(&CalendarFeed::new(owner_id), &db).await;
+    drop(db);
+
+    res.map_err(|err| {
+        log::error!("could not create calendar feed: {err}");
+        "Could not create calendar feed".to_string()
+    })
+}
+
+pub async fn get_feed_by_token(
+    client: &DBClient,
+    token: &str,
+) -> Result<Option<CalendarFeed>, String> {
+    find_feed(client, CalendarFeed::COLUMNS.token.eq(token)).await
+}
+
+/// Replaces the token so the old feed URL stops working.
+pub async fn regenerate_feed(client: &DBClient, owner_id: String) -> Result<CalendarFeed, String> {
+    let mut feed = get_or_create_feed(client, owner_id).await?;
+    feed.token = new_token();
+    feed.updated_at = chrono::Utc::now();
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let update_result = feed.update(&db).await;
+    drop(db);
+
+    match update_result {
+        Ok(feed) => {
+            log::info!("regenerated calendar feed for owner {}", feed.owner_id);
+            Ok(feed)
+        }
+        Err(err) => {
+            log::error!("could not update calendar feed: {err}");
+            Err("Failed to regenerate calendar feed".to_string())
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use libsql_orm::{FilterOperator, Model};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+
+/// An account at an identity provider, linked to a user.
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("identities")]
+pub struct Identity {
+    pub id: std::option::Option<i64>,
+    pub user_id: String,
+    pub provider: String,
+    /// The `sub` claim, unique per provider.
+    pub subject: String,
+    /// Lowercased email as reported by the provider.
+    pub email: Option<String>,
+    pub email_verified: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// What a provider told us about the account that just signed in.
+pub struct ProviderAccount<'a> {
+    pub provider: &'a str,
+    /// Accounts of the primary provider keep their subject as user id.
+    pub primary: bool,
+    pub subject: &'a str,
+    pub email: Option<&'a str>,
+    pub email_verified: bool,
+}
+
+async fn find_identity(
+    client: &DBClient,
+    filter: FilterOperator,
+) -> Result<Option<Identity>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let identities = Identity::find_where(filter, &db).await;
+    drop(db);
+
+    match identities {
+        Ok(identities) => Ok(identities.into_iter().next()),
+        Err(err) => {
+            log::error!("Error getting identity: {err}");
+            Err("Could not get identity".to_string())
+        }
+    }
+}
+
+pub async fn get_identity(
+    client: &DBClient,
+    provider: &str,
+    subject: &str,
+) -> Result<Option<Identity>, String> {
+    find_identity(
+        client,
+        Identity::COLUMNS
+            .provider
+            .eq(provider)
+            .and_with(Identity::COLUMNS.subject.eq(subject)),
+    )
+    .await
+}
+
+/// Returns the user id for a provider account. On the first login with an
+/// account it is linked to the user who already signed in with the same
+/// verified email elsewhere, or a new user is created.
+pub async fn resolve_user_id(
+    client: &DBClient,
+    account: &ProviderAccount<'_>,
+) -> Result<String, String> {
+    let email = account.email.map(str::to_lowercase);
+    let verified = account.email_verified && email.is_some();
+
+    if let Some(mut identity) = get_identity(client, account.provider, account.subject).await? {
+        if identity.email != email || (identity.email_verified == 1) != verified {
+            identity.email = email;
+            identity.email_verified = verified as i64;
+            identity.updated_at = chrono::Utc::now();
+
+            let db = client.get().await.map_err(super::pool_error)?;
+            let update_result = identity.update(&db).await;
+            drop(db);
+            if let Err(err) = update_result {
+                log::error!("could not update identity: {err}");
+            }
+        }
+        return Ok(identity.user_id);
+    }
+
+    // Only verified addresses on both sides are trusted, otherwise anyone
+    // could take over an account by registering its email elsewhere
+    let linked = match (&email, verified) {
+        (Some(email), true) => {
+            find_identity(
+                client,
+                Identity::COLUMNS
+                    .email
+                    .eq(email.clone())
+                    .and_with(Identity::COLUMNS.email_verified.eq(1)),
+            )
+            .await?
+        }
+        _ => None,
+    };
+
+    let user_id = match &linked {
+        Some(identity) => {
+            log::info!(
+                "linking {} account to user {}",
+                account.provider,
+                identity.user_id
+            );
+            identity.user_id.clone()
+        }
+        None if account.primary => account.subject.to_string(),
+        None => uuid::Uuid::new_v4().to_string(),
+    };
+
+    let identity = Identity {
+        id: None,
+        user_id,
+        provider: account.provider.to_string(),
+        subject: account.subject.to_string(),
+        email,
+        email_verified: verified as i64,
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    };
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let res = Identity::create(&identity, &db).await;
+    drop(db);
+
+    match res {
+        Ok(created) => {
+            log::info!(
+                "created {} identity for user {}",
+                created.provider,
+                created.user_id
+            );
+            Ok(created.user_id)
+        }
+        Err(err) => {
+            log::error!("could not create identity: {err}");
+            Err("Could not create identity".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account<'a>(
+        provider: &'a str,
+        subject: &'a str,
+        email: &'a str,
+        email_verified: bool,
+    ) -> ProviderAccount<'a> {
+        ProviderAccount {
+            provider,
+            primary: provider == "primary",
+            subject,
+            email: Some(email),
+            email_verified,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_verified_email_links_accounts() {
+        let client = crate::database::test_client().await;
+        let primary = resolve_user_id(
+            &client,
+            &account("primary", "sub-1", "Ann@Example.com", true),
+        )
+        .await
+        .unwrap();
+        // Existing data is owned by the subject of the primary provider
+        assert_eq!(primary, "sub-1");
+
+        let linked = resolve_user_id(&client, &account("github", "gh-1", "ann@example.com", true))
+            .await
+            .unwrap();
+        assert_eq!(linked, "sub-1");
+    }
+
+    #[actix_web::test]
+    async fn test_unverified_email_creates_a_new_user() {
+        let client = crate::database::test_client().await;
+        resolve_user_id(
+            &client,
+            &account("primary", "sub-1", "ann@example.com", false),
+        )
+        .await
+        .unwrap();
+        let other = resolve_user_id(&client, &account("github", "gh-1", "ann@example.com", true))
+            .await
+            .unwrap();
+        assert_ne!(other, "sub-1");
+
+        resolve_user_id(
+            &client,
+            &account("primary", "sub-2", "bob@example.com", true),
+        )
+        .await
+        .unwrap();
+        let other = resolve_user_id(
+            &client,
+            &account("github", "gh-2", "bob@example.com", false),
+        )
+        .await
+        .unwrap();
+        assert_ne!(other, "sub-2");
+    }
+
+    #[actix_web::test]
+    async fn test_repeat_login_updates_the_email() {
+        let client = crate::database::test_client().await;
+        let first = resolve_user_id(
+            &client,
+            &account("github", "gh-1", "old@example.com", false),
+        )
+        .await
+        .unwrap();
+        let again = resolve_user_id(&client, &account("github", "gh-1", "New@Example.com", true))
+            .await
+            .unwrap();
+        // Only the primary provider's subjects are used as user ids
+        assert_ne!(first, "gh-1");
+        assert_eq!(again, first);
+
+        let identity = get_identity(&client, "github", "gh-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(identity.email.as_deref(), Some("new@example.com"));
+        assert_eq!(identity.email_verified, 1);
+    }
+}
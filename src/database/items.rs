@@ -1,4 +1,4 @@
-use libsql_orm::{Filter, FilterOperator, Model};
+use libsql_orm::{FilterOperator, Model, PaginatedResult, Pagination, QueryBuilder};
 use serde::{Deserialize, Serialize};
 
 use crate::database::DBClient;
@@ -7,10 +7,17 @@ use crate::database::DBClient;
 #[table_name("items")]
 pub struct Item {
     pub id: std::option::Option<i64>,
+    #[orm_column(not_null)]
     pub owner_id: String,
+    #[orm_column(not_null)]
     pub task: String,
+    #[orm_column(not_null, default = false)]
     pub completed: u16,
+    /// Name of the list the item belongs to, `None` for the main shopping list.
+    pub list: Option<String>,
+    #[orm_column(type = "DATETIME DEFAULT CURRENT_TIMESTAMP")]
     pub created_at: chrono::DateTime<chrono::Utc>,
+    #[orm_column(type = "DATETIME", not_null)]
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -37,17 +44,64 @@ impl Item {
     pub fn owner_id(&self) -> String {
         self.owner_id.to_string()
     }
+
+    pub fn list(&self) -> Option<&str> {
+        self.list.as_deref()
+    }
+}
+
+/// Names of all lists the owner has items in, sorted.
+pub fn list_names(items: &[Item]) -> Vec<String> {
+    let mut names: Vec<String> = items.iter().filter_map(|item| item.list.clone()).collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names.dedup();
+    names
+}
+
+/// Narrows down the items of an owner.
+#[derive(Debug, Default)]
+pub struct ItemFilter {
+    /// `Some(None)` selects the main shopping list.
+    pub list: Option<Option<String>>,
+    pub completed: Option<bool>,
+}
+
+/// One page of the owner's items, oldest first.
+pub async fn get_items_page(
+    client: &DBClient,
+    owner_id: String,
+    filter: &ItemFilter,
+    pagination: &Pagination,
+) -> Result<PaginatedResult<Item>, String> {
+    let mut conditions = vec![Item::COLUMNS.owner_id.eq(owner_id)];
+    match &filter.list {
+        Some(Some(list)) => conditions.push(Item::COLUMNS.list.eq(list.clone())),
+        Some(None) => conditions.push(Item::COLUMNS.list.is_null()),
+        None => {}
+    }
+    if let Some(completed) = filter.completed {
+        conditions.push(Item::COLUMNS.completed.eq(u16::from(completed)));
+    }
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let page = QueryBuilder::new(Item::table_name())
+        .r#where(FilterOperator::And(conditions))
+        .order_by(Item::COLUMNS.id.asc())
+        .execute_paginated::<Item>(&db, pagination)
+        .await;
+    drop(db);
+
+    page.map_err(|err| {
+        log::error!("Error getting items: {err}");
+        "Could not get items".to_string()
+    })
 }
 
 pub async fn get_items(client: &DBClient, owner_id: String) -> Result<Vec<Item>, String> {
     log::info!("getting items for owner: {owner_id}");
 
-    let db = super::unlock_client(client).await;
-    let items = Item::find_where(
-        FilterOperator::Single(Filter::eq("owner_id".to_string(), owner_id.clone())),
-        &db,
-    )
-    .await;
+    let db = client.get().await.map_err(super::pool_error)?;
+    let items = Item::find_where(Item::COLUMNS.owner_id.eq(owner_id.clone()), &db).await;
     drop(db);
 
     match items {
@@ -67,7 +121,9 @@ pub async fn create_items(client: &DBClient, items: Vec<Item>) {
         return;
     }
 
-    let client = super::unlock_client(client).await;
+    let Ok(client) = client.get().await.map_err(super::pool_error) else {
+        return;
+    };
     let result = Item::bulk_create(items.as_slice(), &client).await;
     match result {
         Ok(_) => log::info!("created items"),
@@ -76,7 +132,7 @@ pub async fn create_items(client: &DBClient, items: Vec<Item>) {
 }
 
 pub async fn create_item(client: &DBClient, item: Item) -> Result<Item, String> {
-    let db = super::unlock_client(client).await;
+    let db = client.get().await.map_err(super::pool_error)?;
 
     let res = Item::create(&item, &db).await;
     drop(db);
@@ -92,8 +148,8 @@ pub async fn create_item(client: &DBClient, item: Item) -> Result<Item, String>
         }
     }
 }
-pub async fn delete_item(client: &DBClient, item_id: i64, owner_id: String) {
-    let db = super::unlock_client(client).await;
+pub async fn delete_item(client: &DBClient, item_id: i64, owner_id: String) -> Result<(), String> {
+    let db = client.get().await.map_err(super::pool_error)?;
     let item_result = Item::find_by_id(item_id, &db).await;
 
     match item_result {
@@ -101,24 +157,32 @@ pub async fn delete_item(client: &DBClient, item_id: i64, owner_id: String) {
             if item.owner_id() != owner_id {
                 log::error!("Unauthorized delete attempt for item {item_id}");
                 drop(db);
-                return;
+                return Err("Unauthorized".to_string());
             }
 
             let delete_result = item.delete(&db).await;
             drop(db);
 
             match delete_result {
-                Ok(_) => log::info!("Successfully deleted item {item_id}"),
-                Err(err) => log::error!("Failed to delete item {item_id}: {err:?}"),
+                Ok(_) => {
+                    log::info!("Successfully deleted item {item_id}");
+                    Ok(())
+                }
+                Err(err) => {
+                    log::error!("Failed to delete item {item_id}: {err:?}");
+                    Err("Failed to delete item".to_string())
+                }
             }
         }
         Ok(None) => {
             log::error!("Item {item_id} not found");
             drop(db);
+            Err("Item not found".to_string())
         }
         Err(err) => {
             log::error!("Error finding item {item_id}: {err:?}");
             drop(db);
+            Err("Database error".to_string())
         }
     }
 }
@@ -128,7 +192,7 @@ pub async fn toggle_item(
     item_id: i64,
     owner_id: String,
 ) -> Result<Item, String> {
-    let db = super::unlock_client(client).await;
+    let db = client.get().await.map_err(super::pool_error)?;
     let item_result = Item::find_by_id(item_id, &db).await;
 
     let mut item = match item_result {
@@ -167,7 +231,7 @@ pub async fn toggle_item(
 }
 
 pub async fn get_item(client: &DBClient, item_id: i64, owner_id: String) -> Result<Item, String> {
-    let db = super::unlock_client(client).await;
+    let db = client.get().await.map_err(super::pool_error)?;
     let item_result = Item::find_by_id(item_id, &db).await;
     drop(db);
 
@@ -196,7 +260,7 @@ pub async fn update_item(
     new_task: String,
     owner_id: String,
 ) -> Result<Item, String> {
-    let db = super::unlock_client(client).await;
+    let db = client.get().await.map_err(super::pool_error)?;
     let item_result = Item::find_by_id(item_id, &db).await;
 
     let mut item = match item_result {
@@ -225,3 +289,79 @@ pub async fn update_item(
 
     update_result.map_err(|e| e.to_string())
 }
+
+/// Stores all fields of an item the caller already checked the owner of.
+pub async fn save_item(client: &DBClient, mut item: Item) -> Result<Item, String> {
+    item.updated_at = chrono::Utc::now();
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let update_result = item.update(&db).await;
+    drop(db);
+
+    update_result.map_err(|err| {
+        log::error!("could not update item: {err}");
+        "Failed to update item".to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(owner_id: &str, task: &str) -> Item {
+        let now = chrono::Utc::now();
+        Item {
+            id: None,
+            owner_id: owner_id.to_string(),
+            task: task.to_string(),
+            completed: 0,
+            list: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_toggle_item() {
+        let client = crate::database::test_client().await;
+        let created = create_item(&client, item("alice", "Milk")).await.unwrap();
+
+        assert!(
+            toggle_item(&client, created.id(), "bob".to_string())
+                .await
+                .is_err()
+        );
+        let toggled = toggle_item(&client, created.id(), "alice".to_string())
+            .await
+            .unwrap();
+        assert!(toggled.completed());
+
+        let items = get_items(&client, "alice".to_string()).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert!(items[0].completed());
+        assert!(
+            get_items(&client, "bob".to_string())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_delete_item() {
+        let client = crate::database::test_client().await;
+        let created = create_item(&client, item("alice", "Milk")).await.unwrap();
+
+        assert_eq!(
+            delete_item(&client, created.id(), "bob".to_string()).await,
+            Err("Unauthorized".to_string())
+        );
+        delete_item(&client, created.id(), "alice".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            delete_item(&client, created.id(), "alice".to_string()).await,
+            Err("Item not found".to_string())
+        );
+    }
+}
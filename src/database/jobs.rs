@@ -0,0 +1,538 @@
+use libsql_orm::{Model, QueryBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+
+/// Delay before the first retry, doubled for every further attempt.
+const RETRY_BASE_SECONDS: i64 = 30;
+/// Upper bound for the retry delay.
+const RETRY_MAX_SECONDS: i64 = 30 * 60;
+/// How many due jobs a worker looks at when claiming.
+const CLAIM_BATCH: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Dead,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Dead => "dead",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Process,
+    Extract,
+    Reextract,
+}
+
+impl JobKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobKind::Process => "Process recipe",
+            JobKind::Extract => "Extract recipe structure",
+            JobKind::Reextract => "Re-extract recipe",
+        }
+    }
+}
+
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("jobs")]
+pub struct Job {
+    pub id: std::option::Option<i64>,
+    pub owner_id: String,
+    pub kind: JobKind,
+    pub payload: String,
+    pub status: JobStatus,
+    pub attempts: i64,
+    pub max_attempts: i64,
+    pub last_error: Option<String>,
+    pub result: Option<String>,
+    /// Recipe the job saved, set together with the recipe so a retry does
+    /// not save it again.
+    pub recipe_id: Option<i64>,
+    pub run_after: chrono::DateTime<chrono::Utc>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Job {
+    pub fn new(owner_id: String, kind: JobKind, payload: String, max_attempts: i64) -> Self {
+        let now = chrono::Utc::now();
+        Job {
+            id: None,
+            owner_id,
+            kind,
+            payload,
+            status: JobStatus::Queued,
+            attempts: 0,
+            max_attempts,
+            last_error: None,
+            result: None,
+            recipe_id: None,
+            run_after: now,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id.unwrap_or(0)
+    }
+
+    pub fn owner_id(&self) -> &str {
+        &self.owner_id
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(self.status, JobStatus::Queued | JobStatus::Running)
+    }
+
+    fn retry_delay(&self) -> chrono::Duration {
+        let exponent = (self.attempts - 1).clamp(0, 16) as u32;
+        let seconds = RETRY_BASE_SECONDS.saturating_mul(2_i64.pow(exponent));
+        chrono::Duration::seconds(seconds.min(RETRY_MAX_SECONDS))
+    }
+}
+
+pub async fn enqueue_job(client: &DBClient, job: Job) -> Result<Job, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let res = Job::create(&job, &db).await;
+    drop(db);
+
+    match res {
+        Ok(created_job) => {
+            log::info!("enqueued job {} ({:?})", created_job.id(), created_job.kind);
+            Ok(created_job)
+        }
+        Err(err) => {
+            log::error!("{err:?}");
+            Err("Could not enqueue job".to_string())
+        }
+    }
+}
+
+/// Picks the oldest due job and marks it as running.
+///
+/// The status check in the UPDATE makes the claim safe when several workers
+/// race for the same row: only one of them sees an affected row.
+pub async fn claim_next_job(client: &DBClient) -> Result<Option<Job>, String> {
+    let now = chrono::Utc::now();
+    let db = client.get().await.map_err(super::pool_error)?;
+    let candidates = QueryBuilder::new(Job::table_name())
+        .r#where(
+            Job::COLUMNS
+                .status
+                .eq(JobStatus::Queued)
+                .and_with(Job::COLUMNS.run_after.le(now)),
+        )
+        .order_by(Job::COLUMNS.run_after.asc())
+        .limit(CLAIM_BATCH)
+        .execute::<Job>(&db)
+        .await
+        .map_err(|err| {
+            log::error!("Error looking for queued jobs: {err}");
+            "Could not look for queued jobs".to_string()
+        })?;
+
+    for mut job in candidates {
+        let claimed = db
+            .get_connection()
+            .execute(
+                "UPDATE jobs SET status = ?, attempts = attempts + 1, updated_at = ? WHERE id = ? AND status = ?",
+                (
+                    JobStatus::Running.as_str(),
+                    now.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+                    job.id(),
+                    JobStatus::Queued.as_str(),
+                ),
+            )
+            .await
+            .map_err(|err| {
+                log::error!("Error claiming job {}: {err}", job.id());
+                "Could not claim job".to_string()
+            })?;
+
+        if claimed == 1 {
+            job.status = JobStatus::Running;
+            job.attempts += 1;
+            job.updated_at = now;
+            log::info!("claimed job {} (attempt {})", job.id(), job.attempts);
+            return Ok(Some(job));
+        }
+    }
+
+    Ok(None)
+}
+
+pub async fn complete_job(client: &DBClient, mut job: Job, result: String) -> Result<Job, String> {
+    job.status = JobStatus::Succeeded;
+    job.result = Some(result);
+    job.last_error = None;
+    job.updated_at = chrono::Utc::now();
+
+    save_job(client, job).await
+}
+
+/// Records a failed attempt. The job is queued again with an exponential
+/// backoff until it runs out of attempts, then it is dead-lettered.
+pub async fn fail_job(client: &DBClient, mut job: Job, error: String) -> Result<Job, String> {
+    let now = chrono::Utc::now();
+    if job.attempts >= job.max_attempts {
+        log::error!(
+            "job {} failed permanently after {} attempts: {error}",
+            job.id(),
+            job.attempts
+        );
+        job.status = JobStatus::Dead;
+    } else {
+        let delay = job.retry_delay();
+        log::warn!(
+            "job {} failed (attempt {}), retrying in {}s: {error}",
+            job.id(),
+            job.attempts,
+            delay.num_seconds()
+        );
+        job.status = JobStatus::Queued;
+        job.run_after = now + delay;
+    }
+    job.last_error = Some(error);
+    job.updated_at = now;
+
+    save_job(client, job).await
+}
+
+/// Puts a dead-lettered job back on the queue with a fresh set of attempts.
+pub async fn retry_job(client: &DBClient, job_id: i64, owner_id: String) -> Result<Job, String> {
+    let mut job = get_job(client, job_id, owner_id).await?;
+    if job.status != JobStatus::Dead {
+        return Err("Only failed jobs can be retried".to_string());
+    }
+
+    let now = chrono::Utc::now();
+    job.status = JobStatus::Queued;
+    job.attempts = 0;
+    job.run_after = now;
+    job.updated_at = now;
+
+    save_job(client, job).await
+}
+
+pub async fn get_job(client: &DBClient, job_id: i64, owner_id: String) -> Result<Job, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let job_result = Job::find_by_id(job_id, &db).await;
+    drop(db);
+
+    match job_result {
+        Ok(Some(job)) => {
+            if job.owner_id() != owner_id {
+                return Err("Unauthorized".to_string());
+            }
+            Ok(job)
+        }
+        Ok(None) => {
+            log::error!("job not found: {job_id}");
+            Err("Job not found".to_string())
+        }
+        Err(err) => {
+            log::error!("database error finding job {job_id}: {err}");
+            Err("Database error".to_string())
+        }
+    }
+}
+
+pub async fn get_jobs(client: &DBClient, owner_id: String) -> Result<Vec<Job>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let jobs = QueryBuilder::new(Job::table_name())
+        .r#where(Job::COLUMNS.owner_id.eq(owner_id.clone()))
+        .order_by(Job::COLUMNS.created_at.desc())
+        .execute::<Job>(&db)
+        .await;
+    drop(db);
+
+    match jobs {
+        Ok(jobs) => {
+            log::info!("found {} jobs for owner: {}", jobs.len(), owner_id);
+            Ok(jobs)
+        }
+        Err(err) => {
+            log::error!("Error getting jobs: {err}");
+            Err("Could not get jobs".to_string())
+        }
+    }
+}
+
+/// Latest jobs of every user with the given status, for the admin area.
+pub async fn get_jobs_with_status(
+    client: &DBClient,
+    status: JobStatus,
+    limit: u32,
+) -> Result<Vec<Job>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let jobs = QueryBuilder::new(Job::table_name())
+        .r#where(Job::COLUMNS.status.eq(status))
+        .order_by(Job::COLUMNS.updated_at.desc())
+        .limit(limit)
+        .execute::<Job>(&db)
+        .await;
+    drop(db);
+
+    jobs.map_err(|err| {
+        log::error!("Error getting {} jobs: {err}", status.as_str());
+        "Could not get jobs".to_string()
+    })
+}
+
+/// Queues running jobs again whose claim is older than `lease`. A run never
+/// takes longer than the lease, so these were interrupted, e.g. by a restart.
+/// Younger claims may still be running on another instance.
+pub async fn requeue_interrupted_jobs(client: &DBClient, lease: chrono::Duration) {
+    let Ok(db) = client.get().await.map_err(super::pool_error) else {
+        return;
+    };
+    let now = chrono::Utc::now();
+    let result = db
+        .get_connection()
+        .execute(
+            "UPDATE jobs SET status = ?, updated_at = ? WHERE status = ? AND updated_at < ?",
+            (
+                JobStatus::Queued.as_str(),
+                now.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+                JobStatus::Running.as_str(),
+                (now - lease).to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+            ),
+        )
+        .await;
+
+    match result {
+        Ok(0) => {}
+        Ok(count) => log::info!("requeued {count} interrupted jobs"),
+        Err(err) => log::error!("could not requeue interrupted jobs: {err}"),
+    }
+}
+
+async fn save_job(client: &DBClient, mut job: Job) -> Result<Job, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    // The recipe id is stored with the recipe while the job runs, after the
+    // job was claimed
+    let update_result = match Job::find_by_id(job.id(), &db).await {
+        Ok(stored) => {
+            job.recipe_id = job.recipe_id.or(stored.and_then(|stored| stored.recipe_id));
+            job.update(&db).await
+        }
+        Err(err) => Err(err),
+    };
+    drop(db);
+
+    update_result.map_err(|err| {
+        log::error!("could not update job: {err}");
+        "Failed to update job".to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_claim_next_job() {
+        let client = crate::database::test_client().await;
+        let first = Job::new("alice".to_string(), JobKind::Process, "a".to_string(), 3);
+        let first = enqueue_job(&client, first).await.unwrap();
+        let mut second = Job::new("bob".to_string(), JobKind::Extract, "b".to_string(), 3);
+        second.run_after = chrono::Utc::now() + chrono::Duration::hours(1);
+        enqueue_job(&client, second).await.unwrap();
+
+        let claimed = claim_next_job(&client).await.unwrap().unwrap();
+        assert_eq!(claimed.id(), first.id());
+        assert_eq!(claimed.status, JobStatus::Running);
+        assert_eq!(claimed.attempts, 1);
+
+        // The other job is not due yet
+        assert!(claim_next_job(&client).await.unwrap().is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_job_is_claimed_once() {
+        let client = crate::database::test_client().await;
+        let job = Job::new("alice".to_string(), JobKind::Process, "a".to_string(), 3);
+        enqueue_job(&client, job).await.unwrap();
+
+        let (first, second) = tokio::join!(claim_next_job(&client), claim_next_job(&client));
+        let claimed: Vec<Job> = [first.unwrap(), second.unwrap()]
+            .into_iter()
+            .flatten()
+            .collect();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].attempts, 1);
+    }
+
+    /// Makes a job queued by `fail_job` due right away.
+    async fn make_due(client: &DBClient, job: &Job) {
+        let mut stored = get_job(client, job.id(), job.owner_id.clone())
+            .await
+            .unwrap();
+        stored.run_after = chrono::Utc::now() - chrono::Duration::seconds(1);
+        save_job(client, stored).await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_failed_job_backs_off_then_dies() {
+        let client = crate::database::test_client().await;
+        let job = Job::new("alice".to_string(), JobKind::Process, "a".to_string(), 2);
+        enqueue_job(&client, job).await.unwrap();
+
+        let claimed = claim_next_job(&client).await.unwrap().unwrap();
+        let started = chrono::Utc::now();
+        let failed = fail_job(&client, claimed, "timeout".to_string())
+            .await
+            .unwrap();
+        assert_eq!(failed.status, JobStatus::Queued);
+        assert_eq!(failed.attempts, 1);
+        assert_eq!(failed.last_error.as_deref(), Some("timeout"));
+        let delay = failed.run_after - started;
+        assert!(delay >= chrono::Duration::seconds(29) && delay <= chrono::Duration::seconds(31));
+        // Not due before the backoff is over
+        assert!(claim_next_job(&client).await.unwrap().is_none());
+
+        make_due(&client, &failed).await;
+        let claimed = claim_next_job(&client).await.unwrap().unwrap();
+        assert_eq!(claimed.attempts, 2);
+        let dead = fail_job(&client, claimed, "timeout".to_string())
+            .await
+            .unwrap();
+        assert_eq!(dead.status, JobStatus::Dead);
+        assert!(claim_next_job(&client).await.unwrap().is_none());
+
+        assert!(
+            retry_job(&client, dead.id(), "bob".to_string())
+                .await
+                .is_err()
+        );
+        let retried = retry_job(&client, dead.id(), "alice".to_string())
+            .await
+            .unwrap();
+        assert_eq!(retried.status, JobStatus::Queued);
+        assert_eq!(retried.attempts, 0);
+        assert!(
+            retry_job(&client, dead.id(), "alice".to_string())
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            claim_next_job(&client).await.unwrap().unwrap().id(),
+            dead.id()
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_the_limit() {
+        let mut job = Job::new("alice".to_string(), JobKind::Process, "a".to_string(), 3);
+        let delays: Vec<i64> = [1, 2, 3, 20]
+            .into_iter()
+            .map(|attempts| {
+                job.attempts = attempts;
+                job.retry_delay().num_seconds()
+            })
+            .collect();
+        assert_eq!(delays, vec![30, 60, 120, RETRY_MAX_SECONDS]);
+    }
+
+    #[actix_web::test]
+    async fn test_requeue_interrupted_jobs() {
+        let client = crate::database::test_client().await;
+        let job = Job::new("alice".to_string(), JobKind::Process, "a".to_string(), 3);
+        enqueue_job(&client, job).await.unwrap();
+        let claimed = claim_next_job(&client).await.unwrap().unwrap();
+
+        // Another instance may still be running the job
+        requeue_interrupted_jobs(&client, chrono::Duration::minutes(5)).await;
+        assert!(claim_next_job(&client).await.unwrap().is_none());
+
+        // The process died with the job running and the lease ran out
+        requeue_interrupted_jobs(&client, chrono::Duration::zero()).await;
+        let resumed = claim_next_job(&client).await.unwrap().unwrap();
+        assert_eq!(resumed.id(), claimed.id());
+        assert_eq!(resumed.attempts, 2);
+    }
+
+    #[actix_web::test]
+    async fn test_claim_writes_timestamps_like_the_orm() {
+        let client = crate::database::test_client().await;
+        let job = Job::new("alice".to_string(), JobKind::Process, "a".to_string(), 3);
+        enqueue_job(&client, job).await.unwrap();
+        claim_next_job(&client).await.unwrap().unwrap();
+
+        let db = crate::database::connection(&client).await;
+        let mut rows = db
+            .get_connection()
+            .query("SELECT updated_at, run_after FROM jobs", ())
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        let claimed: String = row.get(0).unwrap();
+        let queued: String = row.get(1).unwrap();
+        assert!(claimed.ends_with('Z'), "{claimed}");
+        assert!(queued.ends_with('Z'), "{queued}");
+    }
+
+    #[actix_web::test]
+    async fn test_saving_keeps_the_recipe_id() {
+        let client = crate::database::test_client().await;
+        let job = Job::new("alice".to_string(), JobKind::Process, "a".to_string(), 3);
+        enqueue_job(&client, job).await.unwrap();
+        let claimed = claim_next_job(&client).await.unwrap().unwrap();
+
+        let recipe = crate::database::recipes::Recipe::new(
+            None,
+            "alice".to_string(),
+            Some("Soup".to_string()),
+            None,
+            "Water".to_string(),
+        );
+        let recipe =
+            crate::database::recipes::create_job_recipe(&client, claimed.id(), recipe, Vec::new())
+                .await
+                .unwrap();
+
+        // The claimed copy predates the recipe
+        let failed = fail_job(&client, claimed, "timeout".to_string())
+            .await
+            .unwrap();
+        assert_eq!(failed.recipe_id, recipe.id);
+        make_due(&client, &failed).await;
+        let retried = claim_next_job(&client).await.unwrap().unwrap();
+        assert_eq!(retried.recipe_id, recipe.id);
+    }
+
+    #[actix_web::test]
+    async fn test_claim_reports_a_busy_pool() {
+        let client = crate::database::create_orm_client(
+            ":memory:".to_string(),
+            None,
+            libsql_orm::PoolConfig {
+                acquire_timeout: std::time::Duration::from_millis(50),
+                ..libsql_orm::PoolConfig::default()
+            },
+        )
+        .await;
+        crate::database::migrations::run(&client).await;
+
+        let held = client.get().await.unwrap();
+        assert!(claim_next_job(&client).await.is_err());
+        drop(held);
+        assert!(claim_next_job(&client).await.unwrap().is_none());
+    }
+}
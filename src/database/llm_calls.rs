@@ -0,0 +1,99 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+
+/// A request to the LLM provider, kept for the usage overview of admins.
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("llm_calls")]
+pub struct LlmCall {
+    pub id: std::option::Option<i64>,
+    pub owner_id: String,
+    /// Prompt that was sent, e.g. `extract_recipe`.
+    pub kind: String,
+    pub model: String,
+    pub prompt_chars: i64,
+    pub response_chars: i64,
+    pub succeeded: u16,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl LlmCall {
+    pub fn new(
+        owner_id: String,
+        kind: &str,
+        model: &str,
+        prompt: &str,
+        response: Option<&str>,
+    ) -> Self {
+        LlmCall {
+            id: None,
+            owner_id,
+            kind: kind.to_string(),
+            model: model.to_string(),
+            prompt_chars: prompt.chars().count() as i64,
+            response_chars: response.map_or(0, |response| response.chars().count() as i64),
+            succeeded: response.is_some() as u16,
+            created_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// LLM calls of one user.
+#[derive(Debug, Clone, Default)]
+pub struct LlmUsage {
+    pub owner_id: String,
+    pub calls: i64,
+    pub failed: i64,
+    pub prompt_chars: i64,
+    pub response_chars: i64,
+}
+
+pub async fn record_llm_call(client: &DBClient, call: LlmCall) {
+    let Ok(db) = client.get().await.map_err(super::pool_error) else {
+        return;
+    };
+    let res = call.create(&db).await;
+    drop(db);
+
+    if let Err(err) = res {
+        log::error!("could not record LLM call: {err}");
+    }
+}
+
+/// Calls per user since `since`, most calls first.
+pub async fn get_usage(
+    client: &DBClient,
+    since: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<LlmUsage>, String> {
+    let since = since.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true);
+    let db = client.get().await.map_err(super::pool_error)?;
+    let rows = db
+        .get_connection()
+        .query(
+            "SELECT owner_id, COUNT(*), SUM(succeeded = 0), SUM(prompt_chars), SUM(response_chars) \
+             FROM llm_calls WHERE created_at >= ? GROUP BY owner_id ORDER BY COUNT(*) DESC",
+            [since],
+        )
+        .await;
+
+    let mut rows = match rows {
+        Ok(rows) => rows,
+        Err(err) => {
+            log::error!("Error getting LLM usage: {err}");
+            return Err("Could not get LLM usage".to_string());
+        }
+    };
+
+    let mut usage = Vec::new();
+    while let Ok(Some(row)) = rows.next().await {
+        usage.push(LlmUsage {
+            owner_id: row.get::<String>(0).unwrap_or_default(),
+            calls: row.get::<i64>(1).unwrap_or_default(),
+            failed: row.get::<i64>(2).unwrap_or_default(),
+            prompt_chars: row.get::<i64>(3).unwrap_or_default(),
+            response_chars: row.get::<i64>(4).unwrap_or_default(),
+        });
+    }
+    Ok(usage)
+}
@@ -0,0 +1,315 @@
+use libsql_orm::{FilterOperator, Model, WithRelation};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+use crate::database::recipes::Recipe;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MealSlot {
+    Breakfast,
+    Lunch,
+    Dinner,
+    Snack,
+}
+
+impl MealSlot {
+    pub const ALL: [MealSlot; 4] = [
+        MealSlot::Breakfast,
+        MealSlot::Lunch,
+        MealSlot::Dinner,
+        MealSlot::Snack,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MealSlot::Breakfast => "breakfast",
+            MealSlot::Lunch => "lunch",
+            MealSlot::Dinner => "dinner",
+            MealSlot::Snack => "snack",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MealSlot::Breakfast => "Breakfast",
+            MealSlot::Lunch => "Lunch",
+            MealSlot::Dinner => "Dinner",
+            MealSlot::Snack => "Snack",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<MealSlot> {
+        MealSlot::ALL
+            .into_iter()
+            .find(|slot| slot.as_str() == value.trim())
+    }
+}
+
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("meal_plan")]
+#[belongs_to(recipe = Recipe)]
+pub struct MealPlanEntry {
+    pub id: std::option::Option<i64>,
+    pub owner_id: String,
+    pub recipe_id: i64,
+    pub planned_on: chrono::NaiveDate,
+    pub slot: MealSlot,
+    pub servings: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl MealPlanEntry {
+    pub fn new(
+        owner_id: String,
+        recipe_id: i64,
+        planned_on: chrono::NaiveDate,
+        slot: MealSlot,
+        servings: i64,
+    ) -> Self {
+        MealPlanEntry {
+            id: None,
+            owner_id,
+            recipe_id,
+            planned_on,
+            slot,
+            servings,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id.unwrap_or(0)
+    }
+
+    pub fn owner_id(&self) -> &str {
+        &self.owner_id
+    }
+}
+
+/// Monday of the week containing `date`.
+pub fn week_start(date: chrono::NaiveDate) -> chrono::NaiveDate {
+    use chrono::Datelike;
+    date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// All planned meals in the seven days starting at `start`, ordered by day and slot.
+pub async fn get_week(
+    client: &DBClient,
+    owner_id: String,
+    start: chrono::NaiveDate,
+) -> Result<Vec<MealPlanEntry>, String> {
+    get_range(client, owner_id, start, start + chrono::Duration::days(6)).await
+}
+
+/// All planned meals from `start` to `end` inclusive, ordered by day and slot.
+pub async fn get_range(
+    client: &DBClient,
+    owner_id: String,
+    start: chrono::NaiveDate,
+    end: chrono::NaiveDate,
+) -> Result<Vec<MealPlanEntry>, String> {
+    log::info!("getting meal plan for owner {owner_id} from {start} to {end}");
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let entries = MealPlanEntry::find_where(range_filter(&owner_id, start, end), &db).await;
+    drop(db);
+
+    match entries {
+        Ok(mut entries) => {
+            log::info!(
+                "found {} planned meals for owner: {}",
+                entries.len(),
+                owner_id
+            );
+            entries.sort_by_key(|entry| (entry.planned_on, entry.slot));
+            Ok(entries)
+        }
+        Err(err) => {
+            log::error!("Error getting meal plan: {err}");
+            Err("Could not get meal plan".to_string())
+        }
+    }
+}
+
+/// Planned meals of the week starting at `start` and the recipes they use.
+pub async fn get_week_with_recipes(
+    client: &DBClient,
+    owner_id: String,
+    start: chrono::NaiveDate,
+) -> Result<(Vec<MealPlanEntry>, Vec<Recipe>), String> {
+    get_range_with_recipes(client, owner_id, start, start + chrono::Duration::days(6)).await
+}
+
+/// Planned meals like [`get_range`] and the recipes they use, loaded with one
+/// more query instead of every recipe of the owner.
+pub async fn get_range_with_recipes(
+    client: &DBClient,
+    owner_id: String,
+    start: chrono::NaiveDate,
+    end: chrono::NaiveDate,
+) -> Result<(Vec<MealPlanEntry>, Vec<Recipe>), String> {
+    log::info!("getting meal plan with recipes for owner {owner_id} from {start} to {end}");
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let planned = MealPlanEntry::find_where(range_filter(&owner_id, start, end), &db)
+        .with(MealPlanEntry::RECIPE, &db)
+        .await;
+    drop(db);
+
+    let planned = planned.map_err(|err| {
+        log::error!("Error getting meal plan with recipes: {err}");
+        "Could not get meal plan".to_string()
+    })?;
+
+    let mut entries = Vec::with_capacity(planned.len());
+    let mut recipes: Vec<Recipe> = Vec::new();
+    for (entry, recipe) in planned {
+        if let Some(recipe) = recipe.filter(|recipe| recipe.owner_id == owner_id)
+            && !recipes.iter().any(|known| known.id == recipe.id)
+        {
+            recipes.push(recipe);
+        }
+        entries.push(entry);
+    }
+    entries.sort_by_key(|entry| (entry.planned_on, entry.slot));
+    Ok((entries, recipes))
+}
+
+/// The owner's meals from `start` to `end` inclusive.
+fn range_filter(
+    owner_id: &str,
+    start: chrono::NaiveDate,
+    end: chrono::NaiveDate,
+) -> FilterOperator {
+    // Dates are stored as ISO strings, so comparing them as text keeps the order
+    let columns = MealPlanEntry::COLUMNS;
+    columns
+        .owner_id
+        .eq(owner_id)
+        .and_with(columns.planned_on.between(start, end))
+}
+
+pub async fn create_entry(
+    client: &DBClient,
+    entry: MealPlanEntry,
+) -> Result<MealPlanEntry, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let res = MealPlanEntry::create(&entry, &db).await;
+    drop(db);
+
+    match res {
+        Ok(created_entry) => {
+            log::info!("planned meal {}", created_entry.id());
+            Ok(created_entry)
+        }
+        Err(err) => {
+            log::error!("{err:?}");
+            Err("Could not plan meal".to_string())
+        }
+    }
+}
+
+pub async fn get_entry(
+    client: &DBClient,
+    entry_id: i64,
+    owner_id: String,
+) -> Result<MealPlanEntry, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let entry_result = MealPlanEntry::find_by_id(entry_id, &db).await;
+    drop(db);
+
+    match entry_result {
+        Ok(Some(entry)) => {
+            if entry.owner_id() != owner_id {
+                return Err("Unauthorized".to_string());
+            }
+            Ok(entry)
+        }
+        Ok(None) => {
+            log::error!("planned meal not found: {entry_id}");
+            Err("Planned meal not found".to_string())
+        }
+        Err(err) => {
+            log::error!("database error finding planned meal {entry_id}: {err}");
+            Err("Database error".to_string())
+        }
+    }
+}
+
+pub async fn delete_entry(
+    client: &DBClient,
+    entry_id: i64,
+    owner_id: String,
+) -> Result<(), String> {
+    let entry = get_entry(client, entry_id, owner_id).await?;
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let delete_result = entry.delete(&db).await;
+    drop(db);
+
+    match delete_result {
+        Ok(_) => {
+            log::info!("deleted planned meal {entry_id}");
+            Ok(())
+        }
+        Err(err) => {
+            log::error!("could not delete planned meal: {err}");
+            Err("Failed to delete planned meal".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::recipes::create_recipe;
+
+    async fn recipe(client: &DBClient, owner_id: &str, title: &str) -> i64 {
+        let recipe = Recipe::new(
+            None,
+            owner_id.to_string(),
+            Some(title.to_string()),
+            None,
+            String::new(),
+        );
+        create_recipe(client, recipe).await.unwrap().id()
+    }
+
+    #[actix_web::test]
+    async fn test_get_week_with_recipes() {
+        let client = crate::database::test_client().await;
+        let soup = recipe(&client, "alice", "Soup").await;
+        let salad = recipe(&client, "alice", "Salad").await;
+        recipe(&client, "alice", "Not planned").await;
+        let foreign = recipe(&client, "bob", "Bob's stew").await;
+
+        let monday = chrono::NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        for (recipe_id, day, slot) in [
+            (soup, 1, MealSlot::Dinner),
+            (salad, 0, MealSlot::Lunch),
+            (soup, 0, MealSlot::Dinner),
+            (foreign, 2, MealSlot::Lunch),
+        ] {
+            let entry = MealPlanEntry::new(
+                "alice".to_string(),
+                recipe_id,
+                monday + chrono::Duration::days(day),
+                slot,
+                2,
+            );
+            create_entry(&client, entry).await.unwrap();
+        }
+
+        let (entries, recipes) = get_week_with_recipes(&client, "alice".to_string(), monday)
+            .await
+            .unwrap();
+        let planned: Vec<i64> = entries.iter().map(|entry| entry.recipe_id).collect();
+        assert_eq!(planned, vec![salad, soup, soup, foreign]);
+        // Each recipe once, never another owner's
+        let titles: Vec<&str> = recipes.iter().filter_map(|recipe| recipe.title()).collect();
+        assert_eq!(titles, vec!["Salad", "Soup"]);
+    }
+}
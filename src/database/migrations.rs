@@ -1,54 +1,224 @@
+use std::time::Duration;
+
+use libsql_orm::{Error, Migration, MigrationManager, SchemaDiff};
+
 use crate::database::DBClient;
+use crate::database::items::Item;
+use crate::database::recipes::Recipe;
+
+/// How often startup retries while another instance holds the migration lock.
+const LOCK_ATTEMPTS: u32 = 30;
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A table migration made of its CREATE TABLE and index files.
+macro_rules! table {
+    ($version:expr, $table:literal) => {
+        Migration::new(
+            $version,
+            $table,
+            concat!(
+                include_str!(concat!("../../migrations/", $table, ".sql")),
+                "\n",
+                include_str!(concat!("../../migrations/", $table, "_indexes.sql")),
+            ),
+        )
+        .with_down(concat!("DROP TABLE IF EXISTS ", $table, ";"))
+    };
+}
+
+/// Every schema migration, in the order they are applied. Never edit a
+/// migration once released, add a new one instead.
+pub fn migrations() -> Vec<Migration> {
+    vec![
+        table!(1, "items"),
+        table!(2, "recipes"),
+        table!(3, "jobs"),
+        table!(4, "pantry"),
+        table!(5, "meal_plan"),
+        table!(6, "calendar_feeds"),
+        table!(7, "auth_sessions"),
+        table!(8, "identities"),
+        table!(9, "users"),
+        table!(10, "llm_calls"),
+        table!(11, "api_tokens"),
+        table!(12, "sessions"),
+        Migration::new(
+            13,
+            "recipes_fts",
+            include_str!("../../migrations/recipes_fts.sql"),
+        )
+        .with_down(include_str!("../../migrations/recipes_fts_down.sql")),
+        Migration::new(
+            14,
+            "jobs_recipe_id",
+            include_str!("../../migrations/jobs_recipe_id.sql"),
+        )
+        .with_down(include_str!("../../migrations/jobs_recipe_id_down.sql")),
+    ]
+}
+
+/// Columns added before migrations were versioned. Databases from that time
+/// have the table without them, newer ones get them from the CREATE TABLE.
+const LEGACY_COLUMNS: [(&str, &str, &str); 2] = [
+    (
+        "items",
+        "list",
+        include_str!("../../migrations/items_list.sql"),
+    ),
+    (
+        "recipes",
+        "prompt_version",
+        include_str!("../../migrations/recipes_prompt_version.sql"),
+    ),
+];
 
 pub async fn run(client: &DBClient) {
     log::info!("Starting database migrations...");
 
-    // Run base table migrations first
-    let items_sql = include_str!("../../migrations/items.sql");
-    {
-        let client = super::unlock_client(client).await;
-        client
-            .get_connection()
-            .execute_batch(items_sql)
-            .await
-            .expect("items migration failed");
+    for (table, column, sql) in LEGACY_COLUMNS {
+        if lacks_column(client, table, column).await {
+            let client = super::connection(client).await;
+            client
+                .get_connection()
+                .execute_batch(sql)
+                .await
+                .unwrap_or_else(|err| panic!("{table} {column} migration failed: {err}"));
+            log::info!("Added {table}.{column} to an existing database");
+        }
     }
-    log::info!("Items table migration completed");
 
-    let recipes_sql = include_str!("../../migrations/recipes.sql");
-    {
-        let client = super::unlock_client(client).await;
-        client
-            .get_connection()
-            .execute_batch(recipes_sql)
-            .await
-            .expect("recipes migration failed");
+    let migrations = migrations();
+    let mut attempt = 1;
+    let report = loop {
+        let manager = MigrationManager::new(super::connection(client).await.detach());
+        match manager.migrate(&migrations).await {
+            Err(Error::Migration(err)) if err.contains("lock") && attempt < LOCK_ATTEMPTS => {
+                log::info!("Waiting for another instance to finish migrating: {err}");
+                attempt += 1;
+                tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+            }
+            result => break result.expect("database migrations failed"),
+        }
+    };
+
+    if report.migrations.is_empty() {
+        log::info!("Database schema is up to date");
+    }
+    for (version, name) in &report.migrations {
+        log::info!("Applied migration {version} ({name})");
     }
-    log::info!("Recipes table migration completed");
+    log::info!("All database migrations completed successfully");
+}
 
-    // Run index migrations
-    let items_indexes_sql = include_str!("../../migrations/items_indexes.sql");
-    {
-        let client = super::unlock_client(client).await;
-        client
-            .get_connection()
-            .execute_batch(items_indexes_sql)
-            .await
-            .expect("items indexes migration failed");
+/// Differences between the live tables and the `Item` and `Recipe` structs,
+/// as migration SQL to review and add as a new version. Empty when they
+/// match.
+pub async fn schema_diff(client: &DBClient) -> Result<String, String> {
+    let db = super::connection(client).await;
+    let diffs = [
+        SchemaDiff::for_model::<Item>(&db).await,
+        SchemaDiff::for_model::<Recipe>(&db).await,
+    ];
+
+    let mut sql = String::new();
+    for diff in diffs {
+        let diff = diff.map_err(|err| format!("could not diff the schema: {err}"))?;
+        if diff.is_empty() {
+            continue;
+        }
+        if !sql.is_empty() {
+            sql.push('\n');
+        }
+        sql.push_str(&diff.to_sql());
     }
-    log::info!("Items indexes migration completed");
+    Ok(sql)
+}
 
-    let recipes_indexes_sql = include_str!("../../migrations/recipes_indexes.sql");
-    {
-        let client = super::unlock_client(client).await;
-        client
-            .get_connection()
-            .execute_batch(recipes_indexes_sql)
+/// Whether the table exists but predates the column.
+async fn lacks_column(client: &DBClient, table: &str, column: &str) -> bool {
+    let client = super::connection(client).await;
+    let mut rows = client
+        .get_connection()
+        .query(&format!("PRAGMA table_info({table})"), ())
+        .await
+        .expect("could not read table info");
+
+    let mut exists = false;
+    while let Ok(Some(row)) = rows.next().await {
+        exists = true;
+        if row.get::<String>(1).is_ok_and(|name| name == column) {
+            return false;
+        }
+    }
+    exists
+}
+
+#[cfg(test)]
+mod tests {
+    use libsql_orm::Model;
+
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        let migrations = migrations();
+        for (index, migration) in migrations.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1, "{}", migration.name);
+            assert!(migration.down.is_some());
+        }
+    }
+
+    #[test]
+    fn test_recipes_fts_matches_model() {
+        let sql = Recipe::fts_migration_sql().unwrap();
+        assert!(include_str!("../../migrations/recipes_fts.sql").contains(&sql));
+    }
+
+    async fn applied(client: &DBClient) -> usize {
+        MigrationManager::new(crate::database::connection(client).await.detach())
+            .applied()
             .await
-            .expect("recipes indexes migration failed");
+            .unwrap()
+            .len()
     }
 
-    log::info!("Recipes indexes migration completed");
+    #[actix_web::test]
+    async fn test_run_is_idempotent() {
+        let client = crate::database::test_client().await;
+        assert_eq!(applied(&client).await, migrations().len());
 
-    log::info!("All database migrations completed successfully");
+        run(&client).await;
+        assert_eq!(applied(&client).await, migrations().len());
+    }
+
+    #[actix_web::test]
+    async fn test_models_match_migrations() {
+        let client = crate::database::test_client().await;
+        assert_eq!(schema_diff(&client).await.unwrap(), "");
+    }
+
+    #[actix_web::test]
+    async fn test_upgrades_legacy_database() {
+        let client = crate::database::create_orm_client(
+            ":memory:".to_string(),
+            None,
+            libsql_orm::PoolConfig::default(),
+        )
+        .await;
+        crate::database::connection(&client)
+            .await
+            .get_connection()
+            .execute(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY AUTOINCREMENT, owner_id TEXT NOT NULL, task TEXT NOT NULL, completed INTEGER NOT NULL DEFAULT 0, created_at DATETIME, updated_at DATETIME)",
+                (),
+            )
+            .await
+            .unwrap();
+        assert!(lacks_column(&client, "items", "list").await);
+
+        run(&client).await;
+        assert!(!lacks_column(&client, "items", "list").await);
+        // Tables the database never had are left to their migrations
+        assert!(!lacks_column(&client, "recipes", "prompt_version").await);
+    }
 }
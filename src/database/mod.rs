@@ -1,35 +1,61 @@
-use libsql_orm::Database;
-use std::sync::{Arc, Mutex};
+use libsql_orm::{Pool, PoolConfig, PooledDatabase};
 
-pub type DBClient = Arc<Mutex<DB>>;
+/// Pool of database connections, cheap to clone.
+pub type DBClient = Pool;
 
-pub struct DB {
-    url: String,
-    token: Option<String>,
+/// Whether the URL names an embedded database rather than a server.
+pub fn is_local_url(url: &str) -> bool {
+    url == ":memory:" || url.starts_with("file:")
 }
 
-impl DB {
-    pub fn new(url: String, token: Option<String>) -> Self {
-        DB { url, token }
-    }
+/// Borrows a connection from the pool, panicking when none is free in time.
+/// Only for startup code like migrations that cannot go on without the
+/// database, everything else uses `client.get()` with [`pool_error`].
+pub async fn connection(client: &DBClient) -> PooledDatabase {
+    client
+        .get()
+        .await
+        .expect("could not get a database connection")
+}
 
-    pub async fn connect(&self) -> libsql_orm::Database {
-        let token = self.token.clone().unwrap_or_default();
-        Database::new_connect(&self.url, &token).await.unwrap()
-    }
+/// Logs a failed pool checkout and turns it into a user facing message.
+pub fn pool_error(err: libsql_orm::Error) -> String {
+    log::error!("Could not get a database connection: {err}");
+    "The database is busy, please try again".to_string()
 }
 
-#[allow(clippy::await_holding_lock)]
-pub async fn unlock_client(client: &DBClient) -> libsql_orm::Database {
-    client.lock().unwrap().connect().await
+pub async fn create_orm_client(url: String, token: Option<String>, config: PoolConfig) -> DBClient {
+    if is_local_url(&url) {
+        log::info!("Using embedded database {url}");
+    }
+    Pool::new(&url, &token.unwrap_or_default(), config)
+        .await
+        .expect("could not open database")
 }
 
-pub async fn create_orm_client(url: String, token: Option<String>) -> DB {
-    DB::new(url, token)
+/// A migrated in-memory database.
+#[cfg(test)]
+pub async fn test_client() -> DBClient {
+    let client = create_orm_client(":memory:".to_string(), None, PoolConfig::default()).await;
+    migrations::run(&client).await;
+    client
 }
 
+pub mod admin;
+pub mod api_tokens;
+pub mod auth_sessions;
+pub mod calendar_feeds;
+pub mod identities;
 pub mod migrations;
 
 pub mod recipes;
 
 pub mod items;
+
+pub mod jobs;
+pub mod llm_calls;
+pub mod meal_plan;
+
+pub mod pantry;
+pub mod sessions;
+pub mod users;
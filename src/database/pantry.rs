@@ -0,0 +1,361 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+use crate::ingredients::Ingredient;
+
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("pantry")]
+pub struct PantryItem {
+    pub id: std::option::Option<i64>,
+    pub owner_id: String,
+    pub name: String,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub expires_on: Option<chrono::NaiveDate>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PantryItem {
+    pub fn new(
+        owner_id: String,
+        name: String,
+        quantity: Option<f64>,
+        unit: Option<String>,
+        expires_on: Option<chrono::NaiveDate>,
+    ) -> Self {
+        PantryItem {
+            id: None,
+            owner_id,
+            name,
+            quantity,
+            unit,
+            expires_on,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id.unwrap_or(0)
+    }
+
+    pub fn owner_id(&self) -> &str {
+        &self.owner_id
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_on
+            .is_some_and(|date| date < chrono::Utc::now().date_naive())
+    }
+
+    pub fn update_details(
+        &mut self,
+        name: String,
+        quantity: Option<f64>,
+        unit: Option<String>,
+        expires_on: Option<chrono::NaiveDate>,
+    ) {
+        self.name = name;
+        self.quantity = quantity;
+        self.unit = unit;
+        self.expires_on = expires_on;
+        self.updated_at = chrono::Utc::now();
+    }
+}
+
+pub async fn get_pantry_items(
+    client: &DBClient,
+    owner_id: String,
+) -> Result<Vec<PantryItem>, String> {
+    log::info!("getting pantry items for owner: {owner_id}");
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let items =
+        PantryItem::find_where(PantryItem::COLUMNS.owner_id.eq(owner_id.clone()), &db).await;
+    drop(db);
+
+    match items {
+        Ok(mut items) => {
+            log::info!("found {} pantry items for owner: {}", items.len(), owner_id);
+            items.sort_by_key(|item| item.name.to_lowercase());
+            Ok(items)
+        }
+        Err(err) => {
+            log::error!("Error getting pantry items: {err}");
+            Err("Could not get pantry items".to_string())
+        }
+    }
+}
+
+pub async fn create_pantry_item(client: &DBClient, item: PantryItem) -> Result<PantryItem, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let res = PantryItem::create(&item, &db).await;
+    drop(db);
+
+    match res {
+        Ok(created_item) => {
+            log::info!("created pantry item {}", created_item.id());
+            Ok(created_item)
+        }
+        Err(err) => {
+            log::error!("{err:?}");
+            Err("Could not create pantry item".to_string())
+        }
+    }
+}
+
+/// Adds stock to the pantry, topping up an existing entry with the same name
+/// and unit instead of creating a duplicate. Expired stock is replaced by the
+/// new one, otherwise the earlier expiry of the two applies.
+pub async fn add_to_pantry(client: &DBClient, item: PantryItem) -> Result<PantryItem, String> {
+    let existing = get_pantry_items(client, item.owner_id.clone())
+        .await?
+        .into_iter()
+        .find(|stock| {
+            stock.name.eq_ignore_ascii_case(&item.name)
+                && stock.unit.as_deref().map(str::to_lowercase)
+                    == item.unit.as_deref().map(str::to_lowercase)
+        });
+
+    let Some(mut stock) = existing else {
+        return create_pantry_item(client, item).await;
+    };
+
+    let (quantity, expires_on) = if stock.is_expired() {
+        (item.quantity, item.expires_on)
+    } else {
+        let quantity = match (stock.quantity, item.quantity) {
+            (Some(stocked), Some(added)) => Some(stocked + added),
+            _ => None,
+        };
+        let expires_on = match (stock.expires_on, item.expires_on) {
+            (Some(stocked), Some(added)) => Some(stocked.min(added)),
+            (stocked, added) => stocked.or(added),
+        };
+        (quantity, expires_on)
+    };
+    stock.update_details(stock.name.clone(), quantity, stock.unit.clone(), expires_on);
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let update_result = stock.update(&db).await;
+    drop(db);
+
+    update_result.map_err(|err| {
+        log::error!("could not update pantry item: {err}");
+        "Failed to update pantry item".to_string()
+    })
+}
+
+/// Moves a shopping list item to the pantry: "500 g flour" becomes 500 g of
+/// "flour", and the item is removed from the list.
+pub async fn move_item_to_pantry(
+    client: &DBClient,
+    item_id: i64,
+    owner_id: String,
+) -> Result<PantryItem, String> {
+    let item = super::items::get_item(client, item_id, owner_id.clone()).await?;
+
+    let ingredient = Ingredient::parse(&item.task);
+    let pantry_item = PantryItem::new(
+        owner_id.clone(),
+        ingredient.name,
+        ingredient.quantity,
+        ingredient.unit.map(|unit| unit.name.to_string()),
+        None,
+    );
+    let stock = add_to_pantry(client, pantry_item).await?;
+
+    super::items::delete_item(client, item_id, owner_id).await?;
+    Ok(stock)
+}
+
+pub async fn get_pantry_item(
+    client: &DBClient,
+    item_id: i64,
+    owner_id: String,
+) -> Result<PantryItem, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let item_result = PantryItem::find_by_id(item_id, &db).await;
+    drop(db);
+
+    match item_result {
+        Ok(Some(item)) => {
+            if item.owner_id() != owner_id {
+                return Err("Unauthorized".to_string());
+            }
+            Ok(item)
+        }
+        Ok(None) => {
+            log::error!("pantry item not found: {item_id}");
+            Err("Pantry item not found".to_string())
+        }
+        Err(err) => {
+            log::error!("database error finding pantry item {item_id}: {err}");
+            Err("Database error".to_string())
+        }
+    }
+}
+
+pub async fn update_pantry_item(
+    client: &DBClient,
+    item_id: i64,
+    name: String,
+    quantity: Option<f64>,
+    unit: Option<String>,
+    expires_on: Option<chrono::NaiveDate>,
+    owner_id: String,
+) -> Result<PantryItem, String> {
+    let mut item = get_pantry_item(client, item_id, owner_id).await?;
+    item.update_details(name, quantity, unit, expires_on);
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let update_result = item.update(&db).await;
+    drop(db);
+
+    match update_result {
+        Ok(updated_item) => {
+            log::info!("updated pantry item {}", updated_item.id());
+            Ok(updated_item)
+        }
+        Err(err) => {
+            log::error!("could not update pantry item: {err}");
+            Err("Failed to update pantry item".to_string())
+        }
+    }
+}
+
+pub async fn delete_pantry_item(
+    client: &DBClient,
+    item_id: i64,
+    owner_id: String,
+) -> Result<(), String> {
+    let item = get_pantry_item(client, item_id, owner_id).await?;
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let delete_result = item.delete(&db).await;
+    drop(db);
+
+    match delete_result {
+        Ok(_) => {
+            log::info!("deleted pantry item {item_id}");
+            Ok(())
+        }
+        Err(err) => {
+            log::error!("could not delete pantry item: {err}");
+            Err("Failed to delete pantry item".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stock(name: &str, quantity: f64, unit: &str, expires_on: Option<i64>) -> PantryItem {
+        let today = chrono::Utc::now().date_naive();
+        PantryItem::new(
+            "alice".to_string(),
+            name.to_string(),
+            Some(quantity),
+            Some(unit.to_string()),
+            expires_on.map(|days| today + chrono::Duration::days(days)),
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_top_up_merges_quantity() {
+        let client = crate::database::test_client().await;
+        let first = add_to_pantry(&client, stock("Flour", 500.0, "g", Some(30)))
+            .await
+            .unwrap();
+        let topped = add_to_pantry(&client, stock("flour", 250.0, "G", Some(10)))
+            .await
+            .unwrap();
+
+        assert_eq!(topped.id, first.id);
+        assert_eq!(topped.quantity, Some(750.0));
+        // The older stock is used up first
+        assert_eq!(
+            topped.expires_on,
+            Some(chrono::Utc::now().date_naive() + chrono::Duration::days(10))
+        );
+        assert_eq!(
+            get_pantry_items(&client, "alice".to_string())
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_top_up_needs_the_same_unit() {
+        let client = crate::database::test_client().await;
+        add_to_pantry(&client, stock("Flour", 500.0, "g", None))
+            .await
+            .unwrap();
+        add_to_pantry(&client, stock("Flour", 1.0, "kg", None))
+            .await
+            .unwrap();
+
+        let items = get_pantry_items(&client, "alice".to_string())
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_top_up_replaces_expired_stock() {
+        let client = crate::database::test_client().await;
+        add_to_pantry(&client, stock("Milk", 1.0, "l", Some(-2)))
+            .await
+            .unwrap();
+        let topped = add_to_pantry(&client, stock("Milk", 2.0, "l", Some(7)))
+            .await
+            .unwrap();
+
+        assert!(!topped.is_expired());
+        assert_eq!(topped.quantity, Some(2.0));
+        assert_eq!(
+            topped.expires_on,
+            Some(chrono::Utc::now().date_naive() + chrono::Duration::days(7))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_move_item_to_pantry() {
+        let client = crate::database::test_client().await;
+        let now = chrono::Utc::now();
+        let item = crate::database::items::Item {
+            id: None,
+            owner_id: "alice".to_string(),
+            task: "500 g flour".to_string(),
+            completed: 0,
+            list: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let item = crate::database::items::create_item(&client, item)
+            .await
+            .unwrap();
+
+        assert!(
+            move_item_to_pantry(&client, item.id(), "bob".to_string())
+                .await
+                .is_err()
+        );
+        let moved = move_item_to_pantry(&client, item.id(), "alice".to_string())
+            .await
+            .unwrap();
+        assert_eq!(moved.name, "flour");
+        assert_eq!(moved.quantity, Some(500.0));
+        assert_eq!(moved.unit.as_deref(), Some("g"));
+        assert!(
+            crate::database::items::get_item(&client, item.id(), "alice".to_string())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -1,18 +1,28 @@
-use libsql_orm::{Filter, FilterOperator, Model};
+use libsql_orm::{
+    CursorPaginatedResult, CursorPagination, FilterOperator, FullTextMatch, Model, QueryBuilder,
+    Ranking,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::database::DBClient;
+use crate::database::items::Item;
 
 #[allow(unused)]
 #[derive(Model, Debug, Clone, Serialize, Deserialize)]
 #[table_name("recipes")]
+#[fts(title, content, tokenize = "porter unicode61")]
 pub struct Recipe {
     pub id: std::option::Option<i64>,
+    #[orm_column(not_null)]
     pub owner_id: String,
     pub title: Option<String>,
     pub url: Option<String>,
+    #[orm_column(not_null)]
     pub content: String,
+    pub prompt_version: Option<String>,
+    #[orm_column(type = "DATETIME DEFAULT CURRENT_TIMESTAMP")]
     pub created_at: chrono::DateTime<chrono::Utc>,
+    #[orm_column(type = "DATETIME", not_null)]
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -31,6 +41,7 @@ impl Recipe {
             title,
             url,
             content,
+            prompt_version: None,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         }
@@ -48,6 +59,10 @@ impl Recipe {
         &self.content
     }
 
+    pub fn prompt_version(&self) -> Option<&str> {
+        self.prompt_version.as_deref()
+    }
+
     pub fn content_as_prompt(&self) -> String {
         format!(
             "Tell the user that they can now use this recipe to generate the items for this recipe {}: {}",
@@ -97,17 +112,18 @@ impl Recipe {
         self.content = content;
         self.updated_at = chrono::Utc::now();
     }
+
+    pub fn update_prompt_version(&mut self, prompt_version: Option<String>) {
+        self.prompt_version = prompt_version;
+        self.updated_at = chrono::Utc::now();
+    }
 }
 
 pub async fn get_recipes(client: &DBClient, owner_id: String) -> Result<Vec<Recipe>, String> {
     log::info!("getting recipes for owner: {owner_id}");
 
-    let db = super::unlock_client(client).await;
-    let recipes = Recipe::find_where(
-        FilterOperator::Single(Filter::eq("owner_id".to_string(), owner_id.clone())),
-        &db,
-    )
-    .await;
+    let db = client.get().await.map_err(super::pool_error)?;
+    let recipes = Recipe::find_where(Recipe::COLUMNS.owner_id.eq(owner_id.clone()), &db).await;
     drop(db);
 
     match recipes {
@@ -122,8 +138,71 @@ pub async fn get_recipes(client: &DBClient, owner_id: String) -> Result<Vec<Reci
     }
 }
 
+/// One page of the owner's recipes, newest first. The cursor is the id of
+/// the last recipe of the previous page.
+pub async fn get_recipes_page(
+    client: &DBClient,
+    owner_id: String,
+    mut pagination: CursorPagination,
+) -> Result<CursorPaginatedResult<Recipe>, String> {
+    let mut conditions = vec![Recipe::COLUMNS.owner_id.eq(owner_id)];
+    if let Some(before) = pagination.cursor.as_deref() {
+        let Ok(before) = before.parse::<i64>() else {
+            return Err("Invalid cursor".to_string());
+        };
+        conditions.push(Recipe::COLUMNS.id.lt(before));
+    }
+
+    // One extra row tells whether there is another page
+    let db = client.get().await.map_err(super::pool_error)?;
+    let recipes = QueryBuilder::new(Recipe::table_name())
+        .r#where(FilterOperator::And(conditions))
+        .order_by(Recipe::COLUMNS.id.desc())
+        .limit(pagination.limit() + 1)
+        .execute::<Recipe>(&db)
+        .await;
+    drop(db);
+
+    let mut recipes = recipes.map_err(|err| {
+        log::error!("Error getting recipes: {err}");
+        "Could not get recipes".to_string()
+    })?;
+
+    pagination.has_next = recipes.len() > pagination.limit() as usize;
+    recipes.truncate(pagination.limit() as usize);
+    pagination.next_cursor = pagination
+        .has_next
+        .then(|| recipes.last().map(|recipe| recipe.id().to_string()))
+        .flatten();
+
+    Ok(CursorPaginatedResult::new(recipes, pagination))
+}
+
+/// The owner's recipes matching `query` in their title or content, best
+/// matches first. Title matches weigh more.
+pub async fn search_recipes(
+    client: &DBClient,
+    owner_id: String,
+    query: &str,
+) -> Result<Vec<FullTextMatch<Recipe>>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let matches = Recipe::full_text_search_where(
+        query,
+        Ranking::Weighted(vec![5.0, 1.0]),
+        Recipe::COLUMNS.owner_id.eq(owner_id),
+        &db,
+    )
+    .await;
+    drop(db);
+
+    matches.map_err(|err| {
+        log::error!("Error searching recipes for {query:?}: {err}");
+        "Could not search recipes".to_string()
+    })
+}
+
 pub async fn create_recipe(client: &DBClient, recipe: Recipe) -> Result<Recipe, String> {
-    let db = super::unlock_client(client).await;
+    let db = client.get().await.map_err(super::pool_error)?;
 
     let res = Recipe::create(&recipe, &db).await;
     drop(db);
@@ -140,13 +219,52 @@ pub async fn create_recipe(client: &DBClient, recipe: Recipe) -> Result<Recipe,
     }
 }
 
+/// Saves the recipe a job produced together with its grocery items and
+/// records it on the job, all or nothing.
+pub async fn create_job_recipe(
+    client: &DBClient,
+    job_id: i64,
+    recipe: Recipe,
+    items: Vec<Item>,
+) -> Result<Recipe, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+
+    let res = db
+        .transaction(|tx| async move {
+            let recipe = recipe.create(&tx).await?;
+            Item::bulk_create(&items, &tx).await?;
+            tx.get_connection()
+                .execute(
+                    "UPDATE jobs SET recipe_id = ? WHERE id = ?",
+                    (recipe.id(), job_id),
+                )
+                .await?;
+            Ok::<_, libsql_orm::Error>(recipe)
+        })
+        .await;
+    drop(db);
+
+    match res {
+        Ok(created_recipe) => {
+            log::info!("created recipe {} with its items", created_recipe.id());
+            Ok(created_recipe)
+        }
+        Err(err) => {
+            log::error!("{err:?}");
+            Err("Could not create recipe".to_string())
+        }
+    }
+}
+
 #[allow(unused)]
 pub async fn create_recipes(client: &DBClient, recipes: Vec<Recipe>) {
     if recipes.is_empty() {
         return;
     }
 
-    let client = super::unlock_client(client).await;
+    let Ok(client) = client.get().await.map_err(super::pool_error) else {
+        return;
+    };
     let result = Recipe::bulk_create(recipes.as_slice(), &client).await;
     match result {
         Ok(_) => log::info!("created {} recipes", recipes.len()),
@@ -159,7 +277,7 @@ pub async fn get_recipe(
     recipe_id: i64,
     owner_id: String,
 ) -> Result<Recipe, String> {
-    let db = super::unlock_client(client).await;
+    let db = client.get().await.map_err(super::pool_error)?;
     let recipe_result = Recipe::find_by_id(recipe_id, &db).await;
     drop(db);
 
@@ -188,9 +306,10 @@ pub async fn update_recipe(
     title: Option<String>,
     url: Option<String>,
     content: Option<String>,
+    prompt_version: Option<String>,
     owner_id: String,
 ) -> Result<Recipe, String> {
-    let db = super::unlock_client(client).await;
+    let db = client.get().await.map_err(super::pool_error)?;
     let recipe_result = Recipe::find_by_id(recipe_id, &db).await;
 
     let mut recipe = match recipe_result {
@@ -221,6 +340,9 @@ pub async fn update_recipe(
     if let Some(new_content) = content {
         recipe.update_content(new_content);
     }
+    if let Some(new_prompt_version) = prompt_version {
+        recipe.update_prompt_version(Some(new_prompt_version));
+    }
 
     let update_result = recipe.update(&db).await;
     drop(db);
@@ -242,7 +364,7 @@ pub async fn delete_recipe(
     recipe_id: i64,
     owner_id: String,
 ) -> Result<(), String> {
-    let db = super::unlock_client(client).await;
+    let db = client.get().await.map_err(super::pool_error)?;
     let recipe_result = Recipe::find_by_id(recipe_id, &db).await;
 
     match recipe_result {
@@ -279,3 +401,113 @@ pub async fn delete_recipe(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_create_job_recipe() {
+        let client = crate::database::test_client().await;
+        let job = crate::database::jobs::enqueue_job(
+            &client,
+            crate::database::jobs::Job::new(
+                "alice".to_string(),
+                crate::database::jobs::JobKind::Process,
+                "{}".to_string(),
+                3,
+            ),
+        )
+        .await
+        .unwrap();
+        let recipe = Recipe::new(
+            None,
+            "alice".to_string(),
+            Some("Pancakes".to_string()),
+            None,
+            "Flour, milk, eggs".to_string(),
+        );
+        let now = chrono::Utc::now();
+        let items = ["Flour", "Milk"]
+            .iter()
+            .map(|task| Item {
+                id: None,
+                owner_id: "alice".to_string(),
+                task: task.to_string(),
+                completed: 0,
+                list: None,
+                created_at: now,
+                updated_at: now,
+            })
+            .collect();
+
+        let recipe = create_job_recipe(&client, job.id(), recipe, items)
+            .await
+            .unwrap();
+        assert!(recipe.id.is_some());
+        let items = crate::database::items::get_items(&client, "alice".to_string())
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 2);
+        let job = crate::database::jobs::get_job(&client, job.id(), "alice".to_string())
+            .await
+            .unwrap();
+        assert_eq!(job.recipe_id, recipe.id);
+    }
+
+    #[actix_web::test]
+    async fn test_search_recipes() {
+        let client = crate::database::test_client().await;
+        let recipe = |owner: &str, title: &str, content: &str| {
+            Recipe::new(
+                None,
+                owner.to_string(),
+                Some(title.to_string()),
+                None,
+                content.to_string(),
+            )
+        };
+        let soup = create_recipe(
+            &client,
+            recipe("alice", "Tomato soup", "Simmer tomatoes with basil"),
+        )
+        .await
+        .unwrap();
+        create_recipe(
+            &client,
+            recipe("alice", "Bruschetta", "Bread topped with tomato"),
+        )
+        .await
+        .unwrap();
+        create_recipe(
+            &client,
+            recipe("bob", "Tomato salad", "Tomatoes and onions"),
+        )
+        .await
+        .unwrap();
+
+        let matches = search_recipes(&client, "alice".to_string(), "tomato")
+            .await
+            .unwrap();
+        let titles: Vec<_> = matches.iter().map(|hit| hit.model.title()).collect();
+        assert_eq!(titles, [Some("Tomato soup"), Some("Bruschetta")]);
+        assert_eq!(matches[0].highlight("title"), Some("\u{2}Tomato\u{3} soup"));
+
+        // Edits are picked up by the sync triggers
+        update_recipe(
+            &client,
+            soup.id(),
+            None,
+            None,
+            Some("Simmer tomatoes with coriander".to_string()),
+            None,
+            "alice".to_string(),
+        )
+        .await
+        .unwrap();
+        let matches = search_recipes(&client, "alice".to_string(), "basil")
+            .await
+            .unwrap();
+        assert!(matches.is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use libsql_orm::Model;
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+
+/// Session state kept on the server, the cookie only carries `session_key`.
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("sessions")]
+pub struct StoredSession {
+    pub id: std::option::Option<i64>,
+    pub session_key: String,
+    /// The session entries as a JSON object.
+    pub state: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl StoredSession {
+    pub fn new(
+        session_key: String,
+        state: String,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        StoredSession {
+            id: None,
+            session_key,
+            state,
+            expires_at,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now()
+    }
+}
+
+/// Looks up a session, expired sessions are treated as missing.
+pub async fn get_session(
+    client: &DBClient,
+    session_key: &str,
+) -> Result<Option<StoredSession>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let sessions =
+        StoredSession::find_where(StoredSession::COLUMNS.session_key.eq(session_key), &db).await;
+    drop(db);
+
+    match sessions {
+        Ok(sessions) => Ok(sessions.into_iter().next().filter(|s| !s.is_expired())),
+        Err(err) => {
+            log::error!("Error getting session: {err}");
+            Err("Could not get session".to_string())
+        }
+    }
+}
+
+pub async fn create_session(
+    client: &DBClient,
+    session: StoredSession,
+) -> Result<StoredSession, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let res = StoredSession::create(&session, &db).await;
+    drop(db);
+
+    res.map_err(|err| {
+        log::error!("could not create session: {err}");
+        "Could not create session".to_string()
+    })
+}
+
+pub async fn update_session(
+    client: &DBClient,
+    mut session: StoredSession,
+) -> Result<StoredSession, String> {
+    session.updated_at = chrono::Utc::now();
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let update_result = session.update(&db).await;
+    drop(db);
+
+    update_result.map_err(|err| {
+        log::error!("could not update session: {err}");
+        "Failed to update session".to_string()
+    })
+}
+
+pub async fn delete_session(client: &DBClient, session_key: &str) -> Result<(), String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let result = db
+        .get_connection()
+        .execute(
+            "DELETE FROM sessions WHERE session_key = ?",
+            [session_key.to_string()],
+        )
+        .await;
+    drop(db);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            log::error!("could not delete session: {err}");
+            Err("Failed to delete session".to_string())
+        }
+    }
+}
+
+/// Removes sessions that ran out, they are never loaded again anyway.
+pub async fn delete_expired_sessions(client: &DBClient) {
+    let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true);
+    let Ok(db) = client.get().await.map_err(super::pool_error) else {
+        return;
+    };
+    let result = db
+        .get_connection()
+        .execute("DELETE FROM sessions WHERE expires_at < ?", [now])
+        .await;
+
+    match result {
+        Ok(0) => {}
+        Ok(count) => log::info!("deleted {count} expired sessions"),
+        Err(err) => log::error!("could not delete expired sessions: {err}"),
+    }
+}
@@ -0,0 +1,193 @@
+use libsql_orm::{Model, QueryBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+use crate::oidc::UserInfo;
+use crate::user::Role;
+
+/// Profile of a user as last reported by the identity provider.
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("users")]
+pub struct UserRecord {
+    pub id: std::option::Option<i64>,
+    /// The id stored as `owner_id` on everything the user owns.
+    pub user_id: String,
+    pub email: String,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub locale: Option<String>,
+    /// Decided at every login from the groups and the admin allow-list.
+    pub role: Role,
+    /// Set when an admin disabled the account.
+    pub disabled_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub first_seen_at: chrono::DateTime<chrono::Utc>,
+    pub last_login_at: chrono::DateTime<chrono::Utc>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl UserRecord {
+    pub fn new(user_id: String, info: &UserInfo, role: Role) -> Self {
+        let now = chrono::Utc::now();
+        let mut record = UserRecord {
+            id: None,
+            user_id,
+            email: String::new(),
+            name: None,
+            given_name: None,
+            family_name: None,
+            avatar_url: None,
+            locale: None,
+            role,
+            disabled_at: None,
+            first_seen_at: now,
+            last_login_at: now,
+            created_at: now,
+            updated_at: now,
+        };
+        record.apply(info);
+        record
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id.unwrap_or(0)
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled_at.is_some()
+    }
+
+    /// Takes over the claims of the latest login.
+    fn apply(&mut self, info: &UserInfo) {
+        let non_empty = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        self.email = info.email.clone();
+        self.name = non_empty(&info.name);
+        self.given_name = non_empty(&info.given_name);
+        self.family_name = non_empty(&info.family_name);
+        self.avatar_url = non_empty(&info.picture);
+        self.locale = non_empty(&info.locale);
+    }
+}
+
+impl From<UserRecord> for crate::user::User {
+    fn from(record: UserRecord) -> Self {
+        crate::user::User {
+            id: record.user_id,
+            email: record.email,
+            name: record.name,
+            given_name: record.given_name,
+            family_name: record.family_name,
+            avatar_url: record.avatar_url,
+            role: record.role,
+        }
+    }
+}
+
+pub async fn get_user(client: &DBClient, user_id: &str) -> Result<Option<UserRecord>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let users = UserRecord::find_where(UserRecord::COLUMNS.user_id.eq(user_id), &db).await;
+    drop(db);
+
+    match users {
+        Ok(users) => Ok(users.into_iter().next()),
+        Err(err) => {
+            log::error!("Error getting user: {err}");
+            Err("Could not get user".to_string())
+        }
+    }
+}
+
+/// Every user, most recent login first.
+pub async fn get_users(client: &DBClient) -> Result<Vec<UserRecord>, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let users = QueryBuilder::new(UserRecord::table_name())
+        .order_by(UserRecord::COLUMNS.last_login_at.desc())
+        .execute::<UserRecord>(&db)
+        .await;
+    drop(db);
+
+    users.map_err(|err| {
+        log::error!("Error getting users: {err}");
+        "Could not get users".to_string()
+    })
+}
+
+/// Disables or re-enables an account. Disabled users cannot sign in and
+/// their sessions and API tokens stop working.
+pub async fn set_user_disabled(
+    client: &DBClient,
+    id: i64,
+    disabled: bool,
+) -> Result<UserRecord, String> {
+    let db = client.get().await.map_err(super::pool_error)?;
+    let record = UserRecord::find_by_id(id, &db).await;
+    drop(db);
+
+    let mut record = match record {
+        Ok(Some(record)) => record,
+        Ok(None) => return Err("User not found".to_string()),
+        Err(err) => {
+            log::error!("Error getting user: {err}");
+            return Err("Could not get user".to_string());
+        }
+    };
+    record.disabled_at = disabled.then(chrono::Utc::now);
+    record.updated_at = chrono::Utc::now();
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let update_result = record.update(&db).await;
+    drop(db);
+
+    update_result.map_err(|err| {
+        log::error!("could not update user: {err}");
+        "Failed to update user".to_string()
+    })
+}
+
+/// Creates the user on first login, afterwards refreshes the profile and
+/// records the login.
+pub async fn upsert_user(
+    client: &DBClient,
+    user_id: String,
+    info: &UserInfo,
+    role: Role,
+) -> Result<UserRecord, String> {
+    let Some(mut record) = get_user(client, &user_id).await? else {
+        let db = client.get().await.map_err(super::pool_error)?;
+        let res = UserRecord::create(&UserRecord::new(user_id, info, role), &db).await;
+        drop(db);
+
+        return match res {
+            Ok(created) => {
+                log::info!("created user {}", created.user_id);
+                Ok(created)
+            }
+            Err(err) => {
+                log::error!("could not create user: {err}");
+                Err("Could not create user".to_string())
+            }
+        };
+    };
+
+    record.apply(info);
+    record.role = role;
+    record.last_login_at = chrono::Utc::now();
+    record.updated_at = chrono::Utc::now();
+
+    let db = client.get().await.map_err(super::pool_error)?;
+    let update_result = record.update(&db).await;
+    drop(db);
+
+    update_result.map_err(|err| {
+        log::error!("could not update user: {err}");
+        "Failed to update user".to_string()
+    })
+}
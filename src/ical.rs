@@ -0,0 +1,235 @@
+//! iCalendar (RFC 5545) export of the meal plan.
+
+use crate::database::meal_plan::{MealPlanEntry, MealSlot};
+use crate::database::recipes::Recipe;
+use crate::ingredients;
+
+/// Content lines may not be longer than 75 octets, excluding the line break.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Meals have no time of their own, so every slot gets a fixed hour.
+fn slot_start(slot: MealSlot) -> &'static str {
+    match slot {
+        MealSlot::Breakfast => "080000",
+        MealSlot::Lunch => "123000",
+        MealSlot::Snack => "160000",
+        MealSlot::Dinner => "183000",
+    }
+}
+
+/// Escapes a TEXT value: backslash, semicolon, comma and line breaks.
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Folds a content line into chunks of at most 75 octets, continuation lines
+/// start with a single space. Multi-byte characters are never split.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / MAX_LINE_OCTETS * 3 + 2);
+    let mut line_octets = 0;
+
+    for c in line.chars() {
+        if line_octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            line_octets = 1;
+        }
+        folded.push(c);
+        line_octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn push_line(calendar: &mut String, name: &str, value: &str) {
+    calendar.push_str(&fold_line(&format!("{name}:{value}")));
+}
+
+fn description(entry: &MealPlanEntry, recipe: &Recipe, recipe_url: &str) -> String {
+    let mut description = match entry.servings {
+        1 => "1 serving\n".to_string(),
+        servings => format!("{servings} servings\n"),
+    };
+
+    let scaled = ingredients::scaled_recipe_ingredients(recipe.content(), entry.servings as f64);
+    if !scaled.is_empty() {
+        description.push_str("\nIngredients:\n");
+        for ingredient in scaled {
+            description.push_str(&format!("- {}\n", ingredient.render()));
+        }
+    }
+
+    description.push('\n');
+    description.push_str(recipe_url);
+    description
+}
+
+/// Renders one VEVENT per planned meal. Meals whose recipe was deleted are left out.
+pub fn meal_plan_to_ics(entries: &[MealPlanEntry], recipes: &[Recipe], base_url: &str) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let mut calendar = String::new();
+
+    push_line(&mut calendar, "BEGIN", "VCALENDAR");
+    push_line(&mut calendar, "VERSION", "2.0");
+    push_line(&mut calendar, "PRODID", "-//Rezi//Meal Plan//EN");
+    push_line(&mut calendar, "CALSCALE", "GREGORIAN");
+    push_line(&mut calendar, "METHOD", "PUBLISH");
+    push_line(&mut calendar, "X-WR-CALNAME", "Rezi Meal Plan");
+
+    for entry in entries {
+        let Some(recipe) = recipes.iter().find(|recipe| recipe.id() == entry.recipe_id) else {
+            continue;
+        };
+        let title = recipe.title().unwrap_or("Untitled recipe");
+        let recipe_url = format!("{base_url}/recipes#recipe-{}", recipe.id());
+
+        push_line(&mut calendar, "BEGIN", "VEVENT");
+        push_line(&mut calendar, "UID", &format!("meal-{}@rezi", entry.id()));
+        push_line(
+            &mut calendar,
+            "DTSTAMP",
+            &entry.updated_at.format("%Y%m%dT%H%M%SZ").to_string(),
+        );
+        // Floating time, shown at the same hour in every time zone
+        push_line(
+            &mut calendar,
+            "DTSTART",
+            &format!(
+                "{}T{}",
+                entry.planned_on.format("%Y%m%d"),
+                slot_start(entry.slot)
+            ),
+        );
+        push_line(&mut calendar, "DURATION", "PT1H");
+        push_line(
+            &mut calendar,
+            "SUMMARY",
+            &escape_text(&format!("{}: {title}", entry.slot.label())),
+        );
+        push_line(&mut calendar, "URL", &recipe_url);
+        push_line(
+            &mut calendar,
+            "DESCRIPTION",
+            &escape_text(&description(entry, recipe, &recipe_url)),
+        );
+        push_line(&mut calendar, "END", "VEVENT");
+    }
+
+    push_line(&mut calendar, "END", "VCALENDAR");
+    calendar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    fn recipe(id: i64, title: &str, content: &str) -> Recipe {
+        Recipe::new(
+            Some(id),
+            "1".to_string(),
+            Some(title.to_string()),
+            None,
+            content.to_string(),
+        )
+    }
+
+    fn entry(id: i64, recipe_id: i64, day: u32, slot: MealSlot, servings: i64) -> MealPlanEntry {
+        let mut entry = MealPlanEntry::new(
+            "1".to_string(),
+            recipe_id,
+            NaiveDate::from_ymd_opt(2025, 3, day).unwrap(),
+            slot,
+            servings,
+        );
+        entry.id = Some(id);
+        entry.updated_at = Utc.with_ymd_and_hms(2025, 3, 1, 9, 30, 0).unwrap();
+        entry
+    }
+
+    /// Compares against a file in `testdata/`. Run with `UPDATE_GOLDEN=1` to
+    /// rewrite the file after an intended change.
+    fn assert_golden(name: &str, actual: &str) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name);
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            std::fs::write(&path, actual).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(actual, expected, "output differs from {}", path.display());
+    }
+
+    #[test]
+    fn test_escape_text() {
+        assert_eq!(
+            escape_text("Salt, pepper; oil\\vinegar\r\nnext"),
+            "Salt\\, pepper\\; oil\\\\vinegar\\nnext"
+        );
+    }
+
+    #[test]
+    fn test_fold_line_counts_octets() {
+        let line = "a".repeat(160);
+        let folded = fold_line(&line);
+        let lines: Vec<&str> = folded.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 75);
+        assert_eq!(lines[1].len(), 75);
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(lines[2].len(), 12);
+
+        // Two-byte characters must not be split across lines
+        let folded = fold_line(&"é".repeat(50));
+        for line in folded.trim_end_matches("\r\n").split("\r\n") {
+            assert!(line.len() <= 75);
+        }
+        assert_eq!(folded.replace("\r\n ", "").trim_end(), "é".repeat(50));
+    }
+
+    #[test]
+    fn test_meal_plan_golden() {
+        let recipes = vec![
+            recipe(
+                7,
+                "Pasta, tomato; basil",
+                "# Pasta\n\n## Ingredients\n- 200 g spaghetti\n- 2 tomatoes, diced\n- 1 tbsp olive oil\n- Salt & pepper\n\n## Instructions\n1. Cook\n\n**Servings:** 2\n",
+            ),
+            recipe(
+                8,
+                "Crème brûlée with a very long title that needs folding in calendars",
+                "# Crème brûlée\n\n## Ingredients\n- 500 ml crème fraîche\n- 4 egg yolks\n- 100 g sugar\n\n**Servings:** 4\n",
+            ),
+            recipe(9, "Leftovers", "Whatever is in the fridge"),
+        ];
+        let entries = vec![
+            entry(1, 7, 3, MealSlot::Dinner, 4),
+            entry(2, 8, 4, MealSlot::Snack, 2),
+            entry(3, 9, 5, MealSlot::Lunch, 1),
+            // The recipe was deleted, no event
+            entry(4, 99, 6, MealSlot::Dinner, 2),
+        ];
+
+        let calendar = meal_plan_to_ics(&entries, &recipes, "https://rezi.example/");
+        assert!(calendar.split("\r\n").all(|line| line.len() <= 75));
+        assert_golden("meal_plan.ics", &calendar);
+    }
+
+    #[test]
+    fn test_empty_meal_plan_golden() {
+        assert_golden(
+            "meal_plan_empty.ics",
+            &meal_plan_to_ics(&[], &[], "https://rezi.example"),
+        );
+    }
+}
@@ -0,0 +1,591 @@
+use crate::database::pantry::PantryItem;
+
+/// Units we know how to compare. Mass and volume convert to grams and
+/// millilitres, everything else only matches the same unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dimension {
+    Mass,
+    Volume,
+    Other,
+}
+
+const UNITS: &[(&[&str], &str, Dimension, f64)] = &[
+    (
+        &["g", "gr", "gram", "grams", "gramm"],
+        "g",
+        Dimension::Mass,
+        1.0,
+    ),
+    (
+        &["kg", "kilo", "kilos", "kilogram", "kilograms"],
+        "kg",
+        Dimension::Mass,
+        1000.0,
+    ),
+    (&["oz", "ounce", "ounces"], "oz", Dimension::Mass, 28.3495),
+    (
+        &["lb", "lbs", "pound", "pounds"],
+        "lb",
+        Dimension::Mass,
+        453.592,
+    ),
+    (
+        &[
+            "ml",
+            "milliliter",
+            "milliliters",
+            "millilitre",
+            "millilitres",
+        ],
+        "ml",
+        Dimension::Volume,
+        1.0,
+    ),
+    (&["cl"], "cl", Dimension::Volume, 10.0),
+    (&["dl"], "dl", Dimension::Volume, 100.0),
+    (
+        &["l", "liter", "liters", "litre", "litres"],
+        "l",
+        Dimension::Volume,
+        1000.0,
+    ),
+    (
+        &["tsp", "teaspoon", "teaspoons", "tl"],
+        "tsp",
+        Dimension::Volume,
+        4.92892,
+    ),
+    (
+        &["tbsp", "tablespoon", "tablespoons", "el"],
+        "tbsp",
+        Dimension::Volume,
+        14.7868,
+    ),
+    (&["cup", "cups"], "cup", Dimension::Volume, 236.588),
+    (&["clove", "cloves"], "clove", Dimension::Other, 1.0),
+    (
+        &["can", "cans", "tin", "tins"],
+        "can",
+        Dimension::Other,
+        1.0,
+    ),
+    (&["pinch", "pinches"], "pinch", Dimension::Other, 1.0),
+    (&["bunch", "bunches"], "bunch", Dimension::Other, 1.0),
+    (
+        &["piece", "pieces", "pc", "pcs"],
+        "piece",
+        Dimension::Other,
+        1.0,
+    ),
+    (&["slice", "slices"], "slice", Dimension::Other, 1.0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unit {
+    pub name: &'static str,
+    pub dimension: Dimension,
+    factor: f64,
+}
+
+impl Unit {
+    pub fn parse(text: &str) -> Option<Unit> {
+        let text = text.trim().trim_end_matches('.').to_lowercase();
+        UNITS
+            .iter()
+            .find(|(aliases, ..)| aliases.contains(&text.as_str()))
+            .map(|(_, name, dimension, factor)| Unit {
+                name,
+                dimension: *dimension,
+                factor: *factor,
+            })
+    }
+
+    fn converts_to(&self, other: &Unit) -> bool {
+        match self.dimension {
+            Dimension::Other => self.name == other.name,
+            dimension => dimension == other.dimension,
+        }
+    }
+
+    fn to_base(self, quantity: f64) -> f64 {
+        quantity * self.factor
+    }
+
+    fn in_unit(self, quantity: f64) -> f64 {
+        quantity / self.factor
+    }
+}
+
+/// A grocery or recipe line split into amount, unit and name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ingredient {
+    pub quantity: Option<f64>,
+    pub unit: Option<Unit>,
+    /// The name as written, without amount and unit.
+    pub name: String,
+}
+
+impl Ingredient {
+    pub fn parse(line: &str) -> Ingredient {
+        let line = line.trim().trim_start_matches(['-', '*', '•']).trim_start();
+
+        let (quantity, rest) = parse_quantity(line);
+        let rest = rest.trim_start();
+
+        // The unit is either glued to the number ("200g") or the next word
+        let (unit, rest) = match rest.split_once(char::is_whitespace) {
+            Some((word, after)) if quantity.is_some() => match Unit::parse(word) {
+                Some(unit) => (Some(unit), after.trim_start()),
+                None => (None, rest),
+            },
+            _ => (None, rest),
+        };
+        let rest = rest.strip_prefix("of ").unwrap_or(rest);
+
+        Ingredient {
+            quantity,
+            unit,
+            name: rest.trim().to_string(),
+        }
+    }
+
+    /// Normalised name used to match pantry stock, e.g. "Red Onions, diced" -> "red onion".
+    pub fn key(&self) -> String {
+        normalize_name(&self.name)
+    }
+
+    pub fn scale(self, factor: f64) -> Ingredient {
+        Ingredient {
+            quantity: self.quantity.map(|quantity| quantity * factor),
+            ..self
+        }
+    }
+
+    /// Adds `other` to this ingredient when both describe the same thing in
+    /// comparable amounts. Returns `other` back when they cannot be combined.
+    fn combine(&mut self, other: Ingredient) -> Option<Ingredient> {
+        if self.key() != other.key() {
+            return Some(other);
+        }
+
+        match (self.quantity, self.unit, other.quantity, other.unit) {
+            (Some(total), Some(unit), Some(added), Some(added_unit))
+                if unit.converts_to(&added_unit) =>
+            {
+                self.quantity = Some(total + unit.in_unit(added_unit.to_base(added)));
+                None
+            }
+            (Some(total), None, Some(added), None) => {
+                self.quantity = Some(total + added);
+                None
+            }
+            (None, _, None, _) => None,
+            _ => Some(other),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        match (self.quantity, self.unit) {
+            (Some(quantity), Some(unit)) => {
+                format!("{} {} {}", format_quantity(quantity), unit.name, self.name)
+            }
+            (Some(quantity), None) => format!("{} {}", format_quantity(quantity), self.name),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+fn unicode_fraction(c: char) -> Option<f64> {
+    match c {
+        '½' => Some(0.5),
+        '⅓' => Some(1.0 / 3.0),
+        '⅔' => Some(2.0 / 3.0),
+        '¼' => Some(0.25),
+        '¾' => Some(0.75),
+        '⅛' => Some(0.125),
+        _ => None,
+    }
+}
+
+fn parse_number(token: &str) -> Option<f64> {
+    if let Some((numerator, denominator)) = token.split_once('/') {
+        let numerator: f64 = numerator.parse().ok()?;
+        let denominator: f64 = denominator.parse().ok()?;
+        return (denominator != 0.0).then(|| numerator / denominator);
+    }
+
+    let mut chars = token.chars();
+    let last = chars.next_back()?;
+    if let Some(fraction) = unicode_fraction(last) {
+        let whole = chars.as_str();
+        if whole.is_empty() {
+            return Some(fraction);
+        }
+        return whole.parse::<f64>().ok().map(|w| w + fraction);
+    }
+
+    // Ranges like "2-3" need the upper bound so there is enough
+    if let Some((_, upper)) = token.split_once('-') {
+        return parse_number(upper);
+    }
+
+    token.replace(',', ".").parse().ok()
+}
+
+/// Reads a leading amount ("2", "1.5", "1 1/2", "½", "200g") and returns the rest.
+fn parse_quantity(line: &str) -> (Option<f64>, &str) {
+    let number_end = line
+        .find(|c: char| {
+            !(c.is_ascii_digit() || "./,-".contains(c) || unicode_fraction(c).is_some())
+        })
+        .unwrap_or(line.len());
+    let Some(mut quantity) = parse_number(&line[..number_end]) else {
+        return (None, line);
+    };
+    let mut rest = &line[number_end..];
+
+    // "200g" - the unit directly follows the number
+    let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    if word_end > 0 && Unit::parse(&rest[..word_end]).is_some() {
+        return (Some(quantity), rest);
+    }
+
+    // "1 1/2 cups" - a fraction following the whole number
+    let trimmed = rest.trim_start();
+    if let Some(token) = trimmed.split_whitespace().next()
+        && (token.contains('/') || token.chars().all(|c| unicode_fraction(c).is_some()))
+        && let Some(fraction) = parse_number(token)
+    {
+        quantity += fraction;
+        rest = &trimmed[token.len()..];
+    }
+
+    (Some(quantity), rest)
+}
+
+fn normalize_name(name: &str) -> String {
+    let name = name.to_lowercase();
+    // Preparation notes after a comma or in brackets do not change what to buy
+    let name = name.split(',').next().unwrap_or_default();
+    let name = match name.find('(') {
+        Some(start) => &name[..start],
+        None => name,
+    };
+
+    name.split_whitespace()
+        .map(singularize)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn singularize(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ies") {
+        format!("{stem}y")
+    } else if let Some(stem) = word.strip_suffix("oes") {
+        format!("{stem}o")
+    } else if word.len() > 3 && word.ends_with('s') && !word.ends_with("ss") {
+        word[..word.len() - 1].to_string()
+    } else {
+        word.to_string()
+    }
+}
+
+pub fn format_quantity(quantity: f64) -> String {
+    let rounded = (quantity * 100.0).round() / 100.0;
+    if rounded.fract() == 0.0 {
+        format!("{rounded:.0}")
+    } else {
+        format!("{rounded}")
+    }
+}
+
+/// Lines of the "Ingredients" section of a recipe stored as markdown.
+pub fn recipe_ingredients(content: &str) -> Vec<String> {
+    let mut lines = content.lines().map(str::trim);
+    let in_section = lines
+        .by_ref()
+        .any(|line| line.starts_with('#') && line.to_lowercase().contains("ingredients"));
+    if !in_section {
+        return Vec::new();
+    }
+
+    lines
+        .take_while(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            line.strip_prefix("- ")
+                .or_else(|| line.strip_prefix("* "))
+                .map(|line| line.trim().to_string())
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Servings written in a recipe, e.g. "**Servings:** 4 people" -> 4.
+pub fn recipe_servings(content: &str) -> Option<f64> {
+    content.lines().find_map(|line| {
+        let line = line.replace('*', "").to_lowercase();
+        let (_, rest) = line.split_once("servings:")?;
+        parse_quantity(rest.trim())
+            .0
+            .filter(|servings| *servings > 0.0)
+    })
+}
+
+/// Ingredients of a recipe scaled from the servings it is written for to
+/// `servings`. Without servings in the recipe the amounts are used as written.
+pub fn scaled_recipe_ingredients(content: &str, servings: f64) -> Vec<Ingredient> {
+    let factor = recipe_servings(content)
+        .map(|written| servings / written)
+        .unwrap_or(1.0);
+    recipe_ingredients(content)
+        .iter()
+        .map(|line| Ingredient::parse(line).scale(factor))
+        .collect()
+}
+
+/// Merges lines naming the same ingredient into one line with the summed
+/// amount, keeping the order in which ingredients first appear.
+pub fn aggregate(ingredients: Vec<Ingredient>) -> Vec<String> {
+    let mut totals: Vec<Ingredient> = Vec::new();
+    for ingredient in ingredients {
+        let leftover = totals
+            .iter_mut()
+            .try_fold(ingredient, |ingredient, total| total.combine(ingredient));
+        if let Some(ingredient) = leftover {
+            totals.push(ingredient);
+        }
+    }
+
+    totals.iter().map(Ingredient::render).collect()
+}
+
+/// Stock left in the pantry while a grocery list is worked through.
+struct Stock {
+    key: String,
+    quantity: Option<f64>,
+    unit: Option<Unit>,
+}
+
+impl Stock {
+    fn matches(&self, key: &str) -> bool {
+        key == self.key || key.ends_with(&format!(" {}", self.key))
+    }
+
+    /// Takes what it can from the stock and returns the amount still needed,
+    /// or `None` when the stock cannot be compared to the ingredient.
+    fn take(&mut self, ingredient: &Ingredient) -> Option<Option<f64>> {
+        let Some(available) = self.quantity else {
+            // An item without a quantity means "we have some"
+            return Some(None);
+        };
+        if available <= 0.0 {
+            return None;
+        }
+        let Some(needed) = ingredient.quantity else {
+            return Some(None);
+        };
+
+        match (ingredient.unit, self.unit) {
+            (Some(wanted), Some(stocked)) if wanted.converts_to(&stocked) => {
+                let needed_base = wanted.to_base(needed);
+                let available_base = stocked.to_base(available);
+                let used = needed_base.min(available_base);
+                self.quantity = Some(stocked.in_unit(available_base - used));
+                let missing = wanted.in_unit(needed_base - used);
+                Some((missing > 0.001).then_some(missing))
+            }
+            (None, None) => {
+                let used = needed.min(available);
+                self.quantity = Some(available - used);
+                let missing = needed - used;
+                Some((missing > 0.001).then_some(missing))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Result of checking a grocery list against the pantry.
+#[derive(Debug, Default, PartialEq)]
+pub struct PantryCheck {
+    /// Lines that still need to be bought, reduced where the pantry covers part.
+    pub to_buy: Vec<String>,
+    /// Lines the pantry covers completely.
+    pub covered: Vec<String>,
+}
+
+/// Expired pantry items are not counted as stock.
+pub fn subtract_pantry(lines: &[String], pantry: &[PantryItem]) -> PantryCheck {
+    let mut stock: Vec<Stock> = pantry
+        .iter()
+        .filter(|item| !item.is_expired())
+        .map(|item| Stock {
+            key: normalize_name(&item.name),
+            quantity: item.quantity,
+            unit: item.unit.as_deref().and_then(Unit::parse),
+        })
+        .collect();
+
+    let mut check = PantryCheck::default();
+    for line in lines {
+        let ingredient = Ingredient::parse(line);
+        let key = ingredient.key();
+
+        let outcome = stock
+            .iter_mut()
+            .filter(|stock| stock.matches(&key))
+            .find_map(|stock| stock.take(&ingredient));
+
+        match outcome {
+            Some(None) => check.covered.push(line.clone()),
+            Some(Some(missing)) => check.to_buy.push(
+                Ingredient {
+                    quantity: Some(missing),
+                    ..ingredient
+                }
+                .render(),
+            ),
+            None => check.to_buy.push(line.clone()),
+        }
+    }
+
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pantry_item(name: &str, quantity: Option<f64>, unit: Option<&str>) -> PantryItem {
+        PantryItem::new(
+            "1".to_string(),
+            name.to_string(),
+            quantity,
+            unit.map(str::to_string),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_parse_ingredient_with_unit() {
+        let ingredient = Ingredient::parse("- 200g flour");
+        assert_eq!(ingredient.quantity, Some(200.0));
+        assert_eq!(ingredient.unit.map(|u| u.name), Some("g"));
+        assert_eq!(ingredient.name, "flour");
+
+        let ingredient = Ingredient::parse("1 1/2 cups of milk");
+        assert_eq!(ingredient.quantity, Some(1.5));
+        assert_eq!(ingredient.unit.map(|u| u.name), Some("cup"));
+        assert_eq!(ingredient.name, "milk");
+    }
+
+    #[test]
+    fn test_parse_ingredient_without_amount() {
+        let ingredient = Ingredient::parse("Salt and pepper");
+        assert_eq!(ingredient.quantity, None);
+        assert_eq!(ingredient.unit, None);
+        assert_eq!(ingredient.name, "Salt and pepper");
+
+        let ingredient = Ingredient::parse("½ lemon");
+        assert_eq!(ingredient.quantity, Some(0.5));
+        assert_eq!(ingredient.name, "lemon");
+    }
+
+    #[test]
+    fn test_key_normalizes_name() {
+        assert_eq!(Ingredient::parse("3 Red Onions, diced").key(), "red onion");
+        assert_eq!(Ingredient::parse("2 tomatoes").key(), "tomato");
+        assert_eq!(
+            Ingredient::parse("1 can chickpeas (drained)").key(),
+            "chickpea"
+        );
+    }
+
+    #[test]
+    fn test_recipe_ingredients_and_servings() {
+        let content = "# Pancakes\n\n## Ingredients\n- 200 g flour\n- 2 eggs\n\n## Instructions\n1. Mix\n- not an ingredient\n\n**Servings:** 4 people\n";
+
+        assert_eq!(recipe_ingredients(content), vec!["200 g flour", "2 eggs"]);
+        assert_eq!(recipe_servings(content), Some(4.0));
+        assert!(recipe_ingredients("just some text").is_empty());
+        assert_eq!(recipe_servings("just some text"), None);
+    }
+
+    #[test]
+    fn test_aggregate_scaled_ingredients() {
+        let ingredients = vec![
+            Ingredient::parse("200 g flour").scale(2.0),
+            Ingredient::parse("2 onions"),
+            Ingredient::parse("1 kg flour"),
+            Ingredient::parse("1 onion, diced").scale(0.5),
+            Ingredient::parse("salt"),
+            Ingredient::parse("Salt"),
+            Ingredient::parse("1 cup rice"),
+            Ingredient::parse("100 g rice"),
+        ];
+
+        assert_eq!(
+            aggregate(ingredients),
+            vec![
+                "1400 g flour",
+                "2.5 onions",
+                "salt",
+                "1 cup rice",
+                "100 g rice"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subtract_pantry_covers_and_reduces() {
+        let lines = vec![
+            "500 g flour".to_string(),
+            "2 onions".to_string(),
+            "1 l milk".to_string(),
+            "salt".to_string(),
+        ];
+        let pantry = vec![
+            pantry_item("Flour", Some(1.0), Some("kg")),
+            pantry_item("onion", Some(1.0), None),
+            pantry_item("salt", None, None),
+        ];
+
+        let check = subtract_pantry(&lines, &pantry);
+        assert_eq!(check.covered, vec!["500 g flour", "salt"]);
+        assert_eq!(check.to_buy, vec!["1 onions", "1 l milk"]);
+    }
+
+    #[test]
+    fn test_subtract_pantry_uses_stock_once() {
+        let lines = vec!["300 g sugar".to_string(), "300 g sugar".to_string()];
+        let pantry = vec![pantry_item("sugar", Some(400.0), Some("g"))];
+
+        let check = subtract_pantry(&lines, &pantry);
+        assert_eq!(check.covered, vec!["300 g sugar"]);
+        assert_eq!(check.to_buy, vec!["200 g sugar"]);
+    }
+
+    #[test]
+    fn test_subtract_pantry_keeps_incompatible_units() {
+        let lines = vec!["2 cups rice".to_string()];
+        let pantry = vec![pantry_item("rice", Some(1.0), Some("piece"))];
+
+        let check = subtract_pantry(&lines, &pantry);
+        assert!(check.covered.is_empty());
+        assert_eq!(check.to_buy, vec!["2 cups rice"]);
+    }
+
+    #[test]
+    fn test_subtract_pantry_skips_expired_items() {
+        let lines = vec!["1 l milk".to_string(), "2 eggs".to_string()];
+        let today = chrono::Utc::now().date_naive();
+        let mut expired = pantry_item("milk", Some(1.0), Some("l"));
+        expired.expires_on = Some(today - chrono::Days::new(1));
+        let mut fresh = pantry_item("egg", Some(6.0), None);
+        fresh.expires_on = Some(today);
+
+        let check = subtract_pantry(&lines, &[expired, fresh]);
+        assert_eq!(check.covered, vec!["2 eggs"]);
+        assert_eq!(check.to_buy, vec!["1 l milk"]);
+    }
+}
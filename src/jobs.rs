@@ -0,0 +1,541 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use url::Url;
+
+use crate::config::Server;
+use crate::database::items::Item;
+use crate::database::jobs::{Job, JobKind};
+use crate::database::recipes::Recipe;
+use crate::database::{self, DBClient};
+use crate::ingredients::{self, PantryCheck};
+use crate::llm::{self, ExtractedRecipe};
+use crate::prompts::{PromptKind, Prompts};
+use crate::witch;
+
+/// How long an idle worker sleeps before looking for due retries.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Upper bound for a single job run, LLM calls included.
+const JOB_TIMEOUT: Duration = Duration::from_secs(300);
+/// Most URLs a single import request may queue.
+pub const MAX_IMPORT_URLS: usize = 20;
+/// How long a claim keeps a running job from being queued again. Longer than
+/// any run, so only jobs of a worker that died are taken over.
+const CLAIM_LEASE: Duration = Duration::from_secs(JOB_TIMEOUT.as_secs() + 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobPayload {
+    Process {
+        url: Option<String>,
+        content: Option<String>,
+    },
+    Extract {
+        url: Option<String>,
+        content: Option<String>,
+    },
+    Reextract {
+        recipe_id: i64,
+    },
+}
+
+impl JobPayload {
+    pub fn kind(&self) -> JobKind {
+        match self {
+            JobPayload::Process { .. } => JobKind::Process,
+            JobPayload::Extract { .. } => JobKind::Extract,
+            JobPayload::Reextract { .. } => JobKind::Reextract,
+        }
+    }
+}
+
+/// What a finished recipe job stores in `jobs.result`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeJobOutcome {
+    pub recipe_id: i64,
+    pub url: Option<String>,
+    pub grocery_list: Option<String>,
+    pub extracted: Option<ExtractedRecipe>,
+    pub extraction_error: Option<String>,
+}
+
+impl RecipeJobOutcome {
+    pub fn from_job(job: &Job) -> Option<Self> {
+        let result = job.result.as_deref()?;
+        match serde_json::from_str(result) {
+            Ok(outcome) => Some(outcome),
+            Err(err) => {
+                log::error!("could not parse result of job {}: {err}", job.id());
+                None
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct JobQueue {
+    client: DBClient,
+    prompts: Arc<Prompts>,
+    notify: Arc<Notify>,
+    max_attempts: i64,
+}
+
+impl JobQueue {
+    pub fn new(client: DBClient, prompts: Arc<Prompts>, max_attempts: i64) -> Self {
+        JobQueue {
+            client,
+            prompts,
+            notify: Arc::new(Notify::new()),
+            max_attempts,
+        }
+    }
+
+    pub async fn enqueue(&self, owner_id: String, payload: JobPayload) -> Result<Job, String> {
+        let kind = payload.kind();
+        let payload = serde_json::to_string(&payload).map_err(|err| {
+            log::error!("could not serialize job payload: {err}");
+            "Could not enqueue job".to_string()
+        })?;
+
+        let job = Job::new(owner_id, kind, payload, self.max_attempts);
+        let job = database::jobs::enqueue_job(&self.client, job).await?;
+        self.notify.notify_one();
+        Ok(job)
+    }
+
+    /// Wakes a worker, e.g. after a job was retried by hand.
+    pub fn wake(&self) {
+        self.notify.notify_one();
+    }
+
+    /// Spawns the worker pool on the current runtime, together with a task
+    /// that queues jobs of dead workers again.
+    pub fn start(&self, workers: usize, config: Server) {
+        let queue = self.clone();
+        actix_web::rt::spawn(async move {
+            let lease = chrono::Duration::seconds(CLAIM_LEASE.as_secs() as i64);
+            let mut interval = actix_web::rt::time::interval(CLAIM_LEASE);
+            loop {
+                interval.tick().await;
+                database::jobs::requeue_interrupted_jobs(&queue.client, lease).await;
+                queue.notify.notify_one();
+            }
+        });
+
+        log::info!("starting {workers} job workers");
+        for worker in 0..workers {
+            let queue = self.clone();
+            let config = config.clone();
+            actix_web::rt::spawn(async move { queue.work(worker, config).await });
+        }
+    }
+
+    async fn work(self, worker: usize, config: Server) {
+        loop {
+            match database::jobs::claim_next_job(&self.client).await {
+                Ok(Some(job)) => {
+                    self.run(worker, job, &config).await;
+                    continue;
+                }
+                Ok(None) => {}
+                Err(err) => log::error!("worker {worker}: {err}"),
+            }
+
+            tokio::select! {
+                _ = self.notify.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    async fn run(&self, worker: usize, job: Job, config: &Server) {
+        log::info!("worker {worker}: running job {} ({:?})", job.id(), job.kind);
+
+        let outcome = match serde_json::from_str::<JobPayload>(&job.payload) {
+            Ok(payload) => {
+                match tokio::time::timeout(
+                    JOB_TIMEOUT,
+                    handle(&self.client, config, &self.prompts, &job, payload),
+                )
+                .await
+                {
+                    Ok(result) => result,
+                    Err(_) => Err(format!("Job timed out after {}s", JOB_TIMEOUT.as_secs())),
+                }
+            }
+            Err(err) => Err(format!("Invalid job payload: {err}")),
+        };
+
+        let saved = match outcome {
+            Ok(result) => database::jobs::complete_job(&self.client, job, result).await,
+            Err(error) => database::jobs::fail_job(&self.client, job, error).await,
+        };
+        if let Err(err) = saved {
+            log::error!("worker {worker}: {err}");
+        }
+    }
+}
+
+async fn handle(
+    client: &DBClient,
+    config: &Server,
+    prompts: &Arc<Prompts>,
+    job: &Job,
+    payload: JobPayload,
+) -> Result<String, String> {
+    if let Some(outcome) = saved_outcome(job, &payload) {
+        log::info!(
+            "job {} already saved recipe {}",
+            job.id(),
+            outcome.recipe_id
+        );
+        return serde_json::to_string(&outcome)
+            .map_err(|err| format!("Could not store job result: {err}"));
+    }
+
+    let owner_id = job.owner_id().to_string();
+    let outcome = match payload {
+        JobPayload::Process { url, content } => {
+            process_recipe(client, config, prompts, job.id(), owner_id, url, content).await?
+        }
+        JobPayload::Extract { url, content } => {
+            extract_recipe(client, config, prompts, job.id(), owner_id, url, content).await?
+        }
+        JobPayload::Reextract { recipe_id } => {
+            reextract_recipe(client, config, prompts, owner_id, recipe_id).await?
+        }
+    };
+
+    serde_json::to_string(&outcome).map_err(|err| format!("Could not store job result: {err}"))
+}
+
+/// Outcome of a job whose earlier attempt saved the recipe and then failed,
+/// so the retry does not save it again. The grocery list and extraction
+/// details of that attempt are not kept.
+fn saved_outcome(job: &Job, payload: &JobPayload) -> Option<RecipeJobOutcome> {
+    let url = match payload {
+        JobPayload::Process { url, .. } | JobPayload::Extract { url, .. } => url,
+        JobPayload::Reextract { .. } => return None,
+    };
+    Some(RecipeJobOutcome {
+        recipe_id: job.recipe_id?,
+        url: url.clone().filter(|url| !url.trim().is_empty()),
+        grocery_list: None,
+        extracted: None,
+        extraction_error: None,
+    })
+}
+
+/// Parses a recipe URL. Only `http` and `https` are fetched, other schemes
+/// such as `file:` are refused.
+pub fn parse_recipe_url(url: &str) -> Option<Url> {
+    Url::parse(url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+async fn recipe_content(
+    url: Option<String>,
+    content: Option<String>,
+) -> Result<(String, Option<String>), String> {
+    if let Some(url) = url.filter(|url| !url.trim().is_empty()) {
+        let parsed_url = parse_recipe_url(&url).ok_or_else(|| "Invalid URL format".to_string())?;
+        let content = witch::hex(parsed_url.to_string()).await.map_err(|err| {
+            log::error!("Failed to fetch URL content: {err}");
+            format!("Failed to fetch content from {url}")
+        })?;
+        return Ok((content, Some(url)));
+    }
+
+    match content {
+        Some(content) if !content.trim().is_empty() => Ok((content, None)),
+        _ => Err("Please provide either a recipe URL or recipe text content.".to_string()),
+    }
+}
+
+/// Asks the LLM for the recipe's grocery items. Failures are logged and do
+/// not fail the job, the recipe is then saved without items.
+async fn grocery_items(
+    client: &DBClient,
+    config: &Server,
+    prompts: &Arc<Prompts>,
+    owner_id: String,
+    content: &str,
+) -> Option<Vec<String>> {
+    let use_gemini = config.llm_provider().to_lowercase() == "gemini";
+    match llm::extract_grocery_list_with_llm(
+        content,
+        &config.llm_api_key(),
+        use_gemini,
+        prompts.clone(),
+        owner_id,
+        client,
+    )
+    .await
+    {
+        Ok(items) => Some(items),
+        Err(err) => {
+            log::error!("{err}");
+            None
+        }
+    }
+}
+
+/// Saves the recipe and the grocery items its owner's pantry does not cover
+/// in one transaction, marking the job as done with it. Returns the recipe
+/// and a summary of the items.
+async fn save_recipe(
+    client: &DBClient,
+    job_id: i64,
+    recipe: Recipe,
+    grocery_items: Option<Vec<String>>,
+) -> Result<(Recipe, Option<String>), String> {
+    let Some(grocery_items) = grocery_items else {
+        return Ok((
+            database::recipes::create_job_recipe(client, job_id, recipe, Vec::new()).await?,
+            None,
+        ));
+    };
+
+    let pantry = database::pantry::get_pantry_items(client, recipe.owner_id.clone())
+        .await
+        .unwrap_or_default();
+    let check = ingredients::subtract_pantry(&grocery_items, &pantry);
+
+    let now = chrono::Utc::now();
+    let items: Vec<Item> = check
+        .to_buy
+        .iter()
+        .map(|task| Item {
+            id: None,
+            owner_id: recipe.owner_id.clone(),
+            task: task.clone(),
+            completed: 0,
+            list: None,
+            created_at: now,
+            updated_at: now,
+        })
+        .collect();
+    let recipe = database::recipes::create_job_recipe(client, job_id, recipe, items).await?;
+
+    Ok((recipe, Some(grocery_summary(&check))))
+}
+
+fn grocery_summary(check: &PantryCheck) -> String {
+    let items_string = check.to_buy.join("\n");
+    if check.covered.is_empty() {
+        return format!("Created grocery items:\n{items_string}");
+    }
+
+    let covered_string = check.covered.join("\n");
+    format!("Created grocery items:\n{items_string}\n\nAlready in your pantry:\n{covered_string}")
+}
+
+async fn process_recipe(
+    client: &DBClient,
+    config: &Server,
+    prompts: &Arc<Prompts>,
+    job_id: i64,
+    owner_id: String,
+    url: Option<String>,
+    content: Option<String>,
+) -> Result<RecipeJobOutcome, String> {
+    let (recipe_content, recipe_url) = recipe_content(url, content).await?;
+    let use_gemini = config.llm_provider().to_lowercase() == "gemini";
+
+    // Try to extract structured recipe data to get the title
+    let (recipe_title, prompt_version) = match llm::extract_recipe_with_llm(
+        &recipe_content,
+        &config.llm_api_key(),
+        use_gemini,
+        prompts.clone(),
+        owner_id.clone(),
+        client,
+    )
+    .await
+    {
+        Ok(extracted_recipe) => (extracted_recipe.title, extracted_recipe.prompt_version),
+        Err(_) => {
+            // For URLs the page title is a cheaper fallback than another LLM call
+            let html_title = recipe_url
+                .as_ref()
+                .and_then(|_| crate::scrapy::extract_title(&recipe_content));
+            match html_title {
+                Some(html_title) => (html_title, None),
+                None => match llm::generate_title_with_llm(
+                    &recipe_content,
+                    &config.llm_api_key(),
+                    use_gemini,
+                    prompts.clone(),
+                    owner_id.clone(),
+                    client,
+                )
+                .await
+                {
+                    Ok(generated_title) => (
+                        generated_title,
+                        Some(prompts.get(PromptKind::GenerateTitle).tag()),
+                    ),
+                    Err(_) => ("Untitled Recipe".to_string(), None),
+                },
+            }
+        }
+    };
+
+    let mut recipe = Recipe::new(
+        None,
+        owner_id.clone(),
+        Some(recipe_title),
+        recipe_url.clone(),
+        recipe_content.clone(),
+    );
+    recipe.update_prompt_version(prompt_version);
+
+    let grocery_items = grocery_items(client, config, prompts, owner_id, &recipe_content).await;
+    let (recipe, grocery_list) = save_recipe(client, job_id, recipe, grocery_items).await?;
+
+    Ok(RecipeJobOutcome {
+        recipe_id: recipe.id(),
+        url: recipe_url,
+        grocery_list,
+        extracted: None,
+        extraction_error: None,
+    })
+}
+
+async fn extract_recipe(
+    client: &DBClient,
+    config: &Server,
+    prompts: &Arc<Prompts>,
+    job_id: i64,
+    owner_id: String,
+    url: Option<String>,
+    content: Option<String>,
+) -> Result<RecipeJobOutcome, String> {
+    let (recipe_content, recipe_url) = recipe_content(url, content).await?;
+    let use_gemini = config.llm_provider().to_lowercase() == "gemini";
+
+    let extracted = llm::extract_recipe_with_llm(
+        &recipe_content,
+        &config.llm_api_key(),
+        use_gemini,
+        prompts.clone(),
+        owner_id.clone(),
+        client,
+    )
+    .await;
+
+    // Keep the raw content when extraction fails so nothing is lost
+    let (title, stored_content, prompt_version) = match &extracted {
+        Ok(recipe_data) => (
+            Some(recipe_data.title.clone()),
+            recipe_data.to_markdown(),
+            recipe_data.prompt_version.clone(),
+        ),
+        Err(err) => {
+            log::error!("Failed to extract recipe: {err}");
+            (None, recipe_content.clone(), None)
+        }
+    };
+
+    let mut recipe = Recipe::new(
+        None,
+        owner_id.clone(),
+        title,
+        recipe_url.clone(),
+        stored_content,
+    );
+    recipe.update_prompt_version(prompt_version);
+
+    let grocery_items = grocery_items(client, config, prompts, owner_id, &recipe_content).await;
+    let (recipe, grocery_list) = save_recipe(client, job_id, recipe, grocery_items).await?;
+
+    let (extracted, extraction_error) = match extracted {
+        Ok(recipe_data) => (Some(recipe_data), None),
+        Err(err) => (None, Some(err.to_string())),
+    };
+
+    Ok(RecipeJobOutcome {
+        recipe_id: recipe.id(),
+        url: recipe_url,
+        grocery_list,
+        extracted,
+        extraction_error,
+    })
+}
+
+async fn reextract_recipe(
+    client: &DBClient,
+    config: &Server,
+    prompts: &Arc<Prompts>,
+    owner_id: String,
+    recipe_id: i64,
+) -> Result<RecipeJobOutcome, String> {
+    let recipe = database::recipes::get_recipe(client, recipe_id, owner_id.clone()).await?;
+    let use_gemini = config.llm_provider().to_lowercase() == "gemini";
+
+    let recipe_data = llm::extract_recipe_with_llm(
+        recipe.content(),
+        &config.llm_api_key(),
+        use_gemini,
+        prompts.clone(),
+        owner_id.clone(),
+        client,
+    )
+    .await
+    .map_err(|err| format!("Error extracting structured recipe data: {err}"))?;
+
+    let recipe = database::recipes::update_recipe(
+        client,
+        recipe_id,
+        Some(recipe_data.title.clone()),
+        None,
+        Some(recipe_data.to_markdown()),
+        recipe_data.prompt_version.clone(),
+        owner_id,
+    )
+    .await?;
+
+    Ok(RecipeJobOutcome {
+        recipe_id: recipe.id(),
+        url: recipe.url.clone(),
+        grocery_list: None,
+        extracted: Some(recipe_data),
+        extraction_error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recipe_url() {
+        assert!(parse_recipe_url("https://example.com/soup").is_some());
+        assert!(parse_recipe_url("http://example.com/soup").is_some());
+        assert!(parse_recipe_url("file:///etc/passwd").is_none());
+        assert!(parse_recipe_url("ftp://example.com/soup").is_none());
+        assert!(parse_recipe_url("soup").is_none());
+    }
+
+    #[test]
+    fn test_saved_recipe_is_not_created_again() {
+        let payload = JobPayload::Process {
+            url: Some("https://example.com/soup".to_string()),
+            content: None,
+        };
+        let mut job = Job::new("alice".to_string(), JobKind::Process, String::new(), 3);
+        assert!(saved_outcome(&job, &payload).is_none());
+
+        job.recipe_id = Some(7);
+        let outcome = saved_outcome(&job, &payload).unwrap();
+        assert_eq!(outcome.recipe_id, 7);
+        assert_eq!(outcome.url.as_deref(), Some("https://example.com/soup"));
+
+        // Re-extraction updates an existing recipe and always runs
+        let reextract = JobPayload::Reextract { recipe_id: 7 };
+        assert!(saved_outcome(&job, &reextract).is_none());
+    }
+}
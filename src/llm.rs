@@ -2,8 +2,11 @@ use rig::client::CompletionClient;
 use rig::completion::Prompt;
 use rig::providers::{anthropic, gemini, openai};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
-use crate::database::{self, DBClient, items::Item};
+use crate::database::DBClient;
+use crate::database::llm_calls::{self, LlmCall};
+use crate::prompts::{PromptKind, Prompts};
 
 #[derive(Debug)]
 pub enum LlmError {
@@ -13,9 +16,19 @@ pub enum LlmError {
     Parse(String),
 }
 
+impl std::fmt::Display for LlmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LlmError::Request(msg) => write!(f, "{msg}"),
+            LlmError::Auth(msg) => write!(f, "{msg}"),
+            LlmError::Parse(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
 // Rust-based LLM functionality
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtractedRecipe {
     pub title: String,
     pub ingredients: Vec<String>,
@@ -23,6 +36,42 @@ pub struct ExtractedRecipe {
     pub prep_time: Option<String>,
     pub cook_time: Option<String>,
     pub servings: Option<String>,
+    /// Tag of the prompt template that produced this extraction.
+    #[serde(default)]
+    pub prompt_version: Option<String>,
+}
+
+impl ExtractedRecipe {
+    /// Renders the recipe as the markdown stored in `recipes.content`.
+    pub fn to_markdown(&self) -> String {
+        format!(
+            "# {}\n\n## Ingredients\n{}\n\n## Instructions\n{}\n\n{}{}{}",
+            self.title,
+            self.ingredients
+                .iter()
+                .map(|i| format!("- {i}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            self.instructions
+                .iter()
+                .enumerate()
+                .map(|(i, inst)| format!("{}. {}", i + 1, inst))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            self.prep_time
+                .as_ref()
+                .map(|pt| format!("**Prep Time:** {pt}\n"))
+                .unwrap_or_default(),
+            self.cook_time
+                .as_ref()
+                .map(|ct| format!("**Cook Time:** {ct}\n"))
+                .unwrap_or_default(),
+            self.servings
+                .as_ref()
+                .map(|s| format!("**Servings:** {s}\n"))
+                .unwrap_or_default()
+        )
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -36,56 +85,66 @@ pub enum LlmProvider {
     Gemini { api_key: String, model: String },
 }
 
+impl LlmProvider {
+    fn model(&self) -> &str {
+        match self {
+            LlmProvider::OpenAI { model, .. }
+            | LlmProvider::Anthropic { model, .. }
+            | LlmProvider::Gemini { model, .. } => model,
+        }
+    }
+}
+
 pub struct LlmClient {
     provider: LlmProvider,
+    prompts: Arc<Prompts>,
+    /// User the calls are made for, recorded for the admin usage overview.
+    usage: Option<(String, DBClient)>,
 }
 
 impl LlmClient {
-    pub fn new(provider: LlmProvider) -> Self {
-        Self { provider }
+    pub fn new(provider: LlmProvider, prompts: Arc<Prompts>) -> Self {
+        Self {
+            provider,
+            prompts,
+            usage: None,
+        }
     }
 
-    pub async fn extract_recipe(&self, content: &str) -> Result<ExtractedRecipe, LlmError> {
-        let prompt = format!(
-            r#"Extract the recipe information from the following content and return it as JSON.
-            
-Format the response as a JSON object with these fields:
-- title: string (recipe title)
-- ingredients: array of strings (each ingredient with quantity)
-- instructions: array of strings (step-by-step cooking instructions)
-- prep_time: string or null (preparation time)
-- cook_time: string or null (cooking time)
-- servings: string or null (number of servings)
-
-Content to extract from:
-{content}
+    /// Records every call made for the user.
+    pub fn with_usage(mut self, owner_id: String, db_client: &DBClient) -> Self {
+        self.usage = Some((owner_id, db_client.clone()));
+        self
+    }
 
-Return only the JSON object, no additional text."#
-        );
+    pub async fn extract_recipe(&self, content: &str) -> Result<ExtractedRecipe, LlmError> {
+        let template = self.prompts.get(PromptKind::ExtractRecipe);
+        let prompt = template.render(&[("content", content)]);
 
-        let response_text = self.call_llm_api(&prompt).await?;
+        let response_text = self
+            .call_llm_api(PromptKind::ExtractRecipe, &prompt)
+            .await?;
 
         // Try to parse the JSON response
-        let recipe: ExtractedRecipe = serde_json::from_str(&response_text).map_err(|e| {
+        let mut recipe: ExtractedRecipe = serde_json::from_str(&response_text).map_err(|e| {
             LlmError::Parse(format!(
                 "Failed to parse recipe JSON: {e}\nResponse: {response_text}"
             ))
         })?;
+        recipe.prompt_version = Some(template.tag());
 
         Ok(recipe)
     }
 
     pub async fn generate_title(&self, content: &str) -> Result<String, LlmError> {
-        let prompt = format!(
-            r#"Generate a concise, descriptive title for this recipe or cooking content. The title should be 2-8 words and clearly describe what dish is being made.
-
-Content to generate title for:
-{content}
+        let prompt = self
+            .prompts
+            .get(PromptKind::GenerateTitle)
+            .render(&[("content", content)]);
 
-Return only the title text, no quotes or additional formatting."#
-        );
-
-        let response_text = self.call_llm_api(&prompt).await?;
+        let response_text = self
+            .call_llm_api(PromptKind::GenerateTitle, &prompt)
+            .await?;
 
         // Clean up the response - remove quotes and trim whitespace
         let title = response_text
@@ -103,19 +162,12 @@ Return only the title text, no quotes or additional formatting."#
     }
 
     pub async fn extract_grocery_list(&self, content: &str) -> Result<Vec<String>, LlmError> {
-        let prompt = format!(
-            r#"Extract a grocery list from the following recipe or content. Focus only on ingredients that need to be purchased.
-            
-Return the response as a JSON object with this format:
-{{"items": ["ingredient 1", "ingredient 2", ...]}}
-
-Content to extract from:
-{content}
+        let prompt = self
+            .prompts
+            .get(PromptKind::GroceryList)
+            .render(&[("content", content)]);
 
-Return only the JSON object, no additional text."#
-        );
-
-        let response_text = self.call_llm_api(&prompt).await?;
+        let response_text = self.call_llm_api(PromptKind::GroceryList, &prompt).await?;
 
         // Try to parse the JSON response
         let grocery_list: GroceryList = serde_json::from_str(&response_text).map_err(|e| {
@@ -127,8 +179,24 @@ Return only the JSON object, no additional text."#
         Ok(grocery_list.items)
     }
 
-    async fn call_llm_api(&self, prompt: &str) -> Result<String, LlmError> {
-        let system_message = "You are a helpful assistant that extracts recipe information and grocery lists. Always respond with valid JSON.";
+    async fn call_llm_api(&self, kind: PromptKind, prompt: &str) -> Result<String, LlmError> {
+        let response = self.send_prompt(prompt).await;
+
+        if let Some((owner_id, db_client)) = &self.usage {
+            let call = LlmCall::new(
+                owner_id.clone(),
+                kind.name(),
+                self.provider.model(),
+                prompt,
+                response.as_deref().ok(),
+            );
+            llm_calls::record_llm_call(db_client, call).await;
+        }
+        response
+    }
+
+    async fn send_prompt(&self, prompt: &str) -> Result<String, LlmError> {
+        let system_message = self.prompts.get(PromptKind::System).body();
 
         match &self.provider {
             LlmProvider::OpenAI { api_key, model } => {
@@ -173,6 +241,9 @@ pub async fn extract_recipe_with_llm(
     content: &str,
     api_key: &str,
     use_gemini: bool,
+    prompts: Arc<Prompts>,
+    user_id: String,
+    db_client: &DBClient,
 ) -> Result<ExtractedRecipe, LlmError> {
     let provider = if use_gemini {
         LlmProvider::Gemini {
@@ -186,7 +257,7 @@ pub async fn extract_recipe_with_llm(
         }
     };
 
-    let client = LlmClient::new(provider);
+    let client = LlmClient::new(provider, prompts).with_usage(user_id, db_client);
     client.extract_recipe(content).await
 }
 
@@ -195,8 +266,9 @@ pub async fn extract_recipe_with_llm(
 pub async fn extract_recipe_with_provider(
     content: &str,
     provider: LlmProvider,
+    prompts: Arc<Prompts>,
 ) -> Result<ExtractedRecipe, LlmError> {
-    let client = LlmClient::new(provider);
+    let client = LlmClient::new(provider, prompts);
     client.extract_recipe(content).await
 }
 
@@ -204,6 +276,9 @@ pub async fn generate_title_with_llm(
     content: &str,
     api_key: &str,
     use_gemini: bool,
+    prompts: Arc<Prompts>,
+    user_id: String,
+    db_client: &DBClient,
 ) -> Result<String, LlmError> {
     let provider = if use_gemini {
         LlmProvider::Gemini {
@@ -217,7 +292,7 @@ pub async fn generate_title_with_llm(
         }
     };
 
-    let client = LlmClient::new(provider);
+    let client = LlmClient::new(provider, prompts).with_usage(user_id, db_client);
     client.generate_title(content).await
 }
 
@@ -226,8 +301,9 @@ pub async fn generate_title_with_llm(
 pub async fn generate_title_with_provider(
     content: &str,
     provider: LlmProvider,
+    prompts: Arc<Prompts>,
 ) -> Result<String, LlmError> {
-    let client = LlmClient::new(provider);
+    let client = LlmClient::new(provider, prompts);
     client.generate_title(content).await
 }
 
@@ -235,9 +311,10 @@ pub async fn extract_grocery_list_with_llm(
     content: &str,
     api_key: &str,
     use_gemini: bool,
+    prompts: Arc<Prompts>,
     user_id: String,
     db_client: &DBClient,
-) -> Result<String, LlmError> {
+) -> Result<Vec<String>, LlmError> {
     let provider = if use_gemini {
         LlmProvider::Gemini {
             api_key: api_key.to_string(),
@@ -250,26 +327,8 @@ pub async fn extract_grocery_list_with_llm(
         }
     };
 
-    let client = LlmClient::new(provider);
-    let grocery_items = client.extract_grocery_list(content).await?;
-
-    // Create database items from the grocery list
-    let items: Vec<Item> = grocery_items
-        .iter()
-        .map(|item| Item {
-            owner_id: user_id.clone(),
-            id: None,
-            task: item.clone(),
-            completed: 0,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-        })
-        .collect();
-
-    database::items::create_items(db_client, items).await;
-
-    let items_string = grocery_items.join("\n");
-    Ok(format!("Created grocery items:\n{items_string}"))
+    let client = LlmClient::new(provider, prompts).with_usage(user_id, db_client);
+    client.extract_grocery_list(content).await
 }
 
 // New function to support multiple LLM providers
@@ -277,29 +336,10 @@ pub async fn extract_grocery_list_with_llm(
 pub async fn extract_grocery_list_with_provider(
     content: &str,
     provider: LlmProvider,
-    user_id: String,
-    db_client: &DBClient,
-) -> Result<String, LlmError> {
-    let client = LlmClient::new(provider);
-    let grocery_items = client.extract_grocery_list(content).await?;
-
-    // Create database items from the grocery list
-    let items: Vec<Item> = grocery_items
-        .iter()
-        .map(|item| Item {
-            owner_id: user_id.clone(),
-            id: None,
-            task: item.clone(),
-            completed: 0,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-        })
-        .collect();
-
-    database::items::create_items(db_client, items).await;
-
-    let items_string = grocery_items.join("\n");
-    Ok(format!("Created grocery items:\n{items_string}"))
+    prompts: Arc<Prompts>,
+) -> Result<Vec<String>, LlmError> {
+    let client = LlmClient::new(provider, prompts);
+    client.extract_grocery_list(content).await
 }
 
 // Helper function to create providers from config strings
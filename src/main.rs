@@ -1,26 +1,31 @@
+use actix_session::SessionMiddleware;
 use actix_session::config::PersistentSession;
-use actix_session::{SessionMiddleware, storage::CookieSessionStore};
-use actix_web::cookie::Key;
 use actix_web::{App, HttpServer, middleware::Logger, middleware::from_fn, web};
 use env_logger::Env;
 use std::sync::{Arc, Mutex};
 
 use crate::{
     database::DBClient,
-    oidc::{OidcClient, OidcConfig},
+    oidc::{OidcConfig, OidcProviders},
     view::items,
 };
 
 mod config;
 mod csv;
 mod database;
+mod ical;
+mod ingredients;
+mod jobs;
 mod llm;
 mod oidc;
 mod pdf;
+mod prompts;
 mod routes;
 mod scrapy;
+mod session_store;
 mod text_utils;
 mod user;
+mod user_agent;
 mod view;
 mod witch;
 
@@ -52,26 +57,41 @@ pub type ReloadArc = Arc<Mutex<Reload>>;
 async fn main() -> std::io::Result<()> {
     env_logger::init_from_env(Env::default().default_filter_or("debug"));
 
-    let c = config::from_env();
+    let c = match config::from_env() {
+        Ok(c) => c,
+        Err(err) => {
+            log::error!("{err}");
+            return Err(std::io::Error::other(err));
+        }
+    };
     let bind = c.clone();
 
-    let orm_db = database::create_orm_client(c.db_url(), c.db_token()).await;
-
-    let shared_orm_db: DBClient = Arc::new(Mutex::new(orm_db));
+    let shared_orm_db: DBClient =
+        database::create_orm_client(c.db_url(), c.db_token(), c.db_pool()).await;
     database::migrations::run(&shared_orm_db).await;
 
-    let oidc_config = OidcConfig::from_env();
-    let mut oidc_client = OidcClient::new(oidc_config);
-
-    if let Err(e) = oidc_client.discover().await {
-        log::warn!("Failed to discover OIDC endpoints: {e}. OIDC authentication will be disabled.",);
+    if std::env::args().nth(1).as_deref() == Some("schema-diff") {
+        match database::migrations::schema_diff(&shared_orm_db).await {
+            Ok(sql) if sql.is_empty() => log::info!("The schema matches the models"),
+            Ok(sql) => print!("{sql}"),
+            Err(err) => log::error!("{err}"),
+        }
+        return Ok(());
     }
 
-    let oidc_client_arc = Arc::new(tokio::sync::Mutex::new(oidc_client));
+    let prompts = prompts::Prompts::load(c.prompts_dir().as_deref().map(std::path::Path::new))
+        .expect("Could not load prompt templates");
+    let prompts = Arc::new(prompts);
+
+    let job_queue =
+        jobs::JobQueue::new(shared_orm_db.clone(), prompts.clone(), c.job_max_attempts());
+    job_queue.start(c.job_workers(), c.clone());
+
+    let oidc_providers = OidcProviders::discover(OidcConfig::from_env()).await;
 
-    let secret_key = std::env::var("SESSION_SECRET")
-        .unwrap_or_else(|_| "your-secret-key-change-this-in-production".to_string());
-    let secret_key = Key::from(secret_key.as_bytes());
+    let secret_key = c.session_key();
+    let session_store = session_store::LibsqlSessionStore::new(shared_orm_db.clone());
+    session_store.start_cleanup();
 
     let url = format!("http://{}:{}", c.host(), c.port());
 
@@ -85,7 +105,7 @@ async fn main() -> std::io::Result<()> {
             .wrap(Logger::new("%a %{User-Agent}i").exclude("/reload"))
             .wrap(from_fn(oidc::user_extractor))
             .wrap(
-                SessionMiddleware::builder(CookieSessionStore::default(), secret_key.clone())
+                SessionMiddleware::builder(session_store.clone(), secret_key.clone())
                     .cookie_name("rezi_session".to_string())
                     .cookie_secure(!c.local()) // Temporarily disable for debugging
                     .cookie_http_only(true)
@@ -99,20 +119,32 @@ async fn main() -> std::io::Result<()> {
             )
             .app_data(web::Data::new(shared_orm_db.clone()))
             .app_data(web::Data::new(c.clone()))
-            .app_data(web::Data::new(oidc_client_arc.clone()))
+            .app_data(web::Data::new(oidc_providers.clone()))
+            .app_data(web::Data::new(job_queue.clone()))
             .service(routes::auth::login_page)
             .service(routes::auth::auth_login)
             .service(routes::auth::callback)
             .service(routes::auth::logout)
+            .service(routes::auth::backchannel_logout)
+            .service(routes::auth::provider_login)
+            .service(routes::auth::provider_callback)
+            .service(routes::auth::provider_backchannel_logout)
             .service(view::index_route)
             .service(view::about_endpoint)
             .service(view::about_changelog_endpoint)
             .service(view::about_readme_endpoint)
             .service(view::profile::profile_endpoint)
+            .service(routes::profile::revoke_session)
+            .service(routes::profile::create_api_token)
+            .service(routes::profile::revoke_api_token)
+            .service(routes::admin::scope())
             .service(routes::recipes::recipe_endpoint)
             .service(routes::recipes::create_recipe)
             .service(routes::recipes::process_recipe_input)
             .service(routes::recipes::extract_recipe_structure)
+            .service(routes::recipes::import_recipes)
+            .service(routes::recipes::reextract_recipe)
+            .service(routes::recipes::search_recipes)
             .service(routes::recipes::get_recipe)
             .service(routes::recipes::update_recipe)
             .service(routes::recipes::delete_recipe)
@@ -125,10 +157,29 @@ async fn main() -> std::io::Result<()> {
             .service(routes::items::update_item)
             .service(routes::items::edit_item)
             .service(routes::items::cancel_edit_item)
+            .service(routes::items::move_item_to_pantry)
+            .service(routes::pantry::pantry_endpoint)
+            .service(routes::pantry::create_pantry_item)
+            .service(routes::pantry::update_pantry_item)
+            .service(routes::pantry::delete_pantry_item)
+            .service(routes::pantry::edit_pantry_item)
+            .service(routes::pantry::cancel_edit_pantry_item)
+            .service(routes::meal_plan::meal_plan_endpoint)
+            .service(routes::meal_plan::plan_meal)
+            .service(routes::meal_plan::delete_planned_meal)
+            .service(routes::meal_plan::generate_shopping_list)
+            .service(routes::jobs::jobs_endpoint)
+            .service(routes::jobs::job_status)
+            .service(routes::jobs::retry_job)
             .service(routes::export::export_page)
             .service(routes::export::export_items_csv)
             .service(routes::export::export_items_pdf)
+            .service(routes::export::export_meal_plan_ics)
+            .service(routes::export::regenerate_meal_plan_feed)
             .service(routes::technical::health)
+            .service(routes::api::openapi::openapi_json)
+            .service(routes::api::openapi::api_docs)
+            .service(routes::api::scope())
             .service(routes::assets::scope())
     });
     server
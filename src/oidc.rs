@@ -1,43 +1,248 @@
 use actix_session::Session;
+use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, Result};
 use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 use log::info;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::database::api_tokens::{self, ApiScope};
+use crate::database::{DBClient, auth_sessions};
+use crate::routes::api::ApiError;
+use crate::user::Role;
+
+pub type OidcClientArc = Arc<tokio::sync::Mutex<OidcClient>>;
+
+/// Session key holding the id of the server-side `AuthSession`.
+pub const AUTH_SESSION_KEY: &str = "auth_session_id";
+
+/// How long fetched signing keys are trusted before the JWKS is fetched again.
+const JWKS_TTL: Duration = Duration::from_secs(60 * 60);
+/// Minimum time between refetches caused by an unknown key id, so forged
+/// tokens cannot make us hammer the provider.
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+/// Allowed clock difference to the provider when checking `exp`.
+const CLOCK_SKEW_SECONDS: u64 = 60;
+/// How often `last_seen_at` of a session is updated at most.
+const ACTIVITY_INTERVAL_SECONDS: i64 = 5 * 60;
+/// Event a logout token has to carry, see OpenID Connect Back-Channel Logout.
+const BACKCHANNEL_LOGOUT_EVENT: &str = "http://schemas.openid.net/event/backchannel-logout";
 
 #[derive(Debug, Clone)]
 pub struct OidcConfig {
+    /// Short name of the provider used in URLs, e.g. `google`.
+    pub id: String,
+    /// Shown on the login button.
+    pub name: String,
     pub client_id: String,
     pub client_secret: String,
     pub issuer_url: String,
     pub redirect_uri: String,
     pub scopes: String,
+    /// Upper bound in seconds for how long an access token is used before it
+    /// is refreshed, even if the provider grants a longer one.
+    pub access_token_lifetime: u64,
+    /// Where the provider sends the browser after logging out.
+    pub post_logout_redirect_uri: String,
+    /// Claim listing the groups of a user, e.g. `groups` or `roles`.
+    pub groups_claim: String,
+    /// Members of this group are admins.
+    pub admin_group: Option<String>,
 }
 
 impl OidcConfig {
-    pub fn from_env() -> Self {
+    /// One config per provider listed in `OIDC_PROVIDERS`, each configured
+    /// through `OIDC_<ID>_*` variables. Without the list a single provider
+    /// is configured from the plain `OIDC_*` variables.
+    pub fn from_env() -> Vec<Self> {
+        Self::from_vars(|key| std::env::var(key).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Vec<Self> {
+        let Some(providers) = var("OIDC_PROVIDERS") else {
+            return vec![Self::provider(
+                &var,
+                "default",
+                "OIDC_",
+                "OpenID Connect",
+                "http://localhost:3000/auth/callback",
+            )];
+        };
+
+        providers
+            .split(',')
+            .map(|id| id.trim().to_lowercase())
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                let prefix = format!("OIDC_{}_", id.to_uppercase().replace('-', "_"));
+                let mut name = id.clone();
+                if let Some(first) = name.get_mut(0..1) {
+                    first.make_ascii_uppercase();
+                }
+                let redirect_uri = format!("http://localhost:3000/auth/{id}/callback");
+                Self::provider(&var, &id, &prefix, &name, &redirect_uri)
+            })
+            .collect()
+    }
+
+    fn provider(
+        var: &impl Fn(&str) -> Option<String>,
+        id: &str,
+        prefix: &str,
+        default_name: &str,
+        default_redirect_uri: &str,
+    ) -> Self {
+        let var = |name: &str| var(&format!("{prefix}{name}"));
+
+        let redirect_uri = var("REDIRECT_URI").unwrap_or_else(|| default_redirect_uri.to_string());
+        // Default to the root of the site the callback lives on
+        let post_logout_redirect_uri = var("POST_LOGOUT_REDIRECT_URI")
+            .or_else(|| {
+                url::Url::parse(&redirect_uri)
+                    .ok()
+                    .map(|url| format!("{}/", url.origin().ascii_serialization()))
+            })
+            .unwrap_or_else(|| "http://localhost:3000/".to_string());
+
         Self {
-            client_id: std::env::var("OIDC_CLIENT_ID")
-                .unwrap_or_else(|_| "default-client-id".to_string()),
-            client_secret: std::env::var("OIDC_CLIENT_SECRET")
-                .unwrap_or_else(|_| "default-client-secret".to_string()),
-            issuer_url: std::env::var("OIDC_ISSUER_URL")
-                .unwrap_or_else(|_| "https://accounts.google.com".to_string()),
-            redirect_uri: std::env::var("OIDC_REDIRECT_URI")
-                .unwrap_or_else(|_| "http://localhost:3000/auth/callback".to_string()),
-            scopes: std::env::var("OIDC_SCOPES")
-                .unwrap_or_else(|_| "openid email profile".to_string()),
+            id: id.to_string(),
+            name: var("NAME").unwrap_or_else(|| default_name.to_string()),
+            client_id: var("CLIENT_ID").unwrap_or_else(|| "default-client-id".to_string()),
+            client_secret: var("CLIENT_SECRET")
+                .unwrap_or_else(|| "default-client-secret".to_string()),
+            issuer_url: var("ISSUER_URL")
+                .unwrap_or_else(|| "https://accounts.google.com".to_string()),
+            redirect_uri,
+            scopes: var("SCOPES").unwrap_or_else(|| "openid email profile".to_string()),
+            access_token_lifetime: var("ACCESS_TOKEN_LIFETIME")
+                .and_then(|v| v.parse().ok())
+                .filter(|lifetime| *lifetime > 0)
+                .unwrap_or(3600),
+            post_logout_redirect_uri,
+            groups_claim: var("GROUPS_CLAIM").unwrap_or_else(|| "groups".to_string()),
+            admin_group: var("ADMIN_GROUP").filter(|group| !group.trim().is_empty()),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A configured identity provider.
+#[derive(Clone)]
+pub struct OidcProvider {
+    pub id: String,
+    pub name: String,
+    pub client: OidcClientArc,
+    refreshing: SessionLocks,
+}
+
+/// One lock per auth session being refreshed, so concurrent requests refresh
+/// a session once without blocking other sessions.
+#[derive(Clone, Default)]
+struct SessionLocks(Arc<std::sync::Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>);
+
+impl SessionLocks {
+    async fn lock(&self, session_id: &str) -> SessionLock {
+        let lock = self
+            .0
+            .lock()
+            .unwrap()
+            .entry(session_id.to_string())
+            .or_default()
+            .clone();
+        SessionLock {
+            guard: Some(lock.lock_owned().await),
+            locks: self.clone(),
+            session_id: session_id.to_string(),
+        }
+    }
+}
+
+struct SessionLock {
+    guard: Option<tokio::sync::OwnedMutexGuard<()>>,
+    locks: SessionLocks,
+    session_id: String,
+}
+
+impl Drop for SessionLock {
+    fn drop(&mut self) {
+        let mut locks = self.locks.0.lock().unwrap();
+        self.guard.take();
+        // Nobody else holds or waits for the lock once only the map has it
+        if locks
+            .get(&self.session_id)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            locks.remove(&self.session_id);
+        }
+    }
+}
+
+/// All identity providers users can sign in with, in configured order.
+#[derive(Clone)]
+pub struct OidcProviders {
+    providers: Arc<Vec<OidcProvider>>,
+}
+
+impl OidcProviders {
+    /// Runs discovery for every provider. Providers whose discovery fails stay
+    /// listed but cannot be used to sign in.
+    pub async fn discover(configs: Vec<OidcConfig>) -> Self {
+        let mut providers = Vec::with_capacity(configs.len());
+        for config in configs {
+            let (id, name) = (config.id.clone(), config.name.clone());
+            let mut client = OidcClient::new(config);
+            if let Err(e) = client.discover().await {
+                log::warn!(
+                    "Failed to discover OIDC endpoints of {id}: {e}. Signing in with it will be disabled."
+                );
+            }
+            providers.push(OidcProvider {
+                id,
+                name,
+                client: Arc::new(tokio::sync::Mutex::new(client)),
+                refreshing: SessionLocks::default(),
+            });
+        }
+        OidcProviders {
+            providers: Arc::new(providers),
+        }
+    }
+
+    pub fn all(&self) -> &[OidcProvider] {
+        &self.providers
+    }
+
+    pub fn get(&self, id: &str) -> Option<&OidcProvider> {
+        self.providers.iter().find(|provider| provider.id == id)
+    }
+
+    /// The first configured provider. Users who signed up with it keep their
+    /// subject as user id, like before multiple providers were supported.
+    pub fn primary(&self) -> Option<&OidcProvider> {
+        self.providers.first()
+    }
+
+    /// Provider a session was created with, sessions from before multiple
+    /// providers belong to the primary one.
+    pub fn for_session(&self, provider: Option<&str>) -> Option<&OidcProvider> {
+        match provider {
+            Some(id) => self.get(id),
+            None => self.primary(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OidcDiscovery {
+    pub issuer: String,
     pub authorization_endpoint: String,
     pub token_endpoint: String,
     pub userinfo_endpoint: String,
     pub jwks_uri: String,
+    /// Only present when the provider supports RP-initiated logout.
+    pub end_session_endpoint: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,19 +261,108 @@ pub struct UserInfo {
     pub name: Option<String>,
     pub given_name: Option<String>,
     pub family_name: Option<String>,
+    pub picture: Option<String>,
+    pub locale: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub email_verified: Option<bool>,
+    /// Remaining claims, only needed at login and kept out of the session.
+    #[serde(flatten, skip_serializing)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+/// Some providers send boolean claims as `"true"` strings.
+fn deserialize_flag<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<bool>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Text(String),
+    }
+
+    Ok(match Option::<Flag>::deserialize(deserializer)? {
+        Some(Flag::Bool(flag)) => Some(flag),
+        Some(Flag::Text(text)) => Some(text.eq_ignore_ascii_case("true")),
+        None => None,
+    })
+}
+
+/// Result of asking the provider for a new access token.
+pub enum RefreshOutcome {
+    Refreshed(TokenResponse),
+    /// The provider refused, e.g. because the user was disabled or the
+    /// refresh token revoked.
+    Rejected(String),
+}
+
+/// Claims of a verified ID token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub exp: u64,
+    pub nonce: Option<String>,
+    pub azp: Option<String>,
+    pub sid: Option<String>,
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub email_verified: Option<bool>,
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+/// Group names from a claim, which providers send as a list or, with a
+/// single group, as a plain string.
+fn claim_groups(claim: Option<&serde_json::Value>) -> Vec<String> {
+    match claim {
+        Some(serde_json::Value::String(group)) => vec![group.clone()],
+        Some(serde_json::Value::Array(groups)) => groups
+            .iter()
+            .filter_map(|group| group.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Claims of a verified back-channel logout token. At least one of `sub`
+/// and `sid` is set.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LogoutTokenClaims {
+    pub iss: String,
+    pub sub: Option<String>,
+    pub sid: Option<String>,
+    pub iat: u64,
+    pub exp: u64,
+    pub jti: String,
+    #[serde(default)]
+    pub events: serde_json::Map<String, serde_json::Value>,
+    pub nonce: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthState {
+    /// Id of the provider the login was started with.
+    pub provider: String,
     pub state: String,
     pub code_verifier: String,
+    /// Sent with the authorization request and expected back in the ID token.
+    pub nonce: String,
     pub redirect_url: Option<String>,
 }
 
+struct JwksCache {
+    keys: Vec<jsonwebtoken::jwk::Jwk>,
+    fetched_at: Instant,
+}
+
 pub struct OidcClient {
     config: OidcConfig,
     client: reqwest::Client,
     discovery: Option<OidcDiscovery>,
+    jwks: Option<JwksCache>,
+    /// `jti` and `exp` of logout tokens already accepted, to refuse replays.
+    seen_logout_tokens: HashMap<String, u64>,
 }
 
 impl OidcClient {
@@ -77,6 +371,8 @@ impl OidcClient {
             config,
             client: reqwest::Client::new(),
             discovery: None,
+            jwks: None,
+            seen_logout_tokens: HashMap::new(),
         }
     }
 
@@ -107,9 +403,17 @@ impl OidcClient {
         (code_verifier, code_challenge)
     }
 
+    pub fn generate_nonce() -> String {
+        let mut random_bytes = [0u8; 32];
+        use rand::RngCore;
+        rand::rng().fill_bytes(&mut random_bytes);
+        URL_SAFE_NO_PAD.encode(random_bytes)
+    }
+
     pub fn build_auth_url(
         &self,
         state: &str,
+        nonce: &str,
         code_challenge: &str,
         redirect_url: Option<String>,
     ) -> Result<String, Box<dyn std::error::Error>> {
@@ -133,6 +437,7 @@ impl OidcClient {
             ("redirect_uri", &final_redirect_uri),
             ("scope", &self.config.scopes),
             ("state", state),
+            ("nonce", nonce),
             ("code_challenge", code_challenge),
             ("code_challenge_method", "S256"),
         ];
@@ -199,6 +504,285 @@ impl OidcClient {
 
         Ok(user_info)
     }
+
+    /// Groups of the user from the ID token, or from userinfo when the
+    /// token has none.
+    pub fn groups(&self, claims: &IdTokenClaims, user_info: &UserInfo) -> Vec<String> {
+        let claim = &self.config.groups_claim;
+        let groups = claim_groups(claims.other.get(claim));
+        if groups.is_empty() {
+            claim_groups(user_info.other.get(claim))
+        } else {
+            groups
+        }
+    }
+
+    pub fn admin_group(&self) -> Option<&str> {
+        self.config.admin_group.as_deref()
+    }
+
+    /// When a token granted for `expires_in` seconds has to be refreshed.
+    pub fn access_token_expiry(&self, expires_in: Option<u64>) -> chrono::DateTime<chrono::Utc> {
+        let lifetime = expires_in
+            .unwrap_or(self.config.access_token_lifetime)
+            .min(self.config.access_token_lifetime);
+        chrono::Utc::now() + chrono::Duration::seconds(lifetime as i64)
+    }
+
+    /// A client sharing configuration and HTTP connections but no caches,
+    /// so token requests can run without holding the provider's lock.
+    pub fn token_client(&self) -> OidcClient {
+        OidcClient {
+            config: self.config.clone(),
+            client: self.client.clone(),
+            discovery: self.discovery.clone(),
+            jwks: None,
+            seen_logout_tokens: HashMap::new(),
+        }
+    }
+
+    /// How long before expiry a token is refreshed in the background.
+    pub fn refresh_margin(&self) -> chrono::Duration {
+        chrono::Duration::seconds((self.config.access_token_lifetime / 2).min(300) as i64)
+    }
+
+    pub async fn refresh_access_token(
+        &self,
+        refresh_token: &str,
+    ) -> Result<RefreshOutcome, Box<dyn std::error::Error>> {
+        let discovery = self
+            .discovery
+            .as_ref()
+            .ok_or("OIDC discovery not completed")?;
+
+        let mut params = HashMap::new();
+        params.insert("grant_type", "refresh_token");
+        params.insert("client_id", &self.config.client_id);
+        params.insert("client_secret", &self.config.client_secret);
+        params.insert("refresh_token", refresh_token);
+
+        let response = self
+            .client
+            .post(&discovery.token_endpoint)
+            .form(&params)
+            .send()
+            .await?;
+
+        // 4xx means the provider looked at the token and said no, anything
+        // else is an outage we should not log users out for
+        if response.status().is_client_error() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Ok(RefreshOutcome::Rejected(format!("{status}: {body}")));
+        }
+
+        let tokens: TokenResponse = response.error_for_status()?.json().await?;
+        Ok(RefreshOutcome::Refreshed(tokens))
+    }
+
+    async fn fetch_jwks(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let discovery = self
+            .discovery
+            .as_ref()
+            .ok_or("OIDC discovery not completed")?;
+
+        let body: serde_json::Value = self
+            .client
+            .get(&discovery.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Skip keys we cannot use instead of rejecting the whole set
+        let keys: Vec<jsonwebtoken::jwk::Jwk> = body
+            .get("keys")
+            .and_then(|keys| keys.as_array())
+            .ok_or("JWKS response has no keys")?
+            .iter()
+            .filter_map(|key| serde_json::from_value(key.clone()).ok())
+            .collect();
+
+        info!(
+            "Fetched {} signing keys from {}",
+            keys.len(),
+            discovery.jwks_uri
+        );
+        self.jwks = Some(JwksCache {
+            keys,
+            fetched_at: Instant::now(),
+        });
+        Ok(())
+    }
+
+    fn cached_key(&self, kid: Option<&str>) -> Option<jsonwebtoken::jwk::Jwk> {
+        let cache = self.jwks.as_ref()?;
+        match kid {
+            Some(kid) => cache
+                .keys
+                .iter()
+                .find(|key| key.common.key_id.as_deref() == Some(kid))
+                .cloned(),
+            // Without a key id the token can only be checked against a single key
+            None if cache.keys.len() == 1 => cache.keys.first().cloned(),
+            None => None,
+        }
+    }
+
+    /// Looks up the signing key, fetching the JWKS when the cache is stale or
+    /// the provider rotated to a key we have not seen yet.
+    async fn signing_key(
+        &mut self,
+        kid: Option<&str>,
+    ) -> Result<jsonwebtoken::jwk::Jwk, Box<dyn std::error::Error>> {
+        let stale = self
+            .jwks
+            .as_ref()
+            .is_none_or(|cache| cache.fetched_at.elapsed() > JWKS_TTL);
+        if stale {
+            self.fetch_jwks().await?;
+        }
+
+        if let Some(key) = self.cached_key(kid) {
+            return Ok(key);
+        }
+
+        let may_refresh = self
+            .jwks
+            .as_ref()
+            .is_none_or(|cache| cache.fetched_at.elapsed() > JWKS_MIN_REFRESH);
+        if !stale && may_refresh {
+            info!("Unknown signing key {kid:?}, fetching JWKS again");
+            self.fetch_jwks().await?;
+            if let Some(key) = self.cached_key(kid) {
+                return Ok(key);
+            }
+        }
+
+        Err(format!("No signing key found for key id {kid:?}").into())
+    }
+
+    /// Checks the signature against the provider's keys as well as issuer,
+    /// audience and expiry, then returns the claims.
+    async fn decode_signed<T: serde::de::DeserializeOwned>(
+        &mut self,
+        token: &str,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        use jsonwebtoken::Algorithm;
+
+        let issuer = self
+            .discovery
+            .as_ref()
+            .ok_or("OIDC discovery not completed")?
+            .issuer
+            .clone();
+
+        let header = jsonwebtoken::decode_header(token)?;
+        // Symmetric algorithms would let anyone holding the client secret sign tokens
+        if !matches!(
+            header.alg,
+            Algorithm::RS256
+                | Algorithm::RS384
+                | Algorithm::RS512
+                | Algorithm::PS256
+                | Algorithm::PS384
+                | Algorithm::PS512
+                | Algorithm::ES256
+                | Algorithm::ES384
+                | Algorithm::EdDSA
+        ) {
+            return Err(format!("Unsupported token algorithm {:?}", header.alg).into());
+        }
+
+        let jwk = self.signing_key(header.kid.as_deref()).await?;
+        if let Some(key_algorithm) = jwk.common.key_algorithm
+            && key_algorithm.to_string() != format!("{:?}", header.alg)
+        {
+            return Err("Token algorithm does not match the signing key".into());
+        }
+        let key = jsonwebtoken::DecodingKey::from_jwk(&jwk)?;
+
+        let mut validation = jsonwebtoken::Validation::new(header.alg);
+        validation.set_issuer(&[issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+        validation.leeway = CLOCK_SKEW_SECONDS;
+
+        Ok(jsonwebtoken::decode::<T>(token, &key, &validation)?.claims)
+    }
+
+    /// Verifies signature, issuer, audience, expiry and nonce of an ID token.
+    pub async fn verify_id_token(
+        &mut self,
+        id_token: &str,
+        expected_nonce: &str,
+    ) -> Result<IdTokenClaims, Box<dyn std::error::Error>> {
+        let claims: IdTokenClaims = self.decode_signed(id_token).await?;
+
+        if claims.nonce.as_deref() != Some(expected_nonce) {
+            return Err("ID token nonce does not match".into());
+        }
+        if let Some(azp) = &claims.azp
+            && *azp != self.config.client_id
+        {
+            return Err("ID token was issued to another client".into());
+        }
+
+        Ok(claims)
+    }
+
+    /// Verifies a logout token sent to the back-channel logout endpoint. Each
+    /// token is accepted only once.
+    pub async fn verify_logout_token(
+        &mut self,
+        logout_token: &str,
+    ) -> Result<LogoutTokenClaims, Box<dyn std::error::Error>> {
+        let claims: LogoutTokenClaims = self.decode_signed(logout_token).await?;
+
+        if !claims.events.contains_key(BACKCHANNEL_LOGOUT_EVENT) {
+            return Err("Logout token has no back-channel logout event".into());
+        }
+        // Keeps ID tokens from being passed off as logout tokens
+        if claims.nonce.is_some() {
+            return Err("Logout token must not contain a nonce".into());
+        }
+        if claims.sub.is_none() && claims.sid.is_none() {
+            return Err("Logout token names neither sub nor sid".into());
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.seen_logout_tokens
+            .retain(|_, exp| *exp + CLOCK_SKEW_SECONDS >= now);
+        if self
+            .seen_logout_tokens
+            .insert(claims.jti.clone(), claims.exp)
+            .is_some()
+        {
+            return Err("Logout token was already used".into());
+        }
+
+        Ok(claims)
+    }
+
+    /// URL that ends the session at the provider, `None` when the provider
+    /// does not support RP-initiated logout.
+    pub fn end_session_url(&self, id_token_hint: Option<&str>) -> Option<String> {
+        let endpoint = self.discovery.as_ref()?.end_session_endpoint.as_ref()?;
+        let mut url = url::Url::parse(endpoint).ok()?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(id_token) = id_token_hint {
+                query.append_pair("id_token_hint", id_token);
+            }
+            query.append_pair("client_id", &self.config.client_id);
+            query.append_pair(
+                "post_logout_redirect_uri",
+                &self.config.post_logout_redirect_uri,
+            );
+        }
+        Some(url.into())
+    }
 }
 
 #[allow(unused)]
@@ -239,25 +823,336 @@ pub fn require_auth(session: &Session, current_path: &str) -> Result<Option<Http
     Ok(None)
 }
 
+/// Refreshes the access token of a session. Returns whether the session is
+/// still valid, sessions the provider refuses to refresh are revoked.
+async fn refresh_auth_session(db: &DBClient, provider: &OidcProvider, session_id: &str) -> bool {
+    use log::warn;
+
+    let _lock = provider.refreshing.lock(session_id).await;
+    let client = provider.client.lock().await.token_client();
+
+    // Another request may have refreshed while we waited for the lock
+    let auth_session = match auth_sessions::get_auth_session(db, session_id).await {
+        Ok(Some(auth_session)) => auth_session,
+        Ok(None) => return false,
+        Err(_) => return true,
+    };
+    if !auth_session.expires_within(client.refresh_margin()) {
+        return true;
+    }
+
+    let Some(refresh_token) = auth_session.refresh_token.clone() else {
+        if auth_session.is_expired() {
+            info!("Session expired and cannot be refreshed");
+            let _ = auth_sessions::revoke_auth_session(db, session_id).await;
+            return false;
+        }
+        return true;
+    };
+
+    match client.refresh_access_token(&refresh_token).await {
+        Ok(RefreshOutcome::Refreshed(tokens)) => {
+            let expires_at = client.access_token_expiry(tokens.expires_in);
+            let _ = auth_sessions::update_tokens(
+                db,
+                auth_session,
+                tokens.access_token,
+                tokens.refresh_token,
+                tokens.id_token,
+                expires_at,
+            )
+            .await;
+            info!("Refreshed access token");
+            true
+        }
+        Ok(RefreshOutcome::Rejected(reason)) => {
+            warn!("Provider rejected token refresh, revoking session: {reason}");
+            let _ = auth_sessions::revoke_auth_session(db, session_id).await;
+            false
+        }
+        Err(e) => {
+            warn!("Could not refresh access token, trying again later: {e}");
+            true
+        }
+    }
+}
+
+/// Outcome of checking the server-side session behind a cookie.
+enum SessionCheck {
+    /// The session is valid and belongs to this user id.
+    Valid(String),
+    /// The session was revoked, expired or never existed.
+    Invalid,
+    /// The database could not be asked, the cookie is kept for later.
+    Unavailable,
+}
+
+/// Checks the server-side session behind the cookie. Tokens close to expiry
+/// are refreshed in the background, expired ones before the request goes on.
+async fn check_auth_session(
+    req: &actix_web::dev::ServiceRequest,
+    session: &Session,
+    db: &actix_web::web::Data<DBClient>,
+    providers: &OidcProviders,
+) -> SessionCheck {
+    let Ok(Some(session_id)) = session.get::<String>(AUTH_SESSION_KEY) else {
+        info!("Session predates server-side sessions");
+        return SessionCheck::Invalid;
+    };
+
+    let auth_session = match auth_sessions::get_auth_session(db, &session_id).await {
+        Ok(Some(auth_session)) => auth_session,
+        Ok(None) => {
+            info!("Session was revoked");
+            return SessionCheck::Invalid;
+        }
+        Err(_) => return SessionCheck::Unavailable,
+    };
+    let Some(provider) = providers.for_session(auth_session.provider.as_deref()) else {
+        info!("Session belongs to a provider that is no longer configured");
+        return SessionCheck::Invalid;
+    };
+    let owner_id = auth_session.owner_id.clone();
+
+    if chrono::Utc::now() - auth_session.last_seen()
+        > chrono::Duration::seconds(ACTIVITY_INTERVAL_SECONDS)
+    {
+        let db = db.get_ref().clone();
+        let auth_session = auth_session.clone();
+        let ip = req
+            .connection_info()
+            .realip_remote_addr()
+            .map(str::to_string);
+        let user_agent = req
+            .headers()
+            .get(actix_web::http::header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        actix_web::rt::spawn(async move {
+            let _ = auth_sessions::record_activity(&db, auth_session, ip, user_agent).await;
+        });
+    }
+
+    if auth_session.is_expired() {
+        return match refresh_auth_session(db, provider, &session_id).await {
+            true => SessionCheck::Valid(owner_id),
+            false => SessionCheck::Invalid,
+        };
+    }
+
+    let margin = provider.client.lock().await.refresh_margin();
+    if auth_session.expires_within(margin) {
+        let db = db.get_ref().clone();
+        let provider = provider.clone();
+        actix_web::rt::spawn(async move {
+            refresh_auth_session(&db, &provider, &session_id).await;
+        });
+    }
+    SessionCheck::Valid(owner_id)
+}
+
+/// Scope an API token needs for a request, `None` for everything tokens
+/// cannot be used for. Tokens reach the JSON API and the item export, never
+/// the htmx pages.
+pub fn required_scope(method: &actix_web::http::Method, path: &str) -> Option<ApiScope> {
+    use actix_web::http::Method;
+
+    let under =
+        |path: &str, prefix: &str| path == prefix || path.starts_with(&format!("{prefix}/"));
+    let read = *method == Method::GET || *method == Method::HEAD;
+
+    if under(path, "/export/items") {
+        return read.then_some(ApiScope::ItemsRead);
+    }
+    let path = path
+        .strip_prefix("/api/v1")
+        .filter(|rest| rest.starts_with('/'))?;
+    let under = |prefix: &str| under(path, prefix);
+
+    if under("/lists") {
+        return read.then_some(ApiScope::ItemsRead);
+    }
+    match (under("/items"), under("/recipes"), read) {
+        (true, _, true) => Some(ApiScope::ItemsRead),
+        (true, _, false) => Some(ApiScope::ItemsWrite),
+        (_, true, true) => Some(ApiScope::RecipesRead),
+        (_, true, false) => Some(ApiScope::RecipesWrite),
+        _ => None,
+    }
+}
+
+fn bearer_error(error: ApiError, challenge: String) -> HttpResponse {
+    use actix_web::ResponseError;
+    use actix_web::http::header::{HeaderValue, WWW_AUTHENTICATE};
+
+    let mut response = error.error_response();
+    if let Ok(challenge) = HeaderValue::from_str(&challenge) {
+        response.headers_mut().insert(WWW_AUTHENTICATE, challenge);
+    }
+    response
+}
+
+/// Authenticates a request sent with an API token.
+async fn token_user(
+    req: &actix_web::dev::ServiceRequest,
+    secret: &str,
+) -> Result<crate::user::User, HttpResponse> {
+    use actix_web::web::Data;
+
+    let db = req.app_data::<Data<DBClient>>().unwrap();
+    let token = match api_tokens::find_api_token(db, secret).await {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            info!("Request with unknown API token");
+            return Err(bearer_error(
+                ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "invalid_token",
+                    "The API token is unknown or was revoked",
+                ),
+                r#"Bearer error="invalid_token""#.to_string(),
+            ));
+        }
+        Err(_) => {
+            return Err(actix_web::ResponseError::error_response(&ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "unavailable",
+                "Could not check the API token, please try again",
+            )));
+        }
+    };
+
+    let Some(scope) = required_scope(req.method(), req.path()) else {
+        return Err(actix_web::ResponseError::error_response(&ApiError::new(
+            StatusCode::FORBIDDEN,
+            "forbidden",
+            "API tokens cannot be used here",
+        )));
+    };
+    if !token.has_scope(scope) {
+        return Err(bearer_error(
+            ApiError::new(
+                StatusCode::FORBIDDEN,
+                "insufficient_scope",
+                format!("The API token needs the {} scope", scope.as_str()),
+            ),
+            format!(
+                r#"Bearer error="insufficient_scope", scope="{}""#,
+                scope.as_str()
+            ),
+        ));
+    }
+
+    if token.last_used_at.is_none_or(|last_used| {
+        chrono::Utc::now() - last_used > chrono::Duration::seconds(ACTIVITY_INTERVAL_SECONDS)
+    }) {
+        let db = db.get_ref().clone();
+        let token = token.clone();
+        actix_web::rt::spawn(async move {
+            let _ = api_tokens::record_use(&db, token).await;
+        });
+    }
+
+    match crate::database::users::get_user(db, &token.owner_id).await {
+        Ok(Some(record)) if record.is_disabled() => {
+            info!("Request with API token of disabled user");
+            Err(actix_web::ResponseError::error_response(&ApiError::new(
+                StatusCode::FORBIDDEN,
+                "account_disabled",
+                "The account was disabled by an admin",
+            )))
+        }
+        Ok(Some(record)) => Ok(record.into()),
+        Ok(None) => {
+            info!("Request with API token of unknown user");
+            Err(bearer_error(
+                ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "invalid_token",
+                    "The API token is unknown or was revoked",
+                ),
+                r#"Bearer error="invalid_token""#.to_string(),
+            ))
+        }
+        // The disabled check cannot be skipped
+        Err(_) => Err(actix_web::ResponseError::error_response(&ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "unavailable",
+            "Could not check the account, please try again",
+        ))),
+    }
+}
+
 pub async fn user_extractor(
     req: actix_web::dev::ServiceRequest,
     next: actix_web::middleware::Next<impl actix_web::body::MessageBody>,
-) -> Result<actix_web::dev::ServiceResponse<impl actix_web::body::MessageBody>, actix_web::Error> {
+) -> Result<
+    actix_web::dev::ServiceResponse<actix_web::body::EitherBody<impl actix_web::body::MessageBody>>,
+    actix_web::Error,
+> {
     use actix_session::SessionExt;
     use actix_web::{HttpMessage, web::Data};
     use log::{debug, info, warn};
 
+    // Scripts send a token instead of the session cookie
+    let bearer = req
+        .headers()
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+    if let Some(secret) = bearer {
+        return match token_user(&req, &secret).await {
+            Ok(user) => {
+                debug!("Using API token user");
+                req.extensions_mut().insert(Data::new(user));
+                next.call(req).await.map(|res| res.map_into_left_body())
+            }
+            Err(response) => Ok(req.into_response(response).map_into_right_body()),
+        };
+    }
+
     let config = req.app_data::<Data<crate::config::Server>>().unwrap();
     let session = req.get_session();
 
     let user: Option<crate::user::User> = if config.fake_user() {
-        let u = crate::user::User::new("0".to_string(), "guest@gmx.com".to_string());
+        let mut u = crate::user::User::new("0".to_string(), "guest@gmx.com".to_string());
+        u.role = Role::for_login(&[], None, u.email(), true, config.admin_emails());
         warn!("Using fake user");
         Some(u)
     } else if let Some(oidc_user) = get_user_from_session(&session) {
-        let u = crate::user::User::new(oidc_user.sub, oidc_user.email);
-        debug!("Using OIDC user");
-        Some(u)
+        let db = req.app_data::<Data<DBClient>>().unwrap();
+        let providers = req.app_data::<Data<OidcProviders>>().unwrap();
+        match check_auth_session(&req, &session, db, providers).await {
+            SessionCheck::Valid(user_id) => {
+                match crate::database::users::get_user(db, &user_id).await {
+                    Ok(Some(record)) if record.is_disabled() => {
+                        info!("Ending session of disabled user");
+                        session.purge();
+                        None
+                    }
+                    Ok(Some(record)) => {
+                        debug!("Using OIDC user");
+                        Some(record.into())
+                    }
+                    // Logged in before the users table existed
+                    Ok(None) => Some(crate::user::User::new(user_id, oidc_user.email)),
+                    // Without the record a disabled account would get in
+                    Err(_) => {
+                        warn!("Could not check the account, treating request as anonymous");
+                        None
+                    }
+                }
+            }
+            SessionCheck::Invalid => {
+                session.purge();
+                None
+            }
+            SessionCheck::Unavailable => {
+                warn!("Could not check session, treating request as anonymous");
+                None
+            }
+        }
     } else {
         info!("No user found, anonymous");
         None
@@ -266,5 +1161,769 @@ pub async fn user_extractor(
     if let Some(user) = user {
         req.extensions_mut().insert(Data::new(user));
     }
-    next.call(req).await
+    next.call(req).await.map(|res| res.map_into_left_body())
+}
+
+/// Keeps everyone but admins out of the admin scope it wraps. Runs inside
+/// `user_extractor`, which puts the user on the request.
+pub async fn admin_guard(
+    req: actix_web::dev::ServiceRequest,
+    next: actix_web::middleware::Next<impl actix_web::body::MessageBody>,
+) -> Result<
+    actix_web::dev::ServiceResponse<actix_web::body::EitherBody<impl actix_web::body::MessageBody>>,
+    actix_web::Error,
+> {
+    match crate::routes::get_user_or_redirect(req.request()) {
+        Ok(user) if user.is_admin() => next.call(req).await.map(|res| res.map_into_left_body()),
+        Ok(user) => {
+            info!("Refused {} to non-admin {}", req.path(), user.id());
+            let response = crate::routes::admin::forbidden();
+            Ok(req.into_response(response).map_into_right_body())
+        }
+        Err(response) => Ok(req.into_response(response).map_into_right_body()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, HttpServer, web};
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    const CLIENT_ID: &str = "rezi-test";
+
+    fn test_key(n: u8) -> (EncodingKey, serde_json::Value) {
+        let pem = match n {
+            1 => include_str!("../testdata/oidc/test_key_1.pem"),
+            _ => include_str!("../testdata/oidc/test_key_2.pem"),
+        };
+        let jwk = match n {
+            1 => include_str!("../testdata/oidc/test_key_1.jwk.json"),
+            _ => include_str!("../testdata/oidc/test_key_2.jwk.json"),
+        };
+        (
+            EncodingKey::from_rsa_pem(pem.as_bytes()).unwrap(),
+            serde_json::from_str(jwk).unwrap(),
+        )
+    }
+
+    /// Stand-in for an identity provider serving discovery and a JWKS that
+    /// the test can rotate.
+    struct TestIssuer {
+        url: String,
+        keys: Arc<Mutex<Vec<serde_json::Value>>>,
+        jwks_fetches: Arc<AtomicUsize>,
+        token_requests: Arc<AtomicUsize>,
+        /// Status the token endpoint answers with.
+        token_status: Arc<AtomicUsize>,
+    }
+
+    impl TestIssuer {
+        async fn start(keys: Vec<serde_json::Value>) -> TestIssuer {
+            let keys = Arc::new(Mutex::new(keys));
+            let jwks_fetches = Arc::new(AtomicUsize::new(0));
+            let token_requests = Arc::new(AtomicUsize::new(0));
+            let token_status = Arc::new(AtomicUsize::new(200));
+            let url = Arc::new(Mutex::new(String::new()));
+
+            let state = (
+                keys.clone(),
+                jwks_fetches.clone(),
+                token_requests.clone(),
+                token_status.clone(),
+                url.clone(),
+            );
+            let server = HttpServer::new(move || {
+                let (keys, fetches, token_requests, token_status, url) = state.clone();
+                App::new()
+                    .route(
+                        "/.well-known/openid-configuration",
+                        web::get().to(move || {
+                            let issuer = url.lock().unwrap().clone();
+                            async move {
+                                web::Json(serde_json::json!({
+                                    "issuer": issuer,
+                                    "authorization_endpoint": format!("{issuer}/authorize"),
+                                    "token_endpoint": format!("{issuer}/token"),
+                                    "userinfo_endpoint": format!("{issuer}/userinfo"),
+                                    "jwks_uri": format!("{issuer}/jwks"),
+                                    "end_session_endpoint": format!("{issuer}/logout?tenant=1"),
+                                }))
+                            }
+                        }),
+                    )
+                    .route(
+                        "/jwks",
+                        web::get().to(move || {
+                            fetches.fetch_add(1, Ordering::SeqCst);
+                            let keys = keys.lock().unwrap().clone();
+                            async move { web::Json(serde_json::json!({ "keys": keys })) }
+                        }),
+                    )
+                    .route(
+                        "/token",
+                        web::post().to(move || {
+                            let count = token_requests.fetch_add(1, Ordering::SeqCst) + 1;
+                            let status = token_status.load(Ordering::SeqCst) as u16;
+                            async move {
+                                // Slow enough for concurrent refreshes to overlap
+                                actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+                                let status = StatusCode::from_u16(status).unwrap();
+                                if status != StatusCode::OK {
+                                    return HttpResponse::build(status)
+                                        .json(serde_json::json!({ "error": "invalid_grant" }));
+                                }
+                                HttpResponse::Ok().json(serde_json::json!({
+                                    "access_token": format!("access-{count}"),
+                                    "token_type": "Bearer",
+                                    "expires_in": 7200,
+                                    "refresh_token": format!("refresh-{count}"),
+                                }))
+                            }
+                        }),
+                    )
+            })
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .unwrap();
+
+            let address = server.addrs()[0];
+            *url.lock().unwrap() = format!("http://{address}");
+            actix_web::rt::spawn(server.run());
+
+            TestIssuer {
+                url: format!("http://{address}"),
+                keys,
+                jwks_fetches,
+                token_requests,
+                token_status,
+            }
+        }
+
+        async fn client(&self) -> OidcClient {
+            let mut client = OidcClient::new(OidcConfig {
+                id: "test".to_string(),
+                name: "Test".to_string(),
+                client_id: CLIENT_ID.to_string(),
+                client_secret: "secret".to_string(),
+                issuer_url: self.url.clone(),
+                redirect_uri: "http://localhost/auth/callback".to_string(),
+                scopes: "openid email".to_string(),
+                access_token_lifetime: 3600,
+                post_logout_redirect_uri: "http://localhost/".to_string(),
+                groups_claim: "groups".to_string(),
+                admin_group: None,
+            });
+            client.discover().await.unwrap();
+            client
+        }
+
+        fn sign(&self, key: u8, claims: serde_json::Value) -> String {
+            let (encoding_key, jwk) = test_key(key);
+            let mut header = Header::new(Algorithm::RS256);
+            header.kid = jwk["kid"].as_str().map(str::to_string);
+            jsonwebtoken::encode(&header, &claims, &encoding_key).unwrap()
+        }
+
+        fn claims(&self, nonce: &str) -> serde_json::Value {
+            serde_json::json!({
+                "iss": self.url,
+                "sub": "user-1",
+                "aud": CLIENT_ID,
+                "exp": chrono::Utc::now().timestamp() + 300,
+                "iat": chrono::Utc::now().timestamp(),
+                "nonce": nonce,
+                "email": "user@example.com",
+            })
+        }
+
+        fn logout_claims(&self, jti: &str) -> serde_json::Value {
+            serde_json::json!({
+                "iss": self.url,
+                "sub": "user-1",
+                "sid": "provider-session-1",
+                "aud": CLIENT_ID,
+                "exp": chrono::Utc::now().timestamp() + 120,
+                "iat": chrono::Utc::now().timestamp(),
+                "jti": jti,
+                "events": { BACKCHANNEL_LOGOUT_EVENT: {} },
+            })
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_verify_id_token() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        let mut client = issuer.client().await;
+
+        let token = issuer.sign(1, issuer.claims("nonce-1"));
+        let claims = client.verify_id_token(&token, "nonce-1").await.unwrap();
+        assert_eq!(claims.sub, "user-1");
+        assert_eq!(claims.email.as_deref(), Some("user@example.com"));
+
+        // The keys are cached for the next login
+        let token = issuer.sign(1, issuer.claims("nonce-2"));
+        client.verify_id_token(&token, "nonce-2").await.unwrap();
+        assert_eq!(issuer.jwks_fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_rejects_invalid_claims() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        let mut client = issuer.client().await;
+
+        let token = issuer.sign(1, issuer.claims("nonce-1"));
+        assert!(client.verify_id_token(&token, "other").await.is_err());
+
+        let mut claims = issuer.claims("nonce-1");
+        claims["aud"] = "another-client".into();
+        let token = issuer.sign(1, claims);
+        assert!(client.verify_id_token(&token, "nonce-1").await.is_err());
+
+        let mut claims = issuer.claims("nonce-1");
+        claims["iss"] = "https://evil.example".into();
+        let token = issuer.sign(1, claims);
+        assert!(client.verify_id_token(&token, "nonce-1").await.is_err());
+
+        let mut claims = issuer.claims("nonce-1");
+        claims["exp"] = (chrono::Utc::now().timestamp() - 3600).into();
+        let token = issuer.sign(1, claims);
+        assert!(client.verify_id_token(&token, "nonce-1").await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_rejects_unknown_or_symmetric_keys() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        let mut client = issuer.client().await;
+
+        // Signed with a key the issuer does not publish
+        let token = issuer.sign(2, issuer.claims("nonce-1"));
+        assert!(client.verify_id_token(&token, "nonce-1").await.is_err());
+
+        let token = jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &issuer.claims("nonce-1"),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        assert!(client.verify_id_token(&token, "nonce-1").await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_follows_key_rotation() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        let mut client = issuer.client().await;
+
+        let token = issuer.sign(1, issuer.claims("nonce-1"));
+        client.verify_id_token(&token, "nonce-1").await.unwrap();
+
+        // The provider rotates to a new key, a token with the new key id
+        // triggers a refetch once the minimum refresh interval passed
+        *issuer.keys.lock().unwrap() = vec![test_key(2).1];
+        client.jwks.as_mut().unwrap().fetched_at -= JWKS_MIN_REFRESH * 2;
+
+        let token = issuer.sign(2, issuer.claims("nonce-2"));
+        client.verify_id_token(&token, "nonce-2").await.unwrap();
+        assert_eq!(issuer.jwks_fetches.load(Ordering::SeqCst), 2);
+
+        // The old key is gone from the cache
+        let token = issuer.sign(1, issuer.claims("nonce-3"));
+        assert!(client.verify_id_token(&token, "nonce-3").await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_verify_logout_token() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        let mut client = issuer.client().await;
+
+        let token = issuer.sign(1, issuer.logout_claims("jti-1"));
+        let claims = client.verify_logout_token(&token).await.unwrap();
+        assert_eq!(claims.sub.as_deref(), Some("user-1"));
+        assert_eq!(claims.sid.as_deref(), Some("provider-session-1"));
+
+        // Replayed tokens are refused
+        assert!(client.verify_logout_token(&token).await.is_err());
+
+        // Only a sid is enough
+        let mut claims = issuer.logout_claims("jti-2");
+        claims.as_object_mut().unwrap().remove("sub");
+        let token = issuer.sign(1, claims);
+        client.verify_logout_token(&token).await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_rejects_invalid_logout_tokens() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        let mut client = issuer.client().await;
+
+        // An ID token is not a logout token
+        let token = issuer.sign(1, issuer.claims("nonce-1"));
+        assert!(client.verify_logout_token(&token).await.is_err());
+
+        let mut claims = issuer.logout_claims("jti-1");
+        claims["events"] = serde_json::json!({});
+        assert!(
+            client
+                .verify_logout_token(&issuer.sign(1, claims))
+                .await
+                .is_err()
+        );
+
+        let mut claims = issuer.logout_claims("jti-2");
+        claims["nonce"] = "nonce-1".into();
+        assert!(
+            client
+                .verify_logout_token(&issuer.sign(1, claims))
+                .await
+                .is_err()
+        );
+
+        let mut claims = issuer.logout_claims("jti-3");
+        claims.as_object_mut().unwrap().remove("sub");
+        claims.as_object_mut().unwrap().remove("sid");
+        assert!(
+            client
+                .verify_logout_token(&issuer.sign(1, claims))
+                .await
+                .is_err()
+        );
+
+        let mut claims = issuer.logout_claims("jti-4");
+        claims["aud"] = "another-client".into();
+        assert!(
+            client
+                .verify_logout_token(&issuer.sign(1, claims))
+                .await
+                .is_err()
+        );
+
+        // Signed with a key the issuer does not publish
+        let token = issuer.sign(2, issuer.logout_claims("jti-5"));
+        assert!(client.verify_logout_token(&token).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_end_session_url() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        let client = issuer.client().await;
+
+        let url = url::Url::parse(&client.end_session_url(Some("id-token")).unwrap()).unwrap();
+        assert_eq!(url.path(), "/logout");
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(query["tenant"], "1");
+        assert_eq!(query["id_token_hint"], "id-token");
+        assert_eq!(query["client_id"], CLIENT_ID);
+        assert_eq!(query["post_logout_redirect_uri"], "http://localhost/");
+
+        let url = client.end_session_url(None).unwrap();
+        assert!(!url.contains("id_token_hint"));
+
+        // Providers without RP-initiated logout only get the local logout
+        let mut client = issuer.client().await;
+        client.discovery.as_mut().unwrap().end_session_endpoint = None;
+        assert!(client.end_session_url(Some("id-token")).is_none());
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn test_single_provider_config() {
+        let configs = OidcConfig::from_vars(vars(&[
+            ("OIDC_CLIENT_ID", "rezi"),
+            ("OIDC_ISSUER_URL", "https://login.example"),
+            ("OIDC_REDIRECT_URI", "https://rezi.example/auth/callback"),
+        ]));
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].id, "default");
+        assert_eq!(configs[0].name, "OpenID Connect");
+        assert_eq!(configs[0].client_id, "rezi");
+        assert_eq!(configs[0].issuer_url, "https://login.example");
+        assert_eq!(configs[0].post_logout_redirect_uri, "https://rezi.example/");
+        assert_eq!(configs[0].groups_claim, "groups");
+        assert_eq!(configs[0].admin_group, None);
+    }
+
+    #[test]
+    fn test_multiple_provider_config() {
+        let configs = OidcConfig::from_vars(vars(&[
+            ("OIDC_PROVIDERS", "google, our-keycloak,"),
+            ("OIDC_CLIENT_ID", "ignored"),
+            ("OIDC_GOOGLE_CLIENT_ID", "google-client"),
+            ("OIDC_OUR_KEYCLOAK_NAME", "our Keycloak"),
+            (
+                "OIDC_OUR_KEYCLOAK_ISSUER_URL",
+                "https://sso.example/realms/rezi",
+            ),
+            ("OIDC_OUR_KEYCLOAK_SCOPES", "openid email"),
+            ("OIDC_OUR_KEYCLOAK_ADMIN_GROUP", "rezi-admins"),
+            ("OIDC_OUR_KEYCLOAK_GROUPS_CLAIM", "roles"),
+        ]));
+        assert_eq!(configs.len(), 2);
+
+        assert_eq!(configs[0].id, "google");
+        assert_eq!(configs[0].name, "Google");
+        assert_eq!(configs[0].client_id, "google-client");
+        assert_eq!(
+            configs[0].redirect_uri,
+            "http://localhost:3000/auth/google/callback"
+        );
+
+        assert_eq!(configs[1].id, "our-keycloak");
+        assert_eq!(configs[1].name, "our Keycloak");
+        assert_eq!(configs[1].client_id, "default-client-id");
+        assert_eq!(configs[1].issuer_url, "https://sso.example/realms/rezi");
+        assert_eq!(configs[1].scopes, "openid email");
+        assert_eq!(configs[1].admin_group.as_deref(), Some("rezi-admins"));
+        assert_eq!(configs[1].groups_claim, "roles");
+    }
+
+    #[test]
+    fn test_groups_claim() {
+        let info: UserInfo = serde_json::from_value(serde_json::json!({
+            "sub": "1",
+            "email": "user@example.com",
+            "groups": ["staff", "rezi-admins", 3],
+            "department": "kitchen",
+        }))
+        .unwrap();
+        assert_eq!(
+            claim_groups(info.other.get("groups")),
+            vec!["staff", "rezi-admins"]
+        );
+        assert_eq!(claim_groups(info.other.get("department")), vec!["kitchen"]);
+        assert!(claim_groups(info.other.get("roles")).is_empty());
+
+        // Extra claims stay out of the session cookie
+        let stored = serde_json::to_value(&info).unwrap();
+        assert!(stored.get("groups").is_none());
+    }
+
+    #[test]
+    fn test_email_verified_as_string() {
+        let info: UserInfo = serde_json::from_value(serde_json::json!({
+            "sub": "1",
+            "email": "user@example.com",
+            "email_verified": "true",
+        }))
+        .unwrap();
+        assert_eq!(info.email_verified, Some(true));
+
+        let info: UserInfo = serde_json::from_value(serde_json::json!({
+            "sub": "1",
+            "email": "user@example.com",
+            "email_verified": false,
+        }))
+        .unwrap();
+        assert_eq!(info.email_verified, Some(false));
+
+        let info: UserInfo =
+            serde_json::from_value(serde_json::json!({ "sub": "1", "email": "user@example.com" }))
+                .unwrap();
+        assert_eq!(info.email_verified, None);
+    }
+
+    async fn test_provider(issuer: &TestIssuer) -> OidcProvider {
+        OidcProvider {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            client: Arc::new(tokio::sync::Mutex::new(issuer.client().await)),
+            refreshing: SessionLocks::default(),
+        }
+    }
+
+    /// A stored session whose access token expires in `expires_in`.
+    async fn stored_session(
+        db: &DBClient,
+        expires_in: chrono::Duration,
+        refresh_token: Option<&str>,
+    ) -> String {
+        let session = auth_sessions::create_auth_session(
+            db,
+            auth_sessions::AuthSession::new(
+                "1".to_string(),
+                "test".to_string(),
+                "access-0".to_string(),
+                refresh_token.map(str::to_string),
+                None,
+                None,
+                chrono::Utc::now() + expires_in,
+            ),
+        )
+        .await
+        .unwrap();
+        session.session_id().to_string()
+    }
+
+    #[actix_web::test]
+    async fn test_refreshes_a_session_once() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        let provider = test_provider(&issuer).await;
+        let db = crate::database::test_client().await;
+        let session_id =
+            &stored_session(&db, -chrono::Duration::minutes(1), Some("refresh-0")).await;
+
+        let (first, second, client_free) = tokio::join!(
+            refresh_auth_session(&db, &provider, session_id),
+            refresh_auth_session(&db, &provider, session_id),
+            async {
+                actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+                provider.client.try_lock().is_ok()
+            }
+        );
+
+        assert!(first && second);
+        assert!(client_free, "the provider stays usable during a refresh");
+        assert_eq!(issuer.token_requests.load(Ordering::SeqCst), 1);
+        let refreshed = auth_sessions::get_auth_session(&db, session_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(refreshed.access_token, "access-1");
+        assert_eq!(refreshed.refresh_token.as_deref(), Some("refresh-1"));
+        // The configured lifetime caps what the provider grants
+        assert!(refreshed.expires_at <= chrono::Utc::now() + chrono::Duration::seconds(3600));
+        assert!(provider.refreshing.0.lock().unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_refresh_skips_fresh_sessions() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        let provider = test_provider(&issuer).await;
+        let db = crate::database::test_client().await;
+        let session_id = stored_session(&db, chrono::Duration::hours(1), Some("refresh-0")).await;
+
+        assert!(refresh_auth_session(&db, &provider, &session_id).await);
+        assert_eq!(issuer.token_requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_rejected_refresh_revokes_session() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        issuer.token_status.store(400, Ordering::SeqCst);
+        let provider = test_provider(&issuer).await;
+        let db = crate::database::test_client().await;
+        let session_id =
+            stored_session(&db, -chrono::Duration::minutes(1), Some("refresh-0")).await;
+
+        assert!(!refresh_auth_session(&db, &provider, &session_id).await);
+        let stored = auth_sessions::get_auth_session(&db, &session_id)
+            .await
+            .unwrap();
+        assert!(stored.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_provider_outage_keeps_session() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        issuer.token_status.store(503, Ordering::SeqCst);
+        let provider = test_provider(&issuer).await;
+        let db = crate::database::test_client().await;
+        let session_id =
+            stored_session(&db, -chrono::Duration::minutes(1), Some("refresh-0")).await;
+
+        assert!(refresh_auth_session(&db, &provider, &session_id).await);
+        let stored = auth_sessions::get_auth_session(&db, &session_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.access_token, "access-0");
+    }
+
+    #[actix_web::test]
+    async fn test_expired_session_without_refresh_token_is_revoked() {
+        let issuer = TestIssuer::start(vec![test_key(1).1]).await;
+        let provider = test_provider(&issuer).await;
+        let db = crate::database::test_client().await;
+        let session_id = stored_session(&db, -chrono::Duration::minutes(1), None).await;
+
+        assert!(!refresh_auth_session(&db, &provider, &session_id).await);
+        let stored = auth_sessions::get_auth_session(&db, &session_id)
+            .await
+            .unwrap();
+        assert!(stored.is_none());
+        assert_eq!(issuer.token_requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_token_user_fails_closed() {
+        use actix_web::middleware::from_fn;
+        use actix_web::test::{TestRequest, call_service, init_service};
+
+        let db = crate::database::test_client().await;
+        let (token, secret) = api_tokens::ApiToken::new(
+            "1".to_string(),
+            "script".to_string(),
+            &[ApiScope::ItemsRead],
+        );
+        api_tokens::create_api_token(&db, token).await.unwrap();
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .wrap(from_fn(user_extractor))
+                .route("/api/v1/items", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let request = || {
+            TestRequest::get()
+                .uri("/api/v1/items")
+                .insert_header(("Authorization", format!("Bearer {secret}")))
+                .to_request()
+        };
+
+        // The owner has no user record
+        let res = call_service(&app, request()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let info: UserInfo = serde_json::from_value(serde_json::json!({
+            "sub": "1",
+            "email": "a@example.com",
+        }))
+        .unwrap();
+        crate::database::users::upsert_user(&db, "1".to_string(), &info, Default::default())
+            .await
+            .unwrap();
+        let res = call_service(&app, request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // A failing user lookup must not skip the disabled check
+        crate::database::connection(&db)
+            .await
+            .get_connection()
+            .execute("DROP TABLE users", ())
+            .await
+            .unwrap();
+        let res = call_service(&app, request()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[actix_web::test]
+    async fn test_admin_guard() {
+        use actix_web::HttpMessage;
+        use actix_web::http::StatusCode;
+        use actix_web::middleware::{Next, from_fn};
+        use actix_web::test::{TestRequest, call_service, init_service};
+
+        // Stands in for `user_extractor`, the role comes from a header
+        async fn fake_user(
+            req: actix_web::dev::ServiceRequest,
+            next: Next<impl actix_web::body::MessageBody>,
+        ) -> Result<
+            actix_web::dev::ServiceResponse<impl actix_web::body::MessageBody>,
+            actix_web::Error,
+        > {
+            let role = req
+                .headers()
+                .get("x-role")
+                .map(|role| role.to_str().unwrap().to_string());
+            if let Some(role) = role {
+                let mut user = crate::user::User::new("1".to_string(), "a@example.com".to_string());
+                user.role = serde_json::from_value(serde_json::json!(role)).unwrap();
+                req.extensions_mut().insert(web::Data::new(user));
+            }
+            next.call(req).await
+        }
+
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(fake_user))
+                .service(
+                    web::scope("/admin")
+                        .wrap(from_fn(admin_guard))
+                        .route("", web::get().to(HttpResponse::Ok))
+                        .route("/users", web::get().to(HttpResponse::Ok))
+                        .route("/users/{id}/disable", web::post().to(HttpResponse::Ok)),
+                )
+                .route("/administer", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let request = |role: Option<&str>, path: &str| {
+            let mut req = TestRequest::get().uri(path);
+            if let Some(role) = role {
+                req = req.insert_header(("x-role", role));
+            }
+            req.to_request()
+        };
+
+        let res = call_service(&app, request(Some("admin"), "/admin/users")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = call_service(&app, request(Some("member"), "/admin")).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = call_service(&app, request(None, "/admin/users")).await;
+        assert_eq!(res.status(), StatusCode::FOUND);
+        let res = call_service(&app, request(Some("member"), "/administer")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Routing decodes the path, the guard must not be bypassed by encoding
+        let res = call_service(&app, request(Some("member"), "/%61dmin")).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/%61dmin/users/1/disable")
+                .insert_header(("x-role", "member"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_required_scope() {
+        use actix_web::http::Method;
+
+        assert_eq!(
+            required_scope(&Method::GET, "/export/items/csv"),
+            Some(ApiScope::ItemsRead)
+        );
+        assert_eq!(required_scope(&Method::POST, "/export/items/csv"), None);
+        assert_eq!(
+            required_scope(&Method::GET, "/api/v1/items"),
+            Some(ApiScope::ItemsRead)
+        );
+        assert_eq!(
+            required_scope(&Method::DELETE, "/api/v1/recipes/7"),
+            Some(ApiScope::RecipesWrite)
+        );
+        assert_eq!(
+            required_scope(&Method::GET, "/api/v1/recipes"),
+            Some(ApiScope::RecipesRead)
+        );
+
+        // The htmx pages are for browser sessions only
+        assert_eq!(required_scope(&Method::GET, "/items"), None);
+        assert_eq!(required_scope(&Method::POST, "/items/single"), None);
+        assert_eq!(required_scope(&Method::POST, "/items/3/pantry"), None);
+        assert_eq!(required_scope(&Method::DELETE, "/recipes/7"), None);
+        assert_eq!(required_scope(&Method::GET, "/lists"), None);
+
+        assert_eq!(
+            required_scope(&Method::GET, "/api/v1/lists"),
+            Some(ApiScope::ItemsRead)
+        );
+        assert_eq!(
+            required_scope(&Method::PATCH, "/api/v1/items/3"),
+            Some(ApiScope::ItemsWrite)
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/api/v1/recipes/imports"),
+            Some(ApiScope::RecipesWrite)
+        );
+        assert_eq!(required_scope(&Method::GET, "/api/v1items"), None);
+
+        // Tokens must not be able to manage tokens or sessions
+        assert_eq!(required_scope(&Method::GET, "/profile"), None);
+        assert_eq!(required_scope(&Method::POST, "/profile/tokens"), None);
+        assert_eq!(required_scope(&Method::GET, "/itemsfoo"), None);
+        assert_eq!(required_scope(&Method::GET, "/"), None);
+    }
 }
@@ -0,0 +1,282 @@
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    System,
+    ExtractRecipe,
+    GenerateTitle,
+    GroceryList,
+}
+
+impl PromptKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PromptKind::System => "system",
+            PromptKind::ExtractRecipe => "extract_recipe",
+            PromptKind::GenerateTitle => "generate_title",
+            PromptKind::GroceryList => "grocery_list",
+        }
+    }
+
+    fn file_name(&self) -> String {
+        format!("{}.txt", self.name())
+    }
+
+    fn builtin(&self) -> &'static str {
+        match self {
+            PromptKind::System => include_str!("../prompts/system.txt"),
+            PromptKind::ExtractRecipe => include_str!("../prompts/extract_recipe.txt"),
+            PromptKind::GenerateTitle => include_str!("../prompts/generate_title.txt"),
+            PromptKind::GroceryList => include_str!("../prompts/grocery_list.txt"),
+        }
+    }
+
+    /// Placeholders a template of this kind must use, and the only ones it may use.
+    fn placeholders(&self) -> &'static [&'static str] {
+        match self {
+            PromptKind::System => &[],
+            PromptKind::ExtractRecipe | PromptKind::GenerateTitle | PromptKind::GroceryList => {
+                &["content"]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    kind: PromptKind,
+    version: String,
+    body: String,
+}
+
+impl PromptTemplate {
+    /// Parses a template file: a `---` delimited header with a `version`
+    /// followed by the prompt text with `{{placeholder}}` markers. Files
+    /// saved with Windows line endings are accepted as well.
+    pub fn parse(kind: PromptKind, source: &str) -> Result<Self, String> {
+        let name = kind.name();
+        let source = source.replace("\r\n", "\n");
+        let rest = source
+            .strip_prefix("---\n")
+            .ok_or_else(|| format!("prompt {name}: missing `---` header"))?;
+        let (header, body) = rest
+            .split_once("\n---\n")
+            .ok_or_else(|| format!("prompt {name}: unterminated header"))?;
+
+        let version = header
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim() == "version")
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| format!("prompt {name}: header has no version"))?;
+
+        let body = body.trim().to_string();
+        let used = placeholders(&body);
+        for expected in kind.placeholders() {
+            if !used.contains(expected) {
+                return Err(format!(
+                    "prompt {name}: missing placeholder {{{{{expected}}}}}"
+                ));
+            }
+        }
+        if let Some(unknown) = used.iter().find(|p| !kind.placeholders().contains(p)) {
+            return Err(format!(
+                "prompt {name}: unknown placeholder {{{{{unknown}}}}}"
+            ));
+        }
+
+        Ok(PromptTemplate {
+            kind,
+            version,
+            body,
+        })
+    }
+
+    /// Identifies the template and version, e.g. `extract_recipe@1`.
+    pub fn tag(&self) -> String {
+        format!("{}@{}", self.kind.name(), self.version)
+    }
+
+    pub fn render(&self, values: &[(&str, &str)]) -> String {
+        let mut rendered = String::with_capacity(self.body.len());
+        let mut rest = self.body.as_str();
+
+        while let Some(start) = rest.find("{{") {
+            rendered.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            match after.find("}}") {
+                Some(end) => {
+                    let key = after[..end].trim();
+                    match values.iter().find(|(name, _)| *name == key) {
+                        Some((_, value)) => rendered.push_str(value),
+                        None => rendered.push_str(&rest[start..start + 2 + end + 2]),
+                    }
+                    rest = &after[end + 2..];
+                }
+                None => {
+                    rendered.push_str(&rest[start..]);
+                    rest = "";
+                }
+            }
+        }
+        rendered.push_str(rest);
+
+        rendered
+    }
+
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+}
+
+fn placeholders(body: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        found.push(after[..end].trim());
+        rest = &after[end + 2..];
+    }
+    found
+}
+
+/// The full set of prompts used by the LLM client.
+#[derive(Debug, Clone)]
+pub struct Prompts {
+    system: PromptTemplate,
+    extract_recipe: PromptTemplate,
+    generate_title: PromptTemplate,
+    grocery_list: PromptTemplate,
+}
+
+impl Prompts {
+    /// Loads the built-in templates, replacing each one that has a file of the
+    /// same name in `override_dir`.
+    pub fn load(override_dir: Option<&Path>) -> Result<Self, String> {
+        let load = |kind: PromptKind| -> Result<PromptTemplate, String> {
+            if let Some(dir) = override_dir {
+                let path = dir.join(kind.file_name());
+                if path.exists() {
+                    let source = std::fs::read_to_string(&path)
+                        .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+                    let template = PromptTemplate::parse(kind, &source)?;
+                    log::info!("using prompt {} from {}", template.tag(), path.display());
+                    return Ok(template);
+                }
+            }
+            PromptTemplate::parse(kind, kind.builtin())
+        };
+
+        Ok(Prompts {
+            system: load(PromptKind::System)?,
+            extract_recipe: load(PromptKind::ExtractRecipe)?,
+            generate_title: load(PromptKind::GenerateTitle)?,
+            grocery_list: load(PromptKind::GroceryList)?,
+        })
+    }
+
+    pub fn get(&self, kind: PromptKind) -> &PromptTemplate {
+        match kind {
+            PromptKind::System => &self.system,
+            PromptKind::ExtractRecipe => &self.extract_recipe,
+            PromptKind::GenerateTitle => &self.generate_title,
+            PromptKind::GroceryList => &self.grocery_list,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_prompts_parse() {
+        let prompts = Prompts::load(None).unwrap();
+        assert_eq!(prompts.get(PromptKind::System).tag(), "system@1");
+        assert_eq!(
+            prompts.get(PromptKind::ExtractRecipe).tag(),
+            "extract_recipe@1"
+        );
+        assert_eq!(
+            prompts.get(PromptKind::GenerateTitle).tag(),
+            "generate_title@1"
+        );
+        assert_eq!(prompts.get(PromptKind::GroceryList).tag(), "grocery_list@1");
+    }
+
+    #[test]
+    fn test_render_replaces_placeholders() {
+        let template = PromptTemplate::parse(
+            PromptKind::GroceryList,
+            "---\nversion: 2\n---\nList for {{ content }}: {\"items\": []}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            template.render(&[("content", "soup")]),
+            "List for soup: {\"items\": []}"
+        );
+    }
+
+    #[test]
+    fn test_parse_accepts_crlf() {
+        let template = PromptTemplate::parse(
+            PromptKind::GroceryList,
+            "---\r\nversion: 3\r\n---\r\nFirst line\r\nList for {{content}}\r\n",
+        )
+        .unwrap();
+        assert_eq!(template.tag(), "grocery_list@3");
+        assert_eq!(
+            template.render(&[("content", "soup")]),
+            "First line\nList for soup"
+        );
+    }
+
+    #[test]
+    fn test_parse_requires_version() {
+        let err =
+            PromptTemplate::parse(PromptKind::System, "---\nauthor: me\n---\nHi").unwrap_err();
+        assert!(err.contains("no version"));
+    }
+
+    #[test]
+    fn test_parse_checks_placeholders() {
+        let missing =
+            PromptTemplate::parse(PromptKind::GenerateTitle, "---\nversion: 1\n---\nTitle?");
+        assert!(missing.unwrap_err().contains("missing placeholder"));
+
+        let unknown = PromptTemplate::parse(
+            PromptKind::GenerateTitle,
+            "---\nversion: 1\n---\n{{content}} in {{language}}",
+        );
+        assert!(unknown.unwrap_err().contains("unknown placeholder"));
+    }
+
+    #[test]
+    fn test_override_dir_replaces_single_prompt() {
+        let dir = std::env::temp_dir().join(format!("rezi-prompts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("generate_title.txt"),
+            "---\nversion: de-2\n---\nGib diesem Rezept einen Titel: {{content}}",
+        )
+        .unwrap();
+
+        let prompts = Prompts::load(Some(&dir)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let title = prompts.get(PromptKind::GenerateTitle);
+        assert_eq!(title.tag(), "generate_title@de-2");
+        assert_eq!(
+            title.render(&[("content", "Kürbis")]),
+            "Gib diesem Rezept einen Titel: Kürbis"
+        );
+        assert_eq!(
+            prompts.get(PromptKind::ExtractRecipe).tag(),
+            "extract_recipe@1"
+        );
+    }
+}
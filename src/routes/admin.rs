@@ -0,0 +1,172 @@
+//! Admin area. Access is checked by `oidc::admin_guard`, which wraps the
+//! whole scope, and again by every handler.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::middleware::from_fn;
+use actix_web::{HttpRequest, HttpResponse, Result, Scope, get, post, web};
+
+use crate::database::jobs::JobStatus;
+use crate::database::{self, DBClient};
+use crate::user::User;
+use crate::view::{self, admin::Overview, index};
+
+/// Jobs shown per table.
+const JOB_LIMIT: u32 = 50;
+/// Days covered by the LLM usage numbers.
+const USAGE_DAYS: i64 = 30;
+
+/// Everything below `/admin`. The guard runs on the routed path, so
+/// percent-encoded variants of the prefix are checked as well.
+pub fn scope() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/admin")
+        .wrap(from_fn(crate::oidc::admin_guard))
+        .service(admin_endpoint)
+        .service(disable_user)
+        .service(enable_user)
+}
+
+/// Response for signed in users without the admin role.
+pub fn forbidden() -> HttpResponse {
+    HttpResponse::Forbidden()
+        .content_type("text/html; charset=utf-8")
+        .body(r#"<div class="alert alert-error">Only admins can open this page.</div>"#)
+}
+
+/// The signed in admin, in case a handler is ever reached without the guard.
+#[allow(clippy::result_large_err)]
+fn get_admin_or_refuse(req: &HttpRequest) -> Result<User, HttpResponse> {
+    let user = crate::routes::get_user_or_redirect(req)?;
+    if !user.is_admin() {
+        log::info!("Refused {} to non-admin {}", req.path(), user.id());
+        return Err(forbidden());
+    }
+    Ok(user)
+}
+
+#[get("")]
+pub async fn admin_endpoint(client: web::Data<DBClient>, req: HttpRequest) -> Result<HttpResponse> {
+    let user = match get_admin_or_refuse(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client = client.get_ref();
+
+    let since = chrono::Utc::now() - chrono::Duration::days(USAGE_DAYS);
+    let mut queue = database::jobs::get_jobs_with_status(client, JobStatus::Running, JOB_LIMIT)
+        .await
+        .unwrap_or_default();
+    queue.extend(
+        database::jobs::get_jobs_with_status(client, JobStatus::Queued, JOB_LIMIT)
+            .await
+            .unwrap_or_default(),
+    );
+
+    let overview = Overview {
+        users: database::users::get_users(client).await.unwrap_or_default(),
+        storage: database::admin::get_storage(client)
+            .await
+            .unwrap_or_default(),
+        usage: database::llm_calls::get_usage(client, since)
+            .await
+            .unwrap_or_default(),
+        queue,
+        failed: database::jobs::get_jobs_with_status(client, JobStatus::Dead, JOB_LIMIT)
+            .await
+            .unwrap_or_default(),
+        pool: client.metrics(),
+    };
+
+    let markup = index(
+        Some(view::admin::admin(&overview, user.id())),
+        false,
+        Some(&user),
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string()))
+}
+
+async fn set_disabled(
+    client: &DBClient,
+    req: &HttpRequest,
+    id: i64,
+    disabled: bool,
+) -> Result<HttpResponse> {
+    let user = match get_admin_or_refuse(req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let error_row = |err: &str| {
+        HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(format!(
+                r#"<tr id="admin-user-{id}"><td colspan="9" class="text-error">{err}</td></tr>"#
+            ))
+    };
+
+    // Admins cannot lock themselves out
+    if let Ok(Some(own)) = database::users::get_user(client, user.id()).await
+        && own.id() == id
+    {
+        return Ok(error_row("You cannot disable your own account"));
+    }
+
+    let record = match database::users::set_user_disabled(client, id, disabled).await {
+        Ok(record) => record,
+        Err(err) => return Ok(error_row(&err)),
+    };
+    log::info!(
+        "{} {} user {}",
+        user.id(),
+        if disabled { "disabled" } else { "enabled" },
+        record.user_id
+    );
+
+    let since = chrono::Utc::now() - chrono::Duration::days(USAGE_DAYS);
+    let storage = database::admin::get_storage(client)
+        .await
+        .unwrap_or_default();
+    let usage = database::llm_calls::get_usage(client, since)
+        .await
+        .unwrap_or_default();
+    let row = view::admin::user_row(
+        &record,
+        storage
+            .iter()
+            .find(|storage| storage.owner_id == record.user_id),
+        usage.iter().find(|usage| usage.owner_id == record.user_id),
+        false,
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(row.into_string()))
+}
+
+#[post("/users/{id}/disable")]
+pub async fn disable_user(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    set_disabled(client.get_ref(), &req, path.into_inner(), true).await
+}
+
+#[post("/users/{id}/enable")]
+pub async fn enable_user(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    set_disabled(client.get_ref(), &req, path.into_inner(), false).await
+}
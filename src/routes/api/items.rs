@@ -0,0 +1,295 @@
+use actix_web::http::StatusCode;
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, delete, get, patch, post, web};
+use libsql_orm::Pagination;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::{ApiError, ErrorBody, Page, api_user, check_if_match, json_response, page_size};
+use crate::database::items::{Item, ItemFilter};
+use crate::database::{self, DBClient};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ItemResponse {
+    pub id: i64,
+    pub task: String,
+    pub completed: bool,
+    /// `None` for the main shopping list.
+    pub list: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<Item> for ItemResponse {
+    fn from(item: Item) -> Self {
+        ItemResponse {
+            id: item.id(),
+            completed: item.completed(),
+            task: item.task,
+            list: item.list,
+            created_at: item.created_at,
+            updated_at: item.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ListResponse {
+    /// `None` for the main shopping list.
+    pub name: Option<String>,
+    pub items: usize,
+    pub open: usize,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ItemsQuery {
+    /// Only items of this list, an empty value selects the main list.
+    pub list: Option<String>,
+    pub completed: Option<bool>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateItemRequest {
+    pub task: String,
+    pub list: Option<String>,
+}
+
+/// Fields left out stay unchanged. An empty `list` moves the item to the
+/// main list.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateItemRequest {
+    pub task: Option<String>,
+    pub completed: Option<bool>,
+    pub list: Option<String>,
+}
+
+fn list_name(list: &str) -> Option<String> {
+    Some(list.trim())
+        .filter(|list| !list.is_empty())
+        .map(str::to_string)
+}
+
+fn task(task: &str) -> Result<String, ApiError> {
+    let task = task.trim();
+    if task.is_empty() {
+        return Err(ApiError::bad_request("task must not be empty"));
+    }
+    Ok(task.to_string())
+}
+
+async fn owned_item(client: &DBClient, id: i64, owner_id: &str) -> Result<Item, ApiError> {
+    database::items::get_item(client, id, owner_id.to_string())
+        .await
+        .map_err(ApiError::from_lookup)
+}
+
+/// The main list and every named list with how many items are on it.
+#[utoipa::path(
+    tag = "lists",
+    responses((status = 200, description = "All lists", body = [ListResponse])),
+    security(("bearer" = ["items:read"]), ("session" = [])),
+)]
+#[get("/lists")]
+pub async fn list_lists(
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    let items = database::items::get_items(client.get_ref(), user.id().to_string())
+        .await
+        .map_err(|_| ApiError::internal())?;
+
+    let names =
+        std::iter::once(None).chain(database::items::list_names(&items).into_iter().map(Some));
+    let lists: Vec<ListResponse> = names
+        .map(|name| {
+            let on_list: Vec<&Item> = items.iter().filter(|item| item.list == name).collect();
+            ListResponse {
+                items: on_list.len(),
+                open: on_list.iter().filter(|item| !item.completed()).count(),
+                name,
+            }
+        })
+        .collect();
+
+    Ok(json_response(&req, StatusCode::OK, &lists))
+}
+
+/// Items, oldest first.
+#[utoipa::path(
+    tag = "items",
+    params(ItemsQuery),
+    responses((status = 200, description = "One page of items", body = Page<ItemResponse>)),
+    security(("bearer" = ["items:read"]), ("session" = [])),
+)]
+#[get("/items")]
+pub async fn list_items(
+    query: web::Query<ItemsQuery>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    let query = query.into_inner();
+
+    let filter = ItemFilter {
+        list: query.list.as_deref().map(list_name),
+        completed: query.completed,
+    };
+    let pagination = Pagination::new(query.page.unwrap_or(1).max(1), page_size(query.per_page));
+
+    let page = database::items::get_items_page(
+        client.get_ref(),
+        user.id().to_string(),
+        &filter,
+        &pagination,
+    )
+    .await
+    .map_err(|_| ApiError::internal())?;
+
+    Ok(json_response(
+        &req,
+        StatusCode::OK,
+        &page.map(ItemResponse::from),
+    ))
+}
+
+/// Adds an item to the main list or a named list.
+#[utoipa::path(
+    tag = "items",
+    request_body = CreateItemRequest,
+    responses(
+        (status = 201, description = "The new item", body = ItemResponse),
+        (status = 400, description = "Invalid item", body = ErrorBody),
+    ),
+    security(("bearer" = ["items:write"]), ("session" = [])),
+)]
+#[post("/items")]
+pub async fn create_item(
+    body: web::Json<CreateItemRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+
+    let item = Item {
+        id: None,
+        owner_id: user.id().to_string(),
+        task: task(&body.task)?,
+        completed: 0,
+        list: body.list.as_deref().and_then(list_name),
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    };
+    let item = database::items::create_item(client.get_ref(), item)
+        .await
+        .map_err(|_| ApiError::internal())?;
+
+    let location = format!("/api/v1/items/{}", item.id());
+    let mut response = json_response(&req, StatusCode::CREATED, &ItemResponse::from(item));
+    if let Ok(location) = header::HeaderValue::from_str(&location) {
+        response.headers_mut().insert(header::LOCATION, location);
+    }
+    Ok(response)
+}
+
+#[utoipa::path(
+    tag = "items",
+    params(("id" = i64, Path, description = "Item id")),
+    responses(
+        (status = 200, description = "The item", body = ItemResponse),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 404, description = "No such item", body = ErrorBody),
+    ),
+    security(("bearer" = ["items:read"]), ("session" = [])),
+)]
+#[get("/items/{id}")]
+pub async fn get_item(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    let item = owned_item(client.get_ref(), path.into_inner(), user.id()).await?;
+
+    Ok(json_response(
+        &req,
+        StatusCode::OK,
+        &ItemResponse::from(item),
+    ))
+}
+
+/// Changes the task, marks the item done or moves it to another list.
+#[utoipa::path(
+    tag = "items",
+    params(("id" = i64, Path, description = "Item id")),
+    request_body = UpdateItemRequest,
+    responses(
+        (status = 200, description = "The changed item", body = ItemResponse),
+        (status = 400, description = "Invalid change", body = ErrorBody),
+        (status = 404, description = "No such item", body = ErrorBody),
+        (status = 412, description = "Changed since the ETag in `If-Match`", body = ErrorBody),
+    ),
+    security(("bearer" = ["items:write"]), ("session" = [])),
+)]
+#[patch("/items/{id}")]
+pub async fn update_item(
+    path: web::Path<i64>,
+    body: web::Json<UpdateItemRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    let client = client.get_ref();
+    let mut item = owned_item(client, path.into_inner(), user.id()).await?;
+    check_if_match(&req, &ItemResponse::from(item.clone()))?;
+
+    let body = body.into_inner();
+    if let Some(new_task) = body.task.as_deref() {
+        item.update_task(&task(new_task)?);
+    }
+    if let Some(completed) = body.completed {
+        item.completed = completed as u16;
+    }
+    if let Some(list) = body.list.as_deref() {
+        item.list = list_name(list);
+    }
+
+    let item = database::items::save_item(client, item)
+        .await
+        .map_err(|_| ApiError::internal())?;
+    Ok(json_response(
+        &req,
+        StatusCode::OK,
+        &ItemResponse::from(item),
+    ))
+}
+
+#[utoipa::path(
+    tag = "items",
+    params(("id" = i64, Path, description = "Item id")),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 404, description = "No such item", body = ErrorBody),
+        (status = 412, description = "Changed since the ETag in `If-Match`", body = ErrorBody),
+    ),
+    security(("bearer" = ["items:write"]), ("session" = [])),
+)]
+#[delete("/items/{id}")]
+pub async fn delete_item(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    let client = client.get_ref();
+    let item = owned_item(client, path.into_inner(), user.id()).await?;
+    check_if_match(&req, &ItemResponse::from(item.clone()))?;
+
+    database::items::delete_item(client, item.id(), user.id().to_string())
+        .await
+        .map_err(ApiError::from_lookup)?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,355 @@
+//! JSON API for scripts and apps, mounted under `/api/v1`.
+//!
+//! Requests are authenticated with the session cookie or an API token.
+//! Errors always come as `{"error": {"code": "...", "message": "..."}}`.
+
+use actix_web::http::StatusCode;
+use actix_web::http::header::{self, EntityTag, Header, IfMatch, IfNoneMatch};
+use actix_web::{HttpRequest, HttpResponse, ResponseError, Scope, web};
+use libsql_orm::{CursorPagination, PaginatedResult, Pagination};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::user::User;
+
+pub mod items;
+pub mod openapi;
+pub mod recipes;
+
+/// Items per page when the client does not ask for a size.
+const DEFAULT_PAGE_SIZE: u32 = 50;
+/// Upper bound for `per_page` and `limit`.
+const MAX_PAGE_SIZE: u32 = 200;
+
+pub fn scope() -> Scope {
+    web::scope("/api/v1")
+        .app_data(
+            web::JsonConfig::default()
+                .error_handler(|err, _| ApiError::bad_request(err.to_string()).into()),
+        )
+        .app_data(
+            web::QueryConfig::default()
+                .error_handler(|err, _| ApiError::bad_request(err.to_string()).into()),
+        )
+        .app_data(
+            web::PathConfig::default()
+                .error_handler(|_, _| ApiError::not_found("Not found").into()),
+        )
+        .service(items::list_lists)
+        .service(items::list_items)
+        .service(items::create_item)
+        .service(items::get_item)
+        .service(items::update_item)
+        .service(items::delete_item)
+        .service(recipes::list_recipes)
+        .service(recipes::create_recipe)
+        .service(recipes::import_recipes)
+        .service(recipes::get_import)
+        .service(recipes::get_recipe)
+        .service(recipes::update_recipe)
+        .service(recipes::delete_recipe)
+        .default_service(web::to(|| async {
+            HttpResponse::from_error(ApiError::not_found("Not found"))
+        }))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: ErrorDetail,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorDetail {
+    /// Stable, machine readable, e.g. `not_found`.
+    pub code: String,
+    pub message: String,
+}
+
+/// A page of a list, with `libsql_orm::Pagination` as metadata.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    #[schema(value_type = PageInfo)]
+    pub pagination: Pagination,
+}
+
+impl<T> From<PaginatedResult<T>> for Page<T> {
+    fn from(page: PaginatedResult<T>) -> Self {
+        Page {
+            data: page.data,
+            pagination: page.pagination,
+        }
+    }
+}
+
+/// A page of a list, with `libsql_orm::CursorPagination` as metadata.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CursorPage<T> {
+    pub data: Vec<T>,
+    #[schema(value_type = CursorInfo)]
+    pub pagination: CursorPagination,
+}
+
+/// How `Pagination` is serialized, the ORM type has no schema.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct PageInfo {
+    pub page: u32,
+    pub per_page: u32,
+    pub total: Option<u64>,
+    pub total_pages: Option<u32>,
+}
+
+/// How `CursorPagination` is serialized. Pass `next_cursor` as `cursor` to
+/// get the next page.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct CursorInfo {
+    pub cursor: Option<String>,
+    pub limit: u32,
+    pub include_cursor: bool,
+    pub has_next: bool,
+    pub has_prev: bool,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    pub total: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_request", message)
+    }
+
+    pub fn unauthorized() -> Self {
+        Self::new(
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+            "Sign in or send an API token",
+        )
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn precondition_failed() -> Self {
+        Self::new(
+            StatusCode::PRECONDITION_FAILED,
+            "precondition_failed",
+            "The resource changed since it was fetched",
+        )
+    }
+
+    pub fn internal() -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+            "Something went wrong, please try again",
+        )
+    }
+
+    /// Maps the errors of the `get_*` database functions. Rows of other
+    /// users are reported like missing ones so ids cannot be probed.
+    pub fn from_lookup(err: String) -> Self {
+        if err == "Unauthorized" || err.ends_with("not found") {
+            let message = if err == "Unauthorized" {
+                "Not found".to_string()
+            } else {
+                err
+            };
+            Self::not_found(message)
+        } else {
+            Self::internal()
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status);
+        response.insert_header((header::CACHE_CONTROL, "no-store"));
+        if self.status == StatusCode::UNAUTHORIZED {
+            response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+        }
+        response.json(ErrorBody {
+            error: ErrorDetail {
+                code: self.code.to_string(),
+                message: self.message.clone(),
+            },
+        })
+    }
+}
+
+pub fn api_user(req: &HttpRequest) -> Result<User, ApiError> {
+    super::get_user(req.clone()).ok_or_else(ApiError::unauthorized)
+}
+
+/// Clamps the page size a client asked for.
+fn page_size(requested: Option<u32>) -> u32 {
+    requested
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Strong ETag over the JSON representation.
+pub fn etag<T: Serialize>(value: &T) -> EntityTag {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+    let digest = Sha256::digest(&body);
+    let tag: String = digest[..16]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    EntityTag::new_strong(tag)
+}
+
+/// Responds with the value and its ETag, or 304 when the client sent a
+/// matching `If-None-Match`.
+pub fn json_response<T: Serialize>(
+    req: &HttpRequest,
+    status: StatusCode,
+    value: &T,
+) -> HttpResponse {
+    let tag = etag(value);
+    let unchanged = match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|other| other.weak_eq(&tag)),
+        Err(_) => false,
+    };
+
+    if unchanged && status == StatusCode::OK {
+        return HttpResponse::NotModified()
+            .insert_header(header::ETag(tag))
+            .finish();
+    }
+    HttpResponse::build(status)
+        .insert_header(header::ETag(tag))
+        .json(value)
+}
+
+/// Rejects writes based on an outdated copy when the client sent `If-Match`.
+pub fn check_if_match<T: Serialize>(req: &HttpRequest, current: &T) -> Result<(), ApiError> {
+    match IfMatch::parse(req) {
+        Ok(IfMatch::Items(tags)) if !tags.is_empty() => {
+            let tag = etag(current);
+            if tags.iter().any(|other| other.strong_eq(&tag)) {
+                Ok(())
+            } else {
+                Err(ApiError::precondition_failed())
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_page_size() {
+        assert_eq!(page_size(None), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_size(Some(0)), 1);
+        assert_eq!(page_size(Some(10)), 10);
+        assert_eq!(page_size(Some(10_000)), MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_lookup_errors_hide_other_users() {
+        assert_eq!(
+            ApiError::from_lookup("Unauthorized".to_string()).status,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            ApiError::from_lookup("Item not found".to_string()).status,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            ApiError::from_lookup("Database error".to_string()).status,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_error_body() {
+        let response = ApiError::not_found("Item not found").error_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"error": {"code": "not_found", "message": "Item not found"}})
+        );
+    }
+
+    #[test]
+    fn test_etags() {
+        let value = serde_json::json!({"id": 1, "task": "milk"});
+        let tag = etag(&value);
+        assert_eq!(tag, etag(&value));
+        assert_ne!(tag, etag(&serde_json::json!({"id": 1, "task": "oat milk"})));
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, tag.to_string()))
+            .to_http_request();
+        assert_eq!(
+            json_response(&req, StatusCode::OK, &value).status(),
+            StatusCode::NOT_MODIFIED
+        );
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(
+            json_response(&req, StatusCode::OK, &value).status(),
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn test_if_match() {
+        let value = serde_json::json!({"id": 1, "task": "milk"});
+
+        let req = TestRequest::default().to_http_request();
+        assert!(check_if_match(&req, &value).is_ok());
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_MATCH, etag(&value).to_string()))
+            .to_http_request();
+        assert!(check_if_match(&req, &value).is_ok());
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_MATCH, "\"outdated\""))
+            .to_http_request();
+        assert_eq!(
+            check_if_match(&req, &value).unwrap_err().status,
+            StatusCode::PRECONDITION_FAILED
+        );
+    }
+}
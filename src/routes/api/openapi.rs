@@ -0,0 +1,209 @@
+//! OpenAPI document of the JSON API, generated from the handler and type
+//! annotations.
+
+use actix_web::{HttpRequest, HttpResponse, Result as AwResult, get};
+use lazy_static::lazy_static;
+use maud::Markup;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use super::{CursorInfo, ErrorBody, ErrorDetail, PageInfo, items, recipes};
+use crate::routes::get_user;
+use crate::view::{self, index};
+
+/// Every operation of the `/api/v1` scope, relative to it.
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        items::list_lists,
+        items::list_items,
+        items::create_item,
+        items::get_item,
+        items::update_item,
+        items::delete_item,
+        recipes::list_recipes,
+        recipes::create_recipe,
+        recipes::import_recipes,
+        recipes::get_import,
+        recipes::get_recipe,
+        recipes::update_recipe,
+        recipes::delete_recipe,
+    ),
+    components(schemas(ErrorBody, ErrorDetail, PageInfo, CursorInfo))
+)]
+struct V1Doc;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Rezi API",
+        description = "Grocery lists, recipes and recipe imports.\n\n\
+            Authenticate with a personal API token from your profile, sent as \
+            `Authorization: Bearer <token>`, or with the session cookie of the web app. \
+            Responses carry an `ETag`; send it as `If-None-Match` to skip unchanged \
+            data and as `If-Match` to make sure a change is based on the latest version."
+    ),
+    nest((path = "/api/v1", api = V1Doc)),
+    modifiers(&SecuritySchemes)
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("Personal API token, created on the profile page"))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::with_description(
+                "rezi_session",
+                "Session cookie of the web app",
+            ))),
+        );
+    }
+}
+
+lazy_static! {
+    static ref OPENAPI_JSON: serde_json::Value = {
+        let mut openapi = ApiDoc::openapi();
+        // Filled from Cargo.toml, which names no license
+        openapi.info.license = None;
+        serde_json::to_value(openapi).expect("OpenAPI document is valid JSON")
+    };
+}
+
+#[get("/api/openapi.json")]
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(&*OPENAPI_JSON)
+}
+
+#[get("/api/docs")]
+pub async fn api_docs(req: HttpRequest) -> AwResult<Markup> {
+    let user = get_user(req);
+    Ok(index(
+        Some(view::api_docs::api_docs(&OPENAPI_JSON)),
+        false,
+        user.as_ref(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    use actix_web::http::Method;
+    use regex::Regex;
+
+    use crate::database::api_tokens::ApiScope;
+    use crate::oidc::required_scope;
+
+    fn spec_routes() -> BTreeSet<(String, String)> {
+        let mut routes = BTreeSet::new();
+        for (path, item) in OPENAPI_JSON["paths"].as_object().unwrap() {
+            for method in item.as_object().unwrap().keys() {
+                routes.insert((method.to_uppercase(), path.clone()));
+            }
+        }
+        routes
+    }
+
+    /// Routes of the handlers `scope()` registers, read from the sources so
+    /// a handler added without documentation is noticed.
+    fn registered_routes() -> BTreeSet<(String, String)> {
+        let main = include_str!("../../main.rs");
+        assert!(
+            main.contains(".service(routes::api::scope())"),
+            "the API scope is not registered in main.rs"
+        );
+
+        let scope_source = include_str!("mod.rs");
+        let prefix = Regex::new(r#"web::scope\("([^"]+)"\)"#)
+            .unwrap()
+            .captures(scope_source)
+            .unwrap()[1]
+            .to_string();
+        let services = Regex::new(r"\.service\((\w+)::(\w+)\)").unwrap();
+        let attribute =
+            Regex::new(r#"#\[(get|post|put|patch|delete)\("([^"]+)"\)\]\s*pub async fn (\w+)"#)
+                .unwrap();
+
+        let mut routes = BTreeSet::new();
+        for service in services.captures_iter(scope_source) {
+            let source = match &service[1] {
+                "items" => include_str!("items.rs"),
+                "recipes" => include_str!("recipes.rs"),
+                module => panic!("unknown API module {module}, add it to this test"),
+            };
+            let route = attribute
+                .captures_iter(source)
+                .find(|route| route[3] == service[2])
+                .unwrap_or_else(|| panic!("no route attribute on {}", &service[2]));
+            routes.insert((route[1].to_uppercase(), format!("{prefix}{}", &route[2])));
+        }
+        routes
+    }
+
+    #[test]
+    fn test_spec_is_openapi_3_1() {
+        assert!(OPENAPI_JSON["openapi"].as_str().unwrap().starts_with("3.1"));
+        assert!(OPENAPI_JSON["components"]["securitySchemes"]["bearer"].is_object());
+    }
+
+    #[test]
+    fn test_pagination_schemas_match_orm_types() {
+        let keys = |value: &serde_json::Value| -> BTreeSet<String> {
+            value.as_object().unwrap().keys().cloned().collect()
+        };
+        let schemas = &OPENAPI_JSON["components"]["schemas"];
+
+        let pagination = serde_json::to_value(libsql_orm::Pagination::new(1, 10)).unwrap();
+        assert_eq!(keys(&pagination), keys(&schemas["PageInfo"]["properties"]));
+
+        let cursor = serde_json::to_value(libsql_orm::CursorPagination::new(10)).unwrap();
+        assert_eq!(keys(&cursor), keys(&schemas["CursorInfo"]["properties"]));
+    }
+
+    #[test]
+    fn test_spec_matches_registered_routes() {
+        assert_eq!(spec_routes(), registered_routes());
+    }
+
+    #[test]
+    fn test_spec_documents_token_scopes() {
+        for (method, path) in spec_routes() {
+            let operation = &OPENAPI_JSON["paths"][&path][method.to_lowercase()];
+            let documented: Vec<&str> = operation["security"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|requirement| requirement["bearer"].as_array())
+                .flatten()
+                .filter_map(|scope| scope.as_str())
+                .collect();
+
+            let method = Method::from_bytes(method.as_bytes()).unwrap();
+            let concrete = path.replace("{id}", "1");
+            let required = required_scope(&method, &concrete).map(|scope| scope.as_str());
+            assert_eq!(
+                documented,
+                required.into_iter().collect::<Vec<_>>(),
+                "{method} {path}"
+            );
+            assert!(
+                ApiScope::ALL
+                    .iter()
+                    .any(|scope| Some(scope.as_str()) == required)
+            );
+        }
+    }
+}
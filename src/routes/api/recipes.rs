@@ -0,0 +1,372 @@
+use actix_web::http::StatusCode;
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, delete, get, patch, post, web};
+use libsql_orm::CursorPagination;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::{ApiError, CursorPage, ErrorBody, api_user, check_if_match, json_response, page_size};
+use crate::database::jobs::{Job, JobStatus};
+use crate::database::recipes::Recipe;
+use crate::database::{self, DBClient};
+use crate::jobs::{JobPayload, JobQueue, MAX_IMPORT_URLS, RecipeJobOutcome, parse_recipe_url};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecipeResponse {
+    pub id: i64,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub content: String,
+    /// Prompt template version that extracted the recipe, if any.
+    pub prompt_version: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<Recipe> for RecipeResponse {
+    fn from(recipe: Recipe) -> Self {
+        RecipeResponse {
+            id: recipe.id(),
+            title: recipe.title,
+            url: recipe.url,
+            content: recipe.content,
+            prompt_version: recipe.prompt_version,
+            created_at: recipe.created_at,
+            updated_at: recipe.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportResponse {
+    pub id: i64,
+    pub status: JobStatus,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    /// The recipe the import created, once it succeeded.
+    pub recipe_id: Option<i64>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<Job> for ImportResponse {
+    fn from(job: Job) -> Self {
+        ImportResponse {
+            id: job.id(),
+            recipe_id: RecipeJobOutcome::from_job(&job).map(|outcome| outcome.recipe_id),
+            status: job.status,
+            attempts: job.attempts,
+            last_error: job.last_error,
+            created_at: job.created_at,
+            updated_at: job.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RecipesQuery {
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateRecipeRequest {
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub content: String,
+}
+
+/// Fields left out stay unchanged.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateRecipeRequest {
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImportRecipesRequest {
+    /// At most 20 `http` or `https` URLs.
+    #[schema(max_items = 20)]
+    pub urls: Vec<String>,
+}
+
+fn recipe_url(url: Option<&str>) -> Result<Option<String>, ApiError> {
+    match url.map(str::trim).filter(|url| !url.is_empty()) {
+        Some(url) => match parse_recipe_url(url) {
+            Some(_) => Ok(Some(url.to_string())),
+            None => Err(ApiError::bad_request(format!("Invalid URL: {url}"))),
+        },
+        None => Ok(None),
+    }
+}
+
+async fn owned_recipe(client: &DBClient, id: i64, owner_id: &str) -> Result<Recipe, ApiError> {
+    database::recipes::get_recipe(client, id, owner_id.to_string())
+        .await
+        .map_err(ApiError::from_lookup)
+}
+
+/// Recipes, newest first.
+#[utoipa::path(
+    tag = "recipes",
+    params(RecipesQuery),
+    responses(
+        (status = 200, description = "One page of recipes", body = CursorPage<RecipeResponse>),
+        (status = 400, description = "Invalid cursor", body = ErrorBody),
+    ),
+    security(("bearer" = ["recipes:read"]), ("session" = [])),
+)]
+#[get("/recipes")]
+pub async fn list_recipes(
+    query: web::Query<RecipesQuery>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    let query = query.into_inner();
+
+    if query
+        .cursor
+        .as_deref()
+        .is_some_and(|cursor| cursor.parse::<i64>().is_err())
+    {
+        return Err(ApiError::bad_request("Invalid cursor"));
+    }
+    let pagination = CursorPagination::with_cursor(page_size(query.limit), query.cursor);
+
+    let page =
+        database::recipes::get_recipes_page(client.get_ref(), user.id().to_string(), pagination)
+            .await
+            .map_err(|_| ApiError::internal())?;
+    let page = CursorPage {
+        data: page.data.into_iter().map(RecipeResponse::from).collect(),
+        pagination: page.pagination,
+    };
+
+    Ok(json_response(&req, StatusCode::OK, &page))
+}
+
+/// Saves a recipe as it is, without asking the LLM.
+#[utoipa::path(
+    tag = "recipes",
+    request_body = CreateRecipeRequest,
+    responses(
+        (status = 201, description = "The new recipe", body = RecipeResponse),
+        (status = 400, description = "Invalid recipe", body = ErrorBody),
+    ),
+    security(("bearer" = ["recipes:write"]), ("session" = [])),
+)]
+#[post("/recipes")]
+pub async fn create_recipe(
+    body: web::Json<CreateRecipeRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    let body = body.into_inner();
+
+    if body.content.trim().is_empty() {
+        return Err(ApiError::bad_request("content must not be empty"));
+    }
+    let recipe = Recipe::new(
+        None,
+        user.id().to_string(),
+        body.title,
+        recipe_url(body.url.as_deref())?,
+        body.content,
+    );
+    let recipe = database::recipes::create_recipe(client.get_ref(), recipe)
+        .await
+        .map_err(|_| ApiError::internal())?;
+
+    let location = format!("/api/v1/recipes/{}", recipe.id());
+    let mut response = json_response(&req, StatusCode::CREATED, &RecipeResponse::from(recipe));
+    if let Ok(location) = header::HeaderValue::from_str(&location) {
+        response.headers_mut().insert(header::LOCATION, location);
+    }
+    Ok(response)
+}
+
+#[utoipa::path(
+    tag = "recipes",
+    params(("id" = i64, Path, description = "Recipe id")),
+    responses(
+        (status = 200, description = "The recipe", body = RecipeResponse),
+        (status = 304, description = "Not modified since the ETag in `If-None-Match`"),
+        (status = 404, description = "No such recipe", body = ErrorBody),
+    ),
+    security(("bearer" = ["recipes:read"]), ("session" = [])),
+)]
+#[get("/recipes/{id}")]
+pub async fn get_recipe(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    let recipe = owned_recipe(client.get_ref(), path.into_inner(), user.id()).await?;
+
+    Ok(json_response(
+        &req,
+        StatusCode::OK,
+        &RecipeResponse::from(recipe),
+    ))
+}
+
+#[utoipa::path(
+    tag = "recipes",
+    params(("id" = i64, Path, description = "Recipe id")),
+    request_body = UpdateRecipeRequest,
+    responses(
+        (status = 200, description = "The changed recipe", body = RecipeResponse),
+        (status = 400, description = "Invalid change", body = ErrorBody),
+        (status = 404, description = "No such recipe", body = ErrorBody),
+        (status = 412, description = "Changed since the ETag in `If-Match`", body = ErrorBody),
+    ),
+    security(("bearer" = ["recipes:write"]), ("session" = [])),
+)]
+#[patch("/recipes/{id}")]
+pub async fn update_recipe(
+    path: web::Path<i64>,
+    body: web::Json<UpdateRecipeRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    let client = client.get_ref();
+    let recipe = owned_recipe(client, path.into_inner(), user.id()).await?;
+    check_if_match(&req, &RecipeResponse::from(recipe.clone()))?;
+
+    let body = body.into_inner();
+    if body
+        .content
+        .as_deref()
+        .is_some_and(|content| content.trim().is_empty())
+    {
+        return Err(ApiError::bad_request("content must not be empty"));
+    }
+    let recipe = database::recipes::update_recipe(
+        client,
+        recipe.id(),
+        body.title,
+        recipe_url(body.url.as_deref())?,
+        body.content,
+        None,
+        user.id().to_string(),
+    )
+    .await
+    .map_err(ApiError::from_lookup)?;
+
+    Ok(json_response(
+        &req,
+        StatusCode::OK,
+        &RecipeResponse::from(recipe),
+    ))
+}
+
+#[utoipa::path(
+    tag = "recipes",
+    params(("id" = i64, Path, description = "Recipe id")),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 404, description = "No such recipe", body = ErrorBody),
+        (status = 412, description = "Changed since the ETag in `If-Match`", body = ErrorBody),
+    ),
+    security(("bearer" = ["recipes:write"]), ("session" = [])),
+)]
+#[delete("/recipes/{id}")]
+pub async fn delete_recipe(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    let client = client.get_ref();
+    let recipe = owned_recipe(client, path.into_inner(), user.id()).await?;
+    check_if_match(&req, &RecipeResponse::from(recipe.clone()))?;
+
+    database::recipes::delete_recipe(client, recipe.id(), user.id().to_string())
+        .await
+        .map_err(ApiError::from_lookup)?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Queues one import per URL. Poll the returned imports for the results.
+#[utoipa::path(
+    tag = "imports",
+    request_body = ImportRecipesRequest,
+    responses(
+        (status = 202, description = "One queued import per URL", body = [ImportResponse]),
+        (status = 400, description = "Invalid URL or too many URLs", body = ErrorBody),
+    ),
+    security(("bearer" = ["recipes:write"]), ("session" = [])),
+)]
+#[post("/recipes/imports")]
+pub async fn import_recipes(
+    body: web::Json<ImportRecipesRequest>,
+    queue: web::Data<JobQueue>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    if body.urls.len() > MAX_IMPORT_URLS {
+        return Err(ApiError::bad_request(format!(
+            "urls must contain at most {MAX_IMPORT_URLS} URLs"
+        )));
+    }
+
+    let mut urls = Vec::with_capacity(body.urls.len());
+    for url in &body.urls {
+        if let Some(url) = recipe_url(Some(url))? {
+            urls.push(url);
+        }
+    }
+    if urls.is_empty() {
+        return Err(ApiError::bad_request("urls must contain at least one URL"));
+    }
+
+    let mut imports = Vec::with_capacity(urls.len());
+    for url in urls {
+        let payload = JobPayload::Process {
+            url: Some(url),
+            content: None,
+        };
+        let job = queue
+            .enqueue(user.id().to_string(), payload)
+            .await
+            .map_err(|_| ApiError::internal())?;
+        imports.push(ImportResponse::from(job));
+    }
+
+    Ok(json_response(&req, StatusCode::ACCEPTED, &imports))
+}
+
+/// State of an import, with the recipe id once it succeeded.
+#[utoipa::path(
+    tag = "imports",
+    params(("id" = i64, Path, description = "Import id")),
+    responses(
+        (status = 200, description = "The import", body = ImportResponse),
+        (status = 404, description = "No such import", body = ErrorBody),
+    ),
+    security(("bearer" = ["recipes:read"]), ("session" = [])),
+)]
+#[get("/recipes/imports/{id}")]
+pub async fn get_import(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user = api_user(&req)?;
+    let job = database::jobs::get_job(client.get_ref(), path.into_inner(), user.id().to_string())
+        .await
+        .map_err(ApiError::from_lookup)?;
+
+    Ok(json_response(
+        &req,
+        StatusCode::OK,
+        &ImportResponse::from(job),
+    ))
+}
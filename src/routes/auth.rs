@@ -1,45 +1,97 @@
-use crate::oidc::{AuthState, OidcClient};
+use crate::config::Server;
+use crate::database::DBClient;
+use crate::database::auth_sessions::{self, AuthSession};
+use crate::database::identities::{self, ProviderAccount};
+use crate::database::users;
+use crate::oidc::{AUTH_SESSION_KEY, AuthState, OidcProvider, OidcProviders};
+use crate::user::Role;
 use crate::view::login;
 use actix_session::Session;
-use actix_web::{HttpRequest, HttpResponse, Result, get, web};
+use actix_web::http::header::CACHE_CONTROL;
+use actix_web::{HttpRequest, HttpResponse, Result, get, post, web};
 use log::{error, info};
 use maud::Markup;
-use std::sync::Arc;
+use serde::Deserialize;
 use uuid::Uuid;
 
-type OidcClientArc = Arc<tokio::sync::Mutex<OidcClient>>;
+fn redirect_to(req: &HttpRequest) -> Option<String> {
+    req.query_string()
+        .split('=')
+        .nth(1)
+        .map(|s| urlencoding::decode(s).unwrap_or_default().to_string())
+}
+
+fn unknown_provider() -> actix_web::Error {
+    actix_web::error::ErrorNotFound("Unknown identity provider")
+}
 
 #[get("/login")]
-pub async fn login_page() -> Result<Markup> {
-    Ok(login::login_page())
+pub async fn login_page(req: HttpRequest, providers: web::Data<OidcProviders>) -> Result<Markup> {
+    Ok(login::login_page(providers.all(), redirect_to(&req)))
 }
 
+/// Starts the login right away when there is a single provider, otherwise
+/// lets the user choose one.
 #[get("/auth/login")]
 pub async fn auth_login(
     req: HttpRequest,
     session: Session,
-    oidc_client: web::Data<OidcClientArc>,
+    providers: web::Data<OidcProviders>,
 ) -> Result<HttpResponse> {
-    let redirect_to = req
-        .query_string()
-        .split('=')
-        .nth(1)
-        .map(|s| urlencoding::decode(s).unwrap_or_default().to_string());
+    match providers.all() {
+        [provider] => start_login(&req, &session, provider).await,
+        _ => {
+            let query = req.query_string();
+            let location = if query.is_empty() {
+                "/login".to_string()
+            } else {
+                format!("/login?{query}")
+            };
+            Ok(HttpResponse::Found()
+                .append_header(("Location", location))
+                .finish())
+        }
+    }
+}
 
+#[get("/auth/{provider}/login")]
+pub async fn provider_login(
+    path: web::Path<String>,
+    req: HttpRequest,
+    session: Session,
+    providers: web::Data<OidcProviders>,
+) -> Result<HttpResponse> {
+    let provider = providers.get(&path).ok_or_else(unknown_provider)?;
+    start_login(&req, &session, provider).await
+}
+
+async fn start_login(
+    req: &HttpRequest,
+    session: &Session,
+    provider: &OidcProvider,
+) -> Result<HttpResponse> {
     let state = Uuid::new_v4().to_string();
     let (code_verifier, code_challenge) = crate::oidc::OidcClient::generate_pkce();
+    let nonce = crate::oidc::OidcClient::generate_nonce();
 
     let auth_state = AuthState {
+        provider: provider.id.clone(),
         state: state.clone(),
         code_verifier,
-        redirect_url: redirect_to,
+        nonce,
+        redirect_url: redirect_to(req),
     };
 
     session.insert("auth_state", &auth_state)?;
 
-    let client = oidc_client.lock().await;
+    let client = provider.client.lock().await;
     let auth_url = client
-        .build_auth_url(&state, &code_challenge, auth_state.redirect_url.clone())
+        .build_auth_url(
+            &state,
+            &auth_state.nonce,
+            &code_challenge,
+            auth_state.redirect_url.clone(),
+        )
         .map_err(|e| {
             actix_web::error::ErrorInternalServerError(format!("Failed to build auth URL: {e}"))
         })?;
@@ -49,12 +101,38 @@ pub async fn auth_login(
         .finish())
 }
 
+/// Callback of the primary provider, kept so existing redirect URIs work.
 #[get("/auth/callback")]
 pub async fn callback(
     req: HttpRequest,
     session: Session,
-    oidc_client: web::Data<OidcClientArc>,
+    providers: web::Data<OidcProviders>,
+    db_client: web::Data<DBClient>,
 ) -> Result<HttpResponse> {
+    let provider = providers.primary().ok_or_else(unknown_provider)?;
+    complete_login(&req, &session, &providers, provider, &db_client).await
+}
+
+#[get("/auth/{provider}/callback")]
+pub async fn provider_callback(
+    path: web::Path<String>,
+    req: HttpRequest,
+    session: Session,
+    providers: web::Data<OidcProviders>,
+    db_client: web::Data<DBClient>,
+) -> Result<HttpResponse> {
+    let provider = providers.get(&path).ok_or_else(unknown_provider)?;
+    complete_login(&req, &session, &providers, provider, &db_client).await
+}
+
+async fn complete_login(
+    req: &HttpRequest,
+    session: &Session,
+    providers: &OidcProviders,
+    provider: &OidcProvider,
+    db_client: &DBClient,
+) -> Result<HttpResponse> {
+    let oidc_client = &provider.client;
     let query = req.query_string();
     let params: std::collections::HashMap<String, String> =
         url::form_urlencoded::parse(query.as_bytes())
@@ -76,6 +154,11 @@ pub async fn callback(
     if auth_state.state != *state {
         return Err(actix_web::error::ErrorBadRequest("Invalid state parameter"));
     }
+    if auth_state.provider != provider.id {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Login was started with another provider",
+        ));
+    }
 
     let token_response = {
         let client = oidc_client.lock().await;
@@ -87,6 +170,22 @@ pub async fn callback(
             })?
     };
 
+    let id_token = token_response
+        .id_token
+        .as_deref()
+        .ok_or_else(|| actix_web::error::ErrorBadRequest("Token response has no ID token"))?;
+
+    let claims = {
+        let mut client = oidc_client.lock().await;
+        client
+            .verify_id_token(id_token, &auth_state.nonce)
+            .await
+            .map_err(|e| {
+                error!("ID token verification failed: {e}");
+                actix_web::error::ErrorUnauthorized("Invalid ID token")
+            })?
+    };
+
     let user_info_result = {
         let client = oidc_client.lock().await;
         client
@@ -97,6 +196,89 @@ pub async fn callback(
             })?
     };
 
+    // Userinfo is only trusted for the subject the ID token was issued for
+    if user_info_result.sub != claims.sub {
+        error!("userinfo subject does not match the ID token");
+        return Err(actix_web::error::ErrorUnauthorized("Invalid user info"));
+    }
+
+    let email_verified = claims
+        .email_verified
+        .or(user_info_result.email_verified)
+        .unwrap_or(false);
+    let user_id = identities::resolve_user_id(
+        db_client,
+        &ProviderAccount {
+            provider: &provider.id,
+            primary: providers
+                .primary()
+                .is_some_and(|primary| primary.id == provider.id),
+            subject: &claims.sub,
+            email: Some(&user_info_result.email),
+            email_verified,
+        },
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    if let Ok(Some(record)) = users::get_user(db_client, &user_id).await
+        && record.is_disabled()
+    {
+        info!("Refused login of disabled user {user_id}");
+        session.remove("auth_state");
+        return Ok(HttpResponse::Forbidden()
+            .content_type("text/html; charset=utf-8")
+            .body(login::account_disabled().into_string()));
+    }
+
+    let role = {
+        let client = oidc_client.lock().await;
+        let admin_emails = req
+            .app_data::<web::Data<Server>>()
+            .map(|config| config.admin_emails().to_vec())
+            .unwrap_or_default();
+        Role::for_login(
+            &client.groups(&claims, &user_info_result),
+            client.admin_group(),
+            &user_info_result.email,
+            email_verified,
+            &admin_emails,
+        )
+    };
+
+    // Not fatal, without a row the name falls back to the email
+    let _ = users::upsert_user(db_client, user_id.clone(), &user_info_result, role).await;
+
+    let expires_at = oidc_client
+        .lock()
+        .await
+        .access_token_expiry(token_response.expires_in);
+    let mut auth_session = AuthSession::new(
+        user_id,
+        provider.id.clone(),
+        token_response.access_token.clone(),
+        token_response.refresh_token.clone(),
+        token_response.id_token.clone(),
+        claims.sid.clone(),
+        expires_at,
+    );
+    auth_session.ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .map(str::to_string);
+    auth_session.user_agent = req
+        .headers()
+        .get(actix_web::http::header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let auth_session = auth_sessions::create_auth_session(db_client, auth_session)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    // A fresh cookie for the new login, nothing from before carries over
+    session.renew();
+    session.insert(AUTH_SESSION_KEY, auth_session.session_id())?;
+
     match session.insert("user", &user_info_result) {
         Ok(_) => info!("Successfully stored user struct in session"),
         Err(e) => error!("Failed to store user struct in session: {e}"),
@@ -129,11 +311,131 @@ pub async fn callback(
         .body(html_response))
 }
 
+/// Ends the local session and, when the provider supports it, the session
+/// at the provider too.
 #[get("/auth/logout")]
-pub async fn logout(session: Session) -> Result<HttpResponse> {
+pub async fn logout(
+    session: Session,
+    providers: web::Data<OidcProviders>,
+    db_client: web::Data<DBClient>,
+) -> Result<HttpResponse> {
+    let mut ended = None;
+    if let Ok(Some(session_id)) = session.get::<String>(AUTH_SESSION_KEY) {
+        ended = auth_sessions::get_auth_session(db_client.get_ref(), &session_id)
+            .await
+            .ok()
+            .flatten();
+        let _ = auth_sessions::revoke_auth_session(db_client.get_ref(), &session_id).await;
+    }
     session.purge();
 
+    let mut location = "/".to_string();
+    if let Some(auth_session) = ended
+        && let Some(provider) = providers.for_session(auth_session.provider.as_deref())
+        && let Some(url) = provider
+            .client
+            .lock()
+            .await
+            .end_session_url(auth_session.id_token.as_deref())
+    {
+        location = url;
+    }
+    info!("Logged out, redirecting to {location}");
+
     Ok(HttpResponse::Found()
-        .append_header(("Location", "/"))
+        .append_header(("Location", location))
         .finish())
 }
+
+#[derive(Deserialize)]
+pub struct BackchannelLogoutRequest {
+    pub logout_token: String,
+}
+
+/// Back-channel logout of the primary provider.
+#[post("/auth/backchannel-logout")]
+pub async fn backchannel_logout(
+    form: web::Form<BackchannelLogoutRequest>,
+    providers: web::Data<OidcProviders>,
+    db_client: web::Data<DBClient>,
+) -> Result<HttpResponse> {
+    let provider = providers.primary().ok_or_else(unknown_provider)?;
+    end_provider_sessions(&form.logout_token, &providers, provider, &db_client).await
+}
+
+#[post("/auth/{provider}/backchannel-logout")]
+pub async fn provider_backchannel_logout(
+    path: web::Path<String>,
+    form: web::Form<BackchannelLogoutRequest>,
+    providers: web::Data<OidcProviders>,
+    db_client: web::Data<DBClient>,
+) -> Result<HttpResponse> {
+    let provider = providers.get(&path).ok_or_else(unknown_provider)?;
+    end_provider_sessions(&form.logout_token, &providers, provider, &db_client).await
+}
+
+/// Called by the provider when a user logs out elsewhere, see OpenID Connect
+/// Back-Channel Logout 1.0.
+async fn end_provider_sessions(
+    logout_token: &str,
+    providers: &OidcProviders,
+    provider: &OidcProvider,
+    db_client: &DBClient,
+) -> Result<HttpResponse> {
+    let claims = match provider
+        .client
+        .lock()
+        .await
+        .verify_logout_token(logout_token)
+        .await
+    {
+        Ok(claims) => claims,
+        Err(e) => {
+            error!("Rejected logout token: {e}");
+            return Ok(HttpResponse::BadRequest()
+                .insert_header((CACHE_CONTROL, "no-store"))
+                .json(serde_json::json!({ "error": "invalid_request" })));
+        }
+    };
+
+    let primary = providers
+        .primary()
+        .is_some_and(|primary| primary.id == provider.id);
+    // Sessions belong to the linked user, not to the provider's subject
+    let owner_id = match &claims.sub {
+        Some(sub) => identities::get_identity(db_client, &provider.id, sub)
+            .await
+            .ok()
+            .flatten()
+            .map(|identity| identity.user_id),
+        None => None,
+    };
+
+    let revoked = match (&claims.sid, &claims.sub, owner_id) {
+        (Some(sid), _, owner_id) => {
+            auth_sessions::revoke_sessions_by_sid(
+                db_client,
+                &provider.id,
+                primary,
+                sid,
+                owner_id.as_deref(),
+            )
+            .await
+        }
+        (None, Some(_), Some(owner_id)) => {
+            auth_sessions::revoke_owner_sessions(db_client, &provider.id, primary, &owner_id).await
+        }
+        // An account that never signed in here has no sessions to end
+        (None, Some(_), None) => Ok(0),
+        (None, None, _) => unreachable!("verify_logout_token requires sub or sid"),
+    };
+
+    let mut response = match revoked {
+        Ok(count) => {
+            info!("Back-channel logout ended {count} sessions");
+            HttpResponse::Ok()
+        }
+        Err(_) => HttpResponse::InternalServerError(),
+    };
+    Ok(response.insert_header((CACHE_CONTROL, "no-store")).finish())
+}
@@ -1,17 +1,37 @@
 use actix_web::http::header::{CACHE_CONTROL, CONTENT_DISPOSITION};
-use actix_web::{HttpRequest, HttpResponse, Result, get, web};
+use actix_web::{HttpRequest, HttpResponse, Result, get, post, web};
 
 use crate::database::{self, DBClient};
-use crate::{csv, pdf, view};
+use crate::{csv, ical, pdf, view};
+
+/// How far back and ahead the calendar feed reaches.
+const FEED_DAYS_BACK: i64 = 28;
+const FEED_DAYS_AHEAD: i64 = 84;
+
+/// Absolute URL of the calendar feed, calendar apps need scheme and host.
+fn feed_url(req: &HttpRequest, token: &str) -> String {
+    let info = req.connection_info();
+    format!(
+        "{}://{}/export/plan/{token}.ics",
+        info.scheme(),
+        info.host()
+    )
+}
 
 #[get("/export")]
-pub async fn export_page(req: HttpRequest) -> Result<HttpResponse> {
+pub async fn export_page(client: web::Data<DBClient>, req: HttpRequest) -> Result<HttpResponse> {
     let user = match super::get_user_or_redirect(&req) {
         Ok(user) => user,
         Err(response) => return Ok(response),
     };
 
-    let markup = view::export::export_page(&user);
+    let feed_url =
+        database::calendar_feeds::get_or_create_feed(client.get_ref(), user.id().to_string())
+            .await
+            .ok()
+            .map(|feed| feed_url(&req, feed.token()));
+
+    let markup = view::export::export_page(&user, feed_url.as_deref());
 
     Ok(HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
@@ -75,3 +95,70 @@ pub async fn export_items_pdf(
         }
     }
 }
+
+/// Calendar apps cannot log in, the token in the URL identifies the owner.
+#[get("/export/plan/{token}.ics")]
+pub async fn export_meal_plan_ics(
+    path: web::Path<String>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let token = path.into_inner();
+    let db_client: &DBClient = client.get_ref();
+
+    let feed = match database::calendar_feeds::get_feed_by_token(db_client, &token).await {
+        Ok(Some(feed)) => feed,
+        Ok(None) => return Ok(HttpResponse::NotFound().finish()),
+        Err(_) => return Ok(HttpResponse::InternalServerError().finish()),
+    };
+    // Disabling an account also ends its feeds
+    match database::users::get_user(db_client, &feed.owner_id).await {
+        Ok(Some(owner)) if owner.is_disabled() => return Ok(HttpResponse::NotFound().finish()),
+        Ok(_) => {}
+        Err(_) => return Ok(HttpResponse::InternalServerError().finish()),
+    }
+
+    let today = chrono::Utc::now().date_naive();
+    // An empty calendar would make subscribed clients delete every event
+    let Ok((entries, recipes)) = database::meal_plan::get_range_with_recipes(
+        db_client,
+        feed.owner_id.clone(),
+        today - chrono::Duration::days(FEED_DAYS_BACK),
+        today + chrono::Duration::days(FEED_DAYS_AHEAD),
+    )
+    .await
+    else {
+        return Ok(HttpResponse::InternalServerError().finish());
+    };
+
+    let info = req.connection_info();
+    let base_url = format!("{}://{}", info.scheme(), info.host());
+    let calendar = ical::meal_plan_to_ics(&entries, &recipes, &base_url);
+
+    Ok(HttpResponse::Ok()
+        .append_header((CONTENT_DISPOSITION, "inline; filename=\"meal-plan.ics\""))
+        .append_header((CACHE_CONTROL, "no-cache"))
+        .content_type("text/calendar; charset=utf-8")
+        .body(calendar))
+}
+
+#[post("/export/plan/regenerate")]
+pub async fn regenerate_meal_plan_feed(
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = match super::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let feed_url =
+        database::calendar_feeds::regenerate_feed(client.get_ref(), user.id().to_string())
+            .await
+            .ok()
+            .map(|feed| feed_url(&req, feed.token()));
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(view::export::calendar_feed(feed_url.as_deref()).into_string()))
+}
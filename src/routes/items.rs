@@ -10,6 +10,7 @@ use crate::view::{self, render_item};
 #[derive(Deserialize)]
 pub struct CreateTodoRequest {
     pub task: String,
+    pub list: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -34,6 +35,12 @@ pub async fn create_item(
         owner_id: user.id().to_string(),
         task: form.task.clone(),
         completed: 0,
+        list: form
+            .list
+            .as_deref()
+            .map(str::trim)
+            .filter(|list| !list.is_empty())
+            .map(str::to_string),
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
     };
@@ -92,7 +99,35 @@ pub async fn delete_item(
         Err(response) => return Ok(response),
     };
 
-    database::items::delete_item(client, id, user.id().to_owned()).await;
+    let _ = database::items::delete_item(client, id, user.id().to_owned()).await;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(""))
+}
+
+#[post("items/{id}/pantry")]
+pub async fn move_item_to_pantry(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let user = match super::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    info!("move_item_to_pantry: {id}");
+
+    if let Err(err) = database::pantry::move_item_to_pantry(client, id, user.id().to_string()).await
+    {
+        log::error!("{err}");
+        return Ok(HttpResponse::InternalServerError()
+            .content_type("text/html; charset=utf-8")
+            .body(""));
+    }
+
     Ok(HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
         .body(""))
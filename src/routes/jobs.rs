@@ -0,0 +1,125 @@
+use actix_web::{HttpRequest, HttpResponse, Result, get, post, web};
+use maud::html;
+
+use crate::config::Server;
+use crate::database::jobs::{JobKind, JobStatus};
+use crate::database::{self, DBClient};
+use crate::jobs::{JobPayload, JobQueue};
+use crate::view::{self, index};
+
+#[get("/jobs")]
+pub async fn jobs_endpoint(
+    server: web::Data<Server>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client = client.get_ref();
+
+    let jobs = database::jobs::get_jobs(client, user.id().to_string())
+        .await
+        .unwrap_or_default();
+
+    let should_poll_reload = server.db_token().is_none();
+    let markup = index(
+        Some(view::jobs::jobs(jobs)),
+        should_poll_reload,
+        Some(&user),
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string()))
+}
+
+#[get("/jobs/{id}")]
+pub async fn job_status(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    let job = match database::jobs::get_job(client, id, user.id().to_string()).await {
+        Ok(job) => job,
+        Err(err) => {
+            let markup = html! {
+                div class="alert alert-error" { (err) }
+            };
+            return Ok(HttpResponse::Ok()
+                .content_type("text/html; charset=utf-8")
+                .body(markup.into_string()));
+        }
+    };
+
+    let markup = if job.is_pending() {
+        view::jobs::job_pending(&job)
+    } else if job.kind == JobKind::Reextract {
+        // Re-extraction replaces the recipe card, so swap the card back in.
+        // Failures show up on the jobs page.
+        let recipe_id = match serde_json::from_str(&job.payload) {
+            Ok(JobPayload::Reextract { recipe_id }) => Some(recipe_id),
+            _ => None,
+        };
+        let recipe = match recipe_id {
+            Some(recipe_id) => {
+                database::recipes::get_recipe(client, recipe_id, user.id().to_string())
+                    .await
+                    .ok()
+            }
+            None => None,
+        };
+        match recipe {
+            Some(recipe) => view::recipes::recipe_row(&recipe),
+            None => view::jobs::job_failed(&job),
+        }
+    } else if job.status == JobStatus::Dead {
+        view::jobs::job_failed(&job)
+    } else {
+        view::jobs::job_done(&job)
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string()))
+}
+
+#[post("/jobs/{id}/retry")]
+pub async fn retry_job(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    queue: web::Data<JobQueue>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    match database::jobs::retry_job(client, id, user.id().to_string()).await {
+        Ok(job) => {
+            queue.wake();
+            let markup = view::jobs::job_row(&job);
+            Ok(HttpResponse::Ok()
+                .content_type("text/html; charset=utf-8")
+                .body(markup.into_string()))
+        }
+        Err(err) => {
+            log::error!("{err}");
+            let markup = view::jobs::job_row_error(id, &err);
+            Ok(HttpResponse::Ok()
+                .content_type("text/html; charset=utf-8")
+                .body(markup.into_string()))
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use actix_web::{HttpRequest, HttpResponse, Result, delete, get, post, web};
+use log::info;
+use maud::html;
+use serde::Deserialize;
+
+use crate::config::Server;
+use crate::database::items::Item;
+use crate::database::meal_plan::{self, MealPlanEntry, MealSlot};
+use crate::database::recipes::Recipe;
+use crate::database::{self, DBClient};
+use crate::ingredients;
+use crate::view::{self, index};
+
+#[derive(Deserialize)]
+pub struct WeekQuery {
+    pub week: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PlanMealRequest {
+    pub recipe_id: i64,
+    pub planned_on: String,
+    pub slot: String,
+    pub servings: i64,
+}
+
+#[derive(Deserialize)]
+pub struct ShoppingListRequest {
+    pub week: String,
+    pub list: String,
+}
+
+fn parse_date(value: &str) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()
+}
+
+fn error_response(message: &str) -> HttpResponse {
+    let markup = html! {
+        div class="alert alert-error" {
+            (message)
+        }
+    };
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string())
+}
+
+/// Ingredients of every planned meal, scaled from the servings written in the
+/// recipe to the servings planned, merged into one list.
+pub struct WeekShoppingList {
+    pub lines: Vec<String>,
+    /// Titles of planned recipes without an ingredients section.
+    pub skipped: Vec<String>,
+}
+
+pub fn week_shopping_list(entries: &[MealPlanEntry], recipes: &[Recipe]) -> WeekShoppingList {
+    let mut scaled = Vec::new();
+    let mut skipped = Vec::new();
+
+    for entry in entries {
+        let Some(recipe) = recipes.iter().find(|recipe| recipe.id() == entry.recipe_id) else {
+            continue;
+        };
+
+        let meal_ingredients =
+            ingredients::scaled_recipe_ingredients(recipe.content(), entry.servings as f64);
+        if meal_ingredients.is_empty() {
+            let title = recipe.title().unwrap_or("Untitled recipe").to_string();
+            if !skipped.contains(&title) {
+                skipped.push(title);
+            }
+            continue;
+        }
+        scaled.extend(meal_ingredients);
+    }
+
+    WeekShoppingList {
+        lines: ingredients::aggregate(scaled),
+        skipped,
+    }
+}
+
+#[get("/plan")]
+pub async fn meal_plan_endpoint(
+    server: web::Data<Server>,
+    client: web::Data<DBClient>,
+    query: web::Query<WeekQuery>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client = client.get_ref();
+
+    let today = chrono::Utc::now().date_naive();
+    let start = meal_plan::week_start(query.week.as_deref().and_then(parse_date).unwrap_or(today));
+
+    let entries = meal_plan::get_week(client, user.id().to_string(), start)
+        .await
+        .unwrap_or_default();
+    let recipes = database::recipes::get_recipes(client, user.id().to_string())
+        .await
+        .unwrap_or_default();
+    let items = database::items::get_items(client, user.id().to_string())
+        .await
+        .unwrap_or_default();
+    let lists = database::items::list_names(&items);
+
+    let should_poll_reload = server.db_token().is_none();
+    let markup = index(
+        Some(view::meal_plan::meal_plan(
+            start, today, &entries, &recipes, &lists,
+        )),
+        should_poll_reload,
+        Some(&user),
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string()))
+}
+
+#[post("/plan")]
+pub async fn plan_meal(
+    form: web::Form<PlanMealRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    let Some(planned_on) = parse_date(&form.planned_on) else {
+        return Ok(error_response("Invalid date"));
+    };
+    let Some(slot) = MealSlot::parse(&form.slot) else {
+        return Ok(error_response("Invalid meal"));
+    };
+    if form.servings < 1 {
+        return Ok(error_response("Please plan at least one serving."));
+    }
+    // Only the owner's recipes can be planned
+    if database::recipes::get_recipe(client, form.recipe_id, user.id().to_string())
+        .await
+        .is_err()
+    {
+        return Ok(error_response("Recipe not found"));
+    }
+
+    info!("plan_meal: recipe {} on {planned_on}", form.recipe_id);
+
+    let entry = MealPlanEntry::new(
+        user.id().to_string(),
+        form.recipe_id,
+        planned_on,
+        slot,
+        form.servings,
+    );
+    if let Err(err) = meal_plan::create_entry(client, entry).await {
+        return Ok(error_response(&err));
+    }
+
+    let entries = meal_plan::get_week(
+        client,
+        user.id().to_string(),
+        meal_plan::week_start(planned_on),
+    )
+    .await
+    .unwrap_or_default();
+    let recipes = database::recipes::get_recipes(client, user.id().to_string())
+        .await
+        .unwrap_or_default();
+
+    let today = chrono::Utc::now().date_naive();
+    let markup = view::meal_plan::day_column(planned_on, today, &entries, &recipes);
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string()))
+}
+
+#[delete("/plan/{id}")]
+pub async fn delete_planned_meal(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    let _ = meal_plan::delete_entry(client, id, user.id().to_string()).await;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(""))
+}
+
+#[post("/plan/shopping-list")]
+pub async fn generate_shopping_list(
+    form: web::Form<ShoppingListRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+    let owner_id = user.id().to_string();
+
+    let Some(start) = parse_date(&form.week).map(meal_plan::week_start) else {
+        return Ok(error_response("Invalid week"));
+    };
+    let list = form.list.trim();
+    if list.is_empty() {
+        return Ok(error_response("Please choose a list."));
+    }
+
+    let (entries, recipes) =
+        match meal_plan::get_week_with_recipes(client, owner_id.clone(), start).await {
+            Ok(week) => week,
+            Err(err) => {
+                log::error!("{err}");
+                return Ok(error_response("Could not load the meal plan."));
+            }
+        };
+    if entries.is_empty() {
+        return Ok(error_response("Nothing is planned for this week yet."));
+    }
+
+    let shopping_list = week_shopping_list(&entries, &recipes);
+    let pantry = database::pantry::get_pantry_items(client, owner_id.clone())
+        .await
+        .unwrap_or_default();
+    let check = ingredients::subtract_pantry(&shopping_list.lines, &pantry);
+
+    info!(
+        "generate_shopping_list: {} items for week of {start} into {list}",
+        check.to_buy.len()
+    );
+
+    let items: Vec<Item> = check
+        .to_buy
+        .iter()
+        .map(|line| Item {
+            id: None,
+            owner_id: owner_id.clone(),
+            task: line.clone(),
+            completed: 0,
+            list: Some(list.to_string()),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        })
+        .collect();
+    database::items::create_items(client, items).await;
+
+    let markup = view::meal_plan::shopping_list_created(
+        list,
+        &check.to_buy,
+        &check.covered,
+        &shopping_list.skipped,
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string()))
+}
@@ -1,15 +1,19 @@
 use actix_web::web::Data;
 use actix_web::{HttpMessage, HttpRequest, HttpResponse};
-use log::error;
 use rand::Rng;
 
-use crate::database::DBClient;
-use crate::{llm, user};
+use crate::user;
 
+pub mod admin;
+pub mod api;
 pub mod assets;
 pub mod auth;
 pub mod export;
 pub mod items;
+pub mod jobs;
+pub mod meal_plan;
+pub mod pantry;
+pub mod profile;
 pub mod recipes;
 pub mod technical;
 
@@ -19,6 +23,7 @@ pub fn get_user(req: HttpRequest) -> Option<user::User> {
         .map(|u| u.as_ref().clone())
 }
 
+#[allow(clippy::result_large_err)]
 pub fn get_user_or_redirect(req: &HttpRequest) -> Result<user::User, HttpResponse> {
     match get_user(req.clone()) {
         Some(user) => Ok(user),
@@ -41,35 +46,3 @@ pub fn random_html_safe_id() -> u64 {
     let mut rng = rand::rng();
     rng.random::<u64>()
 }
-
-// New Rust-based LLM task generation
-pub async fn generate_task_response_rust_llm(
-    user_message: &str,
-    llm_provider: &str,
-    llm_api_key: &str,
-    db_client: &DBClient,
-    user_id: String,
-) -> String {
-    let use_gemini = llm_provider.to_lowercase() == "gemini";
-
-    match llm::extract_grocery_list_with_llm(
-        user_message,
-        llm_api_key,
-        use_gemini,
-        user_id,
-        db_client,
-    )
-    .await
-    {
-        Ok(a) => a,
-        Err(e) => {
-            match e {
-                llm::LlmError::Request(error) => error!("{error}"),
-                llm::LlmError::Auth(error) => error!("{error}"),
-                llm::LlmError::Parse(error) => error!("{error}"),
-            };
-
-            "Something went wrong contacting the LLM agent".to_string()
-        }
-    }
-}
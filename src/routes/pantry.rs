@@ -0,0 +1,247 @@
+use actix_web::{HttpRequest, HttpResponse, Result, delete, get, patch, post, web};
+use log::info;
+use maud::html;
+use serde::Deserialize;
+
+use crate::config::Server;
+use crate::database::pantry::PantryItem;
+use crate::database::{self, DBClient};
+use crate::view::{self, index};
+
+#[derive(Deserialize)]
+pub struct PantryItemRequest {
+    pub name: String,
+    pub quantity: Option<String>,
+    pub unit: Option<String>,
+    pub expires_on: Option<String>,
+}
+
+impl PantryItemRequest {
+    /// Empty form fields come in as empty strings, treat them as unset.
+    fn parse(&self) -> std::result::Result<ParsedPantryItem, String> {
+        let name = self.name.trim().to_string();
+        if name.is_empty() {
+            return Err("Please enter a name.".to_string());
+        }
+
+        let quantity = match non_empty(&self.quantity) {
+            Some(quantity) => Some(
+                quantity
+                    .replace(',', ".")
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|q| *q >= 0.0)
+                    .ok_or_else(|| format!("Invalid quantity: {quantity}"))?,
+            ),
+            None => None,
+        };
+
+        let expires_on = match non_empty(&self.expires_on) {
+            Some(date) => Some(
+                chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid expiry date: {date}"))?,
+            ),
+            None => None,
+        };
+
+        Ok(ParsedPantryItem {
+            name,
+            quantity,
+            unit: non_empty(&self.unit).map(str::to_string),
+            expires_on,
+        })
+    }
+}
+
+struct ParsedPantryItem {
+    name: String,
+    quantity: Option<f64>,
+    unit: Option<String>,
+    expires_on: Option<chrono::NaiveDate>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// Errors are rendered as a table row since they are swapped into the list.
+fn error_response(message: &str) -> HttpResponse {
+    let markup = html! {
+        tr {
+            td colspan="4" {
+                div class="alert alert-error" {
+                    (message)
+                }
+            }
+        }
+    };
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string())
+}
+
+#[get("/pantry")]
+pub async fn pantry_endpoint(
+    server: web::Data<Server>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client = client.get_ref();
+
+    let items = database::pantry::get_pantry_items(client, user.id().to_string())
+        .await
+        .unwrap_or_default();
+
+    let should_poll_reload = server.db_token().is_none();
+    let markup = index(
+        Some(view::pantry::pantry(&items)),
+        should_poll_reload,
+        Some(&user),
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string()))
+}
+
+#[post("/pantry")]
+pub async fn create_pantry_item(
+    form: web::Form<PantryItemRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    let parsed = match form.parse() {
+        Ok(parsed) => parsed,
+        Err(err) => return Ok(error_response(&err)),
+    };
+
+    let item = PantryItem::new(
+        user.id().to_string(),
+        parsed.name,
+        parsed.quantity,
+        parsed.unit,
+        parsed.expires_on,
+    );
+
+    match database::pantry::create_pantry_item(client, item).await {
+        Ok(item) => Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(view::pantry::pantry_row(&item).into_string())),
+        Err(err) => Ok(error_response(&err)),
+    }
+}
+
+#[patch("/pantry/{id}")]
+pub async fn update_pantry_item(
+    path: web::Path<i64>,
+    form: web::Form<PantryItemRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    info!("update_pantry_item: {id}");
+
+    let parsed = match form.parse() {
+        Ok(parsed) => parsed,
+        Err(err) => return Ok(error_response(&err)),
+    };
+
+    let item = database::pantry::update_pantry_item(
+        client,
+        id,
+        parsed.name,
+        parsed.quantity,
+        parsed.unit,
+        parsed.expires_on,
+        user.id().to_string(),
+    )
+    .await;
+
+    match item {
+        Ok(item) => Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(view::pantry::pantry_row(&item).into_string())),
+        Err(err) => Ok(error_response(&err)),
+    }
+}
+
+#[delete("/pantry/{id}")]
+pub async fn delete_pantry_item(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    let _ = database::pantry::delete_pantry_item(client, id, user.id().to_string()).await;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(""))
+}
+
+#[get("/pantry/{id}/edit")]
+pub async fn edit_pantry_item(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    match database::pantry::get_pantry_item(client, id, user.id().to_string()).await {
+        Ok(item) => Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(view::pantry::pantry_edit_row(&item).into_string())),
+        Err(_) => Ok(HttpResponse::InternalServerError()
+            .content_type("text/html; charset=utf-8")
+            .body("")),
+    }
+}
+
+#[get("/pantry/{id}/cancel")]
+pub async fn cancel_edit_pantry_item(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    match database::pantry::get_pantry_item(client, id, user.id().to_string()).await {
+        Ok(item) => Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(view::pantry::pantry_row(&item).into_string())),
+        Err(_) => Ok(HttpResponse::InternalServerError()
+            .content_type("text/html; charset=utf-8")
+            .body("")),
+    }
+}
@@ -0,0 +1,112 @@
+use actix_session::Session;
+use actix_web::{HttpRequest, HttpResponse, Result, delete, post, web};
+
+use crate::database::DBClient;
+use crate::database::api_tokens::{self, ApiScope, ApiToken};
+use crate::database::auth_sessions;
+use crate::oidc::AUTH_SESSION_KEY;
+use crate::view;
+
+#[delete("/profile/sessions/{id}")]
+pub async fn revoke_session(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    session: Session,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    // Revoking the session in use signs this browser out as well
+    let current = match session.get::<String>(AUTH_SESSION_KEY) {
+        Ok(Some(session_id)) => auth_sessions::get_auth_session(client, &session_id)
+            .await
+            .ok()
+            .flatten()
+            .is_some_and(|auth_session| auth_session.id == Some(id)),
+        _ => false,
+    };
+
+    if let Err(err) =
+        auth_sessions::revoke_owner_auth_session(client, id, user.id().to_string()).await
+    {
+        return Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(format!(
+                r#"<tr><td colspan="4" class="text-error">{err}</td></tr>"#
+            )));
+    }
+
+    if current {
+        session.purge();
+        return Ok(HttpResponse::Ok()
+            .insert_header(("HX-Redirect", "/auth/login"))
+            .finish());
+    }
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(""))
+}
+
+/// The form sends one `scope` field per checked box, which `web::Form`
+/// cannot collect, so the body is parsed by hand.
+#[post("/profile/tokens")]
+pub async fn create_api_token(
+    body: web::Bytes,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let client: &DBClient = client.get_ref();
+
+    let mut name = String::new();
+    let mut scopes = Vec::new();
+    for (key, value) in url::form_urlencoded::parse(&body) {
+        match key.as_ref() {
+            "name" => name = value.trim().to_string(),
+            "scope" => scopes.extend(ApiScope::parse(&value)),
+            _ => {}
+        }
+    }
+    if name.is_empty() || scopes.is_empty() {
+        return Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(r#"<div id="api-tokens" class="alert alert-error w-4xl mx-auto mt-4">Please name the token and pick at least one scope. <a href="/profile" class="link">Back</a></div>"#));
+    }
+
+    let (token, secret) = ApiToken::new(user.id().to_string(), name, &scopes);
+    let created = api_tokens::create_api_token(client, token).await.is_ok();
+
+    let tokens = api_tokens::get_api_tokens(client, user.id().to_string())
+        .await
+        .unwrap_or_default();
+    let markup = view::profile::api_tokens_card(&tokens, created.then_some(secret.as_str()));
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string()))
+}
+
+#[delete("/profile/tokens/{id}")]
+pub async fn revoke_api_token(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let user = match crate::routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let _ = api_tokens::revoke_api_token(client.get_ref(), id, user.id().to_string()).await;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(""))
+}
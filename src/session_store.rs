@@ -0,0 +1,238 @@
+//! Session store keeping the session state in the database, so sessions can
+//! be listed and revoked on the server.
+
+use std::collections::HashMap;
+
+use actix_session::storage::{
+    LoadError, SaveError, SessionKey, SessionStore, UpdateError, generate_session_key,
+};
+use actix_web::cookie::time::Duration;
+
+use crate::database::DBClient;
+use crate::database::sessions::{self, StoredSession};
+
+/// How often expired sessions are removed from the database.
+const CLEANUP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+#[derive(Clone)]
+pub struct LibsqlSessionStore {
+    client: DBClient,
+}
+
+impl LibsqlSessionStore {
+    pub fn new(client: DBClient) -> Self {
+        LibsqlSessionStore { client }
+    }
+
+    /// Periodically deletes expired sessions in the background.
+    pub fn start_cleanup(&self) {
+        let client = self.client.clone();
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                sessions::delete_expired_sessions(&client).await;
+            }
+        });
+    }
+}
+
+fn expires_at(ttl: &Duration) -> chrono::DateTime<chrono::Utc> {
+    chrono::Utc::now() + chrono::Duration::seconds(ttl.whole_seconds())
+}
+
+impl SessionStore for LibsqlSessionStore {
+    async fn load(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<Option<HashMap<String, String>>, LoadError> {
+        let Some(session) = sessions::get_session(&self.client, session_key.as_ref())
+            .await
+            .map_err(|err| LoadError::Other(anyhow::anyhow!(err)))?
+        else {
+            return Ok(None);
+        };
+
+        serde_json::from_str(&session.state)
+            .map(Some)
+            .map_err(|err| LoadError::Deserialization(err.into()))
+    }
+
+    async fn save(
+        &self,
+        session_state: HashMap<String, String>,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let state = serde_json::to_string(&session_state)
+            .map_err(|err| SaveError::Serialization(err.into()))?;
+        let session_key = generate_session_key();
+
+        sessions::create_session(
+            &self.client,
+            StoredSession::new(session_key.as_ref().to_string(), state, expires_at(ttl)),
+        )
+        .await
+        .map_err(|err| SaveError::Other(anyhow::anyhow!(err)))?;
+
+        Ok(session_key)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: HashMap<String, String>,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let state = serde_json::to_string(&session_state)
+            .map_err(|err| UpdateError::Serialization(err.into()))?;
+
+        let existing = sessions::get_session(&self.client, session_key.as_ref())
+            .await
+            .map_err(|err| UpdateError::Other(anyhow::anyhow!(err)))?;
+
+        // The session ran out or was revoked in the meantime, start a new one
+        let Some(mut session) = existing else {
+            return self
+                .save(session_state, ttl)
+                .await
+                .map_err(|err| UpdateError::Other(anyhow::anyhow!(err)));
+        };
+
+        session.state = state;
+        session.expires_at = expires_at(ttl);
+        sessions::update_session(&self.client, session)
+            .await
+            .map_err(|err| UpdateError::Other(anyhow::anyhow!(err)))?;
+
+        Ok(session_key)
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> anyhow::Result<()> {
+        if let Some(mut session) = sessions::get_session(&self.client, session_key.as_ref())
+            .await
+            .map_err(|err| anyhow::anyhow!(err))?
+        {
+            session.expires_at = expires_at(ttl);
+            sessions::update_session(&self.client, session)
+                .await
+                .map_err(|err| anyhow::anyhow!(err))?;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> anyhow::Result<()> {
+        sessions::delete_session(&self.client, session_key.as_ref())
+            .await
+            .map_err(|err| anyhow::anyhow!(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(user: &str) -> HashMap<String, String> {
+        HashMap::from([("user".to_string(), user.to_string())])
+    }
+
+    /// Session keys are not `Clone`, `update` takes them by value.
+    fn copy(key: &SessionKey) -> SessionKey {
+        SessionKey::try_from(key.as_ref().to_string()).unwrap()
+    }
+
+    async fn store() -> LibsqlSessionStore {
+        LibsqlSessionStore::new(crate::database::test_client().await)
+    }
+
+    async fn stored_sessions(store: &LibsqlSessionStore) -> i64 {
+        let db = crate::database::connection(&store.client).await;
+        let mut rows = db
+            .get_connection()
+            .query("SELECT COUNT(*) FROM sessions", ())
+            .await
+            .unwrap();
+        rows.next().await.unwrap().unwrap().get(0).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_save_and_load() {
+        let store = store().await;
+        let key = store
+            .save(state("alice"), &Duration::hours(1))
+            .await
+            .unwrap();
+
+        assert_eq!(store.load(&key).await.unwrap(), Some(state("alice")));
+        assert_eq!(store.load(&generate_session_key()).await.unwrap(), None);
+    }
+
+    #[actix_web::test]
+    async fn test_update_keeps_the_key() {
+        let store = store().await;
+        let key = store
+            .save(state("alice"), &Duration::hours(1))
+            .await
+            .unwrap();
+
+        let updated = store
+            .update(copy(&key), state("bob"), &Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(updated.as_ref(), key.as_ref());
+        assert_eq!(store.load(&key).await.unwrap(), Some(state("bob")));
+        assert_eq!(stored_sessions(&store).await, 1);
+    }
+
+    #[actix_web::test]
+    async fn test_update_of_a_missing_session_starts_a_new_one() {
+        let store = store().await;
+        let missing = generate_session_key();
+
+        let key = store
+            .update(copy(&missing), state("alice"), &Duration::hours(1))
+            .await
+            .unwrap();
+        assert_ne!(key.as_ref(), missing.as_ref());
+        assert_eq!(store.load(&key).await.unwrap(), Some(state("alice")));
+    }
+
+    #[actix_web::test]
+    async fn test_delete() {
+        let store = store().await;
+        let key = store
+            .save(state("alice"), &Duration::hours(1))
+            .await
+            .unwrap();
+
+        store.delete(&key).await.unwrap();
+        assert_eq!(store.load(&key).await.unwrap(), None);
+        assert_eq!(stored_sessions(&store).await, 0);
+    }
+
+    #[actix_web::test]
+    async fn test_expired_sessions_are_not_loaded() {
+        let store = store().await;
+        let key = store.save(state("alice"), &Duration::ZERO).await.unwrap();
+        assert_eq!(store.load(&key).await.unwrap(), None);
+
+        // A new TTL does not revive it
+        store.update_ttl(&key, &Duration::hours(1)).await.unwrap();
+        assert_eq!(store.load(&key).await.unwrap(), None);
+
+        let key = store.save(state("bob"), &Duration::hours(1)).await.unwrap();
+        store.update_ttl(&key, &Duration::ZERO).await.unwrap();
+        assert_eq!(store.load(&key).await.unwrap(), None);
+    }
+
+    #[actix_web::test]
+    async fn test_cleanup_deletes_expired_sessions() {
+        let store = store().await;
+        store.save(state("alice"), &Duration::ZERO).await.unwrap();
+        let live = store.save(state("bob"), &Duration::hours(1)).await.unwrap();
+        assert_eq!(stored_sessions(&store).await, 2);
+
+        sessions::delete_expired_sessions(&store.client).await;
+        assert_eq!(stored_sessions(&store).await, 1);
+        assert_eq!(store.load(&live).await.unwrap(), Some(state("bob")));
+    }
+}
@@ -1,14 +1,67 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+/// What a user may do on this instance, decided at login.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    #[default]
+    Member,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Member => "member",
+        }
+    }
+
+    /// Admins are in the admin group of their identity provider or sign in
+    /// with a verified address from the allow-list.
+    pub fn for_login(
+        groups: &[String],
+        admin_group: Option<&str>,
+        email: &str,
+        email_verified: bool,
+        admin_emails: &[String],
+    ) -> Role {
+        let in_group = admin_group.is_some_and(|group| groups.iter().any(|g| g == group));
+        let allowed = email_verified
+            && admin_emails
+                .iter()
+                .any(|admin| admin.eq_ignore_ascii_case(email.trim()));
+        if in_group || allowed {
+            Role::Admin
+        } else {
+            Role::Member
+        }
+    }
+}
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct User {
     pub id: String,
     pub email: String,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub role: Role,
 }
 
 impl User {
     pub fn new(id: String, email: String) -> Self {
-        User { id, email }
+        User {
+            id,
+            email,
+            name: None,
+            given_name: None,
+            family_name: None,
+            avatar_url: None,
+            role: Role::Member,
+        }
     }
 
     pub fn id(&self) -> &str {
@@ -19,14 +72,139 @@ impl User {
         &self.email
     }
 
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+
+    pub fn avatar_url(&self) -> Option<&str> {
+        self.avatar_url.as_deref()
+    }
+
+    /// Full name from the identity provider, the email when it has none.
+    pub fn display_name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        match (&self.given_name, &self.family_name) {
+            (Some(given), Some(family)) => format!("{given} {family}"),
+            (Some(name), None) | (None, Some(name)) => name.clone(),
+            (None, None) => self.email.clone(),
+        }
+    }
+
     pub fn initials(&self) -> String {
-        let mut split = self.email().split(".");
+        let first_letter = |name: &str| name.chars().next().map(|c| c.to_uppercase().to_string());
+
+        if let (Some(given), Some(family)) = (&self.given_name, &self.family_name) {
+            return format!(
+                "{}{}",
+                first_letter(given).unwrap_or_default(),
+                first_letter(family).unwrap_or_default()
+            );
+        }
+
+        let full_name = self.name.as_deref().or(self.given_name.as_deref());
+        if let Some(full_name) = full_name {
+            let mut words = full_name.split_whitespace();
+            if let Some(first) = words.next() {
+                let last = words.next_back();
+                return format!(
+                    "{}{}",
+                    first_letter(first).unwrap_or_default(),
+                    last.and_then(first_letter).unwrap_or_default()
+                );
+            }
+        }
+
+        // Without a name, guess from addresses like first.last@example.com
+        let local_part = self.email().split('@').next().unwrap_or_default();
+        let mut split = local_part.split(".");
         let first_name = split.next().unwrap_or("Unknown");
-        let last_name = split.next().unwrap_or("User");
+        let last_name = split.next().unwrap_or("");
         format!(
             "{}{}",
-            first_name.chars().next().unwrap_or(' ').to_uppercase(),
-            last_name.chars().next().unwrap_or(' ').to_uppercase()
+            first_letter(first_name).unwrap_or_default(),
+            first_letter(last_name).unwrap_or_default()
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(name: Option<&str>, given: Option<&str>, family: Option<&str>, email: &str) -> User {
+        User {
+            name: name.map(str::to_string),
+            given_name: given.map(str::to_string),
+            family_name: family.map(str::to_string),
+            ..User::new("1".to_string(), email.to_string())
+        }
+    }
+
+    #[test]
+    fn test_initials_prefer_given_and_family_name() {
+        let user = user(
+            Some("Ada King"),
+            Some("ada"),
+            Some("Lovelace"),
+            "countess@example.com",
+        );
+        assert_eq!(user.initials(), "AL");
+        assert_eq!(user.display_name(), "Ada King");
+    }
+
+    #[test]
+    fn test_initials_from_full_name() {
+        let user = user(
+            Some("Grace Brewster Hopper"),
+            None,
+            None,
+            "amazing@example.com",
+        );
+        assert_eq!(user.initials(), "GH");
+
+        let user = self::user(Some("Cher"), None, None, "cher@example.com");
+        assert_eq!(user.initials(), "C");
+    }
+
+    #[test]
+    fn test_initials_fall_back_to_email() {
+        let user = user(None, None, None, "jane.doe@example.com");
+        assert_eq!(user.initials(), "JD");
+        assert_eq!(user.display_name(), "jane.doe@example.com");
+
+        let user = self::user(None, None, None, "guest@gmx.com");
+        assert_eq!(user.initials(), "G");
+    }
+
+    #[test]
+    fn test_role_for_login() {
+        let groups = vec!["staff".to_string(), "rezi-admins".to_string()];
+        let admins = vec!["boss@example.com".to_string()];
+
+        assert_eq!(
+            Role::for_login(&groups, Some("rezi-admins"), "a@example.com", false, &[]),
+            Role::Admin
+        );
+        assert_eq!(
+            Role::for_login(&groups, Some("other"), "a@example.com", true, &admins),
+            Role::Member
+        );
+        assert_eq!(
+            Role::for_login(&[], None, "Boss@Example.com", true, &admins),
+            Role::Admin
+        );
+        // Anyone could claim an unverified address
+        assert_eq!(
+            Role::for_login(&[], None, "boss@example.com", false, &admins),
+            Role::Member
+        );
+    }
+
+    #[test]
+    fn test_display_name_from_parts() {
+        let user = user(None, Some("Ada"), Some("Lovelace"), "ada@example.com");
+        assert_eq!(user.display_name(), "Ada Lovelace");
+    }
+}
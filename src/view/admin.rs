@@ -0,0 +1,258 @@
+use libsql_orm::PoolMetrics;
+use maud::{Markup, html};
+
+use crate::database::admin::Storage;
+use crate::database::jobs::Job;
+use crate::database::llm_calls::LlmUsage;
+use crate::database::users::UserRecord;
+use crate::view::jobs::status_badge;
+
+/// Everything shown on the admin page.
+pub struct Overview {
+    pub users: Vec<UserRecord>,
+    pub storage: Vec<Storage>,
+    pub usage: Vec<LlmUsage>,
+    pub queue: Vec<Job>,
+    pub failed: Vec<Job>,
+    pub pool: PoolMetrics,
+}
+
+/// Sizes like `12.3 KB`, in powers of 1000 like file managers show them.
+fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1000.0 {
+            break;
+        }
+        size /= 1000.0;
+        unit = next;
+    }
+    format!("{size:.1} {unit}")
+}
+
+pub fn admin(overview: &Overview, current_user_id: &str) -> Markup {
+    html! {
+        div class="p-2 space-y-4" {
+            (users_card(overview, current_user_id))
+            (pool_card(&overview.pool))
+            (queue_card(&overview.queue))
+            (failed_card(&overview.failed))
+        }
+    }
+}
+
+fn users_card(overview: &Overview, current_user_id: &str) -> Markup {
+    html! {
+        div class="card bg-base-100 shadow-xl" {
+            div class="card-body" {
+                h2 class="card-title text-2xl" { "Users" }
+                p class="text-sm text-base-content/70" {
+                    "Storage counts the text of items, recipes, pantry and jobs. LLM calls cover the last 30 days."
+                }
+                div class="overflow-x-auto" {
+                    table class="table table-zebra" {
+                        thead {
+                            tr {
+                                th { "User" }
+                                th { "Role" }
+                                th { "Last login" }
+                                th { "Items" }
+                                th { "Recipes" }
+                                th { "Pantry" }
+                                th { "Storage" }
+                                th { "LLM calls" }
+                                th {}
+                            }
+                        }
+                        tbody {
+                            @for user in &overview.users {
+                                (user_row(
+                                    user,
+                                    overview.storage.iter().find(|storage| storage.owner_id == user.user_id),
+                                    overview.usage.iter().find(|usage| usage.owner_id == user.user_id),
+                                    user.user_id == current_user_id,
+                                ))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn user_row(
+    user: &UserRecord,
+    storage: Option<&Storage>,
+    usage: Option<&LlmUsage>,
+    is_current: bool,
+) -> Markup {
+    let storage = storage.cloned().unwrap_or_default();
+    let usage = usage.cloned().unwrap_or_default();
+    let name = user.name.as_deref().unwrap_or(&user.email);
+
+    html! {
+        tr id=(format!("admin-user-{}", user.id())) {
+            td {
+                div class="font-medium" { (name) }
+                div class="text-xs text-base-content/60" { (user.email) }
+            }
+            td {
+                span class=(if user.role == crate::user::Role::Admin { "badge badge-primary" } else { "badge badge-ghost" }) {
+                    (user.role.as_str())
+                }
+                @if user.is_disabled() {
+                    " "
+                    span class="badge badge-error" { "disabled" }
+                }
+            }
+            td class="text-xs" { (user.last_login_at.format("%b %d, %Y %H:%M")) }
+            td { (storage.items) }
+            td { (storage.recipes) }
+            td { (storage.pantry) }
+            td { (format_bytes(storage.bytes)) }
+            td title=(format!("{} characters sent, {} received", usage.prompt_chars, usage.response_chars)) {
+                (usage.calls)
+                @if usage.failed > 0 {
+                    span class="text-xs text-error" { " (" (usage.failed) " failed)" }
+                }
+            }
+            td {
+                @if is_current {
+                    span class="text-xs text-base-content/60" { "You" }
+                } @else if user.is_disabled() {
+                    button class="btn btn-xs btn-outline"
+                        hx-post=(format!("/admin/users/{}/enable", user.id()))
+                        hx-target=(format!("#admin-user-{}", user.id()))
+                        hx-swap="outerHTML" {
+                        "Enable"
+                    }
+                } @else {
+                    button class="btn btn-xs btn-error btn-outline"
+                        hx-post=(format!("/admin/users/{}/disable", user.id()))
+                        hx-target=(format!("#admin-user-{}", user.id()))
+                        hx-swap="outerHTML"
+                        hx-confirm=(format!("Disable {name}? They are signed out right away.")) {
+                        "Disable"
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn job_table(jobs: &[Job]) -> Markup {
+    html! {
+        div class="overflow-x-auto" {
+            table class="table table-zebra" {
+                thead {
+                    tr {
+                        th { "Job" }
+                        th { "Owner" }
+                        th { "Status" }
+                        th { "Attempts" }
+                        th { "Updated" }
+                        th { "Error" }
+                    }
+                }
+                tbody {
+                    @for job in jobs {
+                        tr {
+                            td { (job.kind.label()) " #" (job.id()) }
+                            td class="text-xs max-w-32 truncate" { (job.owner_id()) }
+                            td { (status_badge(job.status)) }
+                            td { (job.attempts) " / " (job.max_attempts) }
+                            td class="text-xs" { (job.updated_at.format("%b %d, %Y %H:%M")) }
+                            td class="text-xs max-w-xs truncate" {
+                                @if let Some(error) = &job.last_error {
+                                    span class="text-error" title=(error) { (error) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn pool_card(pool: &PoolMetrics) -> Markup {
+    let average_wait = pool.wait_time / pool.acquired.max(1) as u32;
+    html! {
+        div class="card bg-base-100 shadow-xl" {
+            div class="card-body" {
+                h2 class="card-title text-2xl" { "Database pool" }
+                div class="stats stats-vertical lg:stats-horizontal" {
+                    div class="stat" {
+                        div class="stat-title" { "Connections" }
+                        div class="stat-value" { (pool.in_use) " / " (pool.size) }
+                        div class="stat-desc" { "in use of open, at most " (pool.max_size) }
+                    }
+                    div class="stat" {
+                        div class="stat-title" { "Checkouts" }
+                        div class="stat-value" { (pool.acquired) }
+                        div class="stat-desc" { (format!("{average_wait:.2?}")) " average wait" }
+                    }
+                    div class="stat" {
+                        div class="stat-title" { "Opened / closed" }
+                        div class="stat-value" { (pool.created) " / " (pool.closed) }
+                        div class="stat-desc" { (pool.failed_health_checks) " failed health checks" }
+                    }
+                    div class="stat" {
+                        div class="stat-title" { "Timeouts" }
+                        div class=(if pool.timeouts > 0 { "stat-value text-error" } else { "stat-value" }) { (pool.timeouts) }
+                        div class="stat-desc" { "callers that found no free connection" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn queue_card(queue: &[Job]) -> Markup {
+    html! {
+        div class="card bg-base-100 shadow-xl" {
+            div class="card-body" {
+                h2 class="card-title text-2xl" { "Job queue" }
+                @if queue.is_empty() {
+                    div class="text-center py-4 text-base-content/60" { "Nothing queued or running." }
+                } @else {
+                    (job_table(queue))
+                }
+            }
+        }
+    }
+}
+
+fn failed_card(failed: &[Job]) -> Markup {
+    html! {
+        div class="card bg-base-100 shadow-xl" {
+            div class="card-body" {
+                h2 class="card-title text-2xl" { "Failed imports" }
+                @if failed.is_empty() {
+                    div class="text-center py-4 text-base-content/60" { "No failed jobs." }
+                } @else {
+                    (job_table(failed))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(999), "999 B");
+        assert_eq!(format_bytes(12_345), "12.3 KB");
+        assert_eq!(format_bytes(4_200_000), "4.2 MB");
+    }
+}
@@ -0,0 +1,278 @@
+//! Reference page for the JSON API, rendered from its OpenAPI document so
+//! no third party docs viewer is needed.
+
+use maud::{Markup, PreEscaped, html};
+use serde_json::Value;
+
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+/// Method, path and the operation object.
+type Operation<'a> = (&'a str, String, Value);
+
+/// Short type of a schema like `string | null` or `ItemResponse[]`.
+fn type_name(schema: &Value) -> String {
+    if let Some(reference) = schema["$ref"].as_str() {
+        return reference
+            .rsplit('/')
+            .next()
+            .unwrap_or(reference)
+            .to_string();
+    }
+    for combinator in ["oneOf", "anyOf", "allOf"] {
+        if let Some(schemas) = schema[combinator].as_array() {
+            return schemas
+                .iter()
+                .map(type_name)
+                .collect::<Vec<_>>()
+                .join(" | ");
+        }
+    }
+    if let Some(values) = schema["enum"].as_array() {
+        return values
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|value| format!("\"{value}\""))
+            .collect::<Vec<_>>()
+            .join(" | ");
+    }
+
+    let types: Vec<&str> = match &schema["type"] {
+        Value::String(name) => vec![name.as_str()],
+        Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    };
+    if types.is_empty() {
+        return "any".to_string();
+    }
+    types
+        .into_iter()
+        .map(|name| match name {
+            "array" => format!("{}[]", type_name(&schema["items"])),
+            name => name.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn markdown(text: &str) -> Markup {
+    PreEscaped(markdown::to_html(text))
+}
+
+fn method_badge(method: &str) -> Markup {
+    let class = match method {
+        "get" => "badge badge-info",
+        "post" => "badge badge-success",
+        "delete" => "badge badge-error",
+        _ => "badge badge-warning",
+    };
+    html! {
+        span class=(format!("{class} font-mono w-16")) { (method.to_uppercase()) }
+    }
+}
+
+fn scopes(operation: &Value) -> Vec<String> {
+    operation["security"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|requirement| requirement["bearer"].as_array())
+        .flatten()
+        .filter_map(|scope| scope.as_str().map(str::to_string))
+        .collect()
+}
+
+fn operation(method: &str, path: &str, operation: &Value) -> Markup {
+    let parameters = operation["parameters"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let body = &operation["requestBody"]["content"]["application/json"]["schema"];
+    let responses = operation["responses"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+
+    html! {
+        div class="collapse collapse-arrow bg-base-200" {
+            input type="checkbox";
+            div class="collapse-title flex items-center gap-3" {
+                (method_badge(method))
+                code { (path) }
+                @if let Some(summary) = operation["summary"].as_str() {
+                    span class="text-sm text-base-content/70" { (summary) }
+                }
+            }
+            div class="collapse-content space-y-3" {
+                @if let Some(description) = operation["description"].as_str() {
+                    div class="prose max-w-none" { (markdown(description)) }
+                }
+                @let scopes = scopes(operation);
+                @if !scopes.is_empty() {
+                    p class="text-sm" {
+                        "Token scope: "
+                        @for scope in scopes {
+                            span class="badge badge-outline badge-sm mr-1" { (scope) }
+                        }
+                    }
+                }
+                @if !parameters.is_empty() {
+                    h4 class="font-semibold" { "Parameters" }
+                    table class="table table-sm" {
+                        tbody {
+                            @for parameter in &parameters {
+                                tr {
+                                    td { code { (parameter["name"].as_str().unwrap_or_default()) } }
+                                    td { (parameter["in"].as_str().unwrap_or_default()) }
+                                    td { code { (type_name(&parameter["schema"])) } }
+                                    td {
+                                        @if parameter["required"].as_bool().unwrap_or(false) { "required" }
+                                    }
+                                    td { (parameter["description"].as_str().unwrap_or_default()) }
+                                }
+                            }
+                        }
+                    }
+                }
+                @if !body.is_null() {
+                    p { "Request body: " code { (type_name(body)) } }
+                }
+                h4 class="font-semibold" { "Responses" }
+                table class="table table-sm" {
+                    tbody {
+                        @for (status, response) in &responses {
+                            @let schema = &response["content"]["application/json"]["schema"];
+                            tr {
+                                td { code { (status) } }
+                                td { (response["description"].as_str().unwrap_or_default()) }
+                                td {
+                                    @if !schema.is_null() { code { (type_name(schema)) } }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn schema(name: &str, schema: &Value) -> Markup {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    let properties = schema["properties"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+
+    html! {
+        div id=(format!("schema-{name}")) class="space-y-2" {
+            h3 class="font-semibold font-mono" { (name) }
+            @if let Some(description) = schema["description"].as_str() {
+                div class="prose max-w-none text-sm" { (markdown(description)) }
+            }
+            @if properties.is_empty() {
+                p { code { (type_name(schema)) } }
+            } @else {
+                table class="table table-sm" {
+                    tbody {
+                        @for (property, value) in &properties {
+                            tr {
+                                td { code { (property) } }
+                                td { code { (type_name(value)) } }
+                                td {
+                                    @if required.contains(&property.as_str()) { "required" }
+                                }
+                                td { (value["description"].as_str().unwrap_or_default()) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn api_docs(spec: &Value) -> Markup {
+    let info = &spec["info"];
+    let paths = spec["paths"].as_object().cloned().unwrap_or_default();
+    let schemas = spec["components"]["schemas"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+
+    // Operations grouped by their first tag, in document order
+    let mut tags: Vec<(String, Vec<Operation>)> = Vec::new();
+    for (path, item) in &paths {
+        for method in METHODS {
+            let Some(op) = item.get(method) else {
+                continue;
+            };
+            let tag = op["tags"][0].as_str().unwrap_or("other").to_string();
+            let entry = (method, path.clone(), op.clone());
+            match tags.iter_mut().find(|(name, _)| *name == tag) {
+                Some((_, operations)) => operations.push(entry),
+                None => tags.push((tag, vec![entry])),
+            }
+        }
+    }
+
+    html! {
+        div class="max-w-5xl mx-auto space-y-6" {
+            div class="flex items-center justify-between" {
+                h1 class="text-3xl font-bold" {
+                    (info["title"].as_str().unwrap_or("API"))
+                    " "
+                    span class="badge badge-ghost align-middle" { (info["version"].as_str().unwrap_or_default()) }
+                }
+                a class="btn btn-sm btn-outline" href="/api/openapi.json" hx-boost="false" { "openapi.json" }
+            }
+            @if let Some(description) = info["description"].as_str() {
+                div class="prose max-w-none" { (markdown(description)) }
+            }
+
+            @for (tag, operations) in &tags {
+                section class="space-y-2" {
+                    h2 class="text-2xl font-semibold capitalize" { (tag) }
+                    @for (method, path, op) in operations {
+                        (operation(method, path, op))
+                    }
+                }
+            }
+
+            @if !schemas.is_empty() {
+                section class="space-y-4" {
+                    h2 class="text-2xl font-semibold" { "Schemas" }
+                    @for (name, value) in &schemas {
+                        (schema(name, value))
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_name() {
+        let schema = serde_json::json!({"$ref": "#/components/schemas/ItemResponse"});
+        assert_eq!(type_name(&schema), "ItemResponse");
+
+        let schema = serde_json::json!({"type": ["string", "null"]});
+        assert_eq!(type_name(&schema), "string | null");
+
+        let schema = serde_json::json!({"type": "array", "items": {"$ref": "#/components/schemas/ListResponse"}});
+        assert_eq!(type_name(&schema), "ListResponse[]");
+
+        let schema = serde_json::json!({"type": "string", "enum": ["queued", "running"]});
+        assert_eq!(type_name(&schema), "\"queued\" | \"running\"");
+
+        assert_eq!(type_name(&serde_json::json!({})), "any");
+    }
+}
@@ -1,11 +1,12 @@
 use crate::user;
+use crate::view::icons;
 use maud::{Markup, html};
 
-pub fn export_page(user: &user::User) -> Markup {
-    crate::view::index(Some(render()), false, Some(user))
+pub fn export_page(user: &user::User, feed_url: Option<&str>) -> Markup {
+    crate::view::index(Some(render(feed_url)), false, Some(user))
 }
 
-pub fn render() -> Markup {
+pub fn render(feed_url: Option<&str>) -> Markup {
     html! {
         div .p-2 {
             div class="card bg-base-100 shadow-xl" {
@@ -60,6 +61,8 @@ pub fn render() -> Markup {
                                 }
                             }
 
+                            (calendar_feed(feed_url))
+
                             div class="card bg-base-200 shadow-sm opacity-50" {
                                 div class="card-body" {
                                     h3 class="card-title text-lg mb-2" {
@@ -123,3 +126,37 @@ pub fn render() -> Markup {
         }
     }
 }
+
+/// Card with the secret calendar subscription URL, swapped in place when regenerated.
+pub fn calendar_feed(feed_url: Option<&str>) -> Markup {
+    html! {
+        div id="calendar-feed" class="card bg-base-200 shadow-sm" {
+            div class="card-body" {
+                h3 class="card-title text-lg mb-2" {
+                    span class="w-5 h-5" { (icons::calendar_icon()) }
+                    "Meal Plan Calendar"
+                }
+                p class="text-sm text-base-content/70 mb-4" {
+                    "Subscribe to this URL in your calendar app to see planned meals. Anyone with the URL can see your meal plan, regenerate it to revoke access."
+                }
+                @if let Some(feed_url) = feed_url {
+                    input class="input input-bordered input-sm w-full font-mono" type="text" readonly value=(feed_url) onclick="this.select()";
+                    div class="card-actions justify-end gap-2 mt-2" {
+                        button class="btn btn-outline btn-sm"
+                            hx-post="/export/plan/regenerate"
+                            hx-target="#calendar-feed"
+                            hx-swap="outerHTML"
+                            hx-confirm="The current URL will stop working. Continue?" {
+                            "Regenerate"
+                        }
+                        a href=(feed_url) class="btn btn-primary btn-sm" {
+                            "Download .ics"
+                        }
+                    }
+                } @else {
+                    div class="alert alert-error" { "Could not load the calendar feed." }
+                }
+            }
+        }
+    }
+}
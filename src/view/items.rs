@@ -7,12 +7,19 @@ use actix_web::error::ParseError;
 use actix_web::{HttpRequest, Result as AwResult};
 use actix_web::{get, web};
 use maud::{Markup, html};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ItemsQuery {
+    pub list: Option<String>,
+}
 
 #[get("items")]
 pub async fn index_route(
     client: web::Data<DBClient>,
     req: HttpRequest,
     server: web::Data<Server>,
+    query: web::Query<ItemsQuery>,
 ) -> AwResult<Markup> {
     let client = client.get_ref();
     let user = routes::get_user(req).unwrap();
@@ -22,15 +29,25 @@ pub async fn index_route(
     let Ok(items) = database::items::get_items(client, user.id().to_string()).await else {
         return Err(ParseError::Incomplete.into());
     };
+    let lists = database::items::list_names(&items);
+    let current_list = query.list.as_deref().filter(|list| !list.is_empty());
+    let items: Vec<Item> = match current_list {
+        Some(list) => items
+            .into_iter()
+            .filter(|item| item.list() == Some(list))
+            .collect(),
+        None => items,
+    };
+
     let should_poll_reload = server.db_token().is_none();
     Ok(super::index(
-        Some(render(&items)),
+        Some(render(&items, &lists, current_list)),
         should_poll_reload,
         Some(&user),
     ))
 }
 
-pub fn render(items: &[Item]) -> Markup {
+pub fn render(items: &[Item], lists: &[String], current_list: Option<&str>) -> Markup {
     html! {
         div .p-2 {
             div class="card bg-base-100 shadow-xl" {
@@ -43,7 +60,22 @@ pub fn render(items: &[Item]) -> Markup {
                         }
                         "Items"
                     }
+                    @if !lists.is_empty() {
+                        div role="tablist" class="tabs tabs-boxed mb-4 flex-wrap" {
+                            a role="tab" href="/items" class={ "tab" @if current_list.is_none() { " tab-active" } } { "All" }
+                            @for list in lists {
+                                a role="tab"
+                                    href=(format!("/items?list={}", urlencoding::encode(list)))
+                                    class={ "tab" @if current_list == Some(list.as_str()) { " tab-active" } } {
+                                    (list)
+                                }
+                            }
+                        }
+                    }
                     form class="flex gap-2 mb-4" hx-post="/items/single" hx-target="#todo-list" hx-swap="beforeend" hx-on--after-request="this.reset()" {
+                        @if let Some(list) = current_list {
+                            input type="hidden" name="list" value=(list);
+                        }
                         input class="input input-bordered flex-1" type="text" name="task" placeholder="Add a new task..." required;
                         button class="btn btn-primary" type="submit" {
                             (icons::add_icon())
@@ -92,6 +124,18 @@ pub fn render_item_display(item: &Item) -> Markup {
             title="Click to edit" {
                 (item.task)
             }
+            @if let Some(list) = item.list() {
+                span class="badge badge-ghost badge-sm" { (list) }
+            }
+            @if item.completed() {
+                button class="btn btn-sm btn-ghost"
+                    hx-post=(format!("/items/{}/pantry", item.id()))
+                    hx-target=(format!("#c-todo-{}", item.id()))
+                    hx-swap="outerHTML"
+                    title="Move to pantry" {
+                    span class="w-4 h-4" { (icons::archive_icon()) }
+                }
+            }
             button class="btn btn-sm btn-error btn-outline"
                 hx-delete=(format!("/items/{}", item.id()))
                 hx-target="closest div"
@@ -0,0 +1,311 @@
+use maud::{Markup, html};
+
+use crate::database::jobs::{Job, JobKind, JobStatus};
+use crate::jobs::RecipeJobOutcome;
+
+pub fn jobs(jobs: Vec<Job>) -> Markup {
+    html! {
+        div .p-2 {
+            div class="card bg-base-100 shadow-xl" {
+                div class="card-body" {
+                    h2 class="card-title text-2xl mb-4" { "Jobs" }
+                    p class="text-sm text-base-content/70 mb-4" {
+                        "Recipe imports and AI extraction run in the background. Failed jobs are retried automatically and can be retried by hand once they give up."
+                    }
+
+                    @if jobs.is_empty() {
+                        div class="text-center py-8 text-base-content/60" { "No jobs yet." }
+                    } @else {
+                        div class="overflow-x-auto" {
+                            table class="table table-zebra" {
+                                thead {
+                                    tr {
+                                        th { "Job" }
+                                        th { "Status" }
+                                        th { "Attempts" }
+                                        th { "Created" }
+                                        th { "Details" }
+                                        th {}
+                                    }
+                                }
+                                tbody {
+                                    @for job in &jobs {
+                                        (job_row(job))
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn job_row(job: &Job) -> Markup {
+    html! {
+        tr id=(format!("job-row-{}", job.id())) {
+            td { (job.kind.label()) }
+            td { (status_badge(job.status)) }
+            td { (job.attempts) " / " (job.max_attempts) }
+            td class="text-xs" { (job.created_at.format("%b %d, %Y %H:%M")) }
+            td class="text-xs max-w-xs truncate" {
+                @if let Some(error) = &job.last_error {
+                    span class="text-error" title=(error) { (error) }
+                } @else if let Some(outcome) = RecipeJobOutcome::from_job(job) {
+                    a href="/recipes" class="link" { "Recipe #" (outcome.recipe_id) }
+                }
+            }
+            td {
+                @if job.status == JobStatus::Dead {
+                    button class="btn btn-xs btn-outline"
+                        hx-post=(format!("/jobs/{}/retry", job.id()))
+                        hx-target=(format!("#job-row-{}", job.id()))
+                        hx-swap="outerHTML" {
+                        "Retry"
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Replaces a job's row when an action on it failed.
+pub fn job_row_error(job_id: i64, message: &str) -> Markup {
+    html! {
+        tr id=(format!("job-row-{job_id}")) {
+            td colspan="6" {
+                div class="alert alert-error" { (message) }
+            }
+        }
+    }
+}
+
+pub fn status_badge(status: JobStatus) -> Markup {
+    let class = match status {
+        JobStatus::Queued => "badge badge-ghost",
+        JobStatus::Running => "badge badge-info",
+        JobStatus::Succeeded => "badge badge-success",
+        JobStatus::Dead => "badge badge-error",
+    };
+    html! {
+        span class=(class) { (status.as_str()) }
+    }
+}
+
+/// Inline status for a job started from a form. Polls until the job is done.
+pub fn job_pending(job: &Job) -> Markup {
+    html! {
+        div hx-get=(format!("/jobs/{}", job.id()))
+            hx-trigger="every 2s"
+            hx-swap="outerHTML"
+            class="alert" {
+            span class="loading loading-spinner loading-sm" {}
+            span {
+                (job.kind.label())
+                @if job.status == JobStatus::Queued && job.attempts > 0 {
+                    " failed, retrying (attempt " (job.attempts + 1) " of " (job.max_attempts) ")"
+                } @else if job.status == JobStatus::Queued {
+                    " is queued…"
+                } @else {
+                    " is running…"
+                }
+            }
+        }
+    }
+}
+
+pub fn job_failed(job: &Job) -> Markup {
+    html! {
+        div class="space-y-4" {
+            div class="alert alert-error" {
+                (job.kind.label()) " failed after " (job.attempts) " attempts"
+                @if let Some(error) = &job.last_error {
+                    ": " (error)
+                }
+            }
+            div class="flex gap-2" {
+                a href="/jobs" class="btn btn-primary" { "View Jobs" }
+                button class="btn btn-ghost"
+                       hx-get="/"
+                       hx-target="body"
+                       hx-swap="outerHTML" {
+                    "Try Again"
+                }
+            }
+        }
+    }
+}
+
+pub fn job_done(job: &Job) -> Markup {
+    let Some(outcome) = RecipeJobOutcome::from_job(job) else {
+        return html! {
+            div class="alert alert-success" { (job.kind.label()) " finished" }
+        };
+    };
+
+    match job.kind {
+        JobKind::Extract => extract_outcome(&outcome),
+        _ => process_outcome(&outcome),
+    }
+}
+
+fn grocery_card(grocery_list: Option<&str>) -> Markup {
+    html! {
+        div class="card bg-base-100 shadow-lg" {
+            div class="card-body" {
+                h3 class="card-title" { "Generated Grocery List" }
+                div class="prose max-w-none" {
+                    pre class="whitespace-pre-wrap bg-base-200 p-4 rounded" {
+                        (grocery_list.unwrap_or("Something went wrong contacting the LLM agent"))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn outcome_actions(again: &str) -> Markup {
+    html! {
+        div class="flex gap-2" {
+            a href="/recipes" class="btn btn-primary" {
+                "View All Recipes"
+            }
+            a href="/items" class="btn btn-secondary" {
+                "View Grocery Items"
+            }
+            button class="btn btn-ghost"
+                   hx-get="/"
+                   hx-target="body"
+                   hx-swap="outerHTML" {
+                (again)
+            }
+        }
+    }
+}
+
+fn process_outcome(outcome: &RecipeJobOutcome) -> Markup {
+    html! {
+        div class="space-y-4" {
+            div class="alert alert-success" {
+                "Recipe processed successfully! Grocery list generated and recipe saved."
+            }
+
+            (grocery_card(outcome.grocery_list.as_deref()))
+
+            div class="card bg-base-100 shadow-lg" {
+                div class="card-body" {
+                    h3 class="card-title" { "Saved Recipe" }
+                    p class="text-sm opacity-70" {
+                        "Recipe ID: " (outcome.recipe_id)
+                    }
+                    @if let Some(url) = &outcome.url {
+                        p class="text-sm" {
+                            "Source URL: "
+                            a href=(url) target="_blank" class="link" { (url) }
+                        }
+                    }
+                }
+            }
+
+            (outcome_actions("Add Another Recipe"))
+        }
+    }
+}
+
+fn extract_outcome(outcome: &RecipeJobOutcome) -> Markup {
+    let Some(recipe_data) = &outcome.extracted else {
+        return html! {
+            div class="space-y-4" {
+                div class="alert alert-warning" {
+                    "Recipe extraction failed, but we still generated a grocery list and saved the raw content."
+                }
+
+                (grocery_card(outcome.grocery_list.as_deref()))
+
+                div class="alert alert-error" {
+                    "Error extracting structured recipe data: "
+                    (outcome.extraction_error.as_deref().unwrap_or_default())
+                }
+
+                (outcome_actions("Try Again"))
+            }
+        };
+    };
+
+    html! {
+        div class="space-y-4" {
+            div class="alert alert-success" {
+                "Recipe extracted and structured successfully! Grocery list generated and recipe saved."
+            }
+
+            div class="grid grid-cols-1 lg:grid-cols-2 gap-4" {
+                div class="card bg-base-100 shadow-lg" {
+                    div class="card-body" {
+                        h3 class="card-title" { "Extracted Recipe: " (recipe_data.title) }
+
+                        h4 class="font-semibold mt-4" { "Ingredients" }
+                        ul class="list-disc list-inside" {
+                            @for ingredient in &recipe_data.ingredients {
+                                li { (ingredient) }
+                            }
+                        }
+
+                        h4 class="font-semibold mt-4" { "Instructions" }
+                        ol class="list-decimal list-inside" {
+                            @for instruction in &recipe_data.instructions {
+                                li class="mb-2" { (instruction) }
+                            }
+                        }
+
+                        @if let Some(prep_time) = &recipe_data.prep_time {
+                            div class="mt-4" {
+                                span class="font-semibold" { "Prep Time: " }
+                                (prep_time)
+                            }
+                        }
+                        @if let Some(cook_time) = &recipe_data.cook_time {
+                            div {
+                                span class="font-semibold" { "Cook Time: " }
+                                (cook_time)
+                            }
+                        }
+                        @if let Some(servings) = &recipe_data.servings {
+                            div {
+                                span class="font-semibold" { "Servings: " }
+                                (servings)
+                            }
+                        }
+                    }
+                }
+
+                (grocery_card(outcome.grocery_list.as_deref()))
+            }
+
+            div class="alert alert-info" {
+                "Recipe saved with ID: " (outcome.recipe_id)
+                @if let Some(url) = &outcome.url {
+                    br;
+                    "Source URL: "
+                    a href=(url) target="_blank" class="link" { (url) }
+                }
+            }
+
+            (outcome_actions("Add Another Recipe"))
+        }
+    }
+}
+
+pub fn import_started(jobs: &[Job]) -> Markup {
+    html! {
+        div class="space-y-2" {
+            div class="alert alert-info" {
+                "Queued " (jobs.len()) " recipe imports. "
+                a href="/jobs" class="link" { "Follow them on the jobs page." }
+            }
+            @for job in jobs {
+                (job_pending(job))
+            }
+        }
+    }
+}
@@ -1,6 +1,19 @@
 use maud::{Markup, html};
 
-pub fn login_page() -> Markup {
+use crate::oidc::OidcProvider;
+
+fn login_url(provider: &OidcProvider, redirect_to: Option<&str>) -> String {
+    match redirect_to {
+        Some(redirect_to) => format!(
+            "/auth/{}/login?redirect_to={}",
+            provider.id,
+            urlencoding::encode(redirect_to)
+        ),
+        None => format!("/auth/{}/login", provider.id),
+    }
+}
+
+pub fn login_page(providers: &[OidcProvider], redirect_to: Option<String>) -> Markup {
     html! {
         div class="flex min-h-screen items-center justify-center bg-base-200" {
             div class="w-full max-w-md p-8 space-y-6 bg-base-100 rounded-xl shadow-xl" {
@@ -11,9 +24,11 @@ pub fn login_page() -> Markup {
                     }
                 }
 
-                div class="mt-8" {
-                    a href="/auth/login" class="btn btn-primary w-full" hx-boost="false" {
-                        "Sign in with OpenID Connect"
+                div class="mt-8 flex flex-col gap-3" {
+                    @for provider in providers {
+                        a href=(login_url(provider, redirect_to.as_deref())) class="btn btn-primary w-full" hx-boost="false" {
+                            "Sign in with " (provider.name)
+                        }
                     }
                 }
 
@@ -26,3 +41,17 @@ pub fn login_page() -> Markup {
         }
     }
 }
+
+pub fn account_disabled() -> Markup {
+    html! {
+        div class="flex min-h-screen items-center justify-center bg-base-200" {
+            div class="w-full max-w-md p-8 space-y-4 bg-base-100 rounded-xl shadow-xl text-center" {
+                h1 class="text-2xl font-bold text-base-content" { "Account disabled" }
+                p class="text-sm text-base-content/70" {
+                    "An admin disabled your account. Ask them to enable it again."
+                }
+                a href="/login" class="btn btn-ghost" { "Back to sign in" }
+            }
+        }
+    }
+}
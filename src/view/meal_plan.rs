@@ -0,0 +1,185 @@
+use chrono::NaiveDate;
+use maud::{Markup, html};
+
+use crate::database::meal_plan::{MealPlanEntry, MealSlot};
+use crate::database::recipes::Recipe;
+use crate::view::icons;
+
+pub fn meal_plan(
+    start: NaiveDate,
+    today: NaiveDate,
+    entries: &[MealPlanEntry],
+    recipes: &[Recipe],
+    lists: &[String],
+) -> Markup {
+    let previous_week = start - chrono::Duration::days(7);
+    let next_week = start + chrono::Duration::days(7);
+    let end = start + chrono::Duration::days(6);
+
+    html! {
+        div .p-2 {
+            div class="card bg-base-100 shadow-xl" {
+                div class="card-body" {
+                    div class="flex flex-wrap items-center justify-between gap-2 mb-4" {
+                        h2 class="card-title text-2xl" {
+                            "Meal Plan"
+                            span class="text-base font-normal text-base-content/60" {
+                                (start.format("%b %d")) " – " (end.format("%b %d, %Y"))
+                            }
+                        }
+                        div class="join" {
+                            a class="btn btn-sm join-item" href=(format!("/plan?week={previous_week}")) { "‹ Previous" }
+                            a class="btn btn-sm join-item" href="/plan" { "This week" }
+                            a class="btn btn-sm join-item" href=(format!("/plan?week={next_week}")) { "Next ›" }
+                        }
+                    }
+
+                    div class="grid grid-cols-1 md:grid-cols-4 xl:grid-cols-7 gap-2" {
+                        @for offset in 0..7 {
+                            (day_column(start + chrono::Duration::days(offset), today, entries, recipes))
+                        }
+                    }
+
+                    div class="divider" {}
+
+                    (shopping_list_form(start, lists))
+                }
+            }
+        }
+    }
+}
+
+pub fn day_column(
+    date: NaiveDate,
+    today: NaiveDate,
+    entries: &[MealPlanEntry],
+    recipes: &[Recipe],
+) -> Markup {
+    let day_id = format!("plan-day-{date}");
+    html! {
+        div id=(day_id) class={ "rounded-lg border p-2 flex flex-col gap-2 " @if date == today { "border-primary" } @else { "border-base-300" } } {
+            div class="font-semibold" {
+                (date.format("%A"))
+                span class="text-sm font-normal text-base-content/60 ml-1" { (date.format("%b %d")) }
+            }
+
+            @for entry in entries.iter().filter(|entry| entry.planned_on == date) {
+                (entry_row(entry, recipes))
+            }
+
+            @if recipes.is_empty() {
+                p class="text-xs text-base-content/60" { "Add recipes to plan meals." }
+            } @else {
+                details class="text-sm" {
+                    summary class="cursor-pointer text-base-content/70" { "Add meal" }
+                    form class="flex flex-col gap-1 mt-2"
+                        hx-post="/plan"
+                        hx-target=(format!("#{day_id}"))
+                        hx-swap="outerHTML" {
+                        input type="hidden" name="planned_on" value=(date.to_string());
+                        select class="select select-bordered select-sm" name="recipe_id" required {
+                            @for recipe in recipes {
+                                option value=(recipe.id()) { (recipe.title().unwrap_or("Untitled recipe")) }
+                            }
+                        }
+                        select class="select select-bordered select-sm" name="slot" {
+                            @for slot in MealSlot::ALL {
+                                option value=(slot.as_str()) selected[slot == MealSlot::Dinner] { (slot.label()) }
+                            }
+                        }
+                        input class="input input-bordered input-sm" type="number" name="servings" min="1" value="2" title="Servings" required;
+                        button class="btn btn-sm btn-primary" type="submit" {
+                            (icons::add_icon())
+                            "Plan"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn entry_row(entry: &MealPlanEntry, recipes: &[Recipe]) -> Markup {
+    let recipe = recipes.iter().find(|recipe| recipe.id() == entry.recipe_id);
+    html! {
+        div id=(format!("plan-entry-{}", entry.id())) class="bg-base-200 rounded p-2 text-sm flex items-start gap-1" {
+            div class="flex-1 min-w-0" {
+                div class="text-xs uppercase text-base-content/60" { (entry.slot.label()) }
+                @match recipe {
+                    Some(recipe) => {
+                        a class="link link-hover font-medium break-words" href=(format!("/recipes#recipe-{}", recipe.id())) {
+                            (recipe.title().unwrap_or("Untitled recipe"))
+                        }
+                    }
+                    None => {
+                        span class="italic text-base-content/60" { "Recipe removed" }
+                    }
+                }
+                div class="text-xs text-base-content/60" { (entry.servings) " servings" }
+            }
+            button class="btn btn-xs btn-ghost"
+                hx-delete=(format!("/plan/{}", entry.id()))
+                hx-target=(format!("#plan-entry-{}", entry.id()))
+                hx-swap="outerHTML"
+                title="Remove from plan" {
+                span class="w-4 h-4" { (icons::delete_icon()) }
+            }
+        }
+    }
+}
+
+fn shopping_list_form(start: NaiveDate, lists: &[String]) -> Markup {
+    html! {
+        div {
+            h3 class="font-semibold mb-2" { "Shopping list for this week" }
+            p class="text-sm text-base-content/70 mb-2" {
+                "Adds the ingredients of every planned meal, scaled to the planned servings, to a list. Anything your pantry covers is left out."
+            }
+            form class="flex flex-wrap gap-2"
+                hx-post="/plan/shopping-list"
+                hx-target="#shopping-list-result"
+                hx-swap="innerHTML" {
+                input type="hidden" name="week" value=(start.to_string());
+                input class="input input-bordered flex-1 min-w-48" type="text" name="list" list="item-lists"
+                    value=(format!("Week of {}", start.format("%b %d"))) required;
+                datalist id="item-lists" {
+                    @for list in lists {
+                        option value=(list) {}
+                    }
+                }
+                button class="btn btn-primary" type="submit" {
+                    span class="w-4 h-4" { (icons::list_icon()) }
+                    "Generate shopping list"
+                }
+            }
+            div id="shopping-list-result" class="mt-2" {}
+        }
+    }
+}
+
+pub fn shopping_list_created(
+    list: &str,
+    added: &[String],
+    covered: &[String],
+    skipped: &[String],
+) -> Markup {
+    html! {
+        div class="alert alert-success flex flex-col items-start" {
+            div {
+                "Added " (added.len()) " items to "
+                a class="link font-semibold" href=(format!("/items?list={}", urlencoding::encode(list))) { (list) }
+                "."
+            }
+            @if !covered.is_empty() {
+                div class="text-sm" {
+                    "Already in your pantry: " (covered.join(", "))
+                }
+            }
+        }
+        @if !skipped.is_empty() {
+            div class="alert alert-warning mt-2" {
+                "No ingredients found for: " (skipped.join(", ")) ". Extract the recipe structure to include them."
+            }
+        }
+    }
+}
@@ -3,11 +3,16 @@ use actix_web::{get, web};
 use maud::{Markup, html};
 
 pub mod about;
+pub mod admin;
+pub mod api_docs;
 pub mod export;
 mod icons;
 pub mod items;
+pub mod jobs;
 pub mod login;
+pub mod meal_plan;
 mod navbar;
+pub mod pantry;
 pub mod profile;
 pub mod recipes;
 
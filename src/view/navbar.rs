@@ -1,7 +1,10 @@
 use maud::{Markup, html};
 
 use crate::user::User;
-use crate::view::icons::{self, house_icon, info_icon, list_icon, spark_icon, user_icon};
+use crate::view::icons::{
+    self, archive_icon, calendar_icon, house_icon, info_icon, list_icon, shield_icon, spark_icon,
+    user_icon, wand_icon,
+};
 
 pub fn render(user: Option<&User>) -> Markup {
     html! {
@@ -44,6 +47,24 @@ fn navbar(user: Option<&User>) -> Markup {
                             }
                             "Recipes"
                         }
+                        a href="/plan" class="flex items-center gap-2 text-sm font-medium text-base-content/70 hover:text-base-content transition-colors py-2" {
+                            span class="w-4 h-4 flex items-center justify-center opacity-60" {
+                                (calendar_icon())
+                            }
+                            "Plan"
+                        }
+                        a href="/pantry" class="flex items-center gap-2 text-sm font-medium text-base-content/70 hover:text-base-content transition-colors py-2" {
+                            span class="w-4 h-4 flex items-center justify-center opacity-60" {
+                                (archive_icon())
+                            }
+                            "Pantry"
+                        }
+                        a href="/jobs" class="flex items-center gap-2 text-sm font-medium text-base-content/70 hover:text-base-content transition-colors py-2" {
+                            span class="w-4 h-4 flex items-center justify-center opacity-60" {
+                                (wand_icon())
+                            }
+                            "Jobs"
+                        }
                         a href="/profile" class="flex items-center gap-2 text-sm font-medium text-base-content/70 hover:text-base-content transition-colors py-2" {
                             span class="w-4 h-4 flex items-center justify-center opacity-60" {
                                 (user_icon())
@@ -56,11 +77,24 @@ fn navbar(user: Option<&User>) -> Markup {
                             }
                             "About"
                         }
+                        @if user.is_some_and(User::is_admin) {
+                            a href="/admin" class="flex items-center gap-2 text-sm font-medium text-base-content/70 hover:text-base-content transition-colors py-2" {
+                                span class="w-4 h-4 flex items-center justify-center opacity-60" {
+                                    (shield_icon())
+                                }
+                                "Admin"
+                            }
+                        }
                     }
                 }
 
                 div class="flex items-center gap-4" {
-                    @if user.is_some() {
+                    @if let Some(user) = user {
+                        a href="/profile" class="hidden sm:flex items-center gap-2 text-sm font-medium text-base-content/70 hover:text-base-content transition-colors py-2" title=(user.email()) {
+                            (crate::view::profile::avatar(user, "w-7", "text-xs"))
+                            span class="max-w-40 truncate" { (user.display_name()) }
+                        }
+
                         a href="/export" class="flex items-center gap-2 text-sm font-medium text-base-content/70 hover:text-base-content transition-colors py-2" {
                             span class="w-4 h-4 flex items-center justify-center opacity-60" {
                                 (icons::export_icon())
@@ -91,6 +125,11 @@ fn navbar(user: Option<&User>) -> Markup {
                                 }
                             }
                             ul tabindex="0" class="dropdown-content menu bg-base-100 z-[1] w-52 p-3 shadow-lg border border-base-200 rounded-lg mt-2" {
+                                @if let Some(user) = user {
+                                    li class="menu-title" {
+                                        span class="truncate" { (user.display_name()) }
+                                    }
+                                }
                                 li {
                                     a href="/" class="flex items-center gap-3 text-sm text-base-content/70 hover:text-base-content hover:bg-base-200/50 px-3 py-2 rounded transition-colors" {
                                         span class="w-4 h-4 flex items-center justify-center opacity-60" {
@@ -115,6 +154,30 @@ fn navbar(user: Option<&User>) -> Markup {
                                         "Recipes"
                                     }
                                 }
+                                li {
+                                    a href="/plan" class="flex items-center gap-3 text-sm text-base-content/70 hover:text-base-content hover:bg-base-200/50 px-3 py-2 rounded transition-colors" {
+                                        span class="w-4 h-4 flex items-center justify-center opacity-60" {
+                                            (calendar_icon())
+                                        }
+                                        "Plan"
+                                    }
+                                }
+                                li {
+                                    a href="/pantry" class="flex items-center gap-3 text-sm text-base-content/70 hover:text-base-content hover:bg-base-200/50 px-3 py-2 rounded transition-colors" {
+                                        span class="w-4 h-4 flex items-center justify-center opacity-60" {
+                                            (archive_icon())
+                                        }
+                                        "Pantry"
+                                    }
+                                }
+                                li {
+                                    a href="/jobs" class="flex items-center gap-3 text-sm text-base-content/70 hover:text-base-content hover:bg-base-200/50 px-3 py-2 rounded transition-colors" {
+                                        span class="w-4 h-4 flex items-center justify-center opacity-60" {
+                                            (wand_icon())
+                                        }
+                                        "Jobs"
+                                    }
+                                }
                                 li {
                                     a href="/profile" class="flex items-center gap-3 text-sm text-base-content/70 hover:text-base-content hover:bg-base-200/50 px-3 py-2 rounded transition-colors" {
                                         span class="w-4 h-4 flex items-center justify-center opacity-60" {
@@ -131,6 +194,16 @@ fn navbar(user: Option<&User>) -> Markup {
                                         "About"
                                     }
                                 }
+                                @if user.is_some_and(User::is_admin) {
+                                    li {
+                                        a href="/admin" class="flex items-center gap-3 text-sm text-base-content/70 hover:text-base-content hover:bg-base-200/50 px-3 py-2 rounded transition-colors" {
+                                            span class="w-4 h-4 flex items-center justify-center opacity-60" {
+                                                (shield_icon())
+                                            }
+                                            "Admin"
+                                        }
+                                    }
+                                }
                                 div class="border-t border-base-200 my-2" {}
                                 li {
                                     a href="/export" class="flex items-center gap-3 text-xs text-base-content/60 hover:text-base-content hover:bg-base-200/50 px-3 py-2 rounded transition-colors" {
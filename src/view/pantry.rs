@@ -0,0 +1,122 @@
+use maud::{Markup, html};
+
+use crate::database::pantry::PantryItem;
+use crate::ingredients::format_quantity;
+use crate::view::icons;
+
+pub fn pantry(items: &[PantryItem]) -> Markup {
+    html! {
+        div .p-2 {
+            div class="card bg-base-100 shadow-xl" {
+                div class="card-body" {
+                    h2 class="card-title text-2xl mb-4" { "Pantry" }
+                    p class="text-sm text-base-content/70 mb-4" {
+                        "What you already have at home. Grocery lists generated from recipes leave out anything the pantry covers. Leave the quantity empty for things you always have."
+                    }
+
+                    form class="flex flex-wrap gap-2 mb-4" hx-post="/pantry" hx-target="#pantry-list" hx-swap="beforeend" hx-on--after-request="if(event.detail.successful) this.reset()" {
+                        input class="input input-bordered flex-1 min-w-40" type="text" name="name" placeholder="Item, e.g. flour" required;
+                        input class="input input-bordered w-24" type="number" step="any" min="0" name="quantity" placeholder="Qty";
+                        input class="input input-bordered w-24" type="text" name="unit" placeholder="Unit";
+                        input class="input input-bordered w-40" type="date" name="expires_on" title="Expiry date (optional)";
+                        button class="btn btn-primary" type="submit" {
+                            (icons::add_icon())
+                            "Add"
+                        }
+                    }
+
+                    div class="overflow-x-auto" {
+                        table class="table" {
+                            thead {
+                                tr {
+                                    th { "Item" }
+                                    th { "Quantity" }
+                                    th { "Expires" }
+                                    th {}
+                                }
+                            }
+                            tbody id="pantry-list" {
+                                @for item in items {
+                                    (pantry_row(item))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn quantity_label(item: &PantryItem) -> String {
+    match (item.quantity, item.unit.as_deref()) {
+        (Some(quantity), Some(unit)) => format!("{} {unit}", format_quantity(quantity)),
+        (Some(quantity), None) => format_quantity(quantity),
+        (None, _) => "in stock".to_string(),
+    }
+}
+
+pub fn pantry_row(item: &PantryItem) -> Markup {
+    html! {
+        tr id=(format!("pantry-{}", item.id())) {
+            td class="cursor-pointer"
+                hx-get=(format!("/pantry/{}/edit", item.id()))
+                hx-target=(format!("#pantry-{}", item.id()))
+                hx-swap="outerHTML"
+                title="Click to edit" {
+                (item.name)
+            }
+            td { (quantity_label(item)) }
+            td {
+                @if let Some(expires_on) = item.expires_on {
+                    span class={ @if item.is_expired() { "text-error" } @else { "" } } {
+                        (expires_on.format("%b %d, %Y"))
+                    }
+                }
+            }
+            td class="text-right" {
+                button class="btn btn-sm btn-error btn-outline"
+                    hx-delete=(format!("/pantry/{}", item.id()))
+                    hx-target=(format!("#pantry-{}", item.id()))
+                    hx-swap="outerHTML"
+                    hx-confirm="Remove this item from the pantry?" {
+                    (icons::delete_icon())
+                }
+            }
+        }
+    }
+}
+
+pub fn pantry_edit_row(item: &PantryItem) -> Markup {
+    let form_id = format!("pantry-form-{}", item.id());
+    html! {
+        tr id=(format!("pantry-{}", item.id())) {
+            td {
+                form id=(form_id)
+                    hx-patch=(format!("/pantry/{}", item.id()))
+                    hx-target=(format!("#pantry-{}", item.id()))
+                    hx-swap="outerHTML" {}
+                input class="input input-bordered input-sm w-full" form=(form_id) type="text" name="name" value=(item.name) required autofocus;
+            }
+            td class="flex gap-1" {
+                input class="input input-bordered input-sm w-20" form=(form_id) type="number" step="any" min="0" name="quantity"
+                    value=(item.quantity.map(format_quantity).unwrap_or_default());
+                input class="input input-bordered input-sm w-20" form=(form_id) type="text" name="unit"
+                    value=(item.unit.as_deref().unwrap_or(""));
+            }
+            td {
+                input class="input input-bordered input-sm" form=(form_id) type="date" name="expires_on"
+                    value=(item.expires_on.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default());
+            }
+            td class="text-right" {
+                button class="btn btn-sm btn-primary" form=(form_id) type="submit" { "Save" }
+                button class="btn btn-sm btn-ghost" type="button"
+                    hx-get=(format!("/pantry/{}/cancel", item.id()))
+                    hx-target=(format!("#pantry-{}", item.id()))
+                    hx-swap="outerHTML" {
+                    "Cancel"
+                }
+            }
+        }
+    }
+}
@@ -1,47 +1,251 @@
 use crate::config::Server;
+use crate::database::DBClient;
+use crate::database::api_tokens::{self, ApiScope, ApiToken};
+use crate::database::auth_sessions::{self, AuthSession};
+use crate::database::users::{self, UserRecord};
+use crate::oidc::AUTH_SESSION_KEY;
 use crate::routes::{self};
 use crate::user::User;
-use actix_web::{HttpRequest, Result as AwResult};
+use crate::view::icons;
+use actix_session::Session;
+use actix_web::{HttpRequest, HttpResponse, Result as AwResult};
 use actix_web::{get, web};
 use maud::{Markup, html};
 
 #[get("profile")]
-pub async fn profile_endpoint(server: web::Data<Server>, req: HttpRequest) -> AwResult<Markup> {
-    let user = routes::get_user(req).unwrap();
+pub async fn profile_endpoint(
+    server: web::Data<Server>,
+    client: web::Data<DBClient>,
+    session: Session,
+    req: HttpRequest,
+) -> AwResult<HttpResponse> {
+    let user = match routes::get_user_or_redirect(&req) {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+    let sessions = auth_sessions::get_owner_auth_sessions(client.get_ref(), user.id().to_string())
+        .await
+        .unwrap_or_default();
+    let current_session = session.get::<String>(AUTH_SESSION_KEY).ok().flatten();
+    let record = users::get_user(client.get_ref(), user.id())
+        .await
+        .ok()
+        .flatten();
+    let tokens = api_tokens::get_api_tokens(client.get_ref(), user.id().to_string())
+        .await
+        .unwrap_or_default();
+
     let should_poll_reload = server.db_token().is_none();
-    Ok(super::index(
-        Some(render(&user)),
+    let markup = super::index(
+        Some(render(
+            &user,
+            record.as_ref(),
+            &sessions,
+            current_session.as_deref(),
+            &tokens,
+        )),
         should_poll_reload,
         Some(&user),
-    ))
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(markup.into_string()))
+}
+
+pub fn render(
+    user: &User,
+    record: Option<&UserRecord>,
+    sessions: &[AuthSession],
+    current_session: Option<&str>,
+    tokens: &[ApiToken],
+) -> Markup {
+    html! {
+      (avatar_card(user, record))
+      (sessions_card(sessions, current_session))
+      (api_tokens_card(tokens, None))
+    }
 }
 
-pub fn render(user: &User) -> Markup {
+/// Token management. `created` is the secret of a token that was just
+/// created, it is shown this one time only.
+pub fn api_tokens_card(tokens: &[ApiToken], created: Option<&str>) -> Markup {
     html! {
-      (avatar_card(user))
+        div id="api-tokens" class="card w-4xl bg-base-100 shadow-sm mx-auto mt-4" {
+            div class="card-body" {
+                h2 class="card-title text-2xl" { "API tokens" }
+                p class="text-sm text-base-content/70 mb-2" {
+                    "Tokens let scripts and integrations use Rezi on your behalf. Send them as "
+                    code { "Authorization: Bearer <token>" }
+                    "."
+                }
+
+                @if let Some(secret) = created {
+                    div class="alert alert-success flex flex-col items-start gap-2 mb-2" {
+                        span { "Copy your new token now, it will not be shown again." }
+                        code class="select-all break-all" { (secret) }
+                    }
+                }
+
+                form class="flex flex-col gap-3 mb-4"
+                    hx-post="/profile/tokens"
+                    hx-target="#api-tokens"
+                    hx-swap="outerHTML" {
+                    input class="input input-bordered" type="text" name="name" placeholder="Name, e.g. Home Assistant" required;
+                    div class="flex flex-wrap gap-4" {
+                        @for scope in ApiScope::ALL {
+                            label class="label cursor-pointer gap-2" {
+                                input class="checkbox checkbox-sm" type="checkbox" name="scope" value=(scope.as_str());
+                                span class="label-text" { (scope.label()) }
+                            }
+                        }
+                    }
+                    button class="btn btn-primary self-start" type="submit" {
+                        (icons::add_icon())
+                        "Create token"
+                    }
+                }
+
+                @if !tokens.is_empty() {
+                    div class="overflow-x-auto" {
+                        table class="table" {
+                            thead {
+                                tr {
+                                    th { "Name" }
+                                    th { "Token" }
+                                    th { "Scopes" }
+                                    th { "Last used" }
+                                    th {}
+                                }
+                            }
+                            tbody {
+                                @for token in tokens {
+                                    (api_token_row(token))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn api_token_row(token: &ApiToken) -> Markup {
+    html! {
+        tr id=(format!("api-token-{}", token.id())) {
+            td { (token.name) }
+            td { code { (token.prefix) "…" } }
+            td {
+                div class="flex flex-wrap gap-1" {
+                    @for scope in token.scopes() {
+                        span class="badge badge-outline badge-sm" { (scope.as_str()) }
+                    }
+                }
+            }
+            td {
+                @match token.last_used_at {
+                    Some(last_used) => (last_used.format("%b %d, %Y %H:%M UTC")),
+                    None => "Never",
+                }
+            }
+            td class="text-right" {
+                button class="btn btn-sm btn-error btn-outline"
+                    hx-delete=(format!("/profile/tokens/{}", token.id()))
+                    hx-target=(format!("#api-token-{}", token.id()))
+                    hx-swap="outerHTML"
+                    hx-confirm="Revoke this token? Scripts using it will stop working." {
+                    (icons::delete_icon())
+                    "Revoke"
+                }
+            }
+        }
+    }
+}
 
+fn sessions_card(sessions: &[AuthSession], current_session: Option<&str>) -> Markup {
+    html! {
+        div class="card w-4xl bg-base-100 shadow-sm mx-auto mt-4" {
+            div class="card-body" {
+                h2 class="card-title text-2xl" { "Sessions" }
+                p class="text-sm text-base-content/70 mb-2" {
+                    "Devices you are signed in on. Revoke a session to sign that device out."
+                }
+                div class="overflow-x-auto" {
+                    table class="table" {
+                        thead {
+                            tr {
+                                th { "Device" }
+                                th { "IP address" }
+                                th { "Last seen" }
+                                th {}
+                            }
+                        }
+                        tbody {
+                            @for auth_session in sessions {
+                                (session_row(auth_session, current_session == Some(auth_session.session_id())))
+                            }
+                        }
+                    }
+                }
+            }
+        }
     }
 }
 
-pub fn avatar(initials: &str) -> Markup {
+fn session_row(auth_session: &AuthSession, current: bool) -> Markup {
+    let id = auth_session.id.unwrap_or_default();
     html! {
-        div .avatar .avatar-placeholder {
-            div .bg-neutral .text-neutral-content .w-24 .rounded-full {
-                span .text-3xl {
-                    (initials)
+        tr id=(format!("session-{id}")) {
+            td {
+                (auth_session.device())
+                @if current {
+                    span class="badge badge-primary badge-sm ml-2" { "This device" }
+                }
+            }
+            td { (auth_session.ip.as_deref().unwrap_or("unknown")) }
+            td { (auth_session.last_seen().format("%b %d, %Y %H:%M UTC")) }
+            td class="text-right" {
+                button class="btn btn-sm btn-error btn-outline"
+                    hx-delete=(format!("/profile/sessions/{id}"))
+                    hx-target=(format!("#session-{id}"))
+                    hx-swap="outerHTML"
+                    hx-confirm=(if current { "Sign out of this device?" } else { "Revoke this session?" }) {
+                    (icons::delete_icon())
+                    "Revoke"
                 }
             }
         }
+    }
+}
 
+/// The provider's picture, or the initials when there is none. `size` is a
+/// tailwind width class like `w-24`.
+pub fn avatar(user: &User, size: &str, text_size: &str) -> Markup {
+    html! {
+        @if let Some(avatar_url) = user.avatar_url() {
+            div .avatar {
+                div class=(format!("{size} rounded-full")) {
+                    img src=(avatar_url) alt=(user.display_name()) referrerpolicy="no-referrer";
+                }
+            }
+        } @else {
+            div .avatar .avatar-placeholder {
+                div class=(format!("bg-neutral text-neutral-content {size} rounded-full")) {
+                    span class=(text_size) {
+                        (user.initials())
+                    }
+                }
+            }
+        }
     }
 }
 
-fn avatar_card(user: &User) -> Markup {
+fn avatar_card(user: &User, record: Option<&UserRecord>) -> Markup {
     html! {
         div class="card w-4xl bg-base-100 shadow-sm mx-auto" {
             div class="card-body" {
                 figure .mb-4 {
-                    (avatar(&user.initials()))
+                    (avatar(user, "w-24", "text-3xl"))
                 }
                 div class="flex justify-between" {
                     h2 class="text-3xl font-bold" {
@@ -49,6 +253,11 @@ fn avatar_card(user: &User) -> Markup {
                     }
                 }
                 ul class="mt-6 flex flex-col gap-2 text-xl" {
+                    li {
+                        span {
+                            "Name: " span{(user.display_name())}
+                        }
+                    }
                     li {
                         span {
                             "Initials: " span{(user.initials())}
@@ -59,6 +268,25 @@ fn avatar_card(user: &User) -> Markup {
                             "Email: " span{(user.email())}
                         }
                     }
+                    @if let Some(record) = record {
+                        @if let Some(locale) = &record.locale {
+                            li {
+                                span {
+                                    "Locale: " span{(locale)}
+                                }
+                            }
+                        }
+                        li {
+                            span {
+                                "Member since: " span{(record.first_seen_at.format("%b %d, %Y"))}
+                            }
+                        }
+                        li {
+                            span {
+                                "Last login: " span{(record.last_login_at.format("%b %d, %Y %H:%M UTC"))}
+                            }
+                        }
+                    }
                     li {
                         span {
                              "Theme: "
@@ -1,6 +1,8 @@
 use crate::database::recipes::Recipe;
 use crate::routes::random_html_safe_id;
 use crate::view::icons::{self, add_icon, link_icon, spark_icon, wand_icon};
+use libsql_orm::FullTextMatch;
+use libsql_orm::fts::highlight_parts;
 use maud::{Markup, html};
 
 pub fn recipes(recipes: Vec<Recipe>) -> Markup {
@@ -16,22 +18,91 @@ pub fn recipes(recipes: Vec<Recipe>) -> Markup {
                         }
                         "Recipes"
                         (add_modal())
+                        (import_modal())
                     }
 
 
+                    label class="input input-bordered flex items-center gap-2 mb-4" {
+                        input type="search" name="q" class="grow"
+                            placeholder="Search titles, ingredients and instructions"
+                            hx-get="/recipes/search"
+                            hx-trigger="input changed delay:300ms, search"
+                            hx-target="#recipe-list"
+                            hx-swap="innerHTML";
+                    }
+
                     div id="recipe-list" class="h-[600px] overflow-y-auto pr-2" {
-                        div class="grid grid-cols-1 lg:grid-cols-2 xl:grid-cols-3 gap-4" {
-                            @for recipe in recipes {
-                                (recipe_row(&recipe))
-                            }
+                        (recipe_list(&recipes))
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn recipe_list(recipes: &[Recipe]) -> Markup {
+    html! {
+        div class="grid grid-cols-1 lg:grid-cols-2 xl:grid-cols-3 gap-4" {
+            @for recipe in recipes {
+                (recipe_row(recipe))
+            }
+        }
+    }
+}
+
+pub fn search_results(matches: &[FullTextMatch<Recipe>]) -> Markup {
+    html! {
+        @if matches.is_empty() {
+            div class="text-center text-base-content/60 py-8" { "No recipes found" }
+        } @else {
+            div class="grid grid-cols-1 lg:grid-cols-2 xl:grid-cols-3 gap-4" {
+                @for hit in matches {
+                    (search_result(hit))
+                }
+            }
+        }
+    }
+}
+
+/// A matching recipe with its matches marked, opening the full card on click
+fn search_result(hit: &FullTextMatch<Recipe>) -> Markup {
+    let recipe = &hit.model;
+    html! {
+        div id=(format!("recipe-{}", recipe.id())) class="w-full" {
+            div class="card bg-base-100 border border-base-300 shadow-lg hover:shadow-xl transition-shadow duration-200 h-full cursor-pointer"
+                hx-get=(format!("/recipes/{}", recipe.id()))
+                hx-target=(format!("#recipe-{}", recipe.id()))
+                hx-swap="outerHTML"
+                title="Open recipe" {
+                div class="card-body p-4" {
+                    h3 class="text-xl font-bold" {
+                        @match hit.highlight("title") {
+                            Some(title) => (highlighted(title)),
+                            None => span class="italic text-base-content/60" { "Untitled Recipe" },
                         }
                     }
+                    div class="bg-base-200 rounded-lg p-3 text-sm text-base-content/90 whitespace-pre-wrap leading-relaxed" {
+                        (highlighted(&hit.snippet))
+                    }
                 }
             }
         }
     }
 }
 
+/// Text from a search with its matches in `<mark>`
+fn highlighted(marked: &str) -> Markup {
+    html! {
+        @for (text, is_match) in highlight_parts(marked) {
+            @if is_match {
+                mark class="bg-warning/40 rounded px-0.5" { (text) }
+            } @else {
+                (text)
+            }
+        }
+    }
+}
+
 pub fn recipe_row(recipe: &Recipe) -> Markup {
     let recipe_id = recipe.id();
     let recipe_id_spinner = format!("indicator-{recipe_id}");
@@ -127,9 +198,23 @@ pub fn recipe_row(recipe: &Recipe) -> Markup {
                     div class="card-actions justify-between items-center pt-2 border-t border-base-300" {
                         div class="text-xs text-base-content/60" {
                             "Created: " (recipe.created_at.format("%b %d, %Y"))
+                            @if let Some(prompt_version) = recipe.prompt_version() {
+                                span class="badge badge-ghost badge-xs ml-2" title="Prompt version" {
+                                    (prompt_version)
+                                }
+                            }
                         }
 
                         div class="flex gap-2" {
+                            button class="btn btn-xs btn-accent btn-outline"
+                                hx-post=(format!("/recipes/{}/reextract", recipe.id()))
+                                hx-target=(format!("#recipe-{}", recipe.id()))
+                                hx-swap="outerHTML"
+                                title="Re-extract recipe structure with AI" {
+                                (wand_icon())
+                                "Re-extract"
+                            }
+
                             @if let Some(url) = recipe.url() {
                                 @if !url.is_empty() {
                                     form hx-post="/chat" hx-swap="none" class="inline" {
@@ -280,3 +365,36 @@ pub fn add_modal() -> Markup {
         }
     }
 }
+
+pub fn import_modal() -> Markup {
+    let modal_id = format!("modal{}", random_html_safe_id());
+    html! {
+        button class="btn" onclick=(format!("{modal_id}.showModal()")) title="Import recipes" {
+            (link_icon())
+        }
+        dialog id=(modal_id) class="modal" {
+            div class="modal-box" {
+                h3 class="text-lg font-bold" {
+                    "Import Recipes"
+                }
+                div class="py-4" {
+                    "Paste one recipe URL per line. Each URL is imported in the background, you can follow the progress on the jobs page."
+                }
+                form class="flex flex-col gap-2 mb-4" hx-post="/recipes/import" hx-target=(format!("#{modal_id}-result")) hx-swap="innerHTML" {
+                    textarea class="textarea textarea-bordered" name="urls" placeholder="https://example.com/recipe" rows="5" required{}
+                    button class="btn btn-primary" type="submit" {
+                        (link_icon())
+                        "Import"
+                    }
+                    button class="btn" type="button" onclick=(format!("{modal_id}.close()")) {
+                        "Close"
+                    }
+                }
+                div id=(format!("{modal_id}-result")) {}
+            }
+            form method="dialog" class="modal-backdrop"{
+                button{"close"}
+            }
+        }
+    }
+}